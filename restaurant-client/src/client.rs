@@ -0,0 +1,586 @@
+use std::time::Duration;
+
+use reqwest::{Method, RequestBuilder, StatusCode, Url};
+use serde::{de::DeserializeOwned, Serialize};
+use thiserror::Error;
+
+use crate::{
+    v1::{
+        ApiKeyDetails, AssignSection, AuditPage, AuditQuery, AuditVerification, CreateApiKey,
+        CreateOrder, CreateOrderGroup, CreateReservation, CreateTableGroup, CreateWebhook,
+        CreatedWebhook, HireStaff, IssuedApiKey, JoinWaitlist, Link, Login, Menu, MenuItemDetails,
+        Order, OrderDetails, OrderGroup, OrderPage, OrderQuery, PartyDetails, ReservationDetails,
+        ReservationPatch, ReservationQuery, SectionAssignment, ServerTable, Session,
+        SetOrderQuantity, SplitTableGroup, StaffDetails, Station, SuggestTable, TableDetails,
+        TableGroupDetails, TableStatus, TableSuggestion, Transfer, WaitQuery, WaitQuote,
+        WebhookDelivery, WebhookDetails,
+    },
+    versions::ApiVersion,
+    API_KEY_HEADER, IDEMPOTENCY_KEY_HEADER, RESTAURANT_HEADER, VERSION_HEADER,
+};
+
+#[derive(Error, Debug)]
+pub enum Error {
+    #[error("The request was rejected: {0}")]
+    BadRequest(String),
+    #[error("Sign in first: {0}")]
+    Unauthorized(String),
+    #[error("Not allowed: {0}")]
+    Forbidden(String),
+    #[error("Not found: {0}")]
+    NotFound(String),
+    #[error("The request conflicts with the current state: {0}")]
+    Conflict(String),
+    #[error("The server responded with {status}: {message}")]
+    Server { status: StatusCode, message: String },
+    #[error("Unable to reach the server.")]
+    Transport(#[from] reqwest::Error),
+    #[error("'{0}' is not a valid url.")]
+    InvalidUrl(String),
+    #[error("'{0}' is not a method a link can use.")]
+    InvalidMethod(String),
+}
+pub type Result<T> = std::result::Result<T, Error>;
+
+impl Error {
+    // whether trying the same request again could turn out differently
+    fn is_transient(&self) -> bool {
+        match self {
+            Error::Transport(e) => !e.is_decode() && !e.is_builder(),
+            Error::Server { status, .. } => status.is_server_error(),
+            _ => false,
+        }
+    }
+
+    fn from_response(status: StatusCode, message: String) -> Error {
+        match status {
+            StatusCode::BAD_REQUEST => Error::BadRequest(message),
+            StatusCode::UNAUTHORIZED => Error::Unauthorized(message),
+            StatusCode::FORBIDDEN => Error::Forbidden(message),
+            StatusCode::NOT_FOUND => Error::NotFound(message),
+            StatusCode::CONFLICT => Error::Conflict(message),
+            _ => Error::Server { status, message },
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    /// Including the first attempt, so 1 never retries.
+    pub max_attempts: u32,
+    pub base_delay: Duration,
+    pub max_delay: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        RetryPolicy {
+            max_attempts: 3,
+            base_delay: Duration::from_millis(100),
+            max_delay: Duration::from_secs(2),
+        }
+    }
+}
+
+impl RetryPolicy {
+    pub fn never() -> RetryPolicy {
+        RetryPolicy {
+            max_attempts: 1,
+            ..Default::default()
+        }
+    }
+
+    fn delay(&self, attempt: u32) -> Duration {
+        self.base_delay
+            .saturating_mul(2u32.saturating_pow(attempt.saturating_sub(1)))
+            .min(self.max_delay)
+    }
+}
+
+/// Talks to v1 of the restaurant api.
+#[derive(Debug, Clone)]
+pub struct Client {
+    http: reqwest::Client,
+    root: Url,
+    retries: RetryPolicy,
+    token: Option<String>,
+    api_key: Option<String>,
+    restaurant: Option<u32>,
+}
+
+impl Client {
+    pub fn new(root: &str) -> Result<Client> {
+        Ok(Client {
+            http: reqwest::Client::new(),
+            root: Url::parse(root).map_err(|_| Error::InvalidUrl(root.to_string()))?,
+            retries: RetryPolicy::default(),
+            token: None,
+            api_key: None,
+            restaurant: None,
+        })
+    }
+
+    pub fn with_http_client(self, http: reqwest::Client) -> Client {
+        Client { http, ..self }
+    }
+
+    pub fn with_retries(self, retries: RetryPolicy) -> Client {
+        Client { retries, ..self }
+    }
+
+    /// Sends the token from `login` with every request.
+    pub fn with_token(self, token: String) -> Client {
+        Client {
+            token: Some(token),
+            ..self
+        }
+    }
+
+    /// Sends an api key with every request, for integrations rather than people.
+    pub fn with_api_key(self, key: String) -> Client {
+        Client {
+            api_key: Some(key),
+            ..self
+        }
+    }
+
+    /// Talks to one restaurant of a server that runs several, rather than the default one.
+    pub fn with_restaurant(self, id: u32) -> Client {
+        Client {
+            restaurant: Some(id),
+            ..self
+        }
+    }
+
+    pub async fn login(&self, name: &str, secret: &str) -> Result<Session> {
+        self.send(
+            Method::POST,
+            "/staff/login",
+            Some(&Login {
+                name: name.to_string(),
+                secret: secret.to_string(),
+            }),
+        )
+        .await
+    }
+
+    pub async fn staff(&self) -> Result<Vec<StaffDetails>> {
+        self.send(Method::GET, "/staff", None::<&()>).await
+    }
+
+    pub async fn hire(&self, staff: &HireStaff) -> Result<StaffDetails> {
+        self.send(Method::POST, "/staff", Some(staff)).await
+    }
+
+    /// Stops the staff member from signing in, along with any token they already have.
+    pub async fn dismiss(&self, id: u32) -> Result<StaffDetails> {
+        self.send(Method::POST, &format!("/staff/{}/dismiss", id), None::<&()>)
+            .await
+    }
+
+    /// Who changed what, a page at a time, newest first.
+    pub async fn audit(&self, query: &AuditQuery) -> Result<AuditPage> {
+        let query = serde_urlencoded::to_string(query)
+            .map_err(|_| Error::InvalidUrl(format!("/audit?{:?}", query)))?;
+        self.send(Method::GET, &format!("/audit?{}", query), None::<&()>)
+            .await
+    }
+
+    pub async fn verify_audit(&self) -> Result<AuditVerification> {
+        self.send(Method::GET, "/audit/verify", None::<&()>).await
+    }
+
+    pub async fn section_assignments(&self) -> Result<Vec<SectionAssignment>> {
+        self.send(Method::GET, "/sections/assignments", None::<&()>)
+            .await
+    }
+
+    /// Hands a section of the floor to a server for a shift.
+    pub async fn assign_section(&self, assignment: &AssignSection) -> Result<SectionAssignment> {
+        self.send(Method::POST, "/sections/assignments", Some(assignment))
+            .await
+    }
+
+    pub async fn unassign_section(&self, id: u32) -> Result<SectionAssignment> {
+        self.send(
+            Method::DELETE,
+            &format!("/sections/assignments/{}", id),
+            None::<&()>,
+        )
+        .await
+    }
+
+    /// The tables the server has right now, with their open orders.
+    pub async fn staff_tables(&self, id: u32) -> Result<Vec<ServerTable>> {
+        self.send(Method::GET, &format!("/staff/{}/tables", id), None::<&()>)
+            .await
+    }
+
+    pub async fn api_keys(&self) -> Result<Vec<ApiKeyDetails>> {
+        self.send(Method::GET, "/api_keys", None::<&()>).await
+    }
+
+    pub async fn create_api_key(&self, api_key: &CreateApiKey) -> Result<IssuedApiKey> {
+        self.send(Method::POST, "/api_keys", Some(api_key)).await
+    }
+
+    /// Replaces the key with a new one. The old one stops working straight away.
+    pub async fn rotate_api_key(&self, id: u32) -> Result<IssuedApiKey> {
+        self.send(
+            Method::POST,
+            &format!("/api_keys/{}/rotate", id),
+            None::<&()>,
+        )
+        .await
+    }
+
+    pub async fn revoke_api_key(&self, id: u32) -> Result<ApiKeyDetails> {
+        self.send(Method::DELETE, &format!("/api_keys/{}", id), None::<&()>)
+            .await
+    }
+
+    pub async fn versions(&self) -> Result<Vec<ApiVersion>> {
+        self.send(Method::GET, "/versions", None::<&()>).await
+    }
+
+    pub async fn menu_items(&self) -> Result<Vec<MenuItemDetails>> {
+        self.send(Method::GET, "/menu_items", None::<&()>).await
+    }
+
+    /// The menu by category, laid out the way guests should see it.
+    pub async fn menu(&self) -> Result<Menu> {
+        self.send(Method::GET, "/menu", None::<&()>).await
+    }
+
+    pub async fn tables(&self) -> Result<Vec<TableDetails>> {
+        self.send(Method::GET, "/tables", None::<&()>).await
+    }
+
+    pub async fn create_order(&self, order: &CreateOrder) -> Result<Order> {
+        self.send(Method::POST, "/orders", Some(order)).await
+    }
+
+    /// Places every item or none of them, so a failure partway can't leave half of it placed.
+    pub async fn create_order_group(&self, group: &CreateOrderGroup) -> Result<OrderGroup> {
+        self.send(Method::POST, "/order_groups", Some(group)).await
+    }
+
+    /// One page of the orders matching the query. The page's `next` link leads to the one after it.
+    pub async fn orders(&self, query: &OrderQuery) -> Result<OrderPage> {
+        let query = serde_urlencoded::to_string(query)
+            .map_err(|_| Error::InvalidUrl(format!("/orders?{:?}", query)))?;
+        self.send(Method::GET, &format!("/orders?{}", query), None::<&()>)
+            .await
+    }
+
+    pub async fn set_quantity(&self, id: u32, quantity: u32) -> Result<Order> {
+        self.send(
+            Method::POST,
+            &format!("/orders/{}/setquantity", id),
+            Some(&SetOrderQuantity { quantity }),
+        )
+        .await
+    }
+
+    pub async fn cancel_order(&self, id: u32) -> Result<Order> {
+        self.send(Method::DELETE, &format!("/orders/{}", id), None::<&()>)
+            .await
+    }
+
+    /// Moves the order to another table. It keeps its place in the kitchen's queue.
+    pub async fn transfer_order(&self, id: u32, table: u32) -> Result<Order> {
+        self.send(
+            Method::POST,
+            &format!("/orders/{}/transfer", id),
+            Some(&Transfer { table_id: table }),
+        )
+        .await
+    }
+
+    /// Moves every order at one table to another, all at once.
+    pub async fn transfer_table(&self, from: u32, to: u32) -> Result<Vec<Order>> {
+        self.send(
+            Method::POST,
+            &format!("/table/{}/transfer", from),
+            Some(&Transfer { table_id: to }),
+        )
+        .await
+    }
+
+    pub async fn table_orders(&self, table: u32) -> Result<Vec<OrderDetails>> {
+        self.send(
+            Method::GET,
+            &format!("/table/{}/orders", table),
+            None::<&()>,
+        )
+        .await
+    }
+
+    pub async fn clear_table(&self, table: u32) -> Result<Vec<Order>> {
+        self.send(
+            Method::POST,
+            &format!("/table/{}/clear", table),
+            None::<&()>,
+        )
+        .await
+    }
+
+    /// The tables that would do for the party, best first.
+    pub async fn suggest_tables(&self, party: &SuggestTable) -> Result<Vec<TableSuggestion>> {
+        self.send(Method::POST, "/tables/suggest", Some(party))
+            .await
+    }
+
+    /// Pushes the tables together for one party. The first leads the group.
+    pub async fn create_table_group(&self, tables: &[u32]) -> Result<TableGroupDetails> {
+        self.send(
+            Method::POST,
+            "/table_groups",
+            Some(&CreateTableGroup {
+                tables: tables.to_vec(),
+            }),
+        )
+        .await
+    }
+
+    pub async fn table_group(&self, id: u32) -> Result<TableGroupDetails> {
+        self.send(Method::GET, &format!("/table_groups/{}", id), None::<&()>)
+            .await
+    }
+
+    /// Splits the group back up, moving the party's orders to `table`, or to the lead table if `None`.
+    pub async fn split_table_group(&self, id: u32, table: Option<u32>) -> Result<Vec<Order>> {
+        self.send(
+            Method::POST,
+            &format!("/table_groups/{}/split", id),
+            Some(&SplitTableGroup { table_id: table }),
+        )
+        .await
+    }
+
+    pub async fn book_reservation(
+        &self,
+        reservation: &CreateReservation,
+    ) -> Result<ReservationDetails> {
+        self.send(Method::POST, "/reservations", Some(reservation))
+            .await
+    }
+
+    /// Every reservation with any part of it on the day, by when they start.
+    pub async fn reservations(&self, date: chrono::NaiveDate) -> Result<Vec<ReservationDetails>> {
+        let query = serde_urlencoded::to_string(ReservationQuery { date })
+            .map_err(|_| Error::InvalidUrl(format!("/reservations?date={}", date)))?;
+        self.send(
+            Method::GET,
+            &format!("/reservations?{}", query),
+            None::<&()>,
+        )
+        .await
+    }
+
+    pub async fn modify_reservation(
+        &self,
+        id: u32,
+        patch: &ReservationPatch,
+    ) -> Result<ReservationDetails> {
+        self.send(Method::PATCH, &format!("/reservations/{}", id), Some(patch))
+            .await
+    }
+
+    pub async fn cancel_reservation(&self, id: u32) -> Result<ReservationDetails> {
+        self.send(
+            Method::DELETE,
+            &format!("/reservations/{}", id),
+            None::<&()>,
+        )
+        .await
+    }
+
+    pub async fn join_waitlist(&self, party: &JoinWaitlist) -> Result<PartyDetails> {
+        self.send(Method::POST, "/waitlist", Some(party)).await
+    }
+
+    /// The parties still in line, first come first served.
+    pub async fn waitlist(&self) -> Result<Vec<PartyDetails>> {
+        self.send(Method::GET, "/waitlist", None::<&()>).await
+    }
+
+    /// How long a party of `size` would wait if they joined now.
+    pub async fn wait_quote(&self, size: u32) -> Result<WaitQuote> {
+        let query = serde_urlencoded::to_string(WaitQuery { size })
+            .map_err(|_| Error::InvalidUrl(format!("/waitlist/quote?size={}", size)))?;
+        self.send(
+            Method::GET,
+            &format!("/waitlist/quote?{}", query),
+            None::<&()>,
+        )
+        .await
+    }
+
+    pub async fn party(&self, id: u32) -> Result<PartyDetails> {
+        self.send(Method::GET, &format!("/waitlist/{}", id), None::<&()>)
+            .await
+    }
+
+    pub async fn seat_party(&self, id: u32) -> Result<PartyDetails> {
+        self.send(Method::POST, &format!("/waitlist/{}/seat", id), None::<&()>)
+            .await
+    }
+
+    /// Records that the party didn't turn up. A table held for them goes to the next party that fits.
+    pub async fn no_show(&self, id: u32) -> Result<PartyDetails> {
+        self.send(
+            Method::POST,
+            &format!("/waitlist/{}/no_show", id),
+            None::<&()>,
+        )
+        .await
+    }
+
+    pub async fn table_status(&self, table: u32) -> Result<TableStatus> {
+        self.send(
+            Method::GET,
+            &format!("/table/{}/status", table),
+            None::<&()>,
+        )
+        .await
+    }
+
+    pub async fn station_tickets(&self, station: Station) -> Result<Vec<OrderDetails>> {
+        self.send(
+            Method::GET,
+            &format!("/stations/{}/tickets", station_path(station)),
+            None::<&()>,
+        )
+        .await
+    }
+
+    pub async fn bump(&self, station: Station, id: u32) -> Result<OrderDetails> {
+        self.send(
+            Method::POST,
+            &format!("/stations/{}/tickets/{}/bump", station_path(station), id),
+            None::<&()>,
+        )
+        .await
+    }
+
+    pub async fn create_webhook(&self, webhook: &CreateWebhook) -> Result<CreatedWebhook> {
+        self.send(Method::POST, "/webhooks", Some(webhook)).await
+    }
+
+    pub async fn webhooks(&self) -> Result<Vec<WebhookDetails>> {
+        self.send(Method::GET, "/webhooks", None::<&()>).await
+    }
+
+    pub async fn delete_webhook(&self, id: u32) -> Result<WebhookDetails> {
+        self.send(Method::DELETE, &format!("/webhooks/{}", id), None::<&()>)
+            .await
+    }
+
+    pub async fn dead_letters(&self) -> Result<Vec<WebhookDelivery>> {
+        self.send(Method::GET, "/webhooks/dead_letters", None::<&()>)
+            .await
+    }
+
+    pub async fn retry_dead_letter(&self, id: u32) -> Result<WebhookDelivery> {
+        self.send(
+            Method::POST,
+            &format!("/webhooks/dead_letters/{}/retry", id),
+            None::<&()>,
+        )
+        .await
+    }
+
+    /// Follows one of the `_links` of a response, rather than building its url by hand.
+    pub async fn follow<B: Serialize, T: DeserializeOwned>(
+        &self,
+        link: &Link,
+        body: Option<&B>,
+    ) -> Result<T> {
+        let method = match &link.method {
+            Some(method) => Method::from_bytes(method.as_bytes())
+                .map_err(|_| Error::InvalidMethod(method.clone()))?,
+            None => Method::GET,
+        };
+        let url = self
+            .root
+            .join(&link.href)
+            .map_err(|_| Error::InvalidUrl(link.href.clone()))?;
+
+        self.send_to(method, url, body).await
+    }
+
+    async fn send<B: Serialize, T: DeserializeOwned>(
+        &self,
+        method: Method,
+        path: &str,
+        body: Option<&B>,
+    ) -> Result<T> {
+        let url = self
+            .root
+            .join(&format!("api{}", path))
+            .map_err(|_| Error::InvalidUrl(path.to_string()))?;
+
+        self.send_to(method, url, body).await
+    }
+
+    async fn send_to<B: Serialize, T: DeserializeOwned>(
+        &self,
+        method: Method,
+        url: Url,
+        body: Option<&B>,
+    ) -> Result<T> {
+        // every attempt carries the same key, so the server can tell a retry from a second request
+        let key = (method != Method::GET).then(|| format!("{:032x}", rand::random::<u128>()));
+
+        let mut attempt = 1;
+        loop {
+            let mut request = self
+                .http
+                .request(method.clone(), url.clone())
+                .header(VERSION_HEADER, "v1");
+            if let Some(key) = &key {
+                request = request.header(IDEMPOTENCY_KEY_HEADER, key);
+            }
+            if let Some(token) = &self.token {
+                request = request.bearer_auth(token);
+            }
+            if let Some(api_key) = &self.api_key {
+                request = request.header(API_KEY_HEADER, api_key);
+            }
+            if let Some(restaurant) = self.restaurant {
+                request = request.header(RESTAURANT_HEADER, restaurant);
+            }
+            if let Some(body) = body {
+                request = request.json(body);
+            }
+
+            match Client::attempt(request).await {
+                Err(e) if e.is_transient() && attempt < self.retries.max_attempts => {
+                    tokio::time::sleep(self.retries.delay(attempt)).await;
+                    attempt += 1;
+                }
+                result => return result,
+            }
+        }
+    }
+
+    async fn attempt<T: DeserializeOwned>(request: RequestBuilder) -> Result<T> {
+        let response = request.send().await?;
+        let status = response.status();
+        if status.is_success() {
+            Ok(response.json().await?)
+        } else {
+            Err(Error::from_response(status, response.text().await?))
+        }
+    }
+}
+
+fn station_path(station: Station) -> &'static str {
+    match station {
+        Station::Grill => "grill",
+        Station::Fryer => "fryer",
+        Station::Dessert => "dessert",
+        Station::Drinks => "drinks",
+    }
+}
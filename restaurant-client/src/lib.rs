@@ -0,0 +1,20 @@
+// the request and response types are always here, so the web api can share them without pulling
+// in an http client. the client itself is behind the `http` feature.
+pub mod v1;
+pub mod v2;
+pub mod versions;
+
+#[cfg(feature = "http")]
+mod client;
+
+#[cfg(feature = "http")]
+pub use client::{Client, Error, Result, RetryPolicy};
+
+pub const VERSION_HEADER: &str = "x-api-version";
+pub const IDEMPOTENCY_KEY_HEADER: &str = "idempotency-key";
+// integrations send their api key in this, where staff would send a token in `Authorization`
+pub const API_KEY_HEADER: &str = "x-api-key";
+// ties a request to what it changed in the audit log. the server makes one up when it isn't sent
+pub const REQUEST_ID_HEADER: &str = "x-request-id";
+// which restaurant the request is for, when the server has more than one. the default one otherwise
+pub const RESTAURANT_HEADER: &str = "x-restaurant-id";
@@ -0,0 +1,827 @@
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+
+// what v1 sends and receives. the server and the client both use these, so they can't drift apart.
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[serde(rename_all = "lowercase")]
+pub enum Station {
+    Grill,
+    Fryer,
+    Dessert,
+    Drinks,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[serde(rename_all = "lowercase")]
+pub enum OrderStatus {
+    Placed,
+    Ready,
+}
+
+/// Somewhere a client can go from a resource, or something it can do to it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct Link {
+    pub href: String,
+    /// Left out for plain `GET`s.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub method: Option<String>,
+}
+
+impl Link {
+    pub fn get(href: String) -> Link {
+        Link { href, method: None }
+    }
+
+    pub fn action(method: &str, href: String) -> Link {
+        Link {
+            href,
+            method: Some(method.to_string()),
+        }
+    }
+}
+
+/// What's wrong with one field of a request. v1 reports these as lines of text, `{pointer}: {detail}`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct FieldError {
+    /// A JSON Pointer to the field in the request body, such as `/items/2/quantity`.
+    pub pointer: String,
+    pub detail: String,
+}
+
+/// Actions are only present when the order's current state allows them.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct OrderLinks {
+    /// Left out once the order has been removed.
+    #[serde(rename = "self", default, skip_serializing_if = "Option::is_none")]
+    pub self_link: Option<Link>,
+    pub table: Link,
+    pub menu_item: Link,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub set_quantity: Option<Link>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cancel: Option<Link>,
+    /// Marks the order as ready, from the station that prepares it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub complete: Option<Link>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct TableLinks {
+    #[serde(rename = "self")]
+    pub self_link: Link,
+    pub orders: Link,
+    pub status: Link,
+    pub place_order: Link,
+    pub clear: Link,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct MenuItemLinks {
+    #[serde(rename = "self")]
+    pub self_link: Link,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct TableDetails {
+    pub id: u32,
+    /// How many people the table sits.
+    pub seats: u32,
+    /// The part of the floor the table is in.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub section: Option<String>,
+    #[serde(rename = "_links")]
+    pub links: TableLinks,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct MenuItemDetails {
+    pub id: u32,
+    pub name: String,
+    /// Minutes it takes to prepare one of the item.
+    pub cook_time: u32,
+    pub station: Station,
+    /// Unavailable items can't be ordered until they're back.
+    pub available: bool,
+    /// The category the item is listed under, if it is.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub category_id: Option<u32>,
+    #[serde(rename = "_links")]
+    pub links: MenuItemLinks,
+}
+
+/// The menu as it's laid out for guests.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct Menu {
+    pub categories: Vec<MenuCategory>,
+    /// Items that aren't in any category, which come after all of them.
+    pub uncategorized: Vec<MenuItemDetails>,
+}
+
+/// A category of the menu, with its items and then its subcategories, each in the order they're listed.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct MenuCategory {
+    pub id: u32,
+    pub name: String,
+    /// Lower comes first among categories with the same parent.
+    pub sort_order: u32,
+    pub items: Vec<MenuItemDetails>,
+    #[cfg_attr(feature = "openapi", schema(no_recursion))]
+    pub subcategories: Vec<MenuCategory>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct CreateOrder {
+    pub table_id: u32,
+    pub item_id: u32,
+    pub quantity: u32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct GroupItem {
+    pub item_id: u32,
+    pub quantity: u32,
+}
+
+/// Everything a table orders at once. Either every item is placed or none are.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct CreateOrderGroup {
+    pub table_id: u32,
+    pub items: Vec<GroupItem>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct OrderGroup {
+    pub id: u32,
+    pub table_id: u32,
+    pub time_placed: DateTime<Utc>,
+    /// In the order the items were given.
+    pub orders: Vec<Order>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct SetOrderQuantity {
+    /// Setting the quantity to zero cancels the order.
+    pub quantity: u32,
+}
+
+/// Where an order, or every order at a table, is moving to.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct Transfer {
+    pub table_id: u32,
+}
+
+/// An order as it was placed, changed or removed.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct Order {
+    pub id: u32,
+    pub table: TableDetails,
+    pub menu_item: MenuItemDetails,
+    pub time_placed: DateTime<Utc>,
+    pub quantity: u32,
+    pub status: OrderStatus,
+    /// The group the order was placed with, if it was.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub group_id: Option<u32>,
+    /// The staff member who placed the order.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub placed_by: Option<u32>,
+    /// The staff member who last changed the order.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub changed_by: Option<u32>,
+    #[serde(rename = "_links")]
+    pub links: OrderLinks,
+}
+
+/// An open order, along with how long it's expected to take.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct OrderDetails {
+    pub id: u32,
+    pub table: TableDetails,
+    pub item: MenuItemDetails,
+    pub time_placed: DateTime<Utc>,
+    pub quantity: u32,
+    pub status: OrderStatus,
+    /// The group the order was placed with, if it was.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub group_id: Option<u32>,
+    /// The staff member who placed the order.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub placed_by: Option<u32>,
+    /// The staff member who last changed the order.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub changed_by: Option<u32>,
+    pub estimated_minutes_remaining: u32,
+    #[serde(rename = "_links")]
+    pub links: OrderLinks,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct TableGroupLinks {
+    #[serde(rename = "self")]
+    pub self_link: Link,
+    pub orders: Link,
+    pub split: Link,
+}
+
+/// Tables pushed together for one party. Orders placed at any of them are kept at the lead table,
+/// and asking after any of them answers for the whole party.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct TableGroupDetails {
+    pub id: u32,
+    /// The lead table comes first.
+    pub tables: Vec<u32>,
+    pub lead_table_id: u32,
+    /// Every order of the party, across all of its tables.
+    pub orders: Vec<OrderDetails>,
+    /// How many items the party has ordered in all.
+    pub total_quantity: u32,
+    /// Until the last of the party's open orders is ready.
+    pub estimated_minutes_remaining: u32,
+    /// The staff member who pushed the tables together.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub merged_by: Option<u32>,
+    #[serde(rename = "_links")]
+    pub links: TableGroupLinks,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct CreateTableGroup {
+    /// At least two tables. The first leads the group, and the orders at the others move to it.
+    pub tables: Vec<u32>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct SplitTableGroup {
+    /// The table the party stays at, which the group's orders move to. The lead table if left out.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub table_id: Option<u32>,
+}
+
+/// A party looking for a table.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct SuggestTable {
+    pub size: u32,
+    /// Only suggest tables in this section.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub section: Option<String>,
+    /// How long the party is expected to stay, which is how far ahead reservations are checked. 90 if left out.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stay_minutes: Option<u32>,
+}
+
+/// A table that would do for the party. Lower penalties are better, and the reasons say what went into it.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct TableSuggestion {
+    /// Where the table ranks, starting from 1.
+    pub rank: u32,
+    pub table: TableDetails,
+    pub penalty: u32,
+    pub reasons: Vec<String>,
+}
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[serde(rename_all = "lowercase")]
+pub enum OrderSort {
+    #[default]
+    Id,
+    Placed,
+    Table,
+    Quantity,
+}
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[serde(rename_all = "lowercase")]
+pub enum SortDirection {
+    #[default]
+    Asc,
+    Desc,
+}
+
+/// Which orders to list, and in what order. Filters that are left out match every order.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::IntoParams))]
+#[cfg_attr(feature = "openapi", into_params(parameter_in = Query))]
+pub struct OrderQuery {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub table_id: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub item_id: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub status: Option<OrderStatus>,
+    /// Only orders placed after this time.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub placed_after: Option<DateTime<Utc>>,
+    /// Only orders placed before this time.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub placed_before: Option<DateTime<Utc>>,
+    /// Ties are broken by id. Defaults to id.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sort: Option<OrderSort>,
+    /// Defaults to ascending.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub direction: Option<SortDirection>,
+    /// The `next_cursor` of the previous page, which only fits the same sort and direction.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cursor: Option<String>,
+    /// How many orders a page holds, up to 200. Defaults to 50.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub limit: Option<u32>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct PageLinks {
+    #[serde(rename = "self")]
+    pub self_link: Link,
+    /// Left out on the last page.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub next: Option<Link>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct OrderPage {
+    pub orders: Vec<OrderDetails>,
+    /// Left out on the last page.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub next_cursor: Option<String>,
+    #[serde(rename = "_links")]
+    pub links: PageLinks,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct TableStatus {
+    pub table: u32,
+    /// Whether every order for the table is ready.
+    pub ready: bool,
+    pub waiting_on: Vec<Station>,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub enum WebhookEvent {
+    #[serde(rename = "order.placed")]
+    Placed,
+    #[serde(rename = "order.updated")]
+    Updated,
+    #[serde(rename = "order.ready")]
+    Ready,
+    #[serde(rename = "order.removed")]
+    Removed,
+    #[serde(rename = "order.transferred")]
+    Transferred,
+    /// A party on the waitlist was told their table's ready.
+    #[serde(rename = "waitlist.notified")]
+    PartyNotified,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct CreateWebhook {
+    pub url: String,
+    /// Leave empty to receive every kind of event.
+    #[serde(default)]
+    pub events: Vec<WebhookEvent>,
+}
+
+/// A newly registered webhook. This is the only time its secret is handed out.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct CreatedWebhook {
+    pub id: u32,
+    pub url: String,
+    /// Key for verifying the HMAC-SHA256 signature of deliveries.
+    pub secret: String,
+    pub events: Vec<WebhookEvent>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct WebhookDetails {
+    pub id: u32,
+    pub url: String,
+    pub events: Vec<WebhookEvent>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct WebhookDelivery {
+    pub id: u32,
+    pub subscription: u32,
+    /// The order event being delivered, if it's one.
+    pub event_id: Option<u32>,
+    /// The party being announced, if it's a waitlist notice.
+    pub party_id: Option<u32>,
+    pub kind: WebhookEvent,
+    /// The body that was being sent.
+    pub payload: String,
+    pub attempts: u32,
+    pub next_attempt: DateTime<Utc>,
+    pub last_error: Option<String>,
+}
+
+/// Who to call about a reservation.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct ContactDetails {
+    pub name: String,
+    pub phone: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct ReservationLinks {
+    #[serde(rename = "self")]
+    pub self_link: Link,
+    pub table: Link,
+    pub modify: Link,
+    pub cancel: Link,
+}
+
+/// A table booked for a party. The table is theirs from `starts_at` up to, but not including, `ends_at`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct ReservationDetails {
+    pub id: u32,
+    pub table_id: u32,
+    pub party_size: u32,
+    pub starts_at: DateTime<Utc>,
+    pub ends_at: DateTime<Utc>,
+    pub contact: ContactDetails,
+    /// The staff member who booked the reservation or last changed it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub changed_by: Option<u32>,
+    #[serde(rename = "_links")]
+    pub links: ReservationLinks,
+}
+
+/// The table has to sit the whole party, and can't already be booked for any of the time.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct CreateReservation {
+    pub table_id: u32,
+    pub party_size: u32,
+    pub starts_at: DateTime<Utc>,
+    pub ends_at: DateTime<Utc>,
+    pub contact: ContactDetails,
+}
+
+/// What's changing about a reservation. Anything left out stays as it is.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct ReservationPatch {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub table_id: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub party_size: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub starts_at: Option<DateTime<Utc>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ends_at: Option<DateTime<Utc>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub contact: Option<ContactDetails>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::IntoParams))]
+#[cfg_attr(feature = "openapi", into_params(parameter_in = Query))]
+pub struct ReservationQuery {
+    /// The day to list, in UTC. Bookings that only partly fall on it are included.
+    pub date: NaiveDate,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum PartyStatus {
+    Waiting,
+    /// A table that fits the party freed up, and it's being held for them.
+    Notified,
+    Seated,
+    NoShow,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct PartyLinks {
+    #[serde(rename = "self")]
+    pub self_link: Link,
+    /// Left out once the party has left the line.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub seat: Option<Link>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub no_show: Option<Link>,
+    /// The table held for a notified party.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub table: Option<Link>,
+}
+
+/// A walk-in party on the waitlist.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct PartyDetails {
+    pub id: u32,
+    pub name: String,
+    pub size: u32,
+    pub phone: Option<String>,
+    pub joined_at: DateTime<Utc>,
+    pub status: PartyStatus,
+    /// The table held for the party, once they've been notified.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub table_id: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub notified_at: Option<DateTime<Utc>>,
+    /// How long the party is likely to wait, while they're still in line.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub estimated_minutes_remaining: Option<u32>,
+    /// The staff member who added the party or last changed their status.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub changed_by: Option<u32>,
+    #[serde(rename = "_links")]
+    pub links: PartyLinks,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct JoinWaitlist {
+    pub name: String,
+    pub size: u32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub phone: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::IntoParams))]
+#[cfg_attr(feature = "openapi", into_params(parameter_in = Query))]
+pub struct WaitQuery {
+    /// How many people the party has.
+    pub size: u32,
+}
+
+/// How long a party would wait if they joined the end of the line now.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct WaitQuote {
+    pub size: u32,
+    pub estimated_minutes: u32,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[serde(rename_all = "lowercase")]
+pub enum StaffRole {
+    Server,
+    Kitchen,
+    Host,
+    Manager,
+    Admin,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct StaffDetails {
+    pub id: u32,
+    pub name: String,
+    pub role: StaffRole,
+    /// Staff who've left keep their records, but can't sign in.
+    pub active: bool,
+    /// The restaurants they work at. Admins can reach every restaurant.
+    #[serde(default)]
+    pub restaurants: Vec<u32>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct HireStaff {
+    pub name: String,
+    pub role: StaffRole,
+    /// A PIN or password of at least four characters.
+    pub secret: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct Login {
+    pub name: String,
+    pub secret: String,
+}
+
+/// Sent as `Authorization: Bearer {token}` until it expires.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct Session {
+    pub token: String,
+    pub expires_at: DateTime<Utc>,
+    pub staff: StaffDetails,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub enum ApiScope {
+    #[serde(rename = "orders:read")]
+    OrdersRead,
+    /// Also lets the key read orders.
+    #[serde(rename = "orders:write")]
+    OrdersWrite,
+    #[serde(rename = "kitchen:write")]
+    KitchenWrite,
+    #[serde(rename = "tables:read")]
+    TablesRead,
+    /// Also lets the key read tables. Covers table groups, reservations and the waitlist.
+    #[serde(rename = "tables:write")]
+    TablesWrite,
+    #[serde(rename = "menu:read")]
+    MenuRead,
+    /// Also lets the key read the menu.
+    #[serde(rename = "menu:admin")]
+    MenuAdmin,
+    #[serde(rename = "webhooks:admin")]
+    WebhooksAdmin,
+}
+
+/// An api key, without the key itself.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct ApiKeyDetails {
+    pub id: u32,
+    pub name: String,
+    pub scopes: Vec<ApiScope>,
+    /// The restaurants the key works at.
+    #[serde(default)]
+    pub restaurants: Vec<u32>,
+    pub created_at: DateTime<Utc>,
+    /// The staff member who created the key.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub created_by: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rotated_at: Option<DateTime<Utc>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub revoked_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct CreateApiKey {
+    pub name: String,
+    pub scopes: Vec<ApiScope>,
+}
+
+/// The key is only ever shown here, when it's created or rotated. Send it as `x-api-key`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct IssuedApiKey {
+    pub key: String,
+    pub api_key: ApiKeyDetails,
+}
+
+/// A section of the floor handed to a server from `starts_at` up to, but not including, `ends_at`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct SectionAssignment {
+    pub id: u32,
+    pub section: String,
+    pub server_id: u32,
+    pub starts_at: DateTime<Utc>,
+    pub ends_at: DateTime<Utc>,
+}
+
+/// The section has to have tables in it, and can't already have a server for any of the time.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct AssignSection {
+    pub section: String,
+    pub server_id: u32,
+    pub starts_at: DateTime<Utc>,
+    pub ends_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::IntoParams))]
+#[cfg_attr(feature = "openapi", into_params(parameter_in = Query))]
+pub struct ShiftQuery {
+    /// Only what's on shift at this time. Sections default to every assignment, and tables to now.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub at: Option<DateTime<Utc>>,
+}
+
+/// A table in one of a server's sections, with what's been ordered there.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct ServerTable {
+    pub table: TableDetails,
+    pub orders: Vec<OrderDetails>,
+    /// Until the last of the table's open orders is ready.
+    pub estimated_minutes_remaining: u32,
+}
+
+/// A change made to anything the api keeps, and who made it.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct AuditEntry {
+    pub id: u32,
+    pub time: DateTime<Utc>,
+    /// The staff member who made the change.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub staff_id: Option<u32>,
+    /// The api key the change was made with.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub api_key_id: Option<u32>,
+    /// The `x-request-id` of the request that made the change.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub request_id: Option<String>,
+    /// What was done, such as `order.remove`.
+    pub operation: String,
+    /// What was changed, as it was before. Left out for anything newly created.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub before: Option<serde_json::Value>,
+    /// What was changed, as it was after. Left out for anything removed.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub after: Option<serde_json::Value>,
+    /// The hash of the entry before this one in the restaurant's chain, or in the chain of what's shared between
+    /// restaurants, such as staff, in hex.
+    pub previous_hash: String,
+    /// A SHA-256 over this entry and `previous_hash`, in hex.
+    pub hash: String,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::IntoParams))]
+#[cfg_attr(feature = "openapi", into_params(parameter_in = Query))]
+pub struct AuditQuery {
+    /// Only changes made by this staff member.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub staff_id: Option<u32>,
+    /// Only changes made with this api key. Ignored if `staff_id` is given.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub api_key_id: Option<u32>,
+    /// Only this operation, such as `order.remove`, or every operation on a kind of thing, such as `order`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub operation: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub request_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub since: Option<DateTime<Utc>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub until: Option<DateTime<Utc>>,
+    /// Text to look for in what was changed, such as a menu item's name. Case doesn't matter.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub text: Option<String>,
+    /// The `next_cursor` of the previous page.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cursor: Option<u32>,
+    /// How many entries a page holds, up to 200. Defaults to 50.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub limit: Option<usize>,
+}
+
+/// A page of the changes that match, newest first.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct AuditPage {
+    pub entries: Vec<AuditEntry>,
+    /// Left out on the last page.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub next_cursor: Option<u32>,
+    #[serde(rename = "_links")]
+    pub links: PageLinks,
+}
+
+/// Whether the audit log is as it was written.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct AuditVerification {
+    /// Whether every entry matches its hash and chains on from the one before.
+    pub intact: bool,
+    /// The first entry that doesn't, if any.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tampered_entry_id: Option<u32>,
+}
@@ -0,0 +1,128 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+// what v2 sends and receives. unlike v1, nothing here is shaped after the domain's repository items:
+// related resources are referred to by id, and only what a caller can act on is included.
+
+pub use crate::v1::{
+    FieldError, Link, MenuItemLinks, OrderLinks, OrderQuery, OrderSort, OrderStatus, PageLinks,
+    SortDirection, Station, TableLinks, Transfer,
+};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct Table {
+    pub id: u32,
+    /// How many people the table sits.
+    pub seats: u32,
+    /// The part of the floor the table is in.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub section: Option<String>,
+    #[serde(rename = "_links")]
+    pub links: TableLinks,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct MenuItem {
+    pub id: u32,
+    pub name: String,
+    /// Minutes it takes to prepare one of the item.
+    pub cook_time_minutes: u32,
+    pub station: Station,
+    /// Unavailable items can't be ordered until they're back.
+    pub available: bool,
+    #[serde(rename = "_links")]
+    pub links: MenuItemLinks,
+}
+
+/// What was ordered, as it was on the menu when the order was placed.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct OrderedItem {
+    pub id: u32,
+    pub name: String,
+    pub station: Station,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct Order {
+    pub id: u32,
+    pub table_id: u32,
+    pub item: OrderedItem,
+    pub quantity: u32,
+    pub status: OrderStatus,
+    pub placed_at: DateTime<Utc>,
+    pub estimated_minutes_remaining: u32,
+    /// The group the order was placed with, if it was.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub group_id: Option<u32>,
+    #[serde(rename = "_links")]
+    pub links: OrderLinks,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct OrderPage {
+    pub orders: Vec<Order>,
+    /// Left out on the last page.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub next_cursor: Option<String>,
+    #[serde(rename = "_links")]
+    pub links: PageLinks,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct NewOrder {
+    pub item_id: u32,
+    pub quantity: u32,
+}
+
+/// Everything a table orders at once. Either every item is placed or none are.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct NewOrderGroup {
+    pub items: Vec<NewOrder>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct OrderGroup {
+    pub id: u32,
+    pub table_id: u32,
+    pub placed_at: DateTime<Utc>,
+    /// In the order the items were given.
+    pub orders: Vec<Order>,
+}
+
+/// Fields left out are left as they are.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct OrderPatch {
+    /// Must be at least one. Delete the order to cancel it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub quantity: Option<u32>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct TableStatus {
+    pub table_id: u32,
+    /// Whether every order for the table is ready.
+    pub ready: bool,
+    pub waiting_on: Vec<Station>,
+}
+
+/// An RFC 9457 problem, which is what every v2 error response carries.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct Problem {
+    pub title: String,
+    pub status: u16,
+    pub detail: String,
+    /// Every field of the request that was rejected, when that's why the request failed.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub errors: Vec<FieldError>,
+}
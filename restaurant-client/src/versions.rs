@@ -0,0 +1,17 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+// what `GET /api/versions` lists. it sits outside of any one version, so it isn't in either module.
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct ApiVersion {
+    pub version: String,
+    /// Whether this is the version requests without one get.
+    pub default: bool,
+    /// What to send in `Accept` to ask for this version.
+    pub media_type: String,
+    pub deprecated_at: Option<DateTime<Utc>>,
+    /// When the version stops being served.
+    pub sunset: Option<DateTime<Utc>>,
+}
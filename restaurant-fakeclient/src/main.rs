@@ -1,6 +1,3 @@
-use anyhow::anyhow;
-use chrono::{DateTime, Utc};
-use futures::TryFutureExt;
 use rand::{
     distributions::{
         uniform::{SampleRange, SampleUniform},
@@ -9,8 +6,10 @@ use rand::{
     seq::IteratorRandom,
     Rng,
 };
-use reqwest::{Client, Url};
-use serde::{Deserialize, Serialize};
+use restaurant_client::{
+    v1::{CreateOrderGroup, GroupItem, Order, SetOrderQuantity},
+    Client,
+};
 use tokio::task::JoinSet;
 
 #[tokio::main(worker_threads = 16)]
@@ -20,15 +19,29 @@ async fn main() {
         .unwrap_or("http://127.0.0.1:13982/".to_string());
     println!("Spamming traffic at: {}", root);
 
-    let client = reqwest::Client::new();
+    let client = Client::new(&root).expect("Invalid root url");
+    // an api key with `orders:write` is all it needs. without one, it signs in as a staff member that can
+    // both order and clear tables
+    let client = match std::env::var("RESTAURANT_API_KEY") {
+        Ok(key) => client.with_api_key(key),
+        Err(_) => {
+            let name = std::env::var("RESTAURANT_STAFF_NAME").unwrap_or("admin".to_string());
+            let secret = std::env::var("RESTAURANT_STAFF_SECRET").expect(
+                "RESTAURANT_API_KEY or RESTAURANT_STAFF_SECRET should be set to sign in with.",
+            );
+            let session = client
+                .login(&name, &secret)
+                .await
+                .expect("Signing in should succeed.");
+            client.with_token(session.token)
+        }
+    };
 
     let mut tasks: JoinSet<Result<(), anyhow::Error>> = JoinSet::new();
     (1..=100).for_each(|i| {
-        let root = root.clone();
         let client = client.clone();
         tasks.spawn(async move {
             let simulator = OrderSimulator {
-                root: Url::parse(&root).expect("Invalid root url"),
                 table: i,
                 client: &client,
             };
@@ -50,14 +63,13 @@ async fn main() {
 }
 
 struct OrderSimulator<'a> {
-    root: Url,
     table: u32,
     client: &'a Client,
 }
 
 impl<'a> OrderSimulator<'a> {
     async fn simulate_table(&self) -> Result<(), anyhow::Error> {
-        let orders = self.get_orders().await?;
+        let orders = self.client.table_orders(self.table).await?;
         assert!(
             orders.is_empty(),
             "Orders found for what should be a clear table {}",
@@ -65,16 +77,16 @@ impl<'a> OrderSimulator<'a> {
         );
 
         let order_count = self.gen_range(5..=10);
-        let mut orders: Vec<u32> = Vec::with_capacity(order_count);
-        for _ in 0..order_count {
-            let order = CreateOrder {
-                table_id: self.table,
-                item_id: self.gen::<Menu>() as u32,
-                quantity: self.gen_range(1..=10),
-            };
-
-            orders.push(self.create_order(order).await?.id);
-        }
+        let group = CreateOrderGroup {
+            table_id: self.table,
+            items: (0..order_count)
+                .map(|_| GroupItem {
+                    item_id: self.gen::<Menu>() as u32,
+                    quantity: self.gen_range(1..=10),
+                })
+                .collect(),
+        };
+        let mut orders: Vec<Order> = self.client.create_order_group(&group).await?.orders;
         assert_eq!(
             orders.len(),
             order_count,
@@ -83,31 +95,43 @@ impl<'a> OrderSimulator<'a> {
             orders.len()
         );
 
+        // from here on, orders are changed through the links they come with
         for _ in 0..self.gen_range(1..=5) {
-            let order = self
-                .choose(orders.iter())
+            let index = self
+                .choose(0..orders.len())
                 .expect("Somehow didnt choose a random order.");
+            let link = orders[index]
+                .links
+                .set_quantity
+                .clone()
+                .expect("Orders that haven't been made yet should be changeable.");
             let quantity = self.gen_range(1..=20);
-            let result = self.set_quantity(*order, quantity).await?;
+            let result: Order = self
+                .client
+                .follow(&link, Some(&SetOrderQuantity { quantity }))
+                .await?;
             assert_eq!(
                 quantity, result.quantity,
                 "Set quantity to {}, got {}",
                 quantity, result.quantity
             );
 
-            if result.quantity == 0 {
-                orders.retain(|id| *id != result.id);
-            }
+            orders[index] = result;
         }
 
         for _ in 0..self.gen_range(1..=7) {
-            if let Some(order) = self.choose(orders.iter()) {
-                let result = self.delete_order(*order).await?;
-                orders.retain(|id| *id != result.id);
+            if let Some(index) = self.choose(0..orders.len()) {
+                let link = orders[index]
+                    .links
+                    .cancel
+                    .clone()
+                    .expect("Open orders should be cancellable.");
+                let result: Order = self.client.follow(&link, None::<&()>).await?;
+                orders.retain(|o| o.id != result.id);
             }
         }
 
-        let final_orders = self.clear_table().await?;
+        let final_orders = self.client.clear_table(self.table).await?;
         assert_eq!(
             orders.len(),
             final_orders.len(),
@@ -116,7 +140,7 @@ impl<'a> OrderSimulator<'a> {
             final_orders.len()
         );
 
-        let orders = self.get_orders().await?;
+        let orders = self.client.table_orders(self.table).await?;
         assert_eq!(
             0,
             orders.len(),
@@ -127,79 +151,6 @@ impl<'a> OrderSimulator<'a> {
         Ok(())
     }
 
-    async fn get_orders(&self) -> Result<Vec<OrderDetails>, anyhow::Error> {
-        self.client
-            .get(
-                self.root
-                    .join(format!("/api/table/{}/orders", self.table).as_str())
-                    .unwrap(),
-            )
-            .header("x-api-version", "v1")
-            .send()
-            .and_then(|r| async { r.error_for_status() })
-            .and_then(|r| r.json())
-            .map_err(|e| anyhow!(e))
-            .await
-    }
-
-    async fn create_order(&self, order: CreateOrder) -> Result<OrderDetails, anyhow::Error> {
-        self.client
-            .post(self.root.join("/api/orders").unwrap())
-            .header("x-api-version", "v1")
-            .json(&order)
-            .send()
-            .and_then(|r| async { r.error_for_status() })
-            .and_then(|r| r.json())
-            .map_err(|e| anyhow!(e))
-            .await
-    }
-
-    async fn set_quantity(&self, id: u32, quantity: u32) -> Result<OrderDetails, anyhow::Error> {
-        self.client
-            .post(
-                self.root
-                    .join(format!("/api/orders/{}/setquantity", id).as_str())
-                    .unwrap(),
-            )
-            .header("x-api-version", "v1")
-            .json(&SetOrderQuantity { quantity })
-            .send()
-            .and_then(|r| async { r.error_for_status() })
-            .and_then(|r| r.json())
-            .map_err(|e| anyhow!(e))
-            .await
-    }
-
-    async fn delete_order(&self, id: u32) -> Result<OrderDetails, anyhow::Error> {
-        self.client
-            .delete(
-                self.root
-                    .join(format!("/api/orders/{}", id).as_str())
-                    .unwrap(),
-            )
-            .header("x-api-version", "v1")
-            .send()
-            .and_then(|r| async { r.error_for_status() })
-            .and_then(|r| r.json())
-            .map_err(|e| anyhow!(e))
-            .await
-    }
-
-    async fn clear_table(&self) -> Result<Vec<OrderDetails>, anyhow::Error> {
-        self.client
-            .post(
-                self.root
-                    .join(format!("/api/table/{}/clear", self.table).as_str())
-                    .unwrap(),
-            )
-            .header("x-api-version", "v1")
-            .send()
-            .and_then(|r| async { r.error_for_status() })
-            .and_then(|r| r.json())
-            .map_err(|e| anyhow!(e))
-            .await
-    }
-
     // these are reimplemented here because Rng is not Send, which poses a problem considering all the awaiting being done
     fn gen_range<T: SampleUniform, R: SampleRange<T>>(&self, range: R) -> T {
         rand::thread_rng().gen_range(range)
@@ -215,57 +166,23 @@ impl<'a> OrderSimulator<'a> {
     }
 }
 
-#[derive(Debug, Deserialize)]
-#[allow(dead_code)]
-struct OrderDetails {
-    id: u32,
-    table: TableDetails,
-    menu_item: MenuItemDetails,
-    time_placed: DateTime<Utc>,
-    quantity: u32,
-    estimated_minutes_remaining: Option<u32>,
-}
-
-#[derive(Debug, Deserialize)]
-#[allow(dead_code)]
-struct TableDetails {
-    id: u32,
-}
-
-#[derive(Debug, Deserialize)]
-#[allow(dead_code)]
-struct MenuItemDetails {
-    id: u32,
-    name: String,
-    cook_time: u32,
-}
-
-#[derive(Debug, Serialize)]
-struct CreateOrder {
-    table_id: u32,
-    item_id: u32,
-    quantity: u32,
-}
-#[derive(Debug, Serialize)]
-struct SetOrderQuantity {
-    quantity: u32,
-}
-
 #[derive(Copy, Clone)]
 enum Menu {
     Pasta = 1,
     Sandwich = 2,
     味噌カツ丼 = 3,
     和風パフェ = 4,
+    抹茶ラテ = 5,
 }
 
 impl Distribution<Menu> for Standard {
     fn sample<R: Rng + ?Sized>(&self, rng: &mut R) -> Menu {
-        match rng.gen_range(1usize..=4) {
+        match rng.gen_range(1usize..=5) {
             1 => Menu::Pasta,
             2 => Menu::Sandwich,
             3 => Menu::味噌カツ丼,
             4 => Menu::和風パフェ,
+            5 => Menu::抹茶ラテ,
             _ => panic!("Somehow hit a weird number"),
         }
     }
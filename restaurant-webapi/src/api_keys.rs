@@ -0,0 +1,73 @@
+use std::{
+    future::Future,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use axum::{
+    extract::Request,
+    http::StatusCode,
+    response::{IntoResponse, Response},
+};
+use restaurant::{api_key, memdb::Database};
+use restaurant_client::API_KEY_HEADER;
+use tower::{Layer, Service};
+
+// checks the `x-api-key` of any request that has one, and hands the key on to the routes in the request's
+// extensions. a bad key is turned away here, before it reaches any route. whether a good one has the scope a
+// route needs is up to the route's `auth::Access`.
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ApiKeyLayer;
+
+impl<S> Layer<S> for ApiKeyLayer {
+    type Service = ApiKeyService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        ApiKeyService { inner }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ApiKeyService<S> {
+    inner: S,
+}
+
+impl<S> Service<Request> for ApiKeyService<S>
+where
+    S: Service<Request, Response = Response> + Clone + Send + 'static,
+    S::Future: Send,
+{
+    type Response = Response;
+    type Error = S::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Response, S::Error>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, mut request: Request) -> Self::Future {
+        // the clone hasn't been polled ready, so it's swapped for the one that has
+        let clone = self.inner.clone();
+        let mut inner = std::mem::replace(&mut self.inner, clone);
+
+        Box::pin(async move {
+            let Some(key) = request.headers().get(API_KEY_HEADER) else {
+                return inner.call(request).await;
+            };
+            let db = request
+                .extensions()
+                .get::<Database>()
+                .cloned()
+                .expect("The database should be added before the api key layer.");
+
+            match api_key::authenticate(&db, key.to_str().unwrap_or_default()).await {
+                Ok(key) => {
+                    request.extensions_mut().insert(key);
+                    inner.call(request).await
+                }
+                Err(e) => Ok((StatusCode::UNAUTHORIZED, e.to_string()).into_response()),
+            }
+        })
+    }
+}
@@ -0,0 +1,251 @@
+use std::sync::Arc;
+
+use axum::{
+    extract::{Request, State},
+    http::{header, Method, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+    Extension,
+};
+use chrono::{DateTime, TimeDelta, Utc};
+use hmac::{Hmac, Mac};
+use rand::RngCore;
+use restaurant::{
+    api_key,
+    memdb::Database,
+    staff,
+    tenant::{self, Scoped},
+};
+use sha2::Sha256;
+
+use crate::idempotency;
+
+// staff sign in with their name and PIN, and get back a token signed with the server's key. the token
+// only says who they are and until when. their role is looked up on every request, so dismissing someone
+// or changing what they do takes effect straight away rather than when their token runs out.
+
+#[derive(Clone)]
+pub struct Auth {
+    key: Arc<[u8]>,
+    ttl: TimeDelta,
+}
+
+impl Auth {
+    pub fn new(key: &[u8], ttl: TimeDelta) -> Auth {
+        Auth {
+            key: key.into(),
+            ttl,
+        }
+    }
+
+    // tokens signed with a random key stop working when the server restarts
+    pub fn random() -> Auth {
+        let mut key = [0u8; 32];
+        rand::thread_rng().fill_bytes(&mut key);
+        Auth::new(&key, TimeDelta::hours(12))
+    }
+
+    // tokens are "{staff id}.{expiry as a unix timestamp}.{hex of the HMAC-SHA256 of the first two}"
+    pub fn issue(&self, staff: staff::Id) -> (String, DateTime<Utc>) {
+        let expires = Utc::now() + self.ttl;
+        let claims = format!("{}.{}", staff.0, expires.timestamp());
+        let signature = hex::encode(self.mac(&claims).finalize().into_bytes());
+        (format!("{}.{}", claims, signature), expires)
+    }
+
+    fn verify(&self, token: &str) -> Option<staff::Id> {
+        let (claims, signature) = token.rsplit_once('.')?;
+        self.mac(claims)
+            .verify_slice(&hex::decode(signature).ok()?)
+            .ok()?;
+
+        let (staff, expires) = claims.split_once('.')?;
+        let expires = DateTime::from_timestamp(expires.parse().ok()?, 0)?;
+        match expires > Utc::now() {
+            true => Some(staff::Id(staff.parse().ok()?)),
+            false => None,
+        }
+    }
+
+    fn mac(&self, claims: &str) -> Hmac<Sha256> {
+        let mut mac =
+            Hmac::<Sha256>::new_from_slice(&self.key).expect("HMAC can take keys of any size.");
+        mac.update(claims.as_bytes());
+        mac
+    }
+}
+
+// who a request was let through for
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Principal {
+    Staff(staff::Id),
+    Key(api_key::Id),
+}
+
+// who a group of routes is open to. reading goes by `read` and anything else by `write`, and
+// either being empty means anyone who's signed in
+#[derive(Debug, Clone, Copy)]
+pub struct Access {
+    read: &'static [staff::Role],
+    write: &'static [staff::Role],
+    // the scopes an api key needs to read and to write. without them, the routes are for staff only
+    scopes: Option<(api_key::Scope, api_key::Scope)>,
+    // whether the token can come as an `access_token` query parameter
+    query_token: bool,
+}
+
+impl Access {
+    pub const ANYONE: Access = Access {
+        read: &[],
+        write: &[],
+        scopes: None,
+        query_token: false,
+    };
+
+    // anyone signed in can look, but only these roles can change anything
+    pub const fn changed_by(roles: &'static [staff::Role]) -> Access {
+        Access {
+            read: &[],
+            write: roles,
+            scopes: None,
+            query_token: false,
+        }
+    }
+
+    pub const fn only(roles: &'static [staff::Role]) -> Access {
+        Access {
+            read: roles,
+            write: roles,
+            scopes: None,
+            query_token: false,
+        }
+    }
+
+    // also lets in api keys with these scopes
+    pub const fn or_key(self, read: api_key::Scope, write: api_key::Scope) -> Access {
+        Access {
+            scopes: Some((read, write)),
+            ..self
+        }
+    }
+
+    // also takes the token from an `access_token` query parameter, for the event feeds, since neither
+    // EventSource nor browser WebSockets can set headers. anywhere else it would only end up in logs
+    pub const fn or_query_token(self) -> Access {
+        Access {
+            query_token: true,
+            ..self
+        }
+    }
+
+    fn reading(method: &Method) -> bool {
+        matches!(*method, Method::GET | Method::HEAD)
+    }
+
+    fn allows(&self, method: &Method, role: staff::Role) -> bool {
+        let needed = match Access::reading(method) {
+            true => self.read,
+            false => self.write,
+        };
+        needed.is_empty() || needed.iter().any(|r| role.covers(*r))
+    }
+
+    fn scope(&self, method: &Method) -> Option<api_key::Scope> {
+        self.scopes
+            .map(|(read, write)| match Access::reading(method) {
+                true => read,
+                false => write,
+            })
+    }
+}
+
+// the token comes from `Authorization: Bearer`, or an `access_token` query parameter where the routes
+// allow it
+fn token(request: &Request, query: bool) -> Option<&str> {
+    if let Some(value) = request.headers().get(header::AUTHORIZATION) {
+        return value.to_str().ok()?.strip_prefix("Bearer ");
+    }
+    if !query {
+        return None;
+    }
+    request
+        .uri()
+        .query()?
+        .split('&')
+        .find_map(|pair| pair.strip_prefix("access_token="))
+}
+
+fn unauthorized(message: &'static str) -> Response {
+    (
+        StatusCode::UNAUTHORIZED,
+        [(header::WWW_AUTHENTICATE, "Bearer")],
+        message,
+    )
+        .into_response()
+}
+
+fn elsewhere(tenant: tenant::Id) -> Response {
+    (
+        StatusCode::FORBIDDEN,
+        format!(
+            "The credentials aren't for restaurant {}.",
+            u32::from(tenant)
+        ),
+    )
+        .into_response()
+}
+
+// lets the request through if it's from someone the routes are open to, at the restaurant `tenant::select`
+// picked. the handlers then get a database that puts whatever they change down to that person. repeats are
+// only answered from `idempotency` after this, so they're subject to the same checks as the first request
+pub async fn authorize(
+    State(access): State<Access>,
+    Extension(auth): Extension<Auth>,
+    Extension(db): Extension<Database>,
+    mut request: Request,
+    next: Next,
+) -> Response {
+    // `ApiKeyLayer` has already checked the key itself. changes made with one aren't put down to any staff
+    // member, only to the key in the audit log
+    if let Some(key) = request.extensions().get::<api_key::RepoApiKey>().cloned() {
+        if !key.works_at(db.tenant()) {
+            return elsewhere(db.tenant());
+        }
+        return match access.scope(request.method()) {
+            Some(scope) if key.allows(scope) => {
+                request.extensions_mut().insert(db.using_key(key.id()));
+                idempotency::guard(Principal::Key(key.id()), request, next).await
+            }
+            Some(scope) => (
+                StatusCode::FORBIDDEN,
+                format!("The api key doesn't have the {} scope.", scope),
+            )
+                .into_response(),
+            None => (StatusCode::FORBIDDEN, "Api keys can't be used here.").into_response(),
+        };
+    }
+
+    let Some(id) = token(&request, access.query_token).and_then(|t| auth.verify(t)) else {
+        return unauthorized("Sign in first, and send the token as `Authorization: Bearer`.");
+    };
+    let staff = match staff::get(&db, id).await {
+        Ok(staff) if staff.active => staff,
+        _ => return unauthorized("The token's staff member can't sign in anymore."),
+    };
+    if !staff.works_at(db.tenant()) {
+        return elsewhere(db.tenant());
+    }
+    if !access.allows(request.method(), staff.role) {
+        return (
+            StatusCode::FORBIDDEN,
+            format!(
+                "{} is signed in as {:?}, which can't do this.",
+                staff.name, staff.role
+            ),
+        )
+            .into_response();
+    }
+
+    request.extensions_mut().insert(db.acting_as(id));
+    idempotency::guard(Principal::Staff(id), request, next).await
+}
@@ -0,0 +1,268 @@
+use std::convert::Infallible;
+
+use axum::{
+    extract::{
+        ws::{Message, WebSocket, WebSocketUpgrade},
+        Query,
+    },
+    http::{HeaderMap, StatusCode},
+    response::{
+        sse::{self, KeepAlive, Sse},
+        IntoResponse,
+    },
+    routing::get,
+    Extension, Router,
+};
+use futures::Stream;
+use restaurant::{
+    layout, menu,
+    order::{self, EventFilter, EventId},
+    shift,
+    staff::{self, Acting},
+    tenant::{self, Scoped},
+};
+use serde::Deserialize;
+use tokio::sync::{broadcast, mpsc};
+use tokio_stream::{wrappers::ReceiverStream, StreamExt};
+
+use crate::Database;
+
+// these live outside of the versioned api because neither EventSource nor browser WebSockets can set headers
+pub fn create() -> Router {
+    Router::new()
+        .route("/events", get(events_sse))
+        .route("/events/ws", get(events_ws))
+        .route("/notifications", get(notifications_sse))
+}
+
+#[derive(Clone)]
+pub struct Feed {
+    db: Database,
+    live: broadcast::Sender<order::RepoEvent>,
+    // orders that are ready, along with the server to tell
+    ready: broadcast::Sender<(staff::Id, order::RepoEvent)>,
+}
+
+impl Feed {
+    pub fn new(db: Database) -> Feed {
+        let (live, _) = broadcast::channel(1024);
+        let (ready, _) = broadcast::channel(1024);
+        Feed { db, live, ready }
+    }
+
+    // fans an event out to live subscribers, whichever restaurant it's from. the outbox relay is what calls this
+    pub fn publish(&self, event: order::RepoEvent) {
+        // no subscribers isn't an error worth knowing about
+        _ = self.live.send(event);
+    }
+
+    // how many are following the feed or waiting on notices
+    pub fn listeners(&self) -> usize {
+        self.live.receiver_count() + self.ready.receiver_count()
+    }
+
+    // tells the server of the order's table that it's ready. the event has been published either way,
+    // so failing to work out who that is only costs the notice
+    pub async fn notify(&self, event: &order::RepoEvent) {
+        // sections are handed out per restaurant
+        let Some(db) = self.db.for_tenant(event.tenant) else {
+            return;
+        };
+        match shift::to_notify(&db, event).await {
+            Ok(Some(server)) => _ = self.ready.send((server, event.clone())),
+            Ok(None) => {}
+            Err(err) => println!(
+                "Error finding who to notify about event {:?}: {:?}",
+                event.id(),
+                err
+            ),
+        }
+    }
+
+    // notices are only for whoever's listening at the time. there's no catching up on ones that were missed,
+    // so a `Last-Event-ID` is ignored, and the tables themselves are where to look for that. staff can work
+    // at more than one restaurant, so these are only the ones at the restaurant they're listening from
+    fn notices(&self, server: staff::Id, at: tenant::Id) -> mpsc::Receiver<order::RepoEvent> {
+        let (sender, receiver) = mpsc::channel(64);
+        let mut ready = self.ready.subscribe();
+
+        tokio::spawn(async move {
+            loop {
+                // a server might not have another order ready for a long while after they've gone
+                let received = tokio::select! {
+                    received = ready.recv() => received,
+                    _ = sender.closed() => return,
+                };
+                match received {
+                    Ok((to, event)) => {
+                        if to == server && event.tenant == at && sender.send(event).await.is_err() {
+                            return;
+                        }
+                    }
+                    Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                    Err(broadcast::error::RecvError::Closed) => return,
+                }
+            }
+        });
+
+        receiver
+    }
+
+    // replays everything after `after` and then follows live events, without gaps or duplicates.
+    // only the events of the restaurant `db` is for, since event ids are only ordered within one
+    fn subscribe(
+        &self,
+        db: Database,
+        after: Option<EventId>,
+        filter: EventFilter,
+    ) -> mpsc::Receiver<order::RepoEvent> {
+        let (sender, receiver) = mpsc::channel(64);
+        // subscribing before reading the log means anything missing from the log shows up live
+        let mut live = self.live.subscribe();
+
+        tokio::spawn(async move {
+            let mut last = after;
+            loop {
+                // catching up from the log is also how we recover from falling behind the live feed
+                let Ok(backlog) = order::events(&db, last, &filter).await else {
+                    return;
+                };
+                for event in backlog {
+                    last = Some(event.id());
+                    if sender.send(event).await.is_err() {
+                        return;
+                    }
+                }
+
+                loop {
+                    // a filtered feed might never have anything to send again, so it can't wait until then
+                    // to find out the client's gone
+                    let received = tokio::select! {
+                        received = live.recv() => received,
+                        _ = sender.closed() => return,
+                    };
+                    match received {
+                        Ok(event) => {
+                            if event.tenant != db.tenant()
+                                || last.is_some_and(|last| event.id() <= last)
+                            {
+                                continue;
+                            }
+                            last = Some(event.id());
+                            if filter.matches(&event) && sender.send(event).await.is_err() {
+                                return;
+                            }
+                        }
+                        Err(broadcast::error::RecvError::Lagged(_)) => break,
+                        Err(broadcast::error::RecvError::Closed) => return,
+                    }
+                }
+            }
+        });
+
+        receiver
+    }
+}
+
+// not flattening an EventFilter in here, since flatten doesn't play well with query strings and numbers
+#[derive(Debug, Deserialize)]
+struct FeedQuery {
+    after: Option<EventId>,
+    table: Option<layout::TableId>,
+    station: Option<menu::Station>,
+    status: Option<order::Status>,
+}
+
+impl FeedQuery {
+    fn filter(&self) -> EventFilter {
+        EventFilter {
+            table: self.table,
+            station: self.station,
+            status: self.status,
+        }
+    }
+}
+
+async fn events_sse(
+    Extension(feed): Extension<Feed>,
+    Extension(db): Extension<Database>,
+    headers: HeaderMap,
+    Query(query): Query<FeedQuery>,
+) -> Sse<impl Stream<Item = Result<sse::Event, Infallible>>> {
+    // EventSource sends this on its own when it reconnects, so it takes precedence
+    let after = headers
+        .get("last-event-id")
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.parse::<u32>().ok())
+        .map(EventId)
+        .or(query.after);
+
+    let events = ReceiverStream::new(feed.subscribe(db, after, query.filter())).map(|event| {
+        let name = match event.change {
+            order::Change::Placed => "placed",
+            order::Change::Updated => "updated",
+            order::Change::Removed => "removed",
+            order::Change::Transferred => "transferred",
+        };
+        Ok(sse::Event::default()
+            .id(u32::from(event.id()).to_string())
+            .event(name)
+            .json_data(&event)
+            .expect("Order events should be serializable."))
+    });
+
+    Sse::new(events).keep_alive(KeepAlive::default())
+}
+
+// orders that are ready at the signed-in server's tables
+async fn notifications_sse(
+    Extension(feed): Extension<Feed>,
+    Extension(db): Extension<Database>,
+) -> Result<Sse<impl Stream<Item = Result<sse::Event, Infallible>>>, (StatusCode, String)> {
+    let Some(me) = db.actor() else {
+        return Err((
+            StatusCode::FORBIDDEN,
+            "Notifications are for signed-in staff.".to_string(),
+        ));
+    };
+
+    let notices = ReceiverStream::new(feed.notices(me, db.tenant())).map(|event| {
+        Ok(sse::Event::default()
+            .id(u32::from(event.id()).to_string())
+            .event("ready")
+            .json_data(&event)
+            .expect("Order events should be serializable."))
+    });
+
+    Ok(Sse::new(notices).keep_alive(KeepAlive::default()))
+}
+
+async fn events_ws(
+    Extension(feed): Extension<Feed>,
+    Extension(db): Extension<Database>,
+    Query(query): Query<FeedQuery>,
+    ws: WebSocketUpgrade,
+) -> impl IntoResponse {
+    let events = feed.subscribe(db, query.after, query.filter());
+    ws.on_upgrade(move |socket| forward_events(socket, events))
+}
+
+async fn forward_events(mut socket: WebSocket, mut events: mpsc::Receiver<order::RepoEvent>) {
+    loop {
+        tokio::select! {
+            event = events.recv() => {
+                let Some(event) = event else { return };
+                let text = serde_json::to_string(&event).expect("Order events should be serializable.");
+                if socket.send(Message::Text(text)).await.is_err() {
+                    return;
+                }
+            }
+            // clients only ever talk to us to go away, but we still need to notice when they do
+            message = socket.recv() => {
+                if !matches!(message, Some(Ok(Message::Ping(_) | Message::Pong(_) | Message::Text(_) | Message::Binary(_)))) {
+                    return;
+                }
+            }
+        }
+    }
+}
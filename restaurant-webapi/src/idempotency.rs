@@ -0,0 +1,194 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use axum::{
+    body::{Body, Bytes},
+    extract::Request,
+    http::{header, HeaderMap, Method, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use restaurant::tenant::{self, Scoped};
+use restaurant_client::IDEMPOTENCY_KEY_HEADER;
+
+use crate::{auth::Principal, Database};
+
+// a request sent again with the same Idempotency-Key gets the first one's response instead of
+// being applied twice, so clients can safely retry a POST or DELETE that timed out. it's only
+// checked once `auth::authorize` has let the request through, so a key never replays a response
+// to someone who couldn't have made the request themselves.
+
+// for responses carrying a credential, such as a new api key or a webhook's signing secret. they're only ever
+// shown the once, so nothing along the way, this cache included, should keep them
+pub const NO_STORE: [(header::HeaderName, &str); 1] = [(header::CACHE_CONTROL, "no-store")];
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct Key {
+    key: String,
+    method: Method,
+    path: String,
+    // the same path means something else at another restaurant
+    tenant: Option<tenant::Id>,
+    // and one person's key says nothing about anyone else's
+    principal: Principal,
+}
+
+enum Entry {
+    InFlight,
+    Done {
+        status: StatusCode,
+        headers: HeaderMap,
+        body: Bytes,
+    },
+    // the request went through, but its response was marked `no-store`, so there's nothing to replay.
+    // running it again would do it twice, such as issuing a second api key
+    Spent,
+}
+
+#[derive(Clone)]
+pub struct Idempotency {
+    entries: Arc<Mutex<HashMap<Key, (Instant, Entry)>>>,
+    keep_for: Duration,
+}
+
+impl Default for Idempotency {
+    fn default() -> Self {
+        Idempotency::new(Duration::from_secs(60 * 60))
+    }
+}
+
+impl Idempotency {
+    pub fn new(keep_for: Duration) -> Idempotency {
+        Idempotency {
+            entries: Default::default(),
+            keep_for,
+        }
+    }
+
+    // the response to a repeat, or what to finish once the request's been handled
+    fn start(&self, key: Key) -> Result<InFlight, Response> {
+        let mut entries = self.entries.lock().unwrap();
+        entries.retain(|_, (at, _)| at.elapsed() < self.keep_for);
+
+        match entries.get(&key) {
+            Some((_, Entry::InFlight)) => Err((
+                StatusCode::CONFLICT,
+                "A request with this idempotency key is still being processed.",
+            )
+                .into_response()),
+            Some((_, Entry::Spent)) => Err((
+                StatusCode::CONFLICT,
+                "A request with this idempotency key already went through, and its response can't be sent again.",
+            )
+                .into_response()),
+            Some((
+                _,
+                Entry::Done {
+                    status,
+                    headers,
+                    body,
+                },
+            )) => {
+                let mut response = Response::new(Body::from(body.clone()));
+                *response.status_mut() = *status;
+                *response.headers_mut() = headers.clone();
+                Err(response)
+            }
+            None => {
+                entries.insert(key.clone(), (Instant::now(), Entry::InFlight));
+                Ok(InFlight {
+                    idempotency: self.clone(),
+                    key: Some(key),
+                })
+            }
+        }
+    }
+}
+
+// a request being handled. unless it's finished, the key is let go of when this is dropped, so a request that
+// panicked or was given up on partway, such as when the client went away, can be retried rather than being
+// stuck as still in flight
+struct InFlight {
+    idempotency: Idempotency,
+    key: Option<Key>,
+}
+
+impl InFlight {
+    fn finish(mut self, entry: Entry) {
+        if let Some(key) = self.key.take() {
+            let mut entries = self.idempotency.entries.lock().unwrap();
+            entries.insert(key, (Instant::now(), entry));
+        }
+    }
+}
+
+impl Drop for InFlight {
+    fn drop(&mut self) {
+        if let Some(key) = self.key.take() {
+            if let Ok(mut entries) = self.idempotency.entries.lock() {
+                entries.remove(&key);
+            }
+        }
+    }
+}
+
+// runs the request for `principal`, unless it's a repeat. routes without an `Idempotency` in their
+// extensions, such as the event feeds, are always just run
+pub async fn guard(principal: Principal, request: Request, next: Next) -> Response {
+    let Some(idempotency) = request.extensions().get::<Idempotency>().cloned() else {
+        return next.run(request).await;
+    };
+    let key = match request
+        .headers()
+        .get(IDEMPOTENCY_KEY_HEADER)
+        .and_then(|k| k.to_str().ok())
+    {
+        Some(key) if request.method() != Method::GET => Key {
+            key: key.to_string(),
+            method: request.method().clone(),
+            path: request.uri().path().to_string(),
+            tenant: request.extensions().get::<Database>().map(|db| db.tenant()),
+            principal,
+        },
+        _ => return next.run(request).await,
+    };
+
+    let in_flight = match idempotency.start(key) {
+        Ok(in_flight) => in_flight,
+        Err(response) => return response,
+    };
+
+    let (parts, body) = next.run(request).await.into_parts();
+    let body = match axum::body::to_bytes(body, usize::MAX).await {
+        Ok(body) => body,
+        Err(e) => {
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("Failed to read response: {:?}", e),
+            )
+                .into_response();
+        }
+    };
+
+    // server errors aren't remembered, so a retry gets another go at it. anything the handler said not
+    // to store, such as a newly issued api key, shouldn't outlive the response, so only that it happened is
+    let no_store = parts
+        .headers
+        .get(header::CACHE_CONTROL)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|v| v.contains("no-store"));
+    if no_store {
+        in_flight.finish(Entry::Spent);
+    } else if !parts.status.is_server_error() {
+        in_flight.finish(Entry::Done {
+            status: parts.status,
+            headers: parts.headers.clone(),
+            body: body.clone(),
+        });
+    }
+
+    Response::from_parts(parts, Body::from(body))
+}
@@ -0,0 +1,10 @@
+use axum::{response::Html, routing::get, Router};
+
+// a static page that drives itself off of the v1 api, so there's nothing to install in the kitchen
+pub fn create() -> Router {
+    Router::new().route("/kds", get(kds_get))
+}
+
+async fn kds_get() -> Html<&'static str> {
+    Html(include_str!("kds.html"))
+}
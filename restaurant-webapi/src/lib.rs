@@ -0,0 +1,70 @@
+use std::sync::Arc;
+
+use axum::{extract::Request, middleware, routing::get, Extension, Router};
+use restaurant::{api_key::Scope, memdb::Database, order, waitlist};
+use tower::ServiceBuilder;
+
+pub mod api_keys;
+pub mod auth;
+pub mod feed;
+pub mod idempotency;
+pub mod kds;
+pub mod relay;
+pub mod request_id;
+pub mod tenant;
+pub mod ver;
+pub mod webhooks;
+
+pub fn app(
+    db: Database,
+    feed: feed::Feed,
+    webhooks: webhooks::Webhooks,
+    versions: ver::Versions,
+    rules: order::Rules,
+    auth: auth::Auth,
+) -> Router {
+    let apis = Arc::new(ver::create_services());
+    let dispatcher = versions.clone();
+    let restaurant = Router::new()
+        .route("/api/versions", get(ver::versions_get))
+        // anything else is for one of the versioned apis, which route it from the top themselves
+        .fallback(move |request: Request| {
+            let (dispatcher, apis) = (dispatcher.clone(), apis.clone());
+            async move { dispatcher.dispatch(apis, request).await }
+        })
+        .layer(
+            ServiceBuilder::new()
+                .layer(Extension(db.clone()))
+                .layer(Extension(auth.clone()))
+                .layer(Extension(webhooks))
+                .layer(Extension(versions))
+                .layer(Extension(rules))
+                .layer(Extension(waitlist::Rules::default()))
+                .layer(Extension(idempotency::Idempotency::default()))
+                .layer(middleware::from_fn(tenant::select))
+                .layer(middleware::from_fn(request_id::layer))
+                .layer(api_keys::ApiKeyLayer),
+        )
+        .merge(
+            feed::create()
+                .route_layer(middleware::from_fn_with_state(
+                    auth::Access::ANYONE
+                        .or_key(Scope::OrdersRead, Scope::OrdersRead)
+                        .or_query_token(),
+                    auth::authorize,
+                ))
+                .layer(middleware::from_fn(tenant::select))
+                .layer(api_keys::ApiKeyLayer)
+                .layer(Extension(feed))
+                .layer(Extension(auth))
+                .layer(Extension(db)),
+        )
+        .merge(kds::create());
+
+    // routing happens inside of layers added to the router itself, so the prefix has to come off out here
+    Router::new().fallback_service(
+        ServiceBuilder::new()
+            .layer(middleware::from_fn(tenant::unprefix))
+            .service(restaurant),
+    )
+}
@@ -1,42 +1,73 @@
-use std::time::Duration;
+use std::{path::PathBuf, time::Duration};
 
-use axum::{
-    body::Body, extract::Request, http::StatusCode, response::Response, routing::any, Extension,
-    Router,
+use chrono::TimeDelta;
+use rand::RngCore;
+use restaurant::{
+    layout,
+    memdb::Database,
+    menu,
+    order::{self, Repository},
+    staff, tenant,
+};
+use restaurant_webapi::{
+    auth::Auth,
+    feed::Feed,
+    relay::Relay,
+    ver::Versions,
+    webhooks::{RetryPolicy, Webhooks},
 };
-use restaurant::{layout, memdb::Database, menu, order::Repository};
 use tokio::join;
-use tower::{Service, ServiceBuilder};
-
-mod ver;
 
 #[tokio::main]
 async fn main() {
-    let db = create_database();
+    let mut db = create_database().await;
+    let admin_secret = std::env::var("RESTAURANT_ADMIN_SECRET").unwrap_or_else(|_| {
+        let secret = random_hex();
+        println!("Sign in as 'admin' with: {}", secret);
+        secret
+    });
+    staff::hire(&mut db, "admin", staff::Role::Admin, &admin_secret)
+        .await
+        .expect("The admin's secret should be at least four characters.");
     //gets moved before we use it for status updates, so cloning ahead of time
     let status_db = db.clone();
+    let feed = Feed::new(db.clone());
+    let webhooks = Webhooks::open(
+        Some(
+            std::env::var("RESTAURANT_WEBHOOKS_PATH")
+                .map(PathBuf::from)
+                .unwrap_or(PathBuf::from("webhooks.json")),
+        ),
+        RetryPolicy::default(),
+    )
+    .expect("Webhook queue should be readable.");
 
-    // purposely putting this in main so that it can be moved to the below closure
-    let mut versioned_apis = ver::create_services();
-    let app = Router::new().route(
-        "/api/*path",
-        any(|request: Request| async move {
-            if let Some(router) = request
-                .headers()
-                .get("x-api-version")
-                .and_then(|v| v.to_str().ok())
-                .and_then(|v| versioned_apis.get_mut(v))
-            {
-                router.call(request).await
-            } else {
-                Ok(Response::builder()
-                    .status(StatusCode::NOT_FOUND)
-                    .body(Body::from("Set 'x-api-version' header."))
-                    .expect("Build response should be well-formed."))
-            }
-        })
-        .layer(ServiceBuilder::new().layer(Extension(db))),
-    );
+    let relay = Relay::new(feed.clone(), webhooks.clone());
+    let relay_db = db.clone();
+
+    let versions = match std::env::var("RESTAURANT_DEFAULT_API_VERSION") {
+        Ok(version) => Versions::default()
+            .with_default(&version)
+            .expect("Default api version should be one that's served."),
+        Err(_) => Versions::default(),
+    };
+
+    let rules = match std::env::var("RESTAURANT_MAX_ORDER_QUANTITY") {
+        Ok(max) => order::Rules {
+            max_quantity: max
+                .parse()
+                .expect("Maximum order quantity should be a number."),
+        },
+        Err(_) => order::Rules::default(),
+    };
+
+    // without a fixed key, everyone has to sign in again whenever the server restarts
+    let auth = match std::env::var("RESTAURANT_TOKEN_KEY") {
+        Ok(key) => Auth::new(key.as_bytes(), TimeDelta::hours(12)),
+        Err(_) => Auth::random(),
+    };
+
+    let app = restaurant_webapi::app(db, feed, webhooks.clone(), versions, rules, auth);
 
     let endpoint = std::env::args()
         .nth(1)
@@ -49,19 +80,109 @@ async fn main() {
         loop {
             interval.tick().await;
 
-            match status_db.get_all().await {
-                Ok(orders) => println!("Total orders: {}", orders.len()),
-                Err(err) => println!("Error getting orders: {:?}", err),
+            let Ok(tenants) = tenant::get_all(&status_db).await else {
+                continue;
             };
+            for tenant in tenants {
+                let Some(db) = status_db.for_tenant(tenant) else {
+                    continue;
+                };
+                match db.get_all().await {
+                    Ok(orders) => println!(
+                        "Total orders at restaurant {}: {}",
+                        u32::from(tenant),
+                        orders.len()
+                    ),
+                    Err(err) => println!("Error getting orders: {:?}", err),
+                };
+            }
         }
     };
 
-    _ = join!(async { axum::serve(listener, app).await }, statusupdate);
+    _ = join!(
+        async { axum::serve(listener, app).await },
+        statusupdate,
+        relay.run(relay_db),
+        webhooks.run()
+    );
 }
 
-fn create_database() -> Database {
+fn random_hex() -> String {
+    let mut bytes = [0u8; 8];
+    rand::thread_rng().fill_bytes(&mut bytes);
+    hex::encode(bytes)
+}
+
+// every restaurant starts out with the same floor and menu. there's one unless RESTAURANT_BRANCHES says otherwise
+async fn create_database() -> Database {
+    let branches: u32 = std::env::var("RESTAURANT_BRANCHES")
+        .map(|n| n.parse().expect("Number of branches should be a number."))
+        .unwrap_or(1);
+
+    let (menu, tables) = seed();
+    let mut db = Database::new(menu, tables, vec![]);
+    lay_out_menu(&mut db).await;
+    for id in 2..=branches {
+        let (menu, tables) = seed();
+        let mut branch = db
+            .open(id.into(), menu, tables, vec![])
+            .expect("Each branch should only be opened once.");
+        lay_out_menu(&mut branch).await;
+    }
+    db
+}
+
+// files the items `seed` makes into categories, with the hot drinks nested under the drinks
+async fn lay_out_menu(db: &mut Database) {
+    async fn add(
+        db: &mut Database,
+        name: &str,
+        parent: Option<menu::CategoryId>,
+        sort_order: u32,
+    ) -> menu::CategoryId {
+        menu::add_category(
+            db,
+            menu::Category {
+                name: name.to_string(),
+                parent,
+                sort_order,
+            },
+        )
+        .await
+        .expect("Seeded categories should only have seeded parents.")
+        .id()
+    }
+    let mains = add(db, "Mains", None, 1).await;
+    let desserts = add(db, "Desserts", None, 2).await;
+    let drinks = add(db, "Drinks", None, 3).await;
+    let hot_drinks = add(db, "Hot drinks", Some(drinks), 1).await;
+
+    for (item, category, sort_order) in [
+        (3, mains, 1),
+        (1, mains, 2),
+        (2, mains, 3),
+        (4, desserts, 1),
+        (5, hot_drinks, 1),
+    ] {
+        menu::file(db, item.into(), Some(category), sort_order)
+            .await
+            .expect("Seeded items should be filed under seeded categories.");
+    }
+}
+
+fn seed() -> (Vec<menu::RepoItem>, Vec<layout::RepoTable>) {
     let tables = (1..101)
-        .map(|id| layout::RepoTable::new(id.into(), layout::Table {}))
+        .map(|id| {
+            layout::RepoTable::new(
+                id.into(),
+                layout::Table {
+                    active: true,
+                    // twos, fours and sixes, in sections of twenty-five
+                    seats: 2 + 2 * (id % 3),
+                    section: Some(format!("section-{}", (id - 1) / 25 + 1)),
+                },
+            )
+        })
         .collect();
     let menu = vec![
         menu::RepoItem::new(
@@ -69,6 +190,10 @@ fn create_database() -> Database {
             menu::Item {
                 name: "Pasta".to_string(),
                 cook_time: menu::Minutes(12),
+                station: menu::Station::Grill,
+                available: true,
+                category: None,
+                sort_order: 0,
             },
         ),
         menu::RepoItem::new(
@@ -76,6 +201,10 @@ fn create_database() -> Database {
             menu::Item {
                 name: "Sandwich".to_string(),
                 cook_time: menu::Minutes(5),
+                station: menu::Station::Grill,
+                available: true,
+                category: None,
+                sort_order: 0,
             },
         ),
         menu::RepoItem::new(
@@ -83,6 +212,10 @@ fn create_database() -> Database {
             menu::Item {
                 name: "味噌カツ丼".to_string(),
                 cook_time: menu::Minutes(15),
+                station: menu::Station::Fryer,
+                available: true,
+                category: None,
+                sort_order: 0,
             },
         ),
         menu::RepoItem::new(
@@ -90,8 +223,23 @@ fn create_database() -> Database {
             menu::Item {
                 name: "和風パフェ".to_string(),
                 cook_time: menu::Minutes(8),
+                station: menu::Station::Dessert,
+                available: true,
+                category: None,
+                sort_order: 0,
+            },
+        ),
+        menu::RepoItem::new(
+            5.into(),
+            menu::Item {
+                name: "抹茶ラテ".to_string(),
+                cook_time: menu::Minutes(3),
+                station: menu::Station::Drinks,
+                available: true,
+                category: None,
+                sort_order: 0,
             },
         ),
     ];
-    Database::new(menu, tables, vec![])
+    (menu, tables)
 }
@@ -0,0 +1,64 @@
+use std::time::Duration;
+
+use restaurant::{memdb::Database, order, outbox, tenant, waitlist};
+
+use crate::{feed::Feed, webhooks::Webhooks};
+
+// hands events from the order outbox, and parties the waitlist has notified, to everything in the web api
+// that announces them
+#[derive(Clone)]
+pub struct Relay {
+    feed: Feed,
+    webhooks: Webhooks,
+}
+
+impl Relay {
+    pub fn new(feed: Feed, webhooks: Webhooks) -> Relay {
+        Relay { feed, webhooks }
+    }
+
+    pub async fn run(&self, db: Database) {
+        let mut interval = tokio::time::interval(Duration::from_millis(100));
+        loop {
+            interval.tick().await;
+
+            // every restaurant has its own outbox. events say which one they're from, for whoever gets them
+            let tenants = match tenant::get_all(&db).await {
+                Ok(tenants) => tenants,
+                Err(err) => {
+                    println!("Error finding restaurants to relay for: {:?}", err);
+                    continue;
+                }
+            };
+            for mut branch in tenants.into_iter().filter_map(|t| db.for_tenant(t)) {
+                if let Err(err) = outbox::relay(&mut branch, self).await {
+                    println!("Error relaying order events: {:?}", err);
+                }
+                if let Err(err) = waitlist::announce(&mut branch, self).await {
+                    println!("Error announcing waitlist notices: {:?}", err);
+                }
+            }
+        }
+    }
+}
+
+impl outbox::Dispatcher for Relay {
+    async fn dispatch(&self, event: &order::RepoEvent) -> anyhow::Result<()> {
+        // webhooks go first, since they're persisted. if that fails, live subscribers haven't seen anything yet
+        self.webhooks.enqueue(event)?;
+        self.feed.publish(event.clone());
+        self.feed.notify(event).await;
+        Ok(())
+    }
+}
+
+impl waitlist::Announcer for Relay {
+    async fn announce(
+        &self,
+        tenant: tenant::Id,
+        party: &waitlist::RepoParty,
+    ) -> anyhow::Result<()> {
+        // only webhooks, since the feeds only carry order events
+        self.webhooks.enqueue_notice(tenant, party)
+    }
+}
@@ -0,0 +1,32 @@
+use axum::{extract::Request, http::HeaderValue, middleware::Next, response::Response};
+use rand::RngCore;
+use restaurant_client::REQUEST_ID_HEADER;
+
+use crate::Database;
+
+// every request gets an id, the caller's own if they sent a usable one, so what it changed can be found in the
+// audit log. it's handed to the routes through the database they're given, and echoed back on the response.
+pub async fn layer(mut request: Request, next: Next) -> Response {
+    let id = request
+        .headers()
+        .get(REQUEST_ID_HEADER)
+        .and_then(|v| v.to_str().ok())
+        .filter(|v| !v.is_empty() && v.len() <= 128)
+        .map(str::to_string)
+        .unwrap_or_else(|| {
+            let mut id = [0u8; 16];
+            rand::thread_rng().fill_bytes(&mut id);
+            hex::encode(id)
+        });
+
+    if let Some(db) = request.extensions().get::<Database>() {
+        let db = db.for_request(&id);
+        request.extensions_mut().insert(db);
+    }
+
+    let mut response = next.run(request).await;
+    if let Ok(value) = HeaderValue::from_str(&id) {
+        response.headers_mut().insert(REQUEST_ID_HEADER, value);
+    }
+    response
+}
@@ -0,0 +1,80 @@
+use axum::{
+    extract::Request,
+    http::{StatusCode, Uri},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use restaurant::tenant;
+use restaurant_client::RESTAURANT_HEADER;
+
+use crate::Database;
+
+// every route is also served under "/restaurants/{id}", for clients that can't set headers, such as the kds
+// page, and it's where links point
+pub const PREFIX: &str = "/restaurants/";
+
+// takes the prefix off before anything is routed, leaving what it said for `select`
+pub async fn unprefix(mut request: Request, next: Next) -> Response {
+    let path = request.uri().path();
+    if let Some(rest) = path.strip_prefix(PREFIX) {
+        let (chosen, rest) = rest.split_at(rest.find('/').unwrap_or(rest.len()));
+        let chosen = FromPath(chosen.to_string());
+        let path_and_query = match request.uri().query() {
+            Some(query) => format!("/{}?{}", rest.trim_start_matches('/'), query),
+            None => format!("/{}", rest.trim_start_matches('/')),
+        };
+
+        let mut parts = request.uri().clone().into_parts();
+        parts.path_and_query = path_and_query.parse().ok();
+        match Uri::from_parts(parts) {
+            Ok(uri) => *request.uri_mut() = uri,
+            Err(_) => return StatusCode::NOT_FOUND.into_response(),
+        }
+        request.extensions_mut().insert(chosen);
+    }
+
+    next.run(request).await
+}
+
+#[derive(Clone)]
+struct FromPath(String);
+
+// picks the restaurant a request is for: the one in the path if it's under `PREFIX`, otherwise the one in
+// the header, and otherwise the default. routes are only ever handed that restaurant's database, so there's
+// nothing they can do to see another's data.
+pub async fn select(mut request: Request, next: Next) -> Response {
+    let chosen = match request.extensions().get::<FromPath>() {
+        Some(FromPath(chosen)) => Some(chosen.clone()),
+        None => request
+            .headers()
+            .get(RESTAURANT_HEADER)
+            .map(|v| v.to_str().unwrap_or_default().to_string()),
+    };
+
+    let id = match chosen {
+        Some(chosen) => match chosen.parse::<u32>() {
+            Ok(id) => tenant::Id(id),
+            Err(_) => {
+                return (
+                    StatusCode::NOT_FOUND,
+                    format!("Restaurant '{}' doesn't exist.", chosen),
+                )
+                    .into_response()
+            }
+        },
+        None => tenant::DEFAULT,
+    };
+
+    if let Some(db) = request.extensions().get::<Database>() {
+        let Some(db) = db.for_tenant(id) else {
+            return (
+                StatusCode::NOT_FOUND,
+                format!("Restaurant '{}' doesn't exist.", u32::from(id)),
+            )
+                .into_response();
+        };
+        request.extensions_mut().insert(db);
+    }
+
+    next.run(request).await
+}
@@ -1,10 +1,42 @@
-use std::collections::HashMap;
+use std::{collections::HashMap, sync::Arc};
 
-use axum::Router;
+use anyhow::anyhow;
+use axum::{
+    extract::Request,
+    http::{header, HeaderMap, HeaderValue, StatusCode, Uri},
+    response::{IntoResponse, Response},
+    Extension, Json, Router,
+};
+use chrono::{DateTime, Utc};
+use restaurant::{
+    layout,
+    memdb::Database,
+    menu, order,
+    tenant::{self, Scoped},
+    waitlist,
+};
+use restaurant_client::{
+    v1::{FieldError, OrderQuery, PageLinks},
+    versions::ApiVersion,
+    API_KEY_HEADER, VERSION_HEADER,
+};
+use serde::Serialize;
+use tower::Service;
+use utoipa::openapi::{
+    path::{Parameter, ParameterBuilder, ParameterIn},
+    schema::{ObjectBuilder, Type},
+    security::{
+        ApiKey, ApiKeyValue, HttpAuthScheme, HttpBuilder, SecurityRequirement, SecurityScheme,
+    },
+    OpenApi, Required,
+};
 
+pub mod links;
 pub mod v1;
 pub mod v2;
 
+pub const VERSIONS: [&str; 2] = ["v1", "v2"];
+
 pub fn create_services() -> HashMap<&'static str, Router> {
     let mut result = HashMap::new();
     let mut add = |VersionedApi(v, r): VersionedApi| {
@@ -24,3 +56,440 @@ impl VersionedApi {
         VersionedApi(ver, Router::new().nest("/api", router))
     }
 }
+
+fn known(version: &str) -> Option<&'static str> {
+    VERSIONS.iter().find(|v| **v == version).copied()
+}
+
+// how a station appears in paths, which is how it's serialized everywhere else
+pub fn station_path(station: menu::Station) -> &'static str {
+    match station {
+        menu::Station::Grill => "grill",
+        menu::Station::Fryer => "fryer",
+        menu::Station::Dessert => "dessert",
+        menu::Station::Drinks => "drinks",
+    }
+}
+
+pub fn media_type(version: &str) -> String {
+    format!("application/vnd.restaurant.{}+json", version)
+}
+
+#[derive(Debug, Clone)]
+pub struct Deprecation {
+    pub deprecated_at: DateTime<Utc>,
+    // once it's passed, the version answers with 410s
+    pub sunset: Option<DateTime<Utc>>,
+}
+
+// which version a request gets: the one in its path if it's under "/api/{version}", as links are, then
+// `x-api-version` if it's set, then a versioned media type in `Accept`, then the default
+#[derive(Debug, Clone)]
+pub struct Versions {
+    default: &'static str,
+    deprecations: HashMap<&'static str, Deprecation>,
+}
+
+impl Default for Versions {
+    fn default() -> Self {
+        Versions {
+            default: "v1",
+            deprecations: HashMap::new(),
+        }
+    }
+}
+
+impl Versions {
+    pub fn with_default(self, version: &str) -> anyhow::Result<Versions> {
+        let default = known(version).ok_or(anyhow!("Unknown api version '{}'.", version))?;
+        Ok(Versions { default, ..self })
+    }
+
+    pub fn deprecate(
+        mut self,
+        version: &str,
+        deprecated_at: DateTime<Utc>,
+        sunset: Option<DateTime<Utc>>,
+    ) -> anyhow::Result<Versions> {
+        let version = known(version).ok_or(anyhow!("Unknown api version '{}'.", version))?;
+        self.deprecations.insert(
+            version,
+            Deprecation {
+                deprecated_at,
+                sunset,
+            },
+        );
+        Ok(self)
+    }
+
+    pub fn list(&self) -> Vec<ApiVersion> {
+        VERSIONS
+            .iter()
+            .map(|v| ApiVersion {
+                version: v.to_string(),
+                default: *v == self.default,
+                media_type: media_type(v),
+                deprecated_at: self.deprecations.get(v).map(|d| d.deprecated_at),
+                sunset: self.deprecations.get(v).and_then(|d| d.sunset),
+            })
+            .collect()
+    }
+
+    fn negotiate(&self, headers: &HeaderMap) -> Result<&'static str, Response> {
+        if let Some(version) = headers.get(VERSION_HEADER) {
+            let version = version.to_str().unwrap_or_default();
+            return known(version).ok_or_else(|| {
+                (
+                    StatusCode::NOT_FOUND,
+                    format!("Unknown api version '{}'.", version),
+                )
+                    .into_response()
+            });
+        }
+
+        let requested: Vec<&str> = headers
+            .get_all(header::ACCEPT)
+            .iter()
+            .filter_map(|a| a.to_str().ok())
+            .flat_map(|a| a.split(','))
+            .filter_map(|m| {
+                m.split(';')
+                    .next()?
+                    .trim()
+                    .strip_prefix("application/vnd.restaurant.")?
+                    .strip_suffix("+json")
+            })
+            .collect();
+        if requested.is_empty() {
+            return Ok(self.default);
+        }
+
+        requested.iter().find_map(|v| known(v)).ok_or_else(|| {
+            (
+                StatusCode::NOT_ACCEPTABLE,
+                format!(
+                    "None of the requested api versions are served: {}.",
+                    requested.join(", ")
+                ),
+            )
+                .into_response()
+        })
+    }
+
+    // hands the request to whichever version it negotiates, and marks up the response accordingly
+    pub async fn dispatch(
+        &self,
+        apis: Arc<HashMap<&'static str, Router>>,
+        mut request: Request,
+    ) -> Response {
+        let version = match version_in_path(&mut request) {
+            Some(version) => version,
+            None => match self.negotiate(request.headers()) {
+                Ok(version) => version,
+                Err(response) => return response,
+            },
+        };
+        let deprecation = self.deprecations.get(version);
+        if let Some(sunset) = deprecation.and_then(|d| d.sunset) {
+            if sunset <= Utc::now() {
+                return (
+                    StatusCode::GONE,
+                    format!("Api version '{}' was retired on {}.", version, sunset),
+                )
+                    .into_response();
+            }
+        }
+
+        // links are under the restaurant `tenant::select` picked, however the request chose it
+        let restaurant = request
+            .extensions()
+            .get::<Database>()
+            .map_or(tenant::DEFAULT, |db| db.tenant());
+        let mut router = apis
+            .get(version)
+            .expect("Every known version should be served.")
+            .clone();
+        let base = format!(
+            "{}{}/api/{}",
+            crate::tenant::PREFIX,
+            u32::from(restaurant),
+            version
+        );
+        let mut response = links::scope(base, router.call(request))
+            .await
+            .unwrap_or_else(|e| match e {});
+
+        let headers = response.headers_mut();
+        headers.insert(VERSION_HEADER, HeaderValue::from_static(version));
+        headers.insert(
+            header::VARY,
+            HeaderValue::from_static("x-api-version, accept"),
+        );
+        if let Some(deprecation) = deprecation {
+            // RFC 9745 and RFC 8594
+            headers.insert(
+                "deprecation",
+                HeaderValue::from_str(&format!("@{}", deprecation.deprecated_at.timestamp()))
+                    .expect("Timestamps should be valid header values."),
+            );
+            if let Some(sunset) = deprecation.sunset {
+                headers.insert(
+                    "sunset",
+                    HeaderValue::from_str(&sunset.format("%a, %d %b %Y %H:%M:%S GMT").to_string())
+                        .expect("Dates should be valid header values."),
+                );
+            }
+            headers.insert(
+                header::LINK,
+                HeaderValue::from_static("</api/versions>; rel=\"deprecation\""),
+            );
+        }
+
+        response
+    }
+}
+
+// takes the version out of an "/api/{version}/..." path, leaving the path the version's routes expect
+fn version_in_path(request: &mut Request) -> Option<&'static str> {
+    let rest = request.uri().path().strip_prefix("/api/")?;
+    let (version, rest) = rest.split_at(rest.find('/')?);
+    let version = known(version)?;
+
+    let path_and_query = match request.uri().query() {
+        Some(query) => format!("/api{}?{}", rest, query),
+        None => format!("/api{}", rest),
+    };
+    let mut parts = request.uri().clone().into_parts();
+    parts.path_and_query = Some(path_and_query.parse().ok()?);
+    *request.uri_mut() = Uri::from_parts(parts).ok()?;
+    Some(version)
+}
+
+pub async fn versions_get(Extension(versions): Extension<Versions>) -> Json<Vec<ApiVersion>> {
+    Json(versions.list())
+}
+
+// the dtos live in restaurant-client so the client and the server can't disagree about them, which
+// also means they can't have From impls for domain types here. these stand in for those.
+
+pub trait FromDomain<T> {
+    fn from_domain(value: T) -> Self;
+}
+
+pub trait IntoDomain<T> {
+    fn into_domain(self) -> T;
+}
+
+// every version lists orders with the same query. cursors are handed out as opaque tokens, so that
+// clients don't come to depend on what's in them.
+
+pub fn order_query(query: &OrderQuery) -> Result<order::Query, String> {
+    let after = match &query.cursor {
+        Some(token) => Some(
+            hex::decode(token)
+                .ok()
+                .and_then(|json| serde_json::from_slice(&json).ok())
+                .ok_or(format!("'{}' is not a valid cursor.", token))?,
+        ),
+        None => None,
+    };
+
+    Ok(order::Query {
+        table: query.table_id.map(layout::TableId),
+        menu_item: query.item_id.map(menu::Id),
+        status: query.status.map(IntoDomain::into_domain),
+        placed_after: query.placed_after,
+        placed_before: query.placed_before,
+        sort: query.sort.unwrap_or_default().into_domain(),
+        direction: query.direction.unwrap_or_default().into_domain(),
+        after,
+        limit: query.limit.map(|l| l as usize),
+    })
+}
+
+pub fn cursor_token(cursor: &order::Cursor) -> String {
+    hex::encode(serde_json::to_vec(cursor).expect("Cursors should always serialize."))
+}
+
+// links to the page itself, and to the one after it, which is the same query continuing from the cursor
+pub fn page_links<Q: Serialize>(path: &str, query: &Q, next: Option<&Q>) -> PageLinks {
+    let href = |query: &Q| match serde_urlencoded::to_string(query) {
+        Ok(q) if !q.is_empty() => format!("{}?{}", path, q),
+        _ => path.to_string(),
+    };
+
+    PageLinks {
+        self_link: links::get(href(query)),
+        next: next.map(|next| links::get(href(next))),
+    }
+}
+
+// the same orders query, continuing from the cursor
+pub fn next_orders(query: &OrderQuery, cursor: Option<&String>) -> Option<OrderQuery> {
+    cursor.map(|cursor| OrderQuery {
+        cursor: Some(cursor.clone()),
+        ..query.clone()
+    })
+}
+
+pub fn field_error(pointer: impl Into<String>, detail: impl ToString) -> FieldError {
+    FieldError {
+        pointer: pointer.into(),
+        detail: detail.to_string(),
+    }
+}
+
+// which fields of an order request a domain error is about, if any. v2 has the table in the path rather than
+// the body, so it answers for inactive tables itself.
+pub fn invalid_fields(e: &order::OrderingError) -> Vec<FieldError> {
+    use order::{LineError, OrderingError};
+
+    match e {
+        OrderingError::ZeroQuantity | OrderingError::QuantityTooLarge { .. } => {
+            vec![field_error("/quantity", e)]
+        }
+        OrderingError::MenuItemUnavailable(_) => vec![field_error("/item_id", e)],
+        OrderingError::TableInactive(_) | OrderingError::SameTable(_) => {
+            vec![field_error("/table_id", e)]
+        }
+        OrderingError::EmptyGroup => vec![field_error("/items", e)],
+        OrderingError::InvalidLines(lines) => lines
+            .iter()
+            .map(|(index, line)| {
+                let field = match line {
+                    LineError::MenuItemNotFound(_) | LineError::MenuItemUnavailable(_) => "item_id",
+                    LineError::ZeroQuantity | LineError::QuantityTooLarge { .. } => "quantity",
+                };
+                field_error(format!("/items/{}/{}", index, field), line)
+            })
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+// where a table's orders are kept, for reads that shouldn't fail just because the table doesn't exist
+pub async fn serving_table_id(db: &Database, id: layout::TableId) -> layout::TableId {
+    layout::serving_table(db, id).await.map_or(id, |t| t.id())
+}
+
+// hands a table that's just been cleared to the waitlist. the table's cleared either way, so a failure to notify
+// anyone is only logged
+pub async fn table_freed(db: &mut Database, id: layout::TableId) {
+    if let Err(err) = waitlist::table_freed(db, id).await {
+        println!(
+            "Error notifying the waitlist about table {:?}: {:?}",
+            id, err
+        );
+    }
+}
+
+// resources carry their repository version as a strong entity tag, since If-Match only compares strong ones
+pub fn etag(version: u32) -> [(header::HeaderName, String); 1] {
+    [(header::ETAG, format!("\"{}\"", version))]
+}
+
+// the versions an If-Match header accepts, or None when there's no header or it accepts any version.
+// tags that aren't one of ours can never match, so they're left out
+pub fn if_match(headers: &HeaderMap) -> Option<Vec<u32>> {
+    let value = headers.get(header::IF_MATCH)?.to_str().unwrap_or_default();
+    if value.trim() == "*" {
+        return None;
+    }
+    Some(
+        value
+            .split(',')
+            .filter_map(|tag| {
+                tag.trim()
+                    .strip_prefix('"')?
+                    .strip_suffix('"')?
+                    .parse()
+                    .ok()
+            })
+            .collect(),
+    )
+}
+
+// the version a change to the order has to apply to for the request's If-Match to hold. it's checked here
+// so that a mismatch is reported before anything else, and again by the domain when the change is written
+pub async fn expected_version(
+    db: &Database,
+    id: order::Id,
+    headers: &HeaderMap,
+) -> order::Result<Option<u32>> {
+    let Some(versions) = if_match(headers) else {
+        return Ok(None);
+    };
+    let current = order::get(db, id).await?.version();
+    match versions.contains(&current) {
+        true => Ok(Some(current)),
+        false => Err(order::OrderingError::VersionConflict {
+            id,
+            expected: versions.first().copied().unwrap_or_default(),
+            actual: current,
+        }),
+    }
+}
+
+// a stale version is the client's precondition failing when it gave one, otherwise someone got there first
+pub fn conflict_status(headers: &HeaderMap) -> StatusCode {
+    match headers.contains_key(header::IF_MATCH) {
+        true => StatusCode::PRECONDITION_FAILED,
+        false => StatusCode::CONFLICT,
+    }
+}
+
+// every operation needs a staff token or an api key unless it says otherwise, as signing in does
+fn with_security(mut api: OpenApi) -> OpenApi {
+    let components = api.components.get_or_insert_with(Default::default);
+    components.add_security_scheme(
+        "bearer",
+        SecurityScheme::Http(HttpBuilder::new().scheme(HttpAuthScheme::Bearer).build()),
+    );
+    components.add_security_scheme(
+        "api_key",
+        SecurityScheme::ApiKey(ApiKey::Header(ApiKeyValue::new(API_KEY_HEADER))),
+    );
+    api.security = Some(vec![
+        SecurityRequirement::new("bearer", Vec::<String>::new()),
+        SecurityRequirement::new("api_key", Vec::<String>::new()),
+    ]);
+    api
+}
+
+// every operation needs the header that routes it to its version in the first place
+fn with_version_header(mut api: OpenApi, version: &'static str) -> OpenApi {
+    let header: Parameter = ParameterBuilder::new()
+        .name("x-api-version")
+        .parameter_in(ParameterIn::Header)
+        .required(Required::False)
+        .description(Some(
+            "Selects the version of the api. Without it, the version comes from a \
+             `application/vnd.restaurant.{version}+json` Accept header, or the server's default.",
+        ))
+        .schema(Some(
+            ObjectBuilder::new()
+                .schema_type(Type::String)
+                .enum_values(Some([version])),
+        ))
+        .build();
+
+    for item in api.paths.paths.values_mut() {
+        for operation in [
+            &mut item.get,
+            &mut item.put,
+            &mut item.post,
+            &mut item.delete,
+            &mut item.patch,
+        ]
+        .into_iter()
+        .flatten()
+        {
+            operation
+                .parameters
+                .get_or_insert_with(Vec::new)
+                .insert(0, header.clone());
+        }
+    }
+
+    api
+}
@@ -0,0 +1,99 @@
+use std::future::Future;
+
+use restaurant::order;
+use restaurant_client::{
+    v1::{self, Link, OrderLinks},
+    v2,
+};
+
+use super::station_path;
+
+// NOTE: links are built deep inside conversions that only see the domain type, so what they need to know about the
+// request they answer, such as its version, is set for the duration of it by `Versions::dispatch`. links always
+// name the restaurant and the version they're for, so following one gets the same restaurant and version back
+// whatever headers the client sends and whatever the server's default is.
+
+tokio::task_local! {
+    static BASE: String;
+}
+
+// runs `f` with links under `base`, such as "/restaurants/1/api/v2"
+pub async fn scope<F: Future>(base: String, f: F) -> F::Output {
+    BASE.scope(base, f).await
+}
+
+// the href of an "/api/..." path, under the base of the request being answered. outside of one it's left as is
+pub fn href(path: &str) -> String {
+    match (BASE.try_with(Clone::clone), path.strip_prefix("/api")) {
+        (Ok(base), Some(rest)) => format!("{}{}", base, rest),
+        _ => path.to_string(),
+    }
+}
+
+pub fn get(path: String) -> Link {
+    Link::get(href(&path))
+}
+
+pub fn action(method: &str, path: String) -> Link {
+    Link::action(method, href(&path))
+}
+
+// where each version keeps what an order's links point at
+pub struct OrderRoutes {
+    pub table: &'static str,
+    pub menu_item: &'static str,
+    // the method, and what follows the order's own path
+    pub set_quantity: (&'static str, &'static str),
+}
+
+// only what the order's current state allows is offered. a ready order can still be sent back.
+pub fn order(routes: &OrderRoutes, o: &order::RepoOrder) -> OrderLinks {
+    let id = o.id().0;
+    let placed = o.status == order::Status::Placed;
+    let (method, set_quantity) = routes.set_quantity;
+
+    OrderLinks {
+        self_link: Some(get(format!("/api/orders/{}", id))),
+        table: get(format!("/api/{}/{}", routes.table, o.table.id().0)),
+        menu_item: get(format!("/api/{}/{}", routes.menu_item, o.menu_item.id().0)),
+        set_quantity: placed.then(|| action(method, format!("/api/orders/{}{}", id, set_quantity))),
+        cancel: Some(action("DELETE", format!("/api/orders/{}", id))),
+        complete: placed.then(|| {
+            action(
+                "POST",
+                format!(
+                    "/api/stations/{}/tickets/{}/bump",
+                    station_path(o.menu_item.station),
+                    id
+                ),
+            )
+        }),
+    }
+}
+
+// responses with an order's links, whichever version they're from
+pub trait WithOrderLinks {
+    fn links_mut(&mut self) -> &mut OrderLinks;
+}
+
+impl WithOrderLinks for v1::Order {
+    fn links_mut(&mut self) -> &mut OrderLinks {
+        &mut self.links
+    }
+}
+
+impl WithOrderLinks for v2::Order {
+    fn links_mut(&mut self) -> &mut OrderLinks {
+        &mut self.links
+    }
+}
+
+// a removed order can't be fetched or acted on anymore, only followed back to what it referred to
+pub fn removed<T: WithOrderLinks>(mut order: T) -> T {
+    let links = order.links_mut();
+    links.self_link = None;
+    links.set_quantity = None;
+    links.cancel = None;
+    links.complete = None;
+    order
+}
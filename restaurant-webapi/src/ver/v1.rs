@@ -1,16 +1,52 @@
-use axum::Router;
+use axum::{routing::get, Json};
+use utoipa::{openapi::OpenApi, OpenApi as _};
+use utoipa_axum::router::OpenApiRouter;
 
-use super::VersionedApi;
+use super::{with_security, with_version_header, VersionedApi};
 
+mod api_keys;
+mod audit;
+mod kitchen;
 mod menu_items;
+pub mod models;
 mod orders;
+mod reservations;
+mod sections;
+mod staff;
 mod tables;
+mod waitlist;
+mod webhooks;
+
+#[derive(utoipa::OpenApi)]
+#[openapi(info(title = "Restaurant API", version = "v1"))]
+struct ApiDoc;
 
 pub fn create() -> VersionedApi {
-    let router = Router::new()
+    let (router, api) = router().split_for_parts();
+    let api = with_security(with_version_header(api, "v1"));
+
+    VersionedApi::new(
+        "v1",
+        router.route("/openapi.json", get(|| async { Json(api) })),
+    )
+}
+
+// the spec is generated from the same routers that get served, so the paths can't drift from the handlers
+pub fn openapi() -> OpenApi {
+    with_security(with_version_header(router().split_for_parts().1, "v1"))
+}
+
+fn router() -> OpenApiRouter {
+    OpenApiRouter::with_openapi(ApiDoc::openapi())
         .merge(orders::create())
+        .merge(api_keys::create())
+        .merge(audit::create())
+        .merge(kitchen::create())
         .merge(menu_items::create())
-        .merge(tables::create());
-
-    VersionedApi::new("v1", router)
+        .merge(reservations::create())
+        .merge(sections::create())
+        .merge(staff::create())
+        .merge(tables::create())
+        .merge(waitlist::create())
+        .merge(webhooks::create())
 }
@@ -0,0 +1,138 @@
+use axum::{extract::Path, http::StatusCode, middleware, response::IntoResponse, Extension, Json};
+use restaurant::{
+    api_key::{self, ApiKeyError},
+    staff::Role,
+};
+use utoipa_axum::{router::OpenApiRouter, routes};
+
+use super::{
+    models::{ApiKeyDetails, CreateApiKey, FromDomain, IntoDomain, IssuedApiKey},
+    orders::invalid,
+};
+use crate::{
+    auth::{self, Access},
+    idempotency::NO_STORE,
+    ver::field_error,
+    Database,
+};
+
+// keys are managed by people, so a key can't be used to make more of them
+pub fn create() -> OpenApiRouter {
+    OpenApiRouter::new()
+        .routes(routes!(api_keys_get, api_keys_post))
+        .routes(routes!(api_key_delete))
+        .routes(routes!(api_key_rotate))
+        .route_layer(middleware::from_fn_with_state(
+            Access::only(&[Role::Manager]),
+            auth::authorize,
+        ))
+}
+
+fn rejected(e: ApiKeyError) -> (StatusCode, String) {
+    match e {
+        ApiKeyError::KeyNotFound(_) => (StatusCode::NOT_FOUND, e.to_string()),
+        ApiKeyError::Revoked(_) => (StatusCode::CONFLICT, e.to_string()),
+        ApiKeyError::NoScopes => invalid(vec![field_error("/scopes", e)]),
+        ApiKeyError::InvalidKey | ApiKeyError::RepoOperation(_) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("Failed to access api keys: {:?}", e),
+        ),
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/api_keys",
+    tag = "api_keys",
+    security(("bearer" = [])),
+    responses((status = 200, description = "Every api key for the restaurant, including revoked ones, without the keys themselves.", body = Vec<ApiKeyDetails>))
+)]
+async fn api_keys_get(
+    Extension(db): Extension<Database>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    api_key::get_all(&db)
+        .await
+        .map(|all| {
+            Json(
+                all.iter()
+                    .map(ApiKeyDetails::from_domain)
+                    .collect::<Vec<_>>(),
+            )
+        })
+        .map_err(rejected)
+}
+
+#[utoipa::path(
+    post,
+    path = "/api_keys",
+    tag = "api_keys",
+    request_body = CreateApiKey,
+    security(("bearer" = [])),
+    responses(
+        (status = 201, description = "The new key, which works at the restaurant. This is the only time it's shown.", body = IssuedApiKey),
+        (status = 400, description = "The key has no scopes.", body = String, content_type = "text/plain"),
+    )
+)]
+async fn api_keys_post(
+    Extension(mut db): Extension<Database>,
+    Json(create): Json<CreateApiKey>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    let scopes: Vec<api_key::Scope> = create
+        .scopes
+        .into_iter()
+        .map(IntoDomain::into_domain)
+        .collect();
+    let (created, key) = api_key::create(&mut db, &create.name, &scopes)
+        .await
+        .map_err(rejected)?;
+
+    Ok((
+        StatusCode::CREATED,
+        NO_STORE,
+        Json(IssuedApiKey::from_domain((&created, key))),
+    ))
+}
+
+#[utoipa::path(
+    post,
+    path = "/api_keys/{id}/rotate",
+    tag = "api_keys",
+    params(("id" = u32, Path, description = "The api key to replace.")),
+    security(("bearer" = [])),
+    responses(
+        (status = 200, description = "The replacement key, with the same scopes. The old one stops working straight away.", body = IssuedApiKey),
+        (status = 404, description = "The api key doesn't exist, or isn't for the restaurant.", body = String, content_type = "text/plain"),
+        (status = 409, description = "The api key has been revoked.", body = String, content_type = "text/plain"),
+    )
+)]
+async fn api_key_rotate(
+    Extension(mut db): Extension<Database>,
+    Path(id): Path<api_key::Id>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    api_key::rotate(&mut db, id)
+        .await
+        .map(|(rotated, key)| (NO_STORE, Json(IssuedApiKey::from_domain((&rotated, key)))))
+        .map_err(rejected)
+}
+
+#[utoipa::path(
+    delete,
+    path = "/api_keys/{id}",
+    tag = "api_keys",
+    params(("id" = u32, Path, description = "The api key to revoke.")),
+    security(("bearer" = [])),
+    responses(
+        (status = 200, description = "The revoked api key, which can't be used anymore.", body = ApiKeyDetails),
+        (status = 404, description = "The api key doesn't exist, or isn't for the restaurant.", body = String, content_type = "text/plain"),
+        (status = 409, description = "The api key was already revoked.", body = String, content_type = "text/plain"),
+    )
+)]
+async fn api_key_delete(
+    Extension(mut db): Extension<Database>,
+    Path(id): Path<api_key::Id>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    api_key::revoke(&mut db, id)
+        .await
+        .map(|k| Json(ApiKeyDetails::from_domain(&k)))
+        .map_err(rejected)
+}
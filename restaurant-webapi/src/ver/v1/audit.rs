@@ -0,0 +1,79 @@
+use axum::{extract::Query, http::StatusCode, middleware, response::IntoResponse, Extension, Json};
+use restaurant::{
+    audit::{self, AuditError},
+    staff::Role,
+};
+use utoipa_axum::{router::OpenApiRouter, routes};
+
+use super::models::{AuditEntry, AuditPage, AuditQuery, AuditVerification, FromDomain, IntoDomain};
+use crate::{
+    auth::{self, Access},
+    ver, Database,
+};
+
+// the log is for managers answering for what happened, so api keys can't read it
+pub fn create() -> OpenApiRouter {
+    OpenApiRouter::new()
+        .routes(routes!(audit_get))
+        .routes(routes!(audit_verify_get))
+        .route_layer(middleware::from_fn_with_state(
+            Access::only(&[Role::Manager]),
+            auth::authorize,
+        ))
+}
+
+fn failed(e: AuditError) -> (StatusCode, String) {
+    (
+        StatusCode::INTERNAL_SERVER_ERROR,
+        format!("Failed to read the audit log: {:?}", e),
+    )
+}
+
+#[utoipa::path(
+    get,
+    path = "/audit",
+    tag = "audit",
+    params(AuditQuery),
+    security(("bearer" = [])),
+    responses((status = 200, description = "A page of the matching changes, newest first.", body = AuditPage))
+)]
+async fn audit_get(
+    Extension(db): Extension<Database>,
+    Query(query): Query<AuditQuery>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    let page = audit::search(&db, &query.clone().into_domain())
+        .await
+        .map_err(failed)?;
+
+    let next = page.next.map(|id| AuditQuery {
+        cursor: Some(id.into()),
+        ..query.clone()
+    });
+    Ok(Json(AuditPage {
+        entries: page.entries.iter().map(AuditEntry::from_domain).collect(),
+        next_cursor: page.next.map(u32::from),
+        links: ver::page_links("/api/audit", &query, next.as_ref()),
+    }))
+}
+
+#[utoipa::path(
+    get,
+    path = "/audit/verify",
+    tag = "audit",
+    security(("bearer" = [])),
+    responses((status = 200, description = "Whether the restaurant's hash chain, and the one for what's shared between restaurants, are unbroken.", body = AuditVerification))
+)]
+async fn audit_verify_get(
+    Extension(db): Extension<Database>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    let tampered_entry_id = match audit::verify(&db).await {
+        Ok(_) => None,
+        Err(AuditError::Tampered(id)) => Some(id.into()),
+        Err(e) => return Err(failed(e)),
+    };
+
+    Ok(Json(AuditVerification {
+        intact: tampered_entry_id.is_none(),
+        tampered_entry_id,
+    }))
+}
@@ -0,0 +1,120 @@
+use axum::{extract::Path, http::StatusCode, middleware, response::IntoResponse, Extension, Json};
+use restaurant::{
+    api_key::Scope,
+    kitchen::{self, KitchenError},
+    layout, order,
+    staff::Role,
+};
+use utoipa_axum::{router::OpenApiRouter, routes};
+
+use super::models::{FromDomain, IntoDomain, OrderDetails, Station, TableStatus};
+use crate::{
+    auth::{self, Access},
+    ver, Database,
+};
+
+pub fn create() -> OpenApiRouter {
+    OpenApiRouter::new()
+        .routes(routes!(station_tickets_get))
+        .routes(routes!(station_ticket_bump))
+        .routes(routes!(table_status_get))
+        .route_layer(middleware::from_fn_with_state(
+            Access::changed_by(&[Role::Kitchen]).or_key(Scope::OrdersRead, Scope::KitchenWrite),
+            auth::authorize,
+        ))
+}
+
+#[utoipa::path(
+    get,
+    path = "/stations/{station}/tickets",
+    tag = "kitchen",
+    params(("station" = Station, Path, description = "The station to get tickets for.")),
+    responses((status = 200, description = "The station's open orders, in fire order.", body = Vec<OrderDetails>))
+)]
+async fn station_tickets_get(
+    Extension(db): Extension<Database>,
+    Path(station): Path<Station>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    let station = station.into_domain();
+    kitchen::station_tickets(&db, station)
+        .await
+        .map(|tickets| {
+            Json(
+                tickets
+                    .iter()
+                    .map(OrderDetails::from_domain)
+                    .collect::<Vec<OrderDetails>>(),
+            )
+        })
+        .map_err(|e| {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("Failed to get tickets for station '{:?}': {:?}", station, e),
+            )
+        })
+}
+
+#[utoipa::path(
+    post,
+    path = "/stations/{station}/tickets/{id}/bump",
+    tag = "kitchen",
+    params(
+        ("station" = Station, Path, description = "The station bumping the ticket."),
+        ("id" = u32, Path, description = "The order that is ready."),
+    ),
+    responses(
+        (status = 200, description = "The order, now ready.", body = OrderDetails),
+        (status = 404, description = "The order doesn't exist.", body = String, content_type = "text/plain"),
+        (status = 409, description = "The order is prepared at a different station, or is already ready.", body = String, content_type = "text/plain"),
+    )
+)]
+async fn station_ticket_bump(
+    Extension(mut db): Extension<Database>,
+    Path((station, id)): Path<(Station, order::Id)>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    let station = station.into_domain();
+    kitchen::bump(&mut db, station, id)
+        .await
+        .map(|o| Json(OrderDetails::from_domain(&o)))
+        .map_err(|e| match e {
+            KitchenError::OrderNotFound(_) => (
+                StatusCode::NOT_FOUND,
+                format!("Order '{:?}' not found.", id),
+            ),
+            KitchenError::WrongStation { .. } => (
+                StatusCode::CONFLICT,
+                format!("Order '{:?}' is not prepared at '{:?}'.", id, station),
+            ),
+            KitchenError::AlreadyReady(_) => (
+                StatusCode::CONFLICT,
+                format!("Order '{:?}' has already been bumped.", id),
+            ),
+            e => (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("Failed to bump order '{:?}': {:?}", id, e),
+            ),
+        })
+}
+
+#[utoipa::path(
+    get,
+    path = "/table/{tableid}/status",
+    tag = "kitchen",
+    params(("tableid" = u32, Path, description = "The table to check on.")),
+    responses((status = 200, description = "Whether the table's orders are ready.", body = TableStatus))
+)]
+async fn table_status_get(
+    Extension(db): Extension<Database>,
+    Path(table_id): Path<layout::TableId>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    let table_id = ver::serving_table_id(&db, table_id).await;
+    kitchen::table_status(&db, table_id)
+        .await
+        .map(|s| Json(TableStatus::from_domain(s)))
+        .map_err(|e| {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("Failed to get status for table '{:?}': {:?}", table_id, e),
+            )
+        })
+}
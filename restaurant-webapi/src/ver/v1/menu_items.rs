@@ -1,14 +1,91 @@
-use crate::Database;
-use axum::{http::StatusCode, response::IntoResponse, routing::get, Extension, Json, Router};
-use restaurant::menu;
+use crate::{
+    auth::{self, Access},
+    ver, Database,
+};
+use axum::{extract::Path, http::StatusCode, middleware, response::IntoResponse, Extension, Json};
+use restaurant::{api_key::Scope, menu, staff::Role};
+use utoipa_axum::{router::OpenApiRouter, routes};
 
-pub fn create() -> Router {
-    Router::new().route("/menu_items", get(get_all))
+use super::models::{FromDomain, Menu, MenuItemDetails};
+
+pub fn create() -> OpenApiRouter {
+    OpenApiRouter::new()
+        .routes(routes!(menu_get))
+        .routes(routes!(get_all))
+        .routes(routes!(get_one))
+        .route_layer(middleware::from_fn_with_state(
+            Access::changed_by(&[Role::Manager]).or_key(Scope::MenuRead, Scope::MenuAdmin),
+            auth::authorize,
+        ))
+}
+
+// what ordering tablets render, where /menu_items is for looking items up
+#[utoipa::path(
+    get,
+    path = "/menu",
+    tag = "menu",
+    responses((status = 200, description = "The menu, by category, in the order it's listed.", body = Menu))
+)]
+async fn menu_get(
+    Extension(db): Extension<Database>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    menu::tree(&db)
+        .await
+        .map(|tree| Json(Menu::from_domain(&tree)))
+        .map_err(|e| {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("Failed to get the menu: {:?}", e),
+            )
+        })
 }
 
+#[utoipa::path(
+    get,
+    path = "/menu_items",
+    tag = "menu",
+    responses((status = 200, description = "Everything on the menu.", body = Vec<MenuItemDetails>))
+)]
 async fn get_all(Extension(db): Extension<Database>) -> Result<impl IntoResponse, StatusCode> {
     menu::get_all(&db)
         .await
-        .map(Json)
+        .map(|items| {
+            Json(
+                items
+                    .iter()
+                    .map(MenuItemDetails::from_domain)
+                    .collect::<Vec<MenuItemDetails>>(),
+            )
+        })
         .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)
 }
+
+#[utoipa::path(
+    get,
+    path = "/menu_items/{id}",
+    tag = "menu",
+    params(("id" = u32, Path, description = "The menu item to get.")),
+    responses(
+        (status = 200, description = "The menu item.", body = MenuItemDetails, headers(("ETag" = String, description = "The menu item's current version."))),
+        (status = 404, description = "The menu item doesn't exist.", body = String, content_type = "text/plain"),
+    )
+)]
+async fn get_one(
+    Extension(db): Extension<Database>,
+    Path(id): Path<menu::Id>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    menu::get(&db, id)
+        .await
+        .map(|i| {
+            (
+                ver::etag(i.version()),
+                Json(MenuItemDetails::from_domain(&i)),
+            )
+        })
+        .map_err(|_| {
+            (
+                StatusCode::NOT_FOUND,
+                format!("Menu item '{:?}' not found.", id),
+            )
+        })
+}
@@ -0,0 +1,570 @@
+use chrono::{TimeDelta, Utc};
+use restaurant::{
+    api_key, audit, kitchen, layout, menu, order, reservation, shift, staff, waitlist,
+};
+
+pub use restaurant_client::v1::*;
+
+pub use crate::ver::{links::removed, FromDomain, IntoDomain};
+use crate::{ver::links, webhooks};
+
+impl FromDomain<menu::Station> for Station {
+    fn from_domain(value: menu::Station) -> Self {
+        match value {
+            menu::Station::Grill => Station::Grill,
+            menu::Station::Fryer => Station::Fryer,
+            menu::Station::Dessert => Station::Dessert,
+            menu::Station::Drinks => Station::Drinks,
+        }
+    }
+}
+
+impl IntoDomain<menu::Station> for Station {
+    fn into_domain(self) -> menu::Station {
+        match self {
+            Station::Grill => menu::Station::Grill,
+            Station::Fryer => menu::Station::Fryer,
+            Station::Dessert => menu::Station::Dessert,
+            Station::Drinks => menu::Station::Drinks,
+        }
+    }
+}
+
+impl FromDomain<order::Status> for OrderStatus {
+    fn from_domain(value: order::Status) -> Self {
+        match value {
+            order::Status::Placed => OrderStatus::Placed,
+            order::Status::Ready => OrderStatus::Ready,
+        }
+    }
+}
+
+impl IntoDomain<order::Status> for OrderStatus {
+    fn into_domain(self) -> order::Status {
+        match self {
+            OrderStatus::Placed => order::Status::Placed,
+            OrderStatus::Ready => order::Status::Ready,
+        }
+    }
+}
+
+impl IntoDomain<order::SortKey> for OrderSort {
+    fn into_domain(self) -> order::SortKey {
+        match self {
+            OrderSort::Id => order::SortKey::Id,
+            OrderSort::Placed => order::SortKey::TimePlaced,
+            OrderSort::Table => order::SortKey::Table,
+            OrderSort::Quantity => order::SortKey::Quantity,
+        }
+    }
+}
+
+impl IntoDomain<order::Direction> for SortDirection {
+    fn into_domain(self) -> order::Direction {
+        match self {
+            SortDirection::Asc => order::Direction::Ascending,
+            SortDirection::Desc => order::Direction::Descending,
+        }
+    }
+}
+
+impl FromDomain<&layout::RepoTable> for TableDetails {
+    fn from_domain(t: &layout::RepoTable) -> Self {
+        let id = t.id().0;
+        TableDetails {
+            id,
+            seats: t.seats,
+            section: t.section.clone(),
+            links: TableLinks {
+                self_link: links::get(format!("/api/table/{}", id)),
+                orders: links::get(format!("/api/table/{}/orders", id)),
+                status: links::get(format!("/api/table/{}/status", id)),
+                place_order: links::action("POST", "/api/orders".to_string()),
+                clear: links::action("POST", format!("/api/table/{}/clear", id)),
+            },
+        }
+    }
+}
+
+impl FromDomain<&menu::RepoItem> for MenuItemDetails {
+    fn from_domain(i: &menu::RepoItem) -> Self {
+        MenuItemDetails {
+            id: i.id().into(),
+            name: i.name.clone(),
+            cook_time: i.cook_time.0,
+            station: Station::from_domain(i.station),
+            available: i.available,
+            category_id: i.category.map(u32::from),
+            links: MenuItemLinks {
+                self_link: links::get(format!("/api/menu_items/{}", i.id().0)),
+            },
+        }
+    }
+}
+
+impl FromDomain<&menu::Section> for MenuCategory {
+    fn from_domain(s: &menu::Section) -> Self {
+        MenuCategory {
+            id: s.category.id().into(),
+            name: s.category.name.clone(),
+            sort_order: s.category.sort_order,
+            items: s.items.iter().map(MenuItemDetails::from_domain).collect(),
+            subcategories: s
+                .subcategories
+                .iter()
+                .map(MenuCategory::from_domain)
+                .collect(),
+        }
+    }
+}
+
+impl FromDomain<&menu::Tree> for Menu {
+    fn from_domain(t: &menu::Tree) -> Self {
+        Menu {
+            categories: t.categories.iter().map(MenuCategory::from_domain).collect(),
+            uncategorized: t
+                .uncategorized
+                .iter()
+                .map(MenuItemDetails::from_domain)
+                .collect(),
+        }
+    }
+}
+
+// v1 keeps tables under "/table", and changes quantities with a POST of their own
+const ORDER_ROUTES: links::OrderRoutes = links::OrderRoutes {
+    table: "table",
+    menu_item: "menu_items",
+    set_quantity: ("POST", "/setquantity"),
+};
+
+impl FromDomain<&order::RepoOrder> for Order {
+    fn from_domain(o: &order::RepoOrder) -> Self {
+        Order {
+            id: o.id().into(),
+            table: TableDetails::from_domain(&o.table),
+            menu_item: MenuItemDetails::from_domain(&o.menu_item),
+            time_placed: o.time_placed,
+            quantity: o.quantity,
+            status: OrderStatus::from_domain(o.status),
+            group_id: o.group.map(u32::from),
+            placed_by: o.placed_by.map(u32::from),
+            changed_by: o.changed_by.map(u32::from),
+            links: links::order(&ORDER_ROUTES, o),
+        }
+    }
+}
+
+impl FromDomain<&order::RepoOrder> for OrderDetails {
+    fn from_domain(o: &order::RepoOrder) -> Self {
+        let remaining = TimeDelta::minutes((o.menu_item.cook_time.0 * o.quantity).into())
+            - (Utc::now() - o.time_placed);
+
+        OrderDetails {
+            id: o.id().into(),
+            table: TableDetails::from_domain(&o.table),
+            item: MenuItemDetails::from_domain(&o.menu_item),
+            time_placed: o.time_placed,
+            quantity: o.quantity,
+            status: OrderStatus::from_domain(o.status),
+            group_id: o.group.map(u32::from),
+            placed_by: o.placed_by.map(u32::from),
+            changed_by: o.changed_by.map(u32::from),
+            estimated_minutes_remaining: remaining.num_minutes().try_into().unwrap_or(0),
+            links: links::order(&ORDER_ROUTES, o),
+        }
+    }
+}
+
+// a group's orders are all at its lead table, so they're handed in alongside it
+impl FromDomain<(&layout::RepoTableGroup, &[order::RepoOrder])> for TableGroupDetails {
+    fn from_domain((group, orders): (&layout::RepoTableGroup, &[order::RepoOrder])) -> Self {
+        let id = group.id().0;
+        let orders: Vec<OrderDetails> = orders.iter().map(OrderDetails::from_domain).collect();
+        TableGroupDetails {
+            id,
+            tables: group.tables.iter().map(|t| t.0).collect(),
+            lead_table_id: group.lead().0,
+            total_quantity: orders.iter().map(|o| o.quantity).sum(),
+            estimated_minutes_remaining: orders
+                .iter()
+                .filter(|o| o.status == OrderStatus::Placed)
+                .map(|o| o.estimated_minutes_remaining)
+                .max()
+                .unwrap_or(0),
+            orders,
+            merged_by: group.merged_by.map(u32::from),
+            links: TableGroupLinks {
+                self_link: links::get(format!("/api/table_groups/{}", id)),
+                orders: links::get(format!("/api/table/{}/orders", group.lead().0)),
+                split: links::action("POST", format!("/api/table_groups/{}/split", id)),
+            },
+        }
+    }
+}
+
+impl FromDomain<&[order::RepoOrder]> for OrderGroup {
+    fn from_domain(orders: &[order::RepoOrder]) -> Self {
+        let first = orders.first().expect("Groups should never be empty.");
+        OrderGroup {
+            id: first.group.map_or(0, u32::from),
+            table_id: first.table.id().into(),
+            time_placed: first.time_placed,
+            orders: orders.iter().map(Order::from_domain).collect(),
+        }
+    }
+}
+
+impl FromDomain<kitchen::TableStatus> for TableStatus {
+    fn from_domain(s: kitchen::TableStatus) -> Self {
+        TableStatus {
+            table: s.table.into(),
+            ready: s.ready,
+            waiting_on: s.waiting_on.into_iter().map(Station::from_domain).collect(),
+        }
+    }
+}
+
+impl FromDomain<webhooks::EventKind> for WebhookEvent {
+    fn from_domain(value: webhooks::EventKind) -> Self {
+        match value {
+            webhooks::EventKind::Placed => WebhookEvent::Placed,
+            webhooks::EventKind::Updated => WebhookEvent::Updated,
+            webhooks::EventKind::Ready => WebhookEvent::Ready,
+            webhooks::EventKind::Removed => WebhookEvent::Removed,
+            webhooks::EventKind::Transferred => WebhookEvent::Transferred,
+            webhooks::EventKind::PartyNotified => WebhookEvent::PartyNotified,
+        }
+    }
+}
+
+impl IntoDomain<webhooks::EventKind> for WebhookEvent {
+    fn into_domain(self) -> webhooks::EventKind {
+        match self {
+            WebhookEvent::Placed => webhooks::EventKind::Placed,
+            WebhookEvent::Updated => webhooks::EventKind::Updated,
+            WebhookEvent::Ready => webhooks::EventKind::Ready,
+            WebhookEvent::Removed => webhooks::EventKind::Removed,
+            WebhookEvent::Transferred => webhooks::EventKind::Transferred,
+            WebhookEvent::PartyNotified => webhooks::EventKind::PartyNotified,
+        }
+    }
+}
+
+impl FromDomain<webhooks::Subscription> for CreatedWebhook {
+    fn from_domain(s: webhooks::Subscription) -> Self {
+        CreatedWebhook {
+            id: s.id.0,
+            url: s.url,
+            secret: s.secret,
+            events: s
+                .events
+                .into_iter()
+                .map(WebhookEvent::from_domain)
+                .collect(),
+        }
+    }
+}
+
+impl FromDomain<webhooks::Subscription> for WebhookDetails {
+    fn from_domain(s: webhooks::Subscription) -> Self {
+        WebhookDetails {
+            id: s.id.0,
+            url: s.url,
+            events: s
+                .events
+                .into_iter()
+                .map(WebhookEvent::from_domain)
+                .collect(),
+        }
+    }
+}
+
+impl FromDomain<webhooks::Delivery> for WebhookDelivery {
+    fn from_domain(d: webhooks::Delivery) -> Self {
+        WebhookDelivery {
+            id: d.id.0,
+            subscription: d.subscription.0,
+            event_id: d.event_id.map(u32::from),
+            party_id: d.party.map(u32::from),
+            kind: WebhookEvent::from_domain(d.kind),
+            payload: d.payload,
+            attempts: d.attempts,
+            next_attempt: d.next_attempt,
+            last_error: d.last_error,
+        }
+    }
+}
+
+impl FromDomain<&reservation::RepoReservation> for ReservationDetails {
+    fn from_domain(r: &reservation::RepoReservation) -> Self {
+        let id = r.id().0;
+        let link = format!("/api/reservations/{}", id);
+        ReservationDetails {
+            id,
+            table_id: r.table.0,
+            party_size: r.party_size,
+            starts_at: r.window.starts,
+            ends_at: r.window.ends,
+            contact: ContactDetails::from_domain(&r.contact),
+            changed_by: r.changed_by.map(u32::from),
+            links: ReservationLinks {
+                self_link: links::get(link.clone()),
+                table: links::get(format!("/api/table/{}", r.table.0)),
+                modify: links::action("PATCH", link.clone()),
+                cancel: links::action("DELETE", link),
+            },
+        }
+    }
+}
+
+impl FromDomain<&reservation::Contact> for ContactDetails {
+    fn from_domain(c: &reservation::Contact) -> Self {
+        ContactDetails {
+            name: c.name.clone(),
+            phone: c.phone.clone(),
+        }
+    }
+}
+
+impl IntoDomain<reservation::Contact> for ContactDetails {
+    fn into_domain(self) -> reservation::Contact {
+        reservation::Contact {
+            name: self.name,
+            phone: self.phone,
+        }
+    }
+}
+
+// the estimate is only there while the party's still waiting
+impl FromDomain<(&waitlist::RepoParty, Option<TimeDelta>)> for PartyDetails {
+    fn from_domain((p, wait): (&waitlist::RepoParty, Option<TimeDelta>)) -> Self {
+        let id = p.id().0;
+        let link = format!("/api/waitlist/{}", id);
+        let (status, table, notified_at) = match p.status {
+            waitlist::Status::Waiting => (PartyStatus::Waiting, None, None),
+            waitlist::Status::Notified { table, at } => {
+                (PartyStatus::Notified, Some(table.0), Some(at))
+            }
+            waitlist::Status::Seated { .. } => (PartyStatus::Seated, None, None),
+            waitlist::Status::NoShow { .. } => (PartyStatus::NoShow, None, None),
+        };
+        let in_line = p.status.in_line();
+
+        PartyDetails {
+            id,
+            name: p.name.clone(),
+            size: p.size,
+            phone: p.phone.clone(),
+            joined_at: p.joined,
+            status,
+            table_id: table,
+            notified_at,
+            estimated_minutes_remaining: wait.map(minutes),
+            changed_by: p.changed_by.map(u32::from),
+            links: PartyLinks {
+                self_link: links::get(link.clone()),
+                seat: in_line.then(|| links::action("POST", format!("{}/seat", link))),
+                no_show: in_line.then(|| links::action("POST", format!("{}/no_show", link))),
+                table: table.map(|t| links::get(format!("/api/table/{}", t))),
+            },
+        }
+    }
+}
+
+// rounded up, so nobody's told a table's ready when it's a few seconds off
+pub fn minutes(wait: TimeDelta) -> u32 {
+    ((wait.num_seconds() + 59) / 60).try_into().unwrap_or(0)
+}
+
+// the suggestions are already ranked, best first
+impl FromDomain<&[layout::assignment::Suggestion]> for Vec<TableSuggestion> {
+    fn from_domain(suggestions: &[layout::assignment::Suggestion]) -> Self {
+        suggestions
+            .iter()
+            .zip(1..)
+            .map(|(s, rank)| TableSuggestion {
+                rank,
+                table: TableDetails::from_domain(&s.table),
+                penalty: s.penalty,
+                reasons: s.reasons.clone(),
+            })
+            .collect()
+    }
+}
+
+impl FromDomain<staff::Role> for StaffRole {
+    fn from_domain(value: staff::Role) -> Self {
+        match value {
+            staff::Role::Server => StaffRole::Server,
+            staff::Role::Kitchen => StaffRole::Kitchen,
+            staff::Role::Host => StaffRole::Host,
+            staff::Role::Manager => StaffRole::Manager,
+            staff::Role::Admin => StaffRole::Admin,
+        }
+    }
+}
+
+impl IntoDomain<staff::Role> for StaffRole {
+    fn into_domain(self) -> staff::Role {
+        match self {
+            StaffRole::Server => staff::Role::Server,
+            StaffRole::Kitchen => staff::Role::Kitchen,
+            StaffRole::Host => staff::Role::Host,
+            StaffRole::Manager => staff::Role::Manager,
+            StaffRole::Admin => staff::Role::Admin,
+        }
+    }
+}
+
+impl FromDomain<&staff::RepoStaff> for StaffDetails {
+    fn from_domain(s: &staff::RepoStaff) -> Self {
+        StaffDetails {
+            id: s.id().into(),
+            name: s.name.clone(),
+            role: StaffRole::from_domain(s.role),
+            active: s.active,
+            restaurants: s.restaurants.iter().copied().map(u32::from).collect(),
+        }
+    }
+}
+
+impl FromDomain<api_key::Scope> for ApiScope {
+    fn from_domain(value: api_key::Scope) -> Self {
+        match value {
+            api_key::Scope::OrdersRead => ApiScope::OrdersRead,
+            api_key::Scope::OrdersWrite => ApiScope::OrdersWrite,
+            api_key::Scope::KitchenWrite => ApiScope::KitchenWrite,
+            api_key::Scope::TablesRead => ApiScope::TablesRead,
+            api_key::Scope::TablesWrite => ApiScope::TablesWrite,
+            api_key::Scope::MenuRead => ApiScope::MenuRead,
+            api_key::Scope::MenuAdmin => ApiScope::MenuAdmin,
+            api_key::Scope::WebhooksAdmin => ApiScope::WebhooksAdmin,
+        }
+    }
+}
+
+impl IntoDomain<api_key::Scope> for ApiScope {
+    fn into_domain(self) -> api_key::Scope {
+        match self {
+            ApiScope::OrdersRead => api_key::Scope::OrdersRead,
+            ApiScope::OrdersWrite => api_key::Scope::OrdersWrite,
+            ApiScope::KitchenWrite => api_key::Scope::KitchenWrite,
+            ApiScope::TablesRead => api_key::Scope::TablesRead,
+            ApiScope::TablesWrite => api_key::Scope::TablesWrite,
+            ApiScope::MenuRead => api_key::Scope::MenuRead,
+            ApiScope::MenuAdmin => api_key::Scope::MenuAdmin,
+            ApiScope::WebhooksAdmin => api_key::Scope::WebhooksAdmin,
+        }
+    }
+}
+
+impl FromDomain<&api_key::RepoApiKey> for ApiKeyDetails {
+    fn from_domain(k: &api_key::RepoApiKey) -> Self {
+        ApiKeyDetails {
+            id: k.id().into(),
+            name: k.name.clone(),
+            scopes: k
+                .scopes
+                .iter()
+                .copied()
+                .map(ApiScope::from_domain)
+                .collect(),
+            restaurants: k.restaurants.iter().copied().map(u32::from).collect(),
+            created_at: k.created,
+            created_by: k.created_by.map(u32::from),
+            rotated_at: k.rotated,
+            revoked_at: k.revoked,
+        }
+    }
+}
+
+impl FromDomain<(&api_key::RepoApiKey, String)> for IssuedApiKey {
+    fn from_domain((api_key, key): (&api_key::RepoApiKey, String)) -> Self {
+        IssuedApiKey {
+            key,
+            api_key: ApiKeyDetails::from_domain(api_key),
+        }
+    }
+}
+
+impl FromDomain<&shift::RepoAssignment> for SectionAssignment {
+    fn from_domain(a: &shift::RepoAssignment) -> Self {
+        SectionAssignment {
+            id: a.id().into(),
+            section: a.section.clone(),
+            server_id: a.server.into(),
+            starts_at: a.shift.starts,
+            ends_at: a.shift.ends,
+        }
+    }
+}
+
+impl IntoDomain<shift::Assignment> for AssignSection {
+    fn into_domain(self) -> shift::Assignment {
+        shift::Assignment {
+            section: self.section,
+            server: self.server_id.into(),
+            shift: reservation::Window {
+                starts: self.starts_at,
+                ends: self.ends_at,
+            },
+        }
+    }
+}
+
+impl FromDomain<&shift::ServerTable> for ServerTable {
+    fn from_domain(t: &shift::ServerTable) -> Self {
+        let orders: Vec<OrderDetails> = t.orders.iter().map(OrderDetails::from_domain).collect();
+        ServerTable {
+            table: TableDetails::from_domain(&t.table),
+            estimated_minutes_remaining: orders
+                .iter()
+                .filter(|o| o.status == OrderStatus::Placed)
+                .map(|o| o.estimated_minutes_remaining)
+                .max()
+                .unwrap_or(0),
+            orders,
+        }
+    }
+}
+
+impl FromDomain<&audit::RepoEntry> for AuditEntry {
+    fn from_domain(e: &audit::RepoEntry) -> Self {
+        let (staff_id, api_key_id) = match e.record.actor {
+            Some(audit::Actor::Staff(id)) => (Some(id.into()), None),
+            Some(audit::Actor::ApiKey(id)) => (None, Some(id.into())),
+            None => (None, None),
+        };
+        AuditEntry {
+            id: e.id().into(),
+            time: e.record.time,
+            staff_id,
+            api_key_id,
+            request_id: e.record.request.clone(),
+            operation: e.record.operation.clone(),
+            before: e.record.before.clone(),
+            after: e.record.after.clone(),
+            previous_hash: hex::encode(e.previous),
+            hash: hex::encode(e.hash),
+        }
+    }
+}
+
+impl IntoDomain<audit::Query> for AuditQuery {
+    fn into_domain(self) -> audit::Query {
+        audit::Query {
+            actor: self
+                .staff_id
+                .map(|id| audit::Actor::Staff(id.into()))
+                .or(self.api_key_id.map(|id| audit::Actor::ApiKey(id.into()))),
+            operation: self.operation,
+            request: self.request_id,
+            since: self.since,
+            until: self.until,
+            text: self.text,
+            before: self.cursor.map(audit::Id),
+            limit: self.limit,
+        }
+    }
+}
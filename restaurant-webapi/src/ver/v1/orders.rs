@@ -1,44 +1,147 @@
 use axum::{
-    extract::Path,
-    http::StatusCode,
-    response::IntoResponse,
-    routing::{delete, get, post},
-    Extension, Json, Router,
+    extract::{Path, Query},
+    http::{HeaderMap, StatusCode},
+    middleware,
+    response::{IntoResponse, Response},
+    Extension, Json,
 };
-use chrono::{DateTime, TimeDelta, Utc};
-use restaurant::{layout, menu, order};
-use serde::{Deserialize, Serialize};
-
-use crate::Database;
-
-pub fn create() -> Router {
-    Router::new()
-        .route("/orders", post(orders_post))
-        .route("/orders/:id/setquantity", post(orders_setquantity))
-        .route("/orders/:id", delete(orders_delete))
-        .route("/table/:tableid/orders", get(table_orders_get))
-        .route("/table/:tableid/clear", post(table_orders_clear))
+use restaurant::{
+    api_key::Scope,
+    layout, menu,
+    order::{self, OrderingError},
+    staff::Role,
+};
+use utoipa_axum::{router::OpenApiRouter, routes};
+
+use super::models::{
+    removed, CreateOrder, CreateOrderGroup, FieldError, FromDomain, Order, OrderDetails,
+    OrderGroup, OrderPage, OrderQuery, SetOrderQuantity, Transfer,
+};
+use crate::{
+    auth::{self, Access},
+    ver::{self, field_error},
+    Database,
+};
+
+pub fn create() -> OpenApiRouter {
+    OpenApiRouter::new()
+        .routes(routes!(orders_get, orders_post))
+        .routes(routes!(order_groups_post))
+        .routes(routes!(orders_setquantity))
+        .routes(routes!(order_get, orders_delete))
+        .routes(routes!(table_orders_get))
+        .routes(routes!(table_orders_clear))
+        .routes(routes!(order_transfer))
+        .routes(routes!(table_orders_transfer))
+        .route_layer(middleware::from_fn_with_state(
+            Access::changed_by(&[Role::Server]).or_key(Scope::OrdersRead, Scope::OrdersWrite),
+            auth::authorize,
+        ))
 }
 
-#[derive(Debug, Deserialize)]
-struct CreateOrder {
-    table_id: layout::TableId,
-    item_id: menu::Id,
-    quantity: u32,
+#[utoipa::path(
+    get,
+    path = "/orders",
+    tag = "orders",
+    params(OrderQuery),
+    responses(
+        (status = 200, description = "A page of the orders across every table.", body = OrderPage),
+        (status = 400, description = "The cursor isn't one this query handed out.", body = String, content_type = "text/plain"),
+    )
+)]
+async fn orders_get(
+    Extension(db): Extension<Database>,
+    Query(query): Query<OrderQuery>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    let page = order::find(
+        &db,
+        &ver::order_query(&query).map_err(|e| (StatusCode::BAD_REQUEST, e))?,
+    )
+    .await
+    .map_err(|e| match e {
+        OrderingError::InvalidCursor => (StatusCode::BAD_REQUEST, e.to_string()),
+        e => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("Failed to get orders: {:?}", e),
+        ),
+    })?;
+
+    let next = page.next.as_ref().map(ver::cursor_token);
+    Ok(Json(OrderPage {
+        orders: page.orders.iter().map(OrderDetails::from_domain).collect(),
+        links: ver::page_links(
+            "/api/orders",
+            &query,
+            ver::next_orders(&query, next.as_ref()).as_ref(),
+        ),
+        next_cursor: next,
+    }))
 }
 
+// v1 errors are plain text, so each invalid field gets a line of its own
+pub(super) fn invalid(errors: Vec<FieldError>) -> (StatusCode, String) {
+    (
+        StatusCode::BAD_REQUEST,
+        errors
+            .iter()
+            .map(|e| format!("{}: {}", e.pointer, e.detail))
+            .collect::<Vec<String>>()
+            .join("\n"),
+    )
+}
+
+#[utoipa::path(
+    post,
+    path = "/orders",
+    tag = "orders",
+    request_body = CreateOrder,
+    responses(
+        (status = 200, description = "The placed order.", body = Order),
+        (status = 400, description = "The order is invalid. Each line of the body names a field and what's wrong with it.", body = String, content_type = "text/plain"),
+    )
+)]
 async fn orders_post(
     Extension(mut db): Extension<Database>,
+    Extension(rules): Extension<order::Rules>,
     Json(order): Json<CreateOrder>,
 ) -> Result<impl IntoResponse, (StatusCode, String)> {
-    match (
-        layout::get(&db, order.table_id).await,
-        menu::get(&db, order.item_id).await,
-    ) {
-        (Ok(table), Ok(item)) => {
-            order::place(&mut db, table, item, order.quantity)
+    let (table_id, item_id) = (layout::TableId(order.table_id), menu::Id(order.item_id));
+    let (table, item) = (
+        layout::serving_table(&db, table_id).await,
+        menu::get(&db, item_id).await,
+    );
+
+    // everything that can be checked up front is, so that every problem is reported at once
+    let mut errors = Vec::new();
+    match &table {
+        Ok(table) if !table.active => {
+            errors.extend(ver::invalid_fields(&OrderingError::TableInactive(table_id)))
+        }
+        Ok(_) => {}
+        Err(_) => errors.push(field_error(
+            "/table_id",
+            format!("Table '{:?}' not found.", table_id),
+        )),
+    }
+    match &item {
+        Ok(item) if !item.available => errors.extend(ver::invalid_fields(
+            &OrderingError::MenuItemUnavailable(item_id),
+        )),
+        Ok(_) => {}
+        Err(_) => errors.push(field_error(
+            "/item_id",
+            format!("Menu item '{:?}' not found.", item_id),
+        )),
+    }
+    if let Err(e) = rules.check_quantity(order.quantity) {
+        errors.extend(ver::invalid_fields(&e));
+    }
+
+    match (table, item) {
+        (Ok(table), Ok(item)) if errors.is_empty() => {
+            order::place(&mut db, &rules, table, item, order.quantity)
                 .await
-                .map(Json)
+                .map(|o| Json(Order::from_domain(&o)))
                 .map_err(|e| {
                     // TODO: to make things more readable, shoving these in a tuple struct should hopefully work
                     (
@@ -47,38 +150,66 @@ async fn orders_post(
                     )
                 })
         }
-        (Ok(_), Err(_)) => Err((
-            (StatusCode::BAD_REQUEST),
-            format!("Menu item '{:?}' not found.", order.item_id),
-        )),
-        (Err(_), Ok(_)) => Err((
-            (StatusCode::BAD_REQUEST),
-            format!("Table '{:?}' not found.", order.table_id),
-        )),
-        (Err(_), Err(_)) => Err((
-            (StatusCode::BAD_REQUEST),
-            format!(
-                "Table '{:?}' and menu item '{:?}' not found.",
-                order.table_id, order.item_id
-            ),
-        )),
+        _ => Err(invalid(errors)),
     }
 }
 
-#[derive(Debug, Serialize)]
-struct OrderDetails {
-    id: order::Id,
-    table: layout::RepoTable,
-    item: menu::RepoItem,
-    time_placed: DateTime<Utc>,
-    quantity: u32,
-    estimated_minutes_remaining: menu::Minutes,
+#[utoipa::path(
+    post,
+    path = "/order_groups",
+    tag = "orders",
+    request_body = CreateOrderGroup,
+    responses(
+        (status = 200, description = "The placed orders, all placed at the same time.", body = OrderGroup),
+        (status = 400, description = "The table doesn't exist or isn't taking orders, or at least one of the items can't be ordered. Nothing was placed. Each line of the body names a field and what's wrong with it.", body = String, content_type = "text/plain"),
+    )
+)]
+async fn order_groups_post(
+    Extension(mut db): Extension<Database>,
+    Extension(rules): Extension<order::Rules>,
+    Json(group): Json<CreateOrderGroup>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    let table_id = layout::TableId(group.table_id);
+    let table = layout::serving_table(&db, table_id).await.map_err(|_| {
+        invalid(vec![field_error(
+            "/table_id",
+            format!("Table '{:?}' not found.", table_id),
+        )])
+    })?;
+    let lines: Vec<order::GroupLine> = group
+        .items
+        .iter()
+        .map(|i| order::GroupLine {
+            menu_item: menu::Id(i.item_id),
+            quantity: i.quantity,
+        })
+        .collect();
+
+    order::place_group(&mut db, &rules, table, &lines)
+        .await
+        .map(|orders| Json(OrderGroup::from_domain(orders.as_slice())))
+        .map_err(|e| match ver::invalid_fields(&e) {
+            errors if !errors.is_empty() => invalid(errors),
+            _ => (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("Failed to create orders: {:?}", e),
+            ),
+        })
 }
 
+#[utoipa::path(
+    get,
+    path = "/table/{tableid}/orders",
+    tag = "orders",
+    params(("tableid" = u32, Path, description = "The table to get orders for.")),
+    responses((status = 200, description = "Every order for the table.", body = Vec<OrderDetails>))
+)]
 async fn table_orders_get(
     Extension(db): Extension<Database>,
-    Path((_, table_id)): Path<(String, layout::TableId)>,
+    Path(table_id): Path<layout::TableId>,
 ) -> Result<impl IntoResponse, (StatusCode, String)> {
+    // any table of a merged group answers for the whole party
+    let table_id = ver::serving_table_id(&db, table_id).await;
     let orders = match order::get_table(&db, table_id).await {
         Ok(orders) => orders,
         Err(e) => {
@@ -92,69 +223,228 @@ async fn table_orders_get(
     Ok(Json(
         orders
             .iter()
-            .map(|o| {
-                let remaining = TimeDelta::minutes((o.menu_item.cook_time.0 * o.quantity).into())
-                    - (Utc::now() - o.time_placed);
-
-                OrderDetails {
-                    id: o.id(),
-                    table: o.table.clone(),
-                    item: o.menu_item.clone(),
-                    time_placed: o.time_placed,
-                    quantity: o.quantity,
-                    estimated_minutes_remaining: menu::Minutes(
-                        remaining.num_minutes().try_into().unwrap_or(0),
-                    ),
-                }
-            })
+            .map(OrderDetails::from_domain)
             .collect::<Vec<OrderDetails>>(),
     ))
 }
 
-#[derive(Debug, Deserialize)]
-struct SetOrderQuantity {
-    quantity: u32,
-}
-
+#[utoipa::path(
+    post,
+    path = "/orders/{id}/setquantity",
+    tag = "orders",
+    params(
+        ("id" = u32, Path, description = "The order to change."),
+        ("If-Match" = Option<String>, Header, description = "Only change the order if it's still at this version."),
+    ),
+    request_body = SetOrderQuantity,
+    responses(
+        (status = 200, description = "The changed order, or the cancelled one if the quantity was zero.", body = Order, headers(("ETag" = String, description = "The order's new version, unless it was cancelled."))),
+        (status = 400, description = "The quantity is over the most an order can have.", body = String, content_type = "text/plain"),
+        (status = 409, description = "The order was changed by someone else while this change was being made.", body = String, content_type = "text/plain"),
+        (status = 412, description = "The order isn't at the version in `If-Match`.", body = String, content_type = "text/plain"),
+    )
+)]
 async fn orders_setquantity(
     Extension(mut db): Extension<Database>,
-    Path((_, id)): Path<(String, order::Id)>,
+    Extension(rules): Extension<order::Rules>,
+    Path(id): Path<order::Id>,
+    headers: HeaderMap,
     Json(SetOrderQuantity { quantity }): Json<SetOrderQuantity>,
+) -> Result<Response, (StatusCode, String)> {
+    let changed = match ver::expected_version(&db, id, &headers).await {
+        Ok(expected) => order::set_quantity(&mut db, &rules, id, quantity, expected).await,
+        Err(e) => Err(e),
+    };
+
+    changed
+        .map(|o| match quantity {
+            0 => Json(removed(Order::from_domain(&o))).into_response(),
+            _ => (ver::etag(o.version()), Json(Order::from_domain(&o))).into_response(),
+        })
+        .map_err(|e| match e {
+            OrderingError::VersionConflict { .. } => {
+                (ver::conflict_status(&headers), e.to_string())
+            }
+            e => match ver::invalid_fields(&e) {
+                errors if !errors.is_empty() => invalid(errors),
+                _ => (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    format!("Failed to set quantity for order '{:?}': {:?}", id, e),
+                ),
+            },
+        })
+}
+
+#[utoipa::path(
+    get,
+    path = "/orders/{id}",
+    tag = "orders",
+    params(("id" = u32, Path, description = "The order to get.")),
+    responses(
+        (status = 200, description = "The order.", body = OrderDetails, headers(("ETag" = String, description = "The order's current version."))),
+        (status = 404, description = "The order doesn't exist.", body = String, content_type = "text/plain"),
+    )
+)]
+async fn order_get(
+    Extension(db): Extension<Database>,
+    Path(id): Path<order::Id>,
 ) -> Result<impl IntoResponse, (StatusCode, String)> {
-    order::set_quantity(&mut db, id, quantity)
+    order::get(&db, id)
         .await
-        .map(Json)
-        .map_err(|e| {
+        .map(|o| (ver::etag(o.version()), Json(OrderDetails::from_domain(&o))))
+        .map_err(|_| {
             (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                format!("Failed to set quantity for order '{:?}': {:?}", id, e),
+                StatusCode::NOT_FOUND,
+                format!("Order '{:?}' not found.", id),
             )
         })
 }
 
+#[utoipa::path(
+    delete,
+    path = "/orders/{id}",
+    tag = "orders",
+    params(
+        ("id" = u32, Path, description = "The order to cancel."),
+        ("If-Match" = Option<String>, Header, description = "Only cancel the order if it's still at this version."),
+    ),
+    responses(
+        (status = 200, description = "The cancelled order.", body = Order),
+        (status = 412, description = "The order isn't at the version in `If-Match`.", body = String, content_type = "text/plain"),
+    )
+)]
 async fn orders_delete(
     Extension(mut db): Extension<Database>,
-    Path((_, id)): Path<(String, order::Id)>,
+    Path(id): Path<order::Id>,
+    headers: HeaderMap,
 ) -> Result<impl IntoResponse, (StatusCode, String)> {
-    order::cancel(&mut db, id).await.map(Json).map_err(|e| {
+    let cancelled = match ver::expected_version(&db, id, &headers).await {
+        Ok(expected) => order::cancel(&mut db, id, expected).await,
+        Err(e) => Err(e),
+    };
+
+    cancelled
+        .map(|o| Json(removed(Order::from_domain(&o))))
+        .map_err(|e| match e {
+            OrderingError::VersionConflict { .. } => {
+                (ver::conflict_status(&headers), e.to_string())
+            }
+            e => (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("Failed to cancel order '{:?}': {:?}", id, e),
+            ),
+        })
+}
+
+#[utoipa::path(
+    post,
+    path = "/table/{tableid}/clear",
+    tag = "orders",
+    params(("tableid" = u32, Path, description = "The table to clear.")),
+    responses((status = 200, description = "The orders that were removed.", body = Vec<Order>))
+)]
+async fn table_orders_clear(
+    Extension(mut db): Extension<Database>,
+    Path(table_id): Path<layout::TableId>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    let table_id = ver::serving_table_id(&db, table_id).await;
+    let orders = order::clear_table(&mut db, table_id).await.map_err(|e| {
         (
             StatusCode::INTERNAL_SERVER_ERROR,
-            format!("Failed to cancel order '{:?}': {:?}", id, e),
+            format!("Failed to clear table'{:?}': {:?}", table_id, e),
         )
-    })
+    })?;
+    ver::table_freed(&mut db, table_id).await;
+
+    Ok(Json(
+        orders
+            .iter()
+            .map(Order::from_domain)
+            .collect::<Vec<Order>>(),
+    ))
 }
 
-async fn table_orders_clear(
+// the table orders are moving to comes from the body, so a missing one is an invalid field rather than a 404
+async fn destination(
+    db: &Database,
+    table_id: u32,
+) -> Result<layout::RepoTable, (StatusCode, String)> {
+    layout::serving_table(db, layout::TableId(table_id))
+        .await
+        .map_err(|_| {
+            invalid(vec![field_error(
+                "/table_id",
+                format!("Table '{:?}' not found.", layout::TableId(table_id)),
+            )])
+        })
+}
+
+fn transfer_failed(e: OrderingError) -> (StatusCode, String) {
+    match e {
+        OrderingError::OrderNotFound(id) => (
+            StatusCode::NOT_FOUND,
+            format!("Order '{:?}' not found.", id),
+        ),
+        e => match ver::invalid_fields(&e) {
+            errors if !errors.is_empty() => invalid(errors),
+            _ => (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("Failed to transfer orders: {:?}", e),
+            ),
+        },
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/orders/{id}/transfer",
+    tag = "orders",
+    params(("id" = u32, Path, description = "The order to move.")),
+    request_body = Transfer,
+    responses(
+        (status = 200, description = "The order at its new table. It keeps its id and when it was placed.", body = Order),
+        (status = 400, description = "The table doesn't exist, isn't taking orders, or is the one the order is already at.", body = String, content_type = "text/plain"),
+        (status = 404, description = "The order doesn't exist.", body = String, content_type = "text/plain"),
+    )
+)]
+async fn order_transfer(
     Extension(mut db): Extension<Database>,
-    Path((_, table_id)): Path<(String, layout::TableId)>,
+    Path(id): Path<order::Id>,
+    Json(transfer): Json<Transfer>,
 ) -> Result<impl IntoResponse, (StatusCode, String)> {
-    order::clear_table(&mut db, table_id)
+    let table = destination(&db, transfer.table_id).await?;
+    order::transfer(&mut db, id, table)
         .await
-        .map(Json)
-        .map_err(|e| {
-            (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                format!("Failed to clear table'{:?}': {:?}", table_id, e),
+        .map(|o| (ver::etag(o.version()), Json(Order::from_domain(&o))))
+        .map_err(transfer_failed)
+}
+
+#[utoipa::path(
+    post,
+    path = "/table/{tableid}/transfer",
+    tag = "orders",
+    params(("tableid" = u32, Path, description = "The table to move orders from.")),
+    request_body = Transfer,
+    responses(
+        (status = 200, description = "Every order that moved, in the order they were placed.", body = Vec<Order>),
+        (status = 400, description = "The table doesn't exist, isn't taking orders, or is the one being moved from.", body = String, content_type = "text/plain"),
+    )
+)]
+async fn table_orders_transfer(
+    Extension(mut db): Extension<Database>,
+    Path(table_id): Path<layout::TableId>,
+    Json(transfer): Json<Transfer>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    let table = destination(&db, transfer.table_id).await?;
+    order::transfer_table(&mut db, table_id, table)
+        .await
+        .map(|orders| {
+            Json(
+                orders
+                    .iter()
+                    .map(Order::from_domain)
+                    .collect::<Vec<Order>>(),
             )
         })
+        .map_err(transfer_failed)
 }
@@ -0,0 +1,210 @@
+use axum::{
+    extract::{Path, Query},
+    http::StatusCode,
+    middleware,
+    response::IntoResponse,
+    Extension, Json,
+};
+use restaurant::reservation::{self, ReservationError};
+use restaurant::{api_key::Scope, staff::Role};
+use utoipa_axum::{router::OpenApiRouter, routes};
+
+use super::{
+    models::{
+        CreateReservation, FromDomain, IntoDomain, ReservationDetails, ReservationPatch,
+        ReservationQuery,
+    },
+    orders::invalid,
+};
+use crate::{
+    auth::{self, Access},
+    ver::{self, field_error},
+    Database,
+};
+
+pub fn create() -> OpenApiRouter {
+    OpenApiRouter::new()
+        .routes(routes!(reservations_get, reservations_post))
+        .routes(routes!(
+            reservation_get,
+            reservation_patch,
+            reservation_delete
+        ))
+        .route_layer(middleware::from_fn_with_state(
+            Access::changed_by(&[Role::Host]).or_key(Scope::TablesRead, Scope::TablesWrite),
+            auth::authorize,
+        ))
+}
+
+fn rejected(e: ReservationError) -> (StatusCode, String) {
+    let pointer = match e {
+        ReservationError::ReservationNotFound(_) => {
+            return (StatusCode::NOT_FOUND, e.to_string());
+        }
+        ReservationError::Overlaps { .. } => return (StatusCode::CONFLICT, e.to_string()),
+        ReservationError::TableNotFound(_) | ReservationError::TableInactive(_) => "/table_id",
+        ReservationError::EmptyParty | ReservationError::TableTooSmall { .. } => "/party_size",
+        ReservationError::InvalidWindow => "/ends_at",
+        ReservationError::RepoOperation(_) => {
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("Failed to access reservations: {:?}", e),
+            );
+        }
+    };
+    invalid(vec![field_error(pointer, e)])
+}
+
+#[utoipa::path(
+    get,
+    path = "/reservations",
+    tag = "reservations",
+    params(ReservationQuery),
+    responses((status = 200, description = "The day's reservations, by when they start.", body = Vec<ReservationDetails>))
+)]
+async fn reservations_get(
+    Extension(db): Extension<Database>,
+    Query(query): Query<ReservationQuery>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    reservation::on_day(&db, query.date)
+        .await
+        .map(|reservations| {
+            Json(
+                reservations
+                    .iter()
+                    .map(ReservationDetails::from_domain)
+                    .collect::<Vec<ReservationDetails>>(),
+            )
+        })
+        .map_err(rejected)
+}
+
+#[utoipa::path(
+    post,
+    path = "/reservations",
+    tag = "reservations",
+    request_body = CreateReservation,
+    responses(
+        (status = 201, description = "The booking.", body = ReservationDetails),
+        (status = 400, description = "The table doesn't exist, is out of service or is too small for the party, or the times are backwards.", body = String, content_type = "text/plain"),
+        (status = 409, description = "The table is already booked for some of the time.", body = String, content_type = "text/plain"),
+    )
+)]
+async fn reservations_post(
+    Extension(mut db): Extension<Database>,
+    Json(booking): Json<CreateReservation>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    let booked = reservation::book(
+        &mut db,
+        reservation::Reservation {
+            table: booking.table_id.into(),
+            party_size: booking.party_size,
+            window: reservation::Window {
+                starts: booking.starts_at,
+                ends: booking.ends_at,
+            },
+            contact: booking.contact.into_domain(),
+            changed_by: None,
+        },
+    )
+    .await
+    .map_err(rejected)?;
+
+    Ok((
+        StatusCode::CREATED,
+        ver::etag(booked.version()),
+        Json(ReservationDetails::from_domain(&booked)),
+    ))
+}
+
+#[utoipa::path(
+    get,
+    path = "/reservations/{id}",
+    tag = "reservations",
+    params(("id" = u32, Path, description = "The reservation to get.")),
+    responses(
+        (status = 200, description = "The reservation.", body = ReservationDetails, headers(("ETag" = String, description = "The reservation's current version."))),
+        (status = 404, description = "The reservation doesn't exist.", body = String, content_type = "text/plain"),
+    )
+)]
+async fn reservation_get(
+    Extension(db): Extension<Database>,
+    Path(id): Path<reservation::Id>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    reservation::get(&db, id)
+        .await
+        .map(|r| {
+            (
+                ver::etag(r.version()),
+                Json(ReservationDetails::from_domain(&r)),
+            )
+        })
+        .map_err(rejected)
+}
+
+#[utoipa::path(
+    patch,
+    path = "/reservations/{id}",
+    tag = "reservations",
+    params(("id" = u32, Path, description = "The reservation to change.")),
+    request_body = ReservationPatch,
+    responses(
+        (status = 200, description = "The changed reservation, which was checked as if it were booked afresh.", body = ReservationDetails, headers(("ETag" = String, description = "The reservation's new version."))),
+        (status = 400, description = "The change leaves the booking with a table that can't take it, or with backwards times.", body = String, content_type = "text/plain"),
+        (status = 404, description = "The reservation doesn't exist.", body = String, content_type = "text/plain"),
+        (status = 409, description = "The table is already booked for some of the new time.", body = String, content_type = "text/plain"),
+    )
+)]
+async fn reservation_patch(
+    Extension(mut db): Extension<Database>,
+    Path(id): Path<reservation::Id>,
+    Json(patch): Json<ReservationPatch>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    // moving just one end of the window keeps the other where it was
+    let window = match (patch.starts_at, patch.ends_at) {
+        (None, None) => None,
+        (starts, ends) => {
+            let current = reservation::get(&db, id).await.map_err(rejected)?.window;
+            Some(reservation::Window {
+                starts: starts.unwrap_or(current.starts),
+                ends: ends.unwrap_or(current.ends),
+            })
+        }
+    };
+    let changes = reservation::Changes {
+        table: patch.table_id.map(Into::into),
+        party_size: patch.party_size,
+        window,
+        contact: patch.contact.map(IntoDomain::into_domain),
+    };
+
+    reservation::modify(&mut db, id, changes)
+        .await
+        .map(|r| {
+            (
+                ver::etag(r.version()),
+                Json(ReservationDetails::from_domain(&r)),
+            )
+        })
+        .map_err(rejected)
+}
+
+#[utoipa::path(
+    delete,
+    path = "/reservations/{id}",
+    tag = "reservations",
+    params(("id" = u32, Path, description = "The reservation to cancel.")),
+    responses(
+        (status = 200, description = "The cancelled reservation.", body = ReservationDetails),
+        (status = 404, description = "The reservation doesn't exist.", body = String, content_type = "text/plain"),
+    )
+)]
+async fn reservation_delete(
+    Extension(mut db): Extension<Database>,
+    Path(id): Path<reservation::Id>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    reservation::cancel(&mut db, id)
+        .await
+        .map(|r| Json(ReservationDetails::from_domain(&r)))
+        .map_err(rejected)
+}
@@ -0,0 +1,154 @@
+use axum::{
+    extract::{Path, Query},
+    http::StatusCode,
+    middleware,
+    response::IntoResponse,
+    Extension, Json,
+};
+use chrono::Utc;
+use restaurant::shift::{self, ShiftError};
+use restaurant::{api_key::Scope, staff, staff::Role};
+use utoipa_axum::{router::OpenApiRouter, routes};
+
+use super::{
+    models::{AssignSection, FromDomain, IntoDomain, SectionAssignment, ServerTable, ShiftQuery},
+    orders::invalid,
+};
+use crate::{
+    auth::{self, Access},
+    ver::field_error,
+    Database,
+};
+
+// anyone on the floor can see who has which tables, but only managers hand out sections
+pub fn create() -> OpenApiRouter {
+    OpenApiRouter::new()
+        .routes(routes!(assignments_get, assignments_post))
+        .routes(routes!(assignment_delete))
+        .routes(routes!(staff_tables_get))
+        .route_layer(middleware::from_fn_with_state(
+            Access::changed_by(&[Role::Manager]).or_key(Scope::TablesRead, Scope::TablesWrite),
+            auth::authorize,
+        ))
+}
+
+fn rejected(e: ShiftError) -> (StatusCode, String) {
+    let pointer = match e {
+        ShiftError::AssignmentNotFound(_) => return (StatusCode::NOT_FOUND, e.to_string()),
+        ShiftError::SectionTaken { .. } => return (StatusCode::CONFLICT, e.to_string()),
+        ShiftError::StaffNotFound(_) | ShiftError::NotAServer(_) => "/server_id",
+        ShiftError::UnknownSection(_) => "/section",
+        ShiftError::InvalidWindow => "/ends_at",
+        ShiftError::RepoOperation(_) => {
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("Failed to access section assignments: {:?}", e),
+            );
+        }
+    };
+    invalid(vec![field_error(pointer, e)])
+}
+
+#[utoipa::path(
+    get,
+    path = "/sections/assignments",
+    tag = "sections",
+    params(ShiftQuery),
+    responses((status = 200, description = "Who has which section, by when their shift starts.", body = Vec<SectionAssignment>))
+)]
+async fn assignments_get(
+    Extension(db): Extension<Database>,
+    Query(query): Query<ShiftQuery>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    let assignments = match query.at {
+        Some(at) => shift::on_shift(&db, at).await,
+        None => shift::get_all(&db).await,
+    }
+    .map_err(rejected)?;
+
+    Ok(Json(
+        assignments
+            .iter()
+            .map(SectionAssignment::from_domain)
+            .collect::<Vec<_>>(),
+    ))
+}
+
+#[utoipa::path(
+    post,
+    path = "/sections/assignments",
+    tag = "sections",
+    request_body = AssignSection,
+    responses(
+        (status = 201, description = "The section's server for the shift.", body = SectionAssignment),
+        (status = 400, description = "The section has no tables, the staff member doesn't wait tables or doesn't work at the restaurant, or the times are backwards.", body = String, content_type = "text/plain"),
+        (status = 409, description = "The section already has a server for some of the time.", body = String, content_type = "text/plain"),
+    )
+)]
+async fn assignments_post(
+    Extension(mut db): Extension<Database>,
+    Json(assignment): Json<AssignSection>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    let assigned = shift::assign(&mut db, assignment.into_domain())
+        .await
+        .map_err(rejected)?;
+
+    Ok((
+        StatusCode::CREATED,
+        Json(SectionAssignment::from_domain(&assigned)),
+    ))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/sections/assignments/{id}",
+    tag = "sections",
+    params(("id" = u32, Path, description = "The section assignment to take back.")),
+    responses(
+        (status = 200, description = "The assignment that was taken back.", body = SectionAssignment),
+        (status = 404, description = "The section assignment doesn't exist.", body = String, content_type = "text/plain"),
+    )
+)]
+async fn assignment_delete(
+    Extension(mut db): Extension<Database>,
+    Path(id): Path<shift::Id>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    shift::unassign(&mut db, id)
+        .await
+        .map(|a| Json(SectionAssignment::from_domain(&a)))
+        .map_err(rejected)
+}
+
+#[utoipa::path(
+    get,
+    path = "/staff/{id}/tables",
+    tag = "sections",
+    params(
+        ("id" = u32, Path, description = "The server whose tables to get."),
+        ShiftQuery,
+    ),
+    responses(
+        (status = 200, description = "The tables in the server's sections, with their open orders.", body = Vec<ServerTable>),
+        (status = 404, description = "The staff member doesn't exist.", body = String, content_type = "text/plain"),
+    )
+)]
+async fn staff_tables_get(
+    Extension(db): Extension<Database>,
+    Path(id): Path<staff::Id>,
+    Query(query): Query<ShiftQuery>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    shift::tables_of(&db, id, query.at.unwrap_or_else(Utc::now))
+        .await
+        .map(|tables| {
+            Json(
+                tables
+                    .iter()
+                    .map(ServerTable::from_domain)
+                    .collect::<Vec<_>>(),
+            )
+        })
+        .map_err(|e| match e {
+            ShiftError::StaffNotFound(_) => (StatusCode::NOT_FOUND, e.to_string()),
+            e => rejected(e),
+        })
+}
@@ -0,0 +1,142 @@
+use axum::{extract::Path, http::StatusCode, middleware, response::IntoResponse, Extension, Json};
+use restaurant::staff::{self, Role, StaffError};
+use utoipa_axum::{router::OpenApiRouter, routes};
+
+use super::{
+    models::{FromDomain, HireStaff, IntoDomain, Login, Session, StaffDetails},
+    orders::invalid,
+};
+use crate::{
+    auth::{self, Access, Auth},
+    idempotency::NO_STORE,
+    ver::field_error,
+    Database,
+};
+
+pub fn create() -> OpenApiRouter {
+    OpenApiRouter::new()
+        .routes(routes!(staff_get, staff_post))
+        .routes(routes!(staff_dismiss))
+        .route_layer(middleware::from_fn_with_state(
+            Access::only(&[Role::Manager]),
+            auth::authorize,
+        ))
+        // signing in is the one thing that can't need a token
+        .routes(routes!(staff_login))
+}
+
+fn rejected(e: StaffError) -> (StatusCode, String) {
+    match e {
+        StaffError::StaffNotFound(_) => (StatusCode::NOT_FOUND, e.to_string()),
+        StaffError::NameTaken(_) => (StatusCode::CONFLICT, e.to_string()),
+        StaffError::WeakSecret => invalid(vec![field_error("/secret", e)]),
+        StaffError::InvalidCredentials => (StatusCode::UNAUTHORIZED, e.to_string()),
+        StaffError::Outranked(_) | StaffError::Elsewhere(_) => {
+            (StatusCode::FORBIDDEN, e.to_string())
+        }
+        StaffError::LockedOut(_) => (StatusCode::TOO_MANY_REQUESTS, e.to_string()),
+        StaffError::RepoOperation(_) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("Failed to access staff: {:?}", e),
+        ),
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/staff/login",
+    tag = "staff",
+    request_body = Login,
+    security(()),
+    responses(
+        (status = 200, description = "A token to send as `Authorization: Bearer` until it expires.", body = Session),
+        (status = 401, description = "The name or PIN is wrong, or the staff member has left.", body = String, content_type = "text/plain"),
+        (status = 429, description = "Too many wrong PINs have been tried for the name lately. The message says when to try again.", body = String, content_type = "text/plain"),
+    )
+)]
+async fn staff_login(
+    Extension(mut db): Extension<Database>,
+    Extension(auth): Extension<Auth>,
+    Json(login): Json<Login>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    let staff = staff::login(&mut db, &login.name, &login.secret)
+        .await
+        .map_err(rejected)?;
+    let (token, expires_at) = auth.issue(staff.id());
+
+    Ok((
+        NO_STORE,
+        Json(Session {
+            token,
+            expires_at,
+            staff: StaffDetails::from_domain(&staff),
+        }),
+    ))
+}
+
+#[utoipa::path(
+    get,
+    path = "/staff",
+    tag = "staff",
+    security(("bearer" = [])),
+    responses((status = 200, description = "Everyone who works at the restaurant, including those who've left.", body = Vec<StaffDetails>))
+)]
+async fn staff_get(
+    Extension(db): Extension<Database>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    staff::get_all(&db)
+        .await
+        .map(|all| {
+            Json(
+                all.iter()
+                    .map(StaffDetails::from_domain)
+                    .collect::<Vec<_>>(),
+            )
+        })
+        .map_err(rejected)
+}
+
+#[utoipa::path(
+    post,
+    path = "/staff",
+    tag = "staff",
+    request_body = HireStaff,
+    security(("bearer" = [])),
+    responses(
+        (status = 201, description = "The new staff member, who works at the restaurant and can sign in straight away.", body = StaffDetails),
+        (status = 400, description = "The PIN or password is too short.", body = String, content_type = "text/plain"),
+        (status = 403, description = "The role is at or above the signed in staff member's own.", body = String, content_type = "text/plain"),
+        (status = 409, description = "Someone already has the name.", body = String, content_type = "text/plain"),
+    )
+)]
+async fn staff_post(
+    Extension(mut db): Extension<Database>,
+    Json(hire): Json<HireStaff>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    staff::hire(&mut db, &hire.name, hire.role.into_domain(), &hire.secret)
+        .await
+        .map(|s| (StatusCode::CREATED, Json(StaffDetails::from_domain(&s))))
+        .map_err(rejected)
+}
+
+#[utoipa::path(
+    post,
+    path = "/staff/{id}/dismiss",
+    tag = "staff",
+    params(("id" = u32, Path, description = "The staff member who's leaving.")),
+    security(("bearer" = [])),
+    responses(
+        (status = 200, description = "The staff member, who can't sign in anymore. Tokens they already have stop working.", body = StaffDetails),
+        (status = 403, description = "The staff member's role is at or above the signed in staff member's own.", body = String, content_type = "text/plain"),
+        (status = 404, description = "The staff member doesn't exist, or doesn't work at the restaurant.", body = String, content_type = "text/plain"),
+    )
+)]
+async fn staff_dismiss(
+    Extension(mut db): Extension<Database>,
+    Path(id): Path<staff::Id>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    staff::dismiss(&mut db, id)
+        .await
+        .map(|s| Json(StaffDetails::from_domain(&s)))
+        .map_err(rejected)
+}
@@ -1,15 +1,234 @@
-use axum::{http::StatusCode, response::IntoResponse, routing::get, Extension, Json, Router};
-use restaurant::layout;
+use axum::{extract::Path, http::StatusCode, middleware, response::IntoResponse, Extension, Json};
+use chrono::TimeDelta;
+use restaurant::{
+    api_key::Scope,
+    layout::{self, assignment, LayoutError},
+    order,
+    staff::Role,
+};
+use utoipa_axum::{router::OpenApiRouter, routes};
 
-use crate::Database;
+use super::{
+    models::{
+        CreateTableGroup, FromDomain, Order, SplitTableGroup, SuggestTable, TableDetails,
+        TableGroupDetails, TableSuggestion,
+    },
+    orders::invalid,
+};
+use crate::{
+    auth::{self, Access},
+    ver::{self, field_error},
+    Database,
+};
 
-pub fn create() -> Router {
-    Router::new().route("/tables", get(tables_get))
+pub fn create() -> OpenApiRouter {
+    OpenApiRouter::new()
+        .routes(routes!(tables_get))
+        .routes(routes!(table_get))
+        .routes(routes!(tables_suggest))
+        .routes(routes!(table_groups_get, table_groups_post))
+        .routes(routes!(table_group_get))
+        .routes(routes!(table_group_split))
+        .route_layer(middleware::from_fn_with_state(
+            Access::changed_by(&[Role::Host, Role::Server])
+                .or_key(Scope::TablesRead, Scope::TablesWrite),
+            auth::authorize,
+        ))
 }
 
+fn group_error(e: LayoutError) -> (StatusCode, String) {
+    let status = match e {
+        LayoutError::GroupNotFound(_) => StatusCode::NOT_FOUND,
+        LayoutError::AlreadyGrouped(..) => StatusCode::CONFLICT,
+        LayoutError::TableNotFound(_)
+        | LayoutError::TableInactive(_)
+        | LayoutError::GroupTooSmall
+        | LayoutError::NotInGroup(..) => StatusCode::BAD_REQUEST,
+        _ => {
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("Failed to change table groups: {:?}", e),
+            )
+        }
+    };
+    (status, e.to_string())
+}
+
+async fn group_details(
+    db: &Database,
+    group: &layout::RepoTableGroup,
+) -> Result<TableGroupDetails, (StatusCode, String)> {
+    let orders = order::get_table(db, group.lead()).await.map_err(|e| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("Failed to get the group's orders: {:?}", e),
+        )
+    })?;
+    Ok(TableGroupDetails::from_domain((group, orders.as_slice())))
+}
+
+#[utoipa::path(
+    get,
+    path = "/tables",
+    tag = "tables",
+    responses((status = 200, description = "Every table in the restaurant.", body = Vec<TableDetails>))
+)]
 async fn tables_get(Extension(db): Extension<Database>) -> Result<impl IntoResponse, StatusCode> {
     layout::get_tables(&db)
         .await
-        .map(Json)
+        .map(|tables| {
+            Json(
+                tables
+                    .iter()
+                    .map(TableDetails::from_domain)
+                    .collect::<Vec<TableDetails>>(),
+            )
+        })
         .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)
 }
+
+#[utoipa::path(
+    post,
+    path = "/tables/suggest",
+    tag = "tables",
+    request_body = SuggestTable,
+    responses(
+        (status = 200, description = "The tables that would do for the party, best first. Empty if none would.", body = Vec<TableSuggestion>),
+        (status = 400, description = "The party is empty.", body = String, content_type = "text/plain"),
+    )
+)]
+async fn tables_suggest(
+    Extension(db): Extension<Database>,
+    Json(party): Json<SuggestTable>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    let mut wanted = assignment::Party::new(party.size);
+    wanted.section = party.section;
+    if let Some(stay) = party.stay_minutes {
+        wanted.stay = TimeDelta::minutes(stay.into());
+    }
+
+    assignment::suggest(&db, &wanted, &assignment::default_strategies())
+        .await
+        .map(|suggestions| Json(Vec::<TableSuggestion>::from_domain(suggestions.as_slice())))
+        .map_err(|e| match e {
+            LayoutError::EmptyParty => invalid(vec![field_error("/size", e)]),
+            e => group_error(e),
+        })
+}
+
+#[utoipa::path(
+    get,
+    path = "/table/{tableid}",
+    tag = "tables",
+    params(("tableid" = u32, Path, description = "The table to get.")),
+    responses(
+        (status = 200, description = "The table.", body = TableDetails, headers(("ETag" = String, description = "The table's current version."))),
+        (status = 404, description = "The table doesn't exist.", body = String, content_type = "text/plain"),
+    )
+)]
+async fn table_get(
+    Extension(db): Extension<Database>,
+    Path(table_id): Path<layout::TableId>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    layout::get(&db, table_id)
+        .await
+        .map(|t| (ver::etag(t.version()), Json(TableDetails::from_domain(&t))))
+        .map_err(|_| {
+            (
+                StatusCode::NOT_FOUND,
+                format!("Table '{:?}' not found.", table_id),
+            )
+        })
+}
+
+#[utoipa::path(
+    get,
+    path = "/table_groups",
+    tag = "tables",
+    responses((status = 200, description = "Every group of tables that's been pushed together.", body = Vec<TableGroupDetails>))
+)]
+async fn table_groups_get(
+    Extension(db): Extension<Database>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    let groups = layout::get_groups(&db).await.map_err(group_error)?;
+    let mut details = Vec::with_capacity(groups.len());
+    for group in groups.iter() {
+        details.push(group_details(&db, group).await?);
+    }
+    Ok(Json(details))
+}
+
+#[utoipa::path(
+    post,
+    path = "/table_groups",
+    tag = "tables",
+    request_body = CreateTableGroup,
+    responses(
+        (status = 200, description = "The new group, with the orders that were already at its tables.", body = TableGroupDetails),
+        (status = 400, description = "There aren't two different tables, or one doesn't exist or isn't in service.", body = String, content_type = "text/plain"),
+        (status = 409, description = "One of the tables is already in a group.", body = String, content_type = "text/plain"),
+    )
+)]
+async fn table_groups_post(
+    Extension(mut db): Extension<Database>,
+    Json(group): Json<CreateTableGroup>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    let tables: Vec<layout::TableId> = group.tables.into_iter().map(layout::TableId).collect();
+    let group = layout::merge(&mut db, &tables).await.map_err(group_error)?;
+    Ok(Json(group_details(&db, &group).await?))
+}
+
+#[utoipa::path(
+    get,
+    path = "/table_groups/{id}",
+    tag = "tables",
+    params(("id" = u32, Path, description = "The group to get.")),
+    responses(
+        (status = 200, description = "The group, with every order of the party and when it should all be ready.", body = TableGroupDetails),
+        (status = 404, description = "The group doesn't exist.", body = String, content_type = "text/plain"),
+    )
+)]
+async fn table_group_get(
+    Extension(db): Extension<Database>,
+    Path(id): Path<layout::TableGroupId>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    let group = layout::get_group(&db, id).await.map_err(group_error)?;
+    Ok(Json(group_details(&db, &group).await?))
+}
+
+#[utoipa::path(
+    post,
+    path = "/table_groups/{id}/split",
+    tag = "tables",
+    params(("id" = u32, Path, description = "The group to split back up.")),
+    request_body = SplitTableGroup,
+    responses(
+        (status = 200, description = "The party's orders, at the table it stayed at.", body = Vec<Order>),
+        (status = 400, description = "The table isn't one of the group's.", body = String, content_type = "text/plain"),
+        (status = 404, description = "The group doesn't exist.", body = String, content_type = "text/plain"),
+    )
+)]
+async fn table_group_split(
+    Extension(mut db): Extension<Database>,
+    Path(id): Path<layout::TableGroupId>,
+    Json(split): Json<SplitTableGroup>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    let to = match split.table_id {
+        Some(table) => layout::TableId(table),
+        None => layout::get_group(&db, id)
+            .await
+            .map_err(group_error)?
+            .lead(),
+    };
+    layout::split(&mut db, id, to)
+        .await
+        .map(|orders| {
+            Json(
+                orders
+                    .iter()
+                    .map(Order::from_domain)
+                    .collect::<Vec<Order>>(),
+            )
+        })
+        .map_err(group_error)
+}
@@ -0,0 +1,196 @@
+use axum::{
+    extract::{Path, Query},
+    http::StatusCode,
+    middleware,
+    response::IntoResponse,
+    Extension, Json,
+};
+use restaurant::waitlist::{self, WaitlistError};
+use restaurant::{api_key::Scope, staff::Role};
+use utoipa_axum::{router::OpenApiRouter, routes};
+
+use super::{
+    models::{minutes, FromDomain, JoinWaitlist, PartyDetails, WaitQuery, WaitQuote},
+    orders::invalid,
+};
+use crate::{
+    auth::{self, Access},
+    ver::field_error,
+    Database,
+};
+
+pub fn create() -> OpenApiRouter {
+    OpenApiRouter::new()
+        .routes(routes!(waitlist_get, waitlist_post))
+        .routes(routes!(waitlist_quote))
+        .routes(routes!(party_get))
+        .routes(routes!(party_seat))
+        .routes(routes!(party_no_show))
+        .route_layer(middleware::from_fn_with_state(
+            Access::changed_by(&[Role::Host]).or_key(Scope::TablesRead, Scope::TablesWrite),
+            auth::authorize,
+        ))
+}
+
+fn rejected(e: WaitlistError) -> (StatusCode, String) {
+    match e {
+        WaitlistError::PartyNotFound(_) => (StatusCode::NOT_FOUND, e.to_string()),
+        WaitlistError::NotWaiting(_) => (StatusCode::CONFLICT, e.to_string()),
+        WaitlistError::EmptyParty | WaitlistError::NoTableFits(_) => {
+            invalid(vec![field_error("/size", e)])
+        }
+        WaitlistError::RepoOperation(_) | WaitlistError::Announce { .. } => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("Failed to access the waitlist: {:?}", e),
+        ),
+    }
+}
+
+async fn details(
+    db: &Database,
+    rules: &waitlist::Rules,
+    party: &waitlist::RepoParty,
+) -> Result<PartyDetails, (StatusCode, String)> {
+    let wait = match party.status.in_line() {
+        true => Some(
+            waitlist::estimate(db, rules, party.id())
+                .await
+                .map_err(rejected)?,
+        ),
+        false => None,
+    };
+    Ok(PartyDetails::from_domain((party, wait)))
+}
+
+#[utoipa::path(
+    get,
+    path = "/waitlist",
+    tag = "waitlist",
+    responses((status = 200, description = "The parties still in line, first come first served.", body = Vec<PartyDetails>))
+)]
+async fn waitlist_get(
+    Extension(db): Extension<Database>,
+    Extension(rules): Extension<waitlist::Rules>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    let mut line = Vec::new();
+    for party in waitlist::in_line(&db).await.map_err(rejected)? {
+        line.push(details(&db, &rules, &party).await?);
+    }
+    Ok(Json(line))
+}
+
+#[utoipa::path(
+    post,
+    path = "/waitlist",
+    tag = "waitlist",
+    request_body = JoinWaitlist,
+    responses(
+        (status = 201, description = "The party, at the end of the line.", body = PartyDetails),
+        (status = 400, description = "The party is empty, or no table in service sits them.", body = String, content_type = "text/plain"),
+    )
+)]
+async fn waitlist_post(
+    Extension(mut db): Extension<Database>,
+    Extension(rules): Extension<waitlist::Rules>,
+    Json(party): Json<JoinWaitlist>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    let party = waitlist::add(
+        &mut db,
+        &rules,
+        waitlist::Party::new(party.name, party.size, party.phone),
+    )
+    .await
+    .map_err(rejected)?;
+
+    Ok((
+        StatusCode::CREATED,
+        Json(details(&db, &rules, &party).await?),
+    ))
+}
+
+#[utoipa::path(
+    get,
+    path = "/waitlist/quote",
+    tag = "waitlist",
+    params(WaitQuery),
+    responses(
+        (status = 200, description = "How long the party would wait if they joined now.", body = WaitQuote),
+        (status = 400, description = "The party is empty, or no table in service sits them.", body = String, content_type = "text/plain"),
+    )
+)]
+async fn waitlist_quote(
+    Extension(db): Extension<Database>,
+    Extension(rules): Extension<waitlist::Rules>,
+    Query(query): Query<WaitQuery>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    waitlist::quote(&db, &rules, query.size)
+        .await
+        .map(|wait| {
+            Json(WaitQuote {
+                size: query.size,
+                estimated_minutes: minutes(wait),
+            })
+        })
+        .map_err(rejected)
+}
+
+#[utoipa::path(
+    get,
+    path = "/waitlist/{id}",
+    tag = "waitlist",
+    params(("id" = u32, Path, description = "The party to get.")),
+    responses(
+        (status = 200, description = "The party, with how long they're likely to wait if they're still in line.", body = PartyDetails),
+        (status = 404, description = "The party doesn't exist.", body = String, content_type = "text/plain"),
+    )
+)]
+async fn party_get(
+    Extension(db): Extension<Database>,
+    Extension(rules): Extension<waitlist::Rules>,
+    Path(id): Path<waitlist::Id>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    let party = waitlist::get(&db, id).await.map_err(rejected)?;
+    Ok(Json(details(&db, &rules, &party).await?))
+}
+
+#[utoipa::path(
+    post,
+    path = "/waitlist/{id}/seat",
+    tag = "waitlist",
+    params(("id" = u32, Path, description = "The party being seated.")),
+    responses(
+        (status = 200, description = "The party, who've left the line.", body = PartyDetails),
+        (status = 404, description = "The party doesn't exist.", body = String, content_type = "text/plain"),
+        (status = 409, description = "The party already left the line.", body = String, content_type = "text/plain"),
+    )
+)]
+async fn party_seat(
+    Extension(mut db): Extension<Database>,
+    Path(id): Path<waitlist::Id>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    waitlist::seat(&mut db, id)
+        .await
+        .map(|p| Json(PartyDetails::from_domain((&p, None))))
+        .map_err(rejected)
+}
+
+#[utoipa::path(
+    post,
+    path = "/waitlist/{id}/no_show",
+    tag = "waitlist",
+    params(("id" = u32, Path, description = "The party that didn't turn up.")),
+    responses(
+        (status = 200, description = "The party, who've left the line. A table held for them goes to the next party that fits.", body = PartyDetails),
+        (status = 404, description = "The party doesn't exist.", body = String, content_type = "text/plain"),
+        (status = 409, description = "The party already left the line.", body = String, content_type = "text/plain"),
+    )
+)]
+async fn party_no_show(
+    Extension(mut db): Extension<Database>,
+    Path(id): Path<waitlist::Id>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    waitlist::no_show(&mut db, id)
+        .await
+        .map(|p| Json(PartyDetails::from_domain((&p, None))))
+        .map_err(rejected)
+}
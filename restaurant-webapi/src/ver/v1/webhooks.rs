@@ -0,0 +1,164 @@
+use axum::{extract::Path, http::StatusCode, middleware, response::IntoResponse, Extension, Json};
+use restaurant::{api_key::Scope, staff::Role, tenant::Scoped};
+use utoipa_axum::{router::OpenApiRouter, routes};
+
+use super::models::{
+    CreateWebhook, CreatedWebhook, FromDomain, IntoDomain, WebhookDelivery, WebhookDetails,
+};
+use crate::auth::{self, Access};
+use crate::idempotency::NO_STORE;
+use crate::webhooks::{DeliveryId, SubscriptionId, Webhooks};
+use crate::Database;
+
+pub fn create() -> OpenApiRouter {
+    OpenApiRouter::new()
+        .routes(routes!(webhooks_post, webhooks_get))
+        .routes(routes!(webhooks_delete))
+        .routes(routes!(dead_letters_get))
+        .routes(routes!(dead_letter_retry))
+        .route_layer(middleware::from_fn_with_state(
+            Access::only(&[Role::Manager]).or_key(Scope::WebhooksAdmin, Scope::WebhooksAdmin),
+            auth::authorize,
+        ))
+}
+
+#[utoipa::path(
+    post,
+    path = "/webhooks",
+    tag = "webhooks",
+    request_body = CreateWebhook,
+    responses(
+        (status = 201, description = "The registered webhook, including its signing secret.", body = CreatedWebhook),
+        (status = 400, description = "The url isn't valid.", body = String, content_type = "text/plain"),
+    )
+)]
+async fn webhooks_post(
+    Extension(webhooks): Extension<Webhooks>,
+    Extension(db): Extension<Database>,
+    Json(webhook): Json<CreateWebhook>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    if reqwest::Url::parse(&webhook.url).is_err() {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            format!("'{}' is not a valid url.", webhook.url),
+        ));
+    }
+
+    webhooks
+        .register(
+            db.tenant(),
+            webhook.url,
+            webhook
+                .events
+                .into_iter()
+                .map(IntoDomain::into_domain)
+                .collect(),
+        )
+        .map(|s| {
+            (
+                StatusCode::CREATED,
+                NO_STORE,
+                Json(CreatedWebhook::from_domain(s)),
+            )
+        })
+        .map_err(|e| {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("Failed to register webhook: {:?}", e),
+            )
+        })
+}
+
+#[utoipa::path(
+    get,
+    path = "/webhooks",
+    tag = "webhooks",
+    responses((status = 200, description = "Every registered webhook.", body = Vec<WebhookDetails>))
+)]
+async fn webhooks_get(
+    Extension(webhooks): Extension<Webhooks>,
+    Extension(db): Extension<Database>,
+) -> impl IntoResponse {
+    Json(
+        webhooks
+            .subscriptions(db.tenant())
+            .into_iter()
+            .map(WebhookDetails::from_domain)
+            .collect::<Vec<WebhookDetails>>(),
+    )
+}
+
+#[utoipa::path(
+    delete,
+    path = "/webhooks/{id}",
+    tag = "webhooks",
+    params(("id" = u32, Path, description = "The webhook to remove.")),
+    responses(
+        (status = 200, description = "The removed webhook.", body = WebhookDetails),
+        (status = 404, description = "The webhook doesn't exist.", body = String, content_type = "text/plain"),
+    )
+)]
+async fn webhooks_delete(
+    Extension(webhooks): Extension<Webhooks>,
+    Extension(db): Extension<Database>,
+    Path(id): Path<u32>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    match webhooks.unregister(db.tenant(), SubscriptionId(id)) {
+        Ok(Some(s)) => Ok(Json(WebhookDetails::from_domain(s))),
+        Ok(None) => Err((
+            StatusCode::NOT_FOUND,
+            format!("Webhook '{}' not found.", id),
+        )),
+        Err(e) => Err((
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("Failed to remove webhook '{}': {:?}", id, e),
+        )),
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/webhooks/dead_letters",
+    tag = "webhooks",
+    responses((status = 200, description = "Deliveries that ran out of retries.", body = Vec<WebhookDelivery>))
+)]
+async fn dead_letters_get(
+    Extension(webhooks): Extension<Webhooks>,
+    Extension(db): Extension<Database>,
+) -> impl IntoResponse {
+    Json(
+        webhooks
+            .dead_letters(db.tenant())
+            .into_iter()
+            .map(WebhookDelivery::from_domain)
+            .collect::<Vec<WebhookDelivery>>(),
+    )
+}
+
+#[utoipa::path(
+    post,
+    path = "/webhooks/dead_letters/{id}/retry",
+    tag = "webhooks",
+    params(("id" = u32, Path, description = "The dead letter to queue up again.")),
+    responses(
+        (status = 200, description = "The delivery, back in the queue.", body = WebhookDelivery),
+        (status = 404, description = "The dead letter doesn't exist.", body = String, content_type = "text/plain"),
+    )
+)]
+async fn dead_letter_retry(
+    Extension(webhooks): Extension<Webhooks>,
+    Extension(db): Extension<Database>,
+    Path(id): Path<u32>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    match webhooks.retry_dead_letter(db.tenant(), DeliveryId(id)) {
+        Ok(Some(d)) => Ok(Json(WebhookDelivery::from_domain(d))),
+        Ok(None) => Err((
+            StatusCode::NOT_FOUND,
+            format!("Dead letter '{}' not found.", id),
+        )),
+        Err(e) => Err((
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("Failed to retry dead letter '{}': {:?}", id, e),
+        )),
+    }
+}
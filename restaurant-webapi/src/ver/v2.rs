@@ -1,11 +1,41 @@
-use axum::{http::StatusCode, routing::any, Router};
+use axum::{routing::get, Json};
+use utoipa::{openapi::OpenApi, OpenApi as _};
+use utoipa_axum::router::OpenApiRouter;
 
-use super::VersionedApi;
+use super::{with_security, with_version_header, VersionedApi};
+
+mod kitchen;
+mod menu;
+pub mod models;
+mod orders;
+mod problem;
+mod tables;
+
+// v2 is served alongside v1 against the same database. it's resource-oriented, and its errors are
+// problem documents rather than plain text.
+
+#[derive(utoipa::OpenApi)]
+#[openapi(info(title = "Restaurant API", version = "v2"))]
+struct ApiDoc;
 
 pub fn create() -> VersionedApi {
-    VersionedApi::new("v2", Router::new().route("/*path", any(any_root)))
+    let (router, api) = router().split_for_parts();
+    let api = with_security(with_version_header(api, "v2"));
+
+    VersionedApi::new(
+        "v2",
+        router.route("/openapi.json", get(|| async { Json(api) })),
+    )
+}
+
+pub fn openapi() -> OpenApi {
+    with_security(with_version_header(router().split_for_parts().1, "v2"))
 }
 
-async fn any_root() -> (StatusCode, &'static str) {
-    (StatusCode::NOT_FOUND, "Use v1")
+fn router() -> OpenApiRouter {
+    OpenApiRouter::with_openapi(ApiDoc::openapi())
+        .merge(orders::create())
+        .merge(kitchen::create())
+        .merge(menu::create())
+        .merge(tables::create())
 }
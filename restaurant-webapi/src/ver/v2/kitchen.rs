@@ -0,0 +1,96 @@
+use axum::{extract::Path, http::StatusCode, middleware, response::IntoResponse, Extension, Json};
+use restaurant::{
+    api_key::Scope,
+    kitchen::{self, KitchenError},
+    order,
+    staff::Role,
+};
+use utoipa_axum::{router::OpenApiRouter, routes};
+
+use super::{
+    models::{FromDomain, IntoDomain, Order, Problem, Station},
+    problem::{problem, ProblemResponse},
+};
+use crate::auth::{self, Access};
+use crate::Database;
+
+pub fn create() -> OpenApiRouter {
+    OpenApiRouter::new()
+        .routes(routes!(station_tickets_get))
+        .routes(routes!(station_ticket_bump))
+        .route_layer(middleware::from_fn_with_state(
+            Access::changed_by(&[Role::Kitchen]).or_key(Scope::OrdersRead, Scope::KitchenWrite),
+            auth::authorize,
+        ))
+}
+
+#[utoipa::path(
+    get,
+    path = "/stations/{station}/tickets",
+    tag = "kitchen",
+    params(("station" = Station, Path, description = "The station to get tickets for.")),
+    responses((status = 200, description = "The station's open orders, in fire order.", body = Vec<Order>))
+)]
+async fn station_tickets_get(
+    Extension(db): Extension<Database>,
+    Path(station): Path<Station>,
+) -> Result<impl IntoResponse, ProblemResponse> {
+    let station = station.into_domain();
+    kitchen::station_tickets(&db, station)
+        .await
+        .map(|tickets| {
+            Json(
+                tickets
+                    .iter()
+                    .map(Order::from_domain)
+                    .collect::<Vec<Order>>(),
+            )
+        })
+        .map_err(|e| {
+            problem(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("Failed to get tickets for station '{:?}': {:?}", station, e),
+            )
+        })
+}
+
+#[utoipa::path(
+    post,
+    path = "/stations/{station}/tickets/{id}/bump",
+    tag = "kitchen",
+    params(
+        ("station" = Station, Path, description = "The station bumping the ticket."),
+        ("id" = u32, Path, description = "The order that is ready."),
+    ),
+    responses(
+        (status = 200, description = "The order, now ready.", body = Order),
+        (status = 404, description = "The order doesn't exist.", body = Problem, content_type = "application/problem+json"),
+        (status = 409, description = "The order is prepared at a different station, or is already ready.", body = Problem, content_type = "application/problem+json"),
+    )
+)]
+async fn station_ticket_bump(
+    Extension(mut db): Extension<Database>,
+    Path((station, id)): Path<(Station, order::Id)>,
+) -> Result<impl IntoResponse, ProblemResponse> {
+    let station = station.into_domain();
+    kitchen::bump(&mut db, station, id)
+        .await
+        .map(|o| Json(Order::from_domain(&o)))
+        .map_err(|e| match e {
+            KitchenError::OrderNotFound(_) => {
+                problem(StatusCode::NOT_FOUND, format!("Order {} not found.", id.0))
+            }
+            KitchenError::WrongStation { .. } => problem(
+                StatusCode::CONFLICT,
+                format!("Order {} is not prepared at '{:?}'.", id.0, station),
+            ),
+            KitchenError::AlreadyReady(_) => problem(
+                StatusCode::CONFLICT,
+                format!("Order {} has already been bumped.", id.0),
+            ),
+            e => problem(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("Failed to bump order {}: {:?}", id.0, e),
+            ),
+        })
+}
@@ -0,0 +1,74 @@
+use axum::{extract::Path, http::StatusCode, middleware, response::IntoResponse, Extension, Json};
+use restaurant::{api_key::Scope, menu, staff::Role};
+use utoipa_axum::{router::OpenApiRouter, routes};
+
+use super::{
+    models::{FromDomain, MenuItem, Problem},
+    problem::{problem, ProblemResponse},
+};
+use crate::{
+    auth::{self, Access},
+    ver, Database,
+};
+
+pub fn create() -> OpenApiRouter {
+    OpenApiRouter::new()
+        .routes(routes!(menu_items_get))
+        .routes(routes!(menu_item_get))
+        .route_layer(middleware::from_fn_with_state(
+            Access::changed_by(&[Role::Manager]).or_key(Scope::MenuRead, Scope::MenuAdmin),
+            auth::authorize,
+        ))
+}
+
+#[utoipa::path(
+    get,
+    path = "/menu/items",
+    tag = "menu",
+    responses((status = 200, description = "Everything on the menu.", body = Vec<MenuItem>))
+)]
+async fn menu_items_get(
+    Extension(db): Extension<Database>,
+) -> Result<impl IntoResponse, ProblemResponse> {
+    menu::get_all(&db)
+        .await
+        .map(|items| {
+            Json(
+                items
+                    .iter()
+                    .map(MenuItem::from_domain)
+                    .collect::<Vec<MenuItem>>(),
+            )
+        })
+        .map_err(|e| {
+            problem(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("Failed to get the menu: {:?}", e),
+            )
+        })
+}
+
+#[utoipa::path(
+    get,
+    path = "/menu/items/{id}",
+    tag = "menu",
+    params(("id" = u32, Path, description = "The menu item to get.")),
+    responses(
+        (status = 200, description = "The menu item.", body = MenuItem, headers(("ETag" = String, description = "The menu item's current version."))),
+        (status = 404, description = "The menu item doesn't exist.", body = Problem, content_type = "application/problem+json"),
+    )
+)]
+async fn menu_item_get(
+    Extension(db): Extension<Database>,
+    Path(id): Path<menu::Id>,
+) -> Result<impl IntoResponse, ProblemResponse> {
+    menu::get(&db, id)
+        .await
+        .map(|i| (ver::etag(i.version()), Json(MenuItem::from_domain(&i))))
+        .map_err(|_| {
+            problem(
+                StatusCode::NOT_FOUND,
+                format!("Menu item {} not found.", id.0),
+            )
+        })
+}
@@ -0,0 +1,95 @@
+use chrono::{TimeDelta, Utc};
+use restaurant::{kitchen, layout, menu, order};
+
+pub use restaurant_client::v2::*;
+
+use crate::ver::links;
+pub use crate::ver::{links::removed, FromDomain, IntoDomain};
+
+impl FromDomain<&layout::RepoTable> for Table {
+    fn from_domain(t: &layout::RepoTable) -> Self {
+        let id = t.id().0;
+        Table {
+            id,
+            seats: t.seats,
+            section: t.section.clone(),
+            links: TableLinks {
+                self_link: links::get(format!("/api/tables/{}", id)),
+                orders: links::get(format!("/api/tables/{}/orders", id)),
+                status: links::get(format!("/api/tables/{}/status", id)),
+                place_order: links::action("POST", format!("/api/tables/{}/orders", id)),
+                clear: links::action("DELETE", format!("/api/tables/{}/orders", id)),
+            },
+        }
+    }
+}
+
+impl FromDomain<&menu::RepoItem> for MenuItem {
+    fn from_domain(i: &menu::RepoItem) -> Self {
+        MenuItem {
+            id: i.id().into(),
+            name: i.name.clone(),
+            cook_time_minutes: i.cook_time.0,
+            station: Station::from_domain(i.station),
+            available: i.available,
+            links: MenuItemLinks {
+                self_link: links::get(format!("/api/menu/items/{}", i.id().0)),
+            },
+        }
+    }
+}
+
+// v2 has resources under plural names, and changes quantities by patching the order
+const ORDER_ROUTES: links::OrderRoutes = links::OrderRoutes {
+    table: "tables",
+    menu_item: "menu/items",
+    set_quantity: ("PATCH", ""),
+};
+
+impl FromDomain<&order::RepoOrder> for Order {
+    fn from_domain(o: &order::RepoOrder) -> Self {
+        let remaining = TimeDelta::minutes((o.menu_item.cook_time.0 * o.quantity).into())
+            - (Utc::now() - o.time_placed);
+
+        Order {
+            id: o.id().into(),
+            table_id: o.table.id().into(),
+            item: OrderedItem {
+                id: o.menu_item.id().into(),
+                name: o.menu_item.name.clone(),
+                station: Station::from_domain(o.menu_item.station),
+            },
+            quantity: o.quantity,
+            status: OrderStatus::from_domain(o.status),
+            group_id: o.group.map(u32::from),
+            placed_at: o.time_placed,
+            estimated_minutes_remaining: match o.status {
+                order::Status::Placed => remaining.num_minutes().try_into().unwrap_or(0),
+                order::Status::Ready => 0,
+            },
+            links: links::order(&ORDER_ROUTES, o),
+        }
+    }
+}
+
+impl FromDomain<&[order::RepoOrder]> for OrderGroup {
+    fn from_domain(orders: &[order::RepoOrder]) -> Self {
+        let first = orders.first().expect("Groups should never be empty.");
+        OrderGroup {
+            id: first.group.map_or(0, u32::from),
+            table_id: first.table.id().into(),
+            placed_at: first.time_placed,
+            orders: orders.iter().map(Order::from_domain).collect(),
+        }
+    }
+}
+
+impl FromDomain<kitchen::TableStatus> for TableStatus {
+    fn from_domain(s: kitchen::TableStatus) -> Self {
+        TableStatus {
+            table_id: s.table.into(),
+            ready: s.ready,
+            waiting_on: s.waiting_on.into_iter().map(Station::from_domain).collect(),
+        }
+    }
+}
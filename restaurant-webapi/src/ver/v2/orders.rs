@@ -0,0 +1,426 @@
+use axum::{
+    extract::{Path, Query},
+    http::{header, HeaderMap, StatusCode},
+    middleware,
+    response::IntoResponse,
+    Extension, Json,
+};
+use restaurant::{
+    api_key::Scope,
+    layout, menu,
+    order::{self, OrderingError},
+    staff::Role,
+};
+use utoipa_axum::{router::OpenApiRouter, routes};
+
+use super::{
+    models::{
+        removed, FromDomain, NewOrder, NewOrderGroup, Order, OrderGroup, OrderPage, OrderPatch,
+        OrderQuery, Problem, Transfer,
+    },
+    problem::{invalid, problem, ProblemResponse},
+};
+use crate::{
+    auth::{self, Access},
+    ver::{self, field_error, links},
+    Database,
+};
+
+pub fn create() -> OpenApiRouter {
+    OpenApiRouter::new()
+        .routes(routes!(
+            table_orders_get,
+            table_orders_post,
+            table_orders_delete
+        ))
+        .routes(routes!(table_order_groups_post))
+        .routes(routes!(orders_get))
+        .routes(routes!(order_get, order_patch, order_delete))
+        .routes(routes!(order_transfer))
+        .routes(routes!(table_orders_transfer))
+        .route_layer(middleware::from_fn_with_state(
+            Access::changed_by(&[Role::Server]).or_key(Scope::OrdersRead, Scope::OrdersWrite),
+            auth::authorize,
+        ))
+}
+
+fn not_found(e: OrderingError) -> ProblemResponse {
+    match e {
+        OrderingError::OrderNotFound(id) => {
+            problem(StatusCode::NOT_FOUND, format!("Order {} not found.", id.0))
+        }
+        e => problem(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("Failed to access orders: {:?}", e),
+        ),
+    }
+}
+
+// for changes the domain refused to make
+fn rejected(e: OrderingError) -> ProblemResponse {
+    match e {
+        OrderingError::TableInactive(id) => problem(
+            StatusCode::CONFLICT,
+            format!("Table {} isn't taking orders.", id.0),
+        ),
+        e => match ver::invalid_fields(&e) {
+            errors if !errors.is_empty() => invalid(errors),
+            _ => not_found(e),
+        },
+    }
+}
+
+// the table the path names, or its group's lead while it's merged
+async fn table(db: &Database, id: layout::TableId) -> Result<layout::RepoTable, ProblemResponse> {
+    layout::serving_table(db, id)
+        .await
+        .map_err(|_| problem(StatusCode::NOT_FOUND, format!("Table {} not found.", id.0)))
+}
+
+#[utoipa::path(
+    get,
+    path = "/tables/{id}/orders",
+    tag = "orders",
+    params(("id" = u32, Path, description = "The table to get orders for.")),
+    responses(
+        (status = 200, description = "Every open order for the table.", body = Vec<Order>),
+        (status = 404, description = "The table doesn't exist.", body = Problem, content_type = "application/problem+json"),
+    )
+)]
+async fn table_orders_get(
+    Extension(db): Extension<Database>,
+    Path(id): Path<layout::TableId>,
+) -> Result<impl IntoResponse, ProblemResponse> {
+    let id = table(&db, id).await?.id();
+
+    order::get_table(&db, id)
+        .await
+        .map(|orders| {
+            Json(
+                orders
+                    .iter()
+                    .map(Order::from_domain)
+                    .collect::<Vec<Order>>(),
+            )
+        })
+        .map_err(not_found)
+}
+
+#[utoipa::path(
+    post,
+    path = "/tables/{id}/orders",
+    tag = "orders",
+    params(("id" = u32, Path, description = "The table placing the order.")),
+    request_body = NewOrder,
+    responses(
+        (status = 201, description = "The placed order. Its location is in the Location header.", body = Order),
+        (status = 400, description = "The menu item doesn't exist or isn't available, or the quantity is out of range. `errors` has each invalid field.", body = Problem, content_type = "application/problem+json"),
+        (status = 404, description = "The table doesn't exist.", body = Problem, content_type = "application/problem+json"),
+        (status = 409, description = "The table isn't taking orders.", body = Problem, content_type = "application/problem+json"),
+    )
+)]
+async fn table_orders_post(
+    Extension(mut db): Extension<Database>,
+    Extension(rules): Extension<order::Rules>,
+    Path(id): Path<layout::TableId>,
+    Json(new): Json<NewOrder>,
+) -> Result<impl IntoResponse, ProblemResponse> {
+    let table = table(&db, id).await?;
+    let item = menu::get(&db, menu::Id(new.item_id)).await;
+
+    // reported together with whatever's wrong with the quantity
+    let mut errors = Vec::new();
+    if item.is_err() {
+        errors.push(field_error(
+            "/item_id",
+            format!("Menu item {} not found.", new.item_id),
+        ));
+    }
+    if let Err(e) = rules.check_quantity(new.quantity) {
+        errors.extend(ver::invalid_fields(&e));
+    }
+    let item = match item {
+        Ok(item) if errors.is_empty() => item,
+        _ => return Err(invalid(errors)),
+    };
+
+    let placed = order::place(&mut db, &rules, table, item, new.quantity)
+        .await
+        .map_err(rejected)?;
+    Ok((
+        StatusCode::CREATED,
+        [(
+            header::LOCATION,
+            links::href(&format!("/api/orders/{}", placed.id().0)),
+        )],
+        Json(Order::from_domain(&placed)),
+    ))
+}
+
+#[utoipa::path(
+    post,
+    path = "/tables/{id}/order_groups",
+    tag = "orders",
+    params(("id" = u32, Path, description = "The table placing the orders.")),
+    request_body = NewOrderGroup,
+    responses(
+        (status = 201, description = "The placed orders, all placed at the same time.", body = OrderGroup),
+        (status = 400, description = "At least one of the items can't be ordered. Nothing was placed. `errors` has each invalid field.", body = Problem, content_type = "application/problem+json"),
+        (status = 404, description = "The table doesn't exist.", body = Problem, content_type = "application/problem+json"),
+        (status = 409, description = "The table isn't taking orders.", body = Problem, content_type = "application/problem+json"),
+    )
+)]
+async fn table_order_groups_post(
+    Extension(mut db): Extension<Database>,
+    Extension(rules): Extension<order::Rules>,
+    Path(id): Path<layout::TableId>,
+    Json(group): Json<NewOrderGroup>,
+) -> Result<impl IntoResponse, ProblemResponse> {
+    let table = table(&db, id).await?;
+    let lines: Vec<order::GroupLine> = group
+        .items
+        .iter()
+        .map(|i| order::GroupLine {
+            menu_item: menu::Id(i.item_id),
+            quantity: i.quantity,
+        })
+        .collect();
+
+    let placed = order::place_group(&mut db, &rules, table, &lines)
+        .await
+        .map_err(rejected)?;
+    Ok((
+        StatusCode::CREATED,
+        Json(OrderGroup::from_domain(placed.as_slice())),
+    ))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/tables/{id}/orders",
+    tag = "orders",
+    params(("id" = u32, Path, description = "The table to clear.")),
+    responses(
+        (status = 200, description = "The orders that were removed.", body = Vec<Order>),
+        (status = 404, description = "The table doesn't exist.", body = Problem, content_type = "application/problem+json"),
+    )
+)]
+async fn table_orders_delete(
+    Extension(mut db): Extension<Database>,
+    Path(id): Path<layout::TableId>,
+) -> Result<impl IntoResponse, ProblemResponse> {
+    let id = table(&db, id).await?.id();
+
+    let orders = order::clear_table(&mut db, id).await.map_err(not_found)?;
+    ver::table_freed(&mut db, id).await;
+
+    Ok(Json(
+        orders
+            .iter()
+            .map(|o| removed(Order::from_domain(o)))
+            .collect::<Vec<Order>>(),
+    ))
+}
+
+#[utoipa::path(
+    get,
+    path = "/orders",
+    tag = "orders",
+    params(OrderQuery),
+    responses(
+        (status = 200, description = "A page of the orders across every table.", body = OrderPage),
+        (status = 400, description = "The cursor isn't one this query handed out.", body = Problem, content_type = "application/problem+json"),
+    )
+)]
+async fn orders_get(
+    Extension(db): Extension<Database>,
+    Query(query): Query<OrderQuery>,
+) -> Result<impl IntoResponse, ProblemResponse> {
+    let page = order::find(
+        &db,
+        &ver::order_query(&query).map_err(|e| problem(StatusCode::BAD_REQUEST, e))?,
+    )
+    .await
+    .map_err(|e| match e {
+        OrderingError::InvalidCursor => problem(StatusCode::BAD_REQUEST, e.to_string()),
+        e => not_found(e),
+    })?;
+
+    let next = page.next.as_ref().map(ver::cursor_token);
+    Ok(Json(OrderPage {
+        orders: page.orders.iter().map(Order::from_domain).collect(),
+        links: ver::page_links(
+            "/api/orders",
+            &query,
+            ver::next_orders(&query, next.as_ref()).as_ref(),
+        ),
+        next_cursor: next,
+    }))
+}
+
+#[utoipa::path(
+    get,
+    path = "/orders/{id}",
+    tag = "orders",
+    params(("id" = u32, Path, description = "The order to get.")),
+    responses(
+        (status = 200, description = "The order.", body = Order, headers(("ETag" = String, description = "The order's current version."))),
+        (status = 404, description = "The order doesn't exist.", body = Problem, content_type = "application/problem+json"),
+    )
+)]
+async fn order_get(
+    Extension(db): Extension<Database>,
+    Path(id): Path<order::Id>,
+) -> Result<impl IntoResponse, ProblemResponse> {
+    order::get(&db, id)
+        .await
+        .map(|o| (ver::etag(o.version()), Json(Order::from_domain(&o))))
+        .map_err(not_found)
+}
+
+// a change that didn't apply because the order wasn't at the version it was meant for
+fn stale(headers: &HeaderMap, e: OrderingError) -> ProblemResponse {
+    match e {
+        OrderingError::VersionConflict { .. } => {
+            problem(ver::conflict_status(headers), e.to_string())
+        }
+        e => rejected(e),
+    }
+}
+
+#[utoipa::path(
+    patch,
+    path = "/orders/{id}",
+    tag = "orders",
+    params(
+        ("id" = u32, Path, description = "The order to change."),
+        ("If-Match" = Option<String>, Header, description = "Only change the order if it's still at this version."),
+    ),
+    request_body = OrderPatch,
+    responses(
+        (status = 200, description = "The changed order.", body = Order, headers(("ETag" = String, description = "The order's new version."))),
+        (status = 400, description = "The quantity is out of range. `errors` has each invalid field.", body = Problem, content_type = "application/problem+json"),
+        (status = 404, description = "The order doesn't exist.", body = Problem, content_type = "application/problem+json"),
+        (status = 409, description = "The order was changed by someone else while this change was being made.", body = Problem, content_type = "application/problem+json"),
+        (status = 412, description = "The order isn't at the version in `If-Match`.", body = Problem, content_type = "application/problem+json"),
+    )
+)]
+async fn order_patch(
+    Extension(mut db): Extension<Database>,
+    Extension(rules): Extension<order::Rules>,
+    Path(id): Path<order::Id>,
+    headers: HeaderMap,
+    Json(patch): Json<OrderPatch>,
+) -> Result<impl IntoResponse, ProblemResponse> {
+    let expected = ver::expected_version(&db, id, &headers)
+        .await
+        .map_err(|e| stale(&headers, e))?;
+    let changed = match patch.quantity {
+        // cancelling is what DELETE is for, rather than a side effect of a quantity of zero
+        Some(0) => return Err(rejected(OrderingError::ZeroQuantity)),
+        Some(quantity) => order::set_quantity(&mut db, &rules, id, quantity, expected).await,
+        None => order::get(&db, id).await,
+    };
+
+    changed
+        .map(|o| (ver::etag(o.version()), Json(Order::from_domain(&o))))
+        .map_err(|e| stale(&headers, e))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/orders/{id}",
+    tag = "orders",
+    params(
+        ("id" = u32, Path, description = "The order to cancel."),
+        ("If-Match" = Option<String>, Header, description = "Only cancel the order if it's still at this version."),
+    ),
+    responses(
+        (status = 204, description = "The order was cancelled."),
+        (status = 404, description = "The order doesn't exist.", body = Problem, content_type = "application/problem+json"),
+        (status = 412, description = "The order isn't at the version in `If-Match`.", body = Problem, content_type = "application/problem+json"),
+    )
+)]
+async fn order_delete(
+    Extension(mut db): Extension<Database>,
+    Path(id): Path<order::Id>,
+    headers: HeaderMap,
+) -> Result<impl IntoResponse, ProblemResponse> {
+    let expected = ver::expected_version(&db, id, &headers)
+        .await
+        .map_err(|e| stale(&headers, e))?;
+    order::cancel(&mut db, id, expected)
+        .await
+        .map(|_| StatusCode::NO_CONTENT)
+        .map_err(|e| stale(&headers, e))
+}
+
+// the table orders are moving to comes from the body, so a missing one is an invalid field rather than a 404
+async fn destination(db: &Database, table_id: u32) -> Result<layout::RepoTable, ProblemResponse> {
+    layout::serving_table(db, layout::TableId(table_id))
+        .await
+        .map_err(|_| {
+            invalid(vec![field_error(
+                "/table_id",
+                format!("Table {} not found.", table_id),
+            )])
+        })
+}
+
+#[utoipa::path(
+    post,
+    path = "/orders/{id}/transfer",
+    tag = "orders",
+    params(("id" = u32, Path, description = "The order to move.")),
+    request_body = Transfer,
+    responses(
+        (status = 200, description = "The order at its new table. It keeps its id and when it was placed.", body = Order),
+        (status = 400, description = "The table doesn't exist, or is the one the order is already at.", body = Problem, content_type = "application/problem+json"),
+        (status = 404, description = "The order doesn't exist.", body = Problem, content_type = "application/problem+json"),
+        (status = 409, description = "The table isn't taking orders.", body = Problem, content_type = "application/problem+json"),
+    )
+)]
+async fn order_transfer(
+    Extension(mut db): Extension<Database>,
+    Path(id): Path<order::Id>,
+    Json(transfer): Json<Transfer>,
+) -> Result<impl IntoResponse, ProblemResponse> {
+    let table = destination(&db, transfer.table_id).await?;
+    order::transfer(&mut db, id, table)
+        .await
+        .map(|o| (ver::etag(o.version()), Json(Order::from_domain(&o))))
+        .map_err(rejected)
+}
+
+#[utoipa::path(
+    post,
+    path = "/tables/{id}/orders/transfer",
+    tag = "orders",
+    params(("id" = u32, Path, description = "The table to move orders from.")),
+    request_body = Transfer,
+    responses(
+        (status = 200, description = "Every order that moved, in the order they were placed.", body = Vec<Order>),
+        (status = 400, description = "The table doesn't exist, or is the one being moved from.", body = Problem, content_type = "application/problem+json"),
+        (status = 404, description = "The table being moved from doesn't exist.", body = Problem, content_type = "application/problem+json"),
+        (status = 409, description = "The table isn't taking orders.", body = Problem, content_type = "application/problem+json"),
+    )
+)]
+async fn table_orders_transfer(
+    Extension(mut db): Extension<Database>,
+    Path(id): Path<layout::TableId>,
+    Json(transfer): Json<Transfer>,
+) -> Result<impl IntoResponse, ProblemResponse> {
+    let id = table(&db, id).await?.id();
+    let to = destination(&db, transfer.table_id).await?;
+    order::transfer_table(&mut db, id, to)
+        .await
+        .map(|orders| {
+            Json(
+                orders
+                    .iter()
+                    .map(Order::from_domain)
+                    .collect::<Vec<Order>>(),
+            )
+        })
+        .map_err(rejected)
+}
@@ -0,0 +1,54 @@
+use axum::{
+    http::{header, StatusCode},
+    response::{IntoResponse, Response},
+    Json,
+};
+
+use super::models::{FieldError, Problem};
+
+pub const CONTENT_TYPE: &str = "application/problem+json";
+
+pub struct ProblemResponse(StatusCode, String, Vec<FieldError>);
+
+pub fn problem(status: StatusCode, detail: impl Into<String>) -> ProblemResponse {
+    ProblemResponse(status, detail.into(), Vec::new())
+}
+
+// a request that was rejected for what's in its fields, each of which is reported
+pub fn invalid(errors: Vec<FieldError>) -> ProblemResponse {
+    ProblemResponse(
+        StatusCode::BAD_REQUEST,
+        format!(
+            "{} field(s) of the request are invalid: {}",
+            errors.len(),
+            errors
+                .iter()
+                .map(|e| e.pointer.as_str())
+                .collect::<Vec<&str>>()
+                .join(", ")
+        ),
+        errors,
+    )
+}
+
+impl IntoResponse for ProblemResponse {
+    fn into_response(self) -> Response {
+        let ProblemResponse(status, detail, errors) = self;
+        let problem = Problem {
+            title: status
+                .canonical_reason()
+                .unwrap_or("Unknown error")
+                .to_string(),
+            status: status.as_u16(),
+            detail,
+            errors,
+        };
+
+        (
+            status,
+            [(header::CONTENT_TYPE, CONTENT_TYPE)],
+            Json(problem),
+        )
+            .into_response()
+    }
+}
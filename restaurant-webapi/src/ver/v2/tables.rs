@@ -0,0 +1,106 @@
+use axum::{extract::Path, http::StatusCode, middleware, response::IntoResponse, Extension, Json};
+use restaurant::{api_key::Scope, kitchen, layout, staff::Role};
+use utoipa_axum::{router::OpenApiRouter, routes};
+
+use super::{
+    models::{FromDomain, Problem, Table, TableStatus},
+    problem::{problem, ProblemResponse},
+};
+use crate::{
+    auth::{self, Access},
+    ver, Database,
+};
+
+pub fn create() -> OpenApiRouter {
+    OpenApiRouter::new()
+        .routes(routes!(tables_get))
+        .routes(routes!(table_get))
+        .routes(routes!(table_status_get))
+        .route_layer(middleware::from_fn_with_state(
+            Access::changed_by(&[Role::Host, Role::Server])
+                .or_key(Scope::TablesRead, Scope::TablesWrite),
+            auth::authorize,
+        ))
+}
+
+#[utoipa::path(
+    get,
+    path = "/tables",
+    tag = "tables",
+    responses((status = 200, description = "Every table in the restaurant.", body = Vec<Table>))
+)]
+async fn tables_get(
+    Extension(db): Extension<Database>,
+) -> Result<impl IntoResponse, ProblemResponse> {
+    layout::get_tables(&db)
+        .await
+        .map(|tables| {
+            Json(
+                tables
+                    .iter()
+                    .map(Table::from_domain)
+                    .collect::<Vec<Table>>(),
+            )
+        })
+        .map_err(|e| {
+            problem(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("Failed to get tables: {:?}", e),
+            )
+        })
+}
+
+#[utoipa::path(
+    get,
+    path = "/tables/{id}",
+    tag = "tables",
+    params(("id" = u32, Path, description = "The table to get.")),
+    responses(
+        (status = 200, description = "The table.", body = Table, headers(("ETag" = String, description = "The table's current version."))),
+        (status = 404, description = "The table doesn't exist.", body = Problem, content_type = "application/problem+json"),
+    )
+)]
+async fn table_get(
+    Extension(db): Extension<Database>,
+    Path(id): Path<layout::TableId>,
+) -> Result<impl IntoResponse, ProblemResponse> {
+    layout::get(&db, id)
+        .await
+        .map(|t| (ver::etag(t.version()), Json(Table::from_domain(&t))))
+        .map_err(|_| problem(StatusCode::NOT_FOUND, format!("Table {} not found.", id.0)))
+}
+
+#[utoipa::path(
+    get,
+    path = "/tables/{id}/status",
+    tag = "tables",
+    params(("id" = u32, Path, description = "The table to check on.")),
+    responses(
+        (status = 200, description = "Whether the table's orders are ready.", body = TableStatus),
+        (status = 404, description = "The table doesn't exist.", body = Problem, content_type = "application/problem+json"),
+    )
+)]
+async fn table_status_get(
+    Extension(db): Extension<Database>,
+    Path(id): Path<layout::TableId>,
+) -> Result<impl IntoResponse, ProblemResponse> {
+    let id = match layout::serving_table(&db, id).await {
+        Ok(table) => table.id(),
+        Err(_) => {
+            return Err(problem(
+                StatusCode::NOT_FOUND,
+                format!("Table {} not found.", id.0),
+            ))
+        }
+    };
+
+    kitchen::table_status(&db, id)
+        .await
+        .map(|s| Json(TableStatus::from_domain(s)))
+        .map_err(|e| {
+            problem(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("Failed to get status for table {}: {:?}", id.0, e),
+            )
+        })
+}
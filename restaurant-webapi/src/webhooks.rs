@@ -0,0 +1,498 @@
+use std::{
+    path::PathBuf,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use chrono::{DateTime, TimeDelta, Utc};
+use futures::StreamExt;
+use hmac::{Hmac, Mac};
+use rand::RngCore;
+use restaurant::{
+    order::{self, EventId},
+    tenant, waitlist,
+};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+
+// receivers verify deliveries by computing HMAC-SHA256 over "{timestamp}.{body}" with their secret
+pub const SIGNATURE_HEADER: &str = "x-restaurant-signature";
+pub const TIMESTAMP_HEADER: &str = "x-restaurant-timestamp";
+// the same event can be delivered more than once, so receivers should dedup on this. it's the event id, or
+// "party-{id}" for a party's notice, prefixed with how many times the server has started, since ids start over
+// when it restarts
+pub const EVENT_ID_HEADER: &str = "x-restaurant-event-id";
+
+// deliveries in flight at once. the rest wait for the next round
+const MAX_CONCURRENT_DELIVERIES: usize = 16;
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash)]
+pub enum EventKind {
+    #[serde(rename = "order.placed")]
+    Placed,
+    #[serde(rename = "order.updated")]
+    Updated,
+    #[serde(rename = "order.ready")]
+    Ready,
+    #[serde(rename = "order.removed")]
+    Removed,
+    #[serde(rename = "order.transferred")]
+    Transferred,
+    // a party on the waitlist has been told their table's ready
+    #[serde(rename = "waitlist.notified")]
+    PartyNotified,
+}
+
+impl EventKind {
+    fn of(event: &order::Event) -> EventKind {
+        match event.change {
+            order::Change::Placed => EventKind::Placed,
+            // changes to an order that was already ready are only updates
+            order::Change::Updated if event.became_ready() => EventKind::Ready,
+            order::Change::Updated => EventKind::Updated,
+            order::Change::Removed => EventKind::Removed,
+            order::Change::Transferred => EventKind::Transferred,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct SubscriptionId(pub u32);
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Subscription {
+    pub id: SubscriptionId,
+    // the restaurant whose events it gets. subscriptions from before there were several are for the default one
+    #[serde(default)]
+    pub tenant: tenant::Id,
+    pub url: String,
+    pub secret: String,
+    // empty means every kind of event
+    pub events: Vec<EventKind>,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct DeliveryId(pub u32);
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Delivery {
+    pub id: DeliveryId,
+    pub subscription: SubscriptionId,
+    // the same as its subscription's, kept here since dead letters outlive unregistering
+    #[serde(default)]
+    pub tenant: tenant::Id,
+    // the order event delivered, or else the party whose notice it is. a party's only ever notified once
+    #[serde(default)]
+    pub event_id: Option<EventId>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub party: Option<waitlist::Id>,
+    // which start of the server the event is from, as event ids are only unique within one
+    #[serde(default)]
+    pub epoch: u32,
+    pub kind: EventKind,
+    pub payload: String,
+    pub attempts: u32,
+    pub next_attempt: DateTime<Utc>,
+    pub last_error: Option<String>,
+}
+
+impl Delivery {
+    // see EVENT_ID_HEADER
+    fn event_key(&self) -> String {
+        match (self.event_id, self.party) {
+            (Some(id), _) => format!("{}-{}", self.epoch, u32::from(id)),
+            (None, Some(party)) => format!("{}-party-{}", self.epoch, u32::from(party)),
+            (None, None) => format!("{}-delivery-{}", self.epoch, self.id.0),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    pub max_attempts: u32,
+    pub base_delay: Duration,
+    pub max_delay: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        RetryPolicy {
+            max_attempts: 8,
+            base_delay: Duration::from_secs(1),
+            max_delay: Duration::from_secs(10 * 60),
+        }
+    }
+}
+
+impl RetryPolicy {
+    fn delay(&self, attempts: u32) -> Duration {
+        self.base_delay
+            .saturating_mul(2u32.saturating_pow(attempts.saturating_sub(1)))
+            .min(self.max_delay)
+    }
+}
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+struct State {
+    next_id: u32,
+    // bumped every time the queue is opened
+    #[serde(default)]
+    epoch: u32,
+    subscriptions: Vec<Subscription>,
+    pending: Vec<Delivery>,
+    dead_letters: Vec<Delivery>,
+}
+
+impl State {
+    fn next_id(&mut self) -> u32 {
+        self.next_id += 1;
+        self.next_id
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct Payload<'a> {
+    id: EventId,
+    #[serde(rename = "type")]
+    kind: EventKind,
+    time: DateTime<Utc>,
+    order: &'a order::RepoOrder,
+}
+
+#[derive(Debug, Serialize)]
+struct NoticePayload<'a> {
+    #[serde(rename = "type")]
+    kind: EventKind,
+    time: DateTime<Utc>,
+    party: &'a waitlist::RepoParty,
+}
+
+// registrations and the delivery queue are written to disk on every change, so a restart doesn't drop deliveries
+#[derive(Clone)]
+pub struct Webhooks {
+    state: Arc<Mutex<State>>,
+    // held by whoever is changing the state, for as long as it takes to write it. reading only needs `state`,
+    // which isn't held while writing
+    writing: Arc<Mutex<()>>,
+    path: Option<PathBuf>,
+    policy: RetryPolicy,
+    client: reqwest::Client,
+}
+
+impl Webhooks {
+    pub fn open(path: Option<PathBuf>, policy: RetryPolicy) -> anyhow::Result<Webhooks> {
+        let state = match &path {
+            Some(path) if path.exists() => serde_json::from_slice(&std::fs::read(path)?)?,
+            _ => State::default(),
+        };
+
+        let webhooks = Webhooks {
+            state: Arc::new(Mutex::new(state)),
+            writing: Arc::new(Mutex::new(())),
+            path,
+            policy,
+            client: reqwest::Client::builder()
+                .timeout(Duration::from_secs(10))
+                .build()?,
+        };
+        webhooks.modify(|state| state.epoch += 1)?;
+        Ok(webhooks)
+    }
+
+    pub fn register(
+        &self,
+        tenant: tenant::Id,
+        url: String,
+        events: Vec<EventKind>,
+    ) -> anyhow::Result<Subscription> {
+        let mut secret = [0u8; 32];
+        rand::thread_rng().fill_bytes(&mut secret);
+
+        self.modify(|state| {
+            let subscription = Subscription {
+                id: SubscriptionId(state.next_id()),
+                tenant,
+                url,
+                secret: hex::encode(secret),
+                events,
+            };
+            state.subscriptions.push(subscription.clone());
+            subscription
+        })
+    }
+
+    // each restaurant only sees its own subscriptions, and the deliveries to them
+    pub fn subscriptions(&self, tenant: tenant::Id) -> Vec<Subscription> {
+        self.state
+            .lock()
+            .unwrap()
+            .subscriptions
+            .iter()
+            .filter(|s| s.tenant == tenant)
+            .cloned()
+            .collect()
+    }
+
+    pub fn unregister(
+        &self,
+        tenant: tenant::Id,
+        id: SubscriptionId,
+    ) -> anyhow::Result<Option<Subscription>> {
+        self.modify(|state| {
+            let index = state
+                .subscriptions
+                .iter()
+                .position(|s| s.id == id && s.tenant == tenant)?;
+            state.pending.retain(|d| d.subscription != id);
+            Some(state.subscriptions.remove(index))
+        })
+    }
+
+    pub fn dead_letters(&self, tenant: tenant::Id) -> Vec<Delivery> {
+        self.state
+            .lock()
+            .unwrap()
+            .dead_letters
+            .iter()
+            .filter(|d| d.tenant == tenant)
+            .cloned()
+            .collect()
+    }
+
+    pub fn retry_dead_letter(
+        &self,
+        tenant: tenant::Id,
+        id: DeliveryId,
+    ) -> anyhow::Result<Option<Delivery>> {
+        self.modify(|state| {
+            let index = state
+                .dead_letters
+                .iter()
+                .position(|d| d.id == id && d.tenant == tenant)?;
+            let mut delivery = state.dead_letters.remove(index);
+            delivery.attempts = 0;
+            delivery.next_attempt = Utc::now();
+            state.pending.push(delivery.clone());
+            Some(delivery)
+        })
+    }
+
+    // queues a delivery of the event to every subscription interested in it, at the event's restaurant
+    pub fn enqueue(&self, event: &order::RepoEvent) -> anyhow::Result<()> {
+        let kind = EventKind::of(event);
+        let payload = serde_json::to_string(&Payload {
+            id: event.id(),
+            kind,
+            time: event.time,
+            order: &event.order,
+        })?;
+        self.queue(event.tenant, kind, Some(event.id()), None, payload)
+    }
+
+    // queues a delivery of a party's notice, that a table's been held for them, to every subscription wanting it
+    pub fn enqueue_notice(
+        &self,
+        tenant: tenant::Id,
+        party: &waitlist::RepoParty,
+    ) -> anyhow::Result<()> {
+        let waitlist::Status::Notified { at, .. } = party.status else {
+            return Err(anyhow::anyhow!(
+                "Party {:?} hasn't been notified.",
+                party.id()
+            ));
+        };
+        let kind = EventKind::PartyNotified;
+        let payload = serde_json::to_string(&NoticePayload {
+            kind,
+            time: at,
+            party,
+        })?;
+        self.queue(tenant, kind, None, Some(party.id()), payload)
+    }
+
+    fn queue(
+        &self,
+        tenant: tenant::Id,
+        kind: EventKind,
+        event_id: Option<EventId>,
+        party: Option<waitlist::Id>,
+        payload: String,
+    ) -> anyhow::Result<()> {
+        self.modify(|state| {
+            // the relay is at-least-once, so the same event may well show up again. ones from before a restart
+            // are different events that happen to have the same id
+            let queued = |s: &Subscription| {
+                state
+                    .pending
+                    .iter()
+                    .chain(state.dead_letters.iter())
+                    .any(|d| {
+                        d.subscription == s.id
+                            && d.event_id == event_id
+                            && d.party == party
+                            && d.epoch == state.epoch
+                    })
+            };
+            let subscriptions: Vec<SubscriptionId> = state
+                .subscriptions
+                .iter()
+                .filter(|s| s.tenant == tenant)
+                .filter(|s| s.events.is_empty() || s.events.contains(&kind))
+                .filter(|s| !queued(s))
+                .map(|s| s.id)
+                .collect();
+
+            for subscription in subscriptions {
+                let id = DeliveryId(state.next_id());
+                state.pending.push(Delivery {
+                    id,
+                    subscription,
+                    tenant,
+                    event_id,
+                    party,
+                    epoch: state.epoch,
+                    kind,
+                    payload: payload.clone(),
+                    attempts: 0,
+                    next_attempt: Utc::now(),
+                    last_error: None,
+                });
+            }
+        })
+    }
+
+    // makes one attempt at every delivery that is due
+    pub async fn deliver_due(&self) -> anyhow::Result<()> {
+        let now = Utc::now();
+        let due: Vec<(Delivery, Subscription)> = {
+            let state = self.state.lock().unwrap();
+            state
+                .pending
+                .iter()
+                .filter(|d| d.next_attempt <= now)
+                .filter_map(|d| {
+                    state
+                        .subscriptions
+                        .iter()
+                        .find(|s| s.id == d.subscription)
+                        .map(|s| (d.clone(), s.clone()))
+                })
+                .collect()
+        };
+        if due.is_empty() {
+            return Ok(());
+        }
+
+        // owned futures, since borrowing ones trip up spawning whatever runs this
+        let results: Vec<anyhow::Result<()>> = futures::stream::iter(due.clone())
+            .map(|(delivery, subscription)| {
+                let webhooks = self.clone();
+                async move { webhooks.send(&delivery, &subscription).await }
+            })
+            .buffered(MAX_CONCURRENT_DELIVERIES)
+            .collect()
+            .await;
+
+        self.modify(|state| {
+            for ((delivery, _), result) in due.into_iter().zip(results) {
+                // it may have been unregistered while we were sending
+                let Some(index) = state.pending.iter().position(|d| d.id == delivery.id) else {
+                    continue;
+                };
+
+                match result {
+                    Ok(()) => {
+                        state.pending.remove(index);
+                    }
+                    Err(err) => {
+                        let pending = &mut state.pending[index];
+                        pending.attempts += 1;
+                        pending.last_error = Some(err.to_string());
+                        pending.next_attempt = Utc::now()
+                            + TimeDelta::from_std(self.policy.delay(pending.attempts))
+                                .expect("Retry delays should be capped to something reasonable.");
+
+                        if pending.attempts >= self.policy.max_attempts {
+                            let dead = state.pending.remove(index);
+                            state.dead_letters.push(dead);
+                        }
+                    }
+                }
+            }
+        })
+    }
+
+    // keeps working through the queue. deliveries are queued by the outbox relay
+    pub async fn run(&self) {
+        let mut interval = tokio::time::interval(Duration::from_millis(250));
+        loop {
+            interval.tick().await;
+
+            if let Err(err) = self.deliver_due().await {
+                println!("Error delivering webhooks: {:?}", err);
+            }
+        }
+    }
+
+    async fn send(&self, delivery: &Delivery, subscription: &Subscription) -> anyhow::Result<()> {
+        let timestamp = Utc::now().timestamp().to_string();
+        self.client
+            .post(&subscription.url)
+            .header(reqwest::header::CONTENT_TYPE, "application/json")
+            .header(
+                SIGNATURE_HEADER,
+                sign(&subscription.secret, &timestamp, &delivery.payload),
+            )
+            .header(TIMESTAMP_HEADER, timestamp)
+            .header(EVENT_ID_HEADER, delivery.event_key())
+            .body(delivery.payload.clone())
+            .send()
+            .await?
+            .error_for_status()?;
+        Ok(())
+    }
+
+    // changes are only kept if they could be written, so what's in memory is never ahead of what's on disk
+    // for longer than the write takes
+    fn modify<R>(&self, f: impl FnOnce(&mut State) -> R) -> anyhow::Result<R> {
+        let _writing = self.writing.lock().unwrap();
+        let (before, result, contents) = {
+            let mut state = self.state.lock().unwrap();
+            let before = state.clone();
+            let result = f(&mut state);
+            match serde_json::to_vec(&*state) {
+                Ok(contents) => (before, result, contents),
+                Err(err) => {
+                    *state = before;
+                    return Err(err.into());
+                }
+            }
+        };
+
+        if let Err(err) = self.write(&contents) {
+            // nobody else could have changed it since, they'd have had to wait for `writing`
+            *self.state.lock().unwrap() = before;
+            return Err(err);
+        }
+        Ok(result)
+    }
+
+    fn write(&self, contents: &[u8]) -> anyhow::Result<()> {
+        if let Some(path) = &self.path {
+            // write-then-rename, so a crash mid-write can't leave us with a corrupt queue
+            let temp = path.with_extension("tmp");
+            std::fs::write(&temp, contents)?;
+            std::fs::rename(temp, path)?;
+        }
+        Ok(())
+    }
+}
+
+pub fn sign(secret: &str, timestamp: &str, payload: &str) -> String {
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC can take keys of any size.");
+    mac.update(timestamp.as_bytes());
+    mac.update(b".");
+    mac.update(payload.as_bytes());
+    format!("sha256={}", hex::encode(mac.finalize().into_bytes()))
+}
@@ -0,0 +1,1068 @@
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    time::Duration,
+};
+
+use axum::{
+    http::{HeaderMap, StatusCode},
+    routing::post,
+    Extension, Json, Router,
+};
+use chrono::{TimeDelta, TimeZone, Utc};
+use restaurant::{api_key, layout, memdb::Database, menu, order, staff};
+use restaurant_client::{
+    v1::{
+        ApiScope, AssignSection, AuditQuery, ContactDetails, CreateApiKey, CreateOrder,
+        CreateOrderGroup, CreateReservation, GroupItem, HireStaff, JoinWaitlist, Order,
+        OrderDetails, OrderPage, OrderQuery, OrderSort, OrderStatus, PartyStatus, ReservationPatch,
+        SetOrderQuantity, SortDirection, StaffRole, Station, SuggestTable, TableDetails,
+    },
+    Client, Error, RetryPolicy, IDEMPOTENCY_KEY_HEADER,
+};
+use restaurant_webapi::{
+    auth::Principal,
+    idempotency::{self, Idempotency},
+};
+use serde_json::json;
+use tower::ServiceExt;
+
+mod common;
+
+async fn start() -> (Database, String) {
+    let mut db = common::database();
+    for (name, role) in [("Sam", staff::Role::Server), ("Kai", staff::Role::Kitchen)] {
+        staff::hire(&mut db, name, role, common::PIN).await.unwrap();
+    }
+    let root = common::serve(common::app(db.clone())).await;
+
+    (db, root)
+}
+
+async fn signed_in(root: &str, name: &str) -> Result<Client, Error> {
+    let client = Client::new(root)?;
+    let session = client.login(name, common::PIN).await?;
+    Ok(client.with_token(session.token))
+}
+
+#[tokio::test]
+async fn orders_round_trip() -> Result<(), Error> {
+    let (_, root) = start().await;
+    let client = signed_in(&root, "Mina").await?;
+
+    let pasta = client
+        .create_order(&CreateOrder {
+            table_id: 1,
+            item_id: 1,
+            quantity: 2,
+        })
+        .await?;
+    assert_eq!(Station::Grill, pasta.menu_item.station);
+    assert_eq!(4, client.set_quantity(pasta.id, 4).await?.quantity);
+
+    let ready = client.bump(Station::Grill, pasta.id).await?;
+    assert_eq!(OrderStatus::Ready, ready.status);
+    assert!(client.table_status(1).await?.ready);
+
+    let cleared = client.clear_table(1).await?;
+    assert_eq!(
+        vec![pasta.id],
+        cleared.iter().map(|o| o.id).collect::<Vec<_>>()
+    );
+    assert!(client.table_orders(1).await?.is_empty());
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn parties_move_tables_with_their_orders() -> Result<(), Error> {
+    let (_, root) = start().await;
+    let client = signed_in(&root, "Mina").await?;
+    let mut placed = Vec::new();
+    for item_id in [1, 2] {
+        let order = CreateOrder {
+            table_id: 1,
+            item_id,
+            quantity: 1,
+        };
+        placed.push(client.create_order(&order).await?);
+    }
+    let (pasta, parfait) = (&placed[0], &placed[1]);
+
+    let moved = client.transfer_order(pasta.id, 2).await?;
+    assert_eq!((pasta.id, 2), (moved.id, moved.table.id));
+    assert_eq!(pasta.time_placed, moved.time_placed);
+
+    let moved = client.transfer_table(1, 2).await?;
+    assert_eq!(
+        vec![parfait.id],
+        moved.iter().map(|o| o.id).collect::<Vec<_>>()
+    );
+    assert!(client.table_orders(1).await?.is_empty());
+    assert_eq!(2, client.table_orders(2).await?.len());
+
+    assert!(matches!(
+        client.transfer_table(2, 2).await,
+        Err(Error::BadRequest(_))
+    ));
+    assert!(matches!(
+        client.transfer_order(pasta.id, 9).await,
+        Err(Error::BadRequest(_))
+    ));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn merged_tables_share_one_bill() -> Result<(), Error> {
+    let (_, root) = start().await;
+    let client = signed_in(&root, "Mina").await?;
+    let order = |table_id, quantity| CreateOrder {
+        table_id,
+        item_id: 1,
+        quantity,
+    };
+
+    let early = client.create_order(&order(2, 1)).await?;
+    let group = client.create_table_group(&[1, 2]).await?;
+    assert_eq!((1, vec![1, 2]), (group.lead_table_id, group.tables));
+    let late = client.create_order(&order(2, 2)).await?;
+    assert_eq!(1, late.table.id);
+
+    // either table answers for the party
+    let group = client.table_group(group.id).await?;
+    assert_eq!(
+        vec![early.id, late.id],
+        group.orders.iter().map(|o| o.id).collect::<Vec<_>>()
+    );
+    assert_eq!(3, group.total_quantity);
+    assert!(group.estimated_minutes_remaining > 0);
+    assert_eq!(2, client.table_orders(2).await?.len());
+
+    let moved = client.split_table_group(group.id, Some(2)).await?;
+    assert!(moved.iter().all(|o| o.table.id == 2));
+    assert!(client.table_orders(1).await?.is_empty());
+    assert!(matches!(
+        client.table_group(group.id).await,
+        Err(Error::NotFound(_))
+    ));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn tables_are_booked_one_party_at_a_time() -> Result<(), Error> {
+    let (_, root) = start().await;
+    let client = signed_in(&root, "Mina").await?;
+    let at = |hour| Utc.with_ymd_and_hms(2024, 6, 1, hour, 0, 0).unwrap();
+    let booking = |table_id, party_size, from, to| CreateReservation {
+        table_id,
+        party_size,
+        starts_at: at(from),
+        ends_at: at(to),
+        contact: ContactDetails {
+            name: "Tanaka".to_string(),
+            phone: "090-1234-5678".to_string(),
+        },
+    };
+
+    let dinner = client.book_reservation(&booking(1, 4, 18, 20)).await?;
+    assert!(matches!(
+        client.book_reservation(&booking(1, 2, 19, 21)).await,
+        Err(Error::Conflict(_))
+    ));
+    assert!(matches!(
+        client.book_reservation(&booking(2, 5, 19, 21)).await,
+        Err(Error::BadRequest(_))
+    ));
+    let lunch = client.book_reservation(&booking(2, 2, 12, 13)).await?;
+
+    // moving the end alone keeps the start where it was
+    let longer = client
+        .modify_reservation(
+            lunch.id,
+            &ReservationPatch {
+                ends_at: Some(at(14)),
+                ..Default::default()
+            },
+        )
+        .await?;
+    assert_eq!((at(12), at(14)), (longer.starts_at, longer.ends_at));
+
+    let day = at(0).date_naive();
+    let listed = client.reservations(day).await?;
+    assert_eq!(
+        vec![lunch.id, dinner.id],
+        listed.iter().map(|r| r.id).collect::<Vec<_>>()
+    );
+
+    client.cancel_reservation(dinner.id).await?;
+    assert_eq!(1, client.reservations(day).await?.len());
+    client.book_reservation(&booking(1, 2, 19, 21)).await?;
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn cleared_tables_go_to_the_waitlist() -> Result<(), Error> {
+    let (_, root) = start().await;
+    let client = signed_in(&root, "Mina").await?;
+    for table_id in [1, 2] {
+        client
+            .create_order(&CreateOrder {
+                table_id,
+                item_id: 1,
+                quantity: 1,
+            })
+            .await?;
+    }
+    let party = |name: &str| JoinWaitlist {
+        name: name.to_string(),
+        size: 2,
+        phone: None,
+    };
+
+    assert_eq!(60, client.wait_quote(2).await?.estimated_minutes);
+    let first = client.join_waitlist(&party("Tanaka")).await?;
+    let second = client.join_waitlist(&party("Suzuki")).await?;
+    let third = client.join_waitlist(&party("Sato")).await?;
+    assert_eq!(Some(60), first.estimated_minutes_remaining);
+    assert_eq!(Some(120), third.estimated_minutes_remaining);
+    assert!(matches!(
+        client
+            .join_waitlist(&JoinWaitlist {
+                size: 9,
+                ..party("Ito")
+            })
+            .await,
+        Err(Error::BadRequest(_))
+    ));
+
+    client.clear_table(2).await?;
+    let first = client.party(first.id).await?;
+    assert_eq!(
+        (PartyStatus::Notified, Some(2), Some(0)),
+        (
+            first.status,
+            first.table_id,
+            first.estimated_minutes_remaining
+        )
+    );
+
+    // the table's passed on to the next party when the first doesn't turn up
+    let gone = client.no_show(first.id).await?;
+    assert_eq!(PartyStatus::NoShow, gone.status);
+    assert!(gone.links.seat.is_none());
+    assert_eq!(Some(2), client.party(second.id).await?.table_id);
+    client.seat_party(second.id).await?;
+    assert_eq!(
+        vec![third.id],
+        client
+            .waitlist()
+            .await?
+            .iter()
+            .map(|p| p.id)
+            .collect::<Vec<_>>()
+    );
+    assert!(matches!(
+        client.seat_party(second.id).await,
+        Err(Error::Conflict(_))
+    ));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn hosts_are_offered_free_tables() -> Result<(), Error> {
+    let (_, root) = start().await;
+    let client = signed_in(&root, "Mina").await?;
+    let party = SuggestTable {
+        size: 3,
+        section: None,
+        stay_minutes: None,
+    };
+
+    let suggestions = client.suggest_tables(&party).await?;
+    assert_eq!(
+        vec![(1, 1), (2, 2)],
+        suggestions
+            .iter()
+            .map(|s| (s.rank, s.table.id))
+            .collect::<Vec<_>>()
+    );
+    assert_eq!(vec!["Sits 4, leaving 1 spare."], suggestions[0].reasons);
+
+    client
+        .create_order(&CreateOrder {
+            table_id: 1,
+            item_id: 1,
+            quantity: 1,
+        })
+        .await?;
+    let suggestions = client.suggest_tables(&party).await?;
+    assert_eq!(
+        vec![2],
+        suggestions.iter().map(|s| s.table.id).collect::<Vec<_>>()
+    );
+    assert!(client
+        .suggest_tables(&SuggestTable { size: 5, ..party })
+        .await?
+        .is_empty());
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn error_responses_are_typed() -> Result<(), Error> {
+    let (_, root) = start().await;
+    let client = signed_in(&root, "Mina").await?;
+
+    let missing_table = client
+        .create_order(&CreateOrder {
+            table_id: 9,
+            item_id: 1,
+            quantity: 1,
+        })
+        .await;
+    assert!(matches!(missing_table, Err(Error::BadRequest(_))));
+
+    let parfait = client
+        .create_order(&CreateOrder {
+            table_id: 1,
+            item_id: 2,
+            quantity: 1,
+        })
+        .await?;
+    assert!(matches!(
+        client.bump(Station::Grill, parfait.id).await,
+        Err(Error::Conflict(_))
+    ));
+    assert!(matches!(
+        client.bump(Station::Grill, 42).await,
+        Err(Error::NotFound(_))
+    ));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn staff_only_do_what_their_role_allows() -> Result<(), Error> {
+    let (_, root) = start().await;
+    let anonymous = Client::new(&root)?;
+    assert!(matches!(
+        anonymous.clear_table(1).await,
+        Err(Error::Unauthorized(_))
+    ));
+    assert!(matches!(
+        anonymous.login("Sam", "4321").await,
+        Err(Error::Unauthorized(_))
+    ));
+
+    let server = signed_in(&root, "Sam").await?;
+    let kitchen = signed_in(&root, "Kai").await?;
+    let order = server
+        .create_order(&CreateOrder {
+            table_id: 1,
+            item_id: 1,
+            quantity: 1,
+        })
+        .await?;
+    let sam = server.login("Sam", "1234").await?.staff.id;
+    assert_eq!(Some(sam), order.placed_by);
+
+    assert!(matches!(
+        kitchen.clear_table(1).await,
+        Err(Error::Forbidden(_))
+    ));
+    assert!(matches!(
+        server.bump(Station::Grill, order.id).await,
+        Err(Error::Forbidden(_))
+    ));
+    let bumped = kitchen.bump(Station::Grill, order.id).await?;
+    assert_eq!(Some(sam), bumped.placed_by);
+    assert_ne!(Some(sam), bumped.changed_by);
+    assert!(matches!(server.staff().await, Err(Error::Forbidden(_))));
+
+    // managers look after the floor and kitchen, not each other or the admins
+    let manager = signed_in(&root, "Mina").await?;
+    assert!(matches!(
+        manager
+            .hire(&HireStaff {
+                name: "Ash".to_string(),
+                role: StaffRole::Admin,
+                secret: "2468".to_string(),
+            })
+            .await,
+        Err(Error::Forbidden(_))
+    ));
+
+    // a dismissed server's token stops working straight away
+    manager.dismiss(sam).await?;
+    assert!(matches!(
+        server.table_orders(1).await,
+        Err(Error::Unauthorized(_))
+    ));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn managers_can_find_who_changed_what() -> Result<(), Error> {
+    let (_, root) = start().await;
+    let manager = signed_in(&root, "Mina").await?;
+    let server = signed_in(&root, "Sam").await?;
+    let sam = server.login("Sam", "1234").await?.staff.id;
+
+    let pasta = server
+        .create_order(&CreateOrder {
+            table_id: 1,
+            item_id: 1,
+            quantity: 1,
+        })
+        .await?;
+    server.cancel_order(pasta.id).await?;
+
+    let cancelled = manager
+        .audit(&AuditQuery {
+            operation: Some("order.remove".to_string()),
+            text: Some("pasta".to_string()),
+            ..Default::default()
+        })
+        .await?
+        .entries;
+    assert_eq!(1, cancelled.len());
+    assert_eq!(Some(sam), cancelled[0].staff_id);
+    assert!(cancelled[0].request_id.is_some());
+    assert_eq!(
+        Some(pasta.id as u64),
+        cancelled[0].before.as_ref().unwrap()["id"].as_u64()
+    );
+
+    // a page at a time, following the cursor
+    let orders = AuditQuery {
+        operation: Some("order".to_string()),
+        limit: Some(1),
+        ..Default::default()
+    };
+    let first = manager.audit(&orders).await?;
+    assert_eq!("order.remove", first.entries[0].operation);
+    let second = manager
+        .audit(&AuditQuery {
+            cursor: first.next_cursor,
+            ..orders
+        })
+        .await?;
+    assert_eq!("order.create", second.entries[0].operation);
+    assert_eq!(None, second.next_cursor);
+
+    assert!(manager.verify_audit().await?.intact);
+    assert!(matches!(
+        server.audit(&AuditQuery::default()).await,
+        Err(Error::Forbidden(_))
+    ));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn servers_see_their_own_tables() -> Result<(), Error> {
+    let (_, root) = start().await;
+    let manager = signed_in(&root, "Mina").await?;
+    let server = signed_in(&root, "Sam").await?;
+    let sam = server.login("Sam", "1234").await?.staff.id;
+    let shift = AssignSection {
+        section: "patio".to_string(),
+        server_id: sam,
+        starts_at: Utc::now() - TimeDelta::hours(1),
+        ends_at: Utc::now() + TimeDelta::hours(5),
+    };
+    assert!(matches!(
+        server.assign_section(&shift).await,
+        Err(Error::Forbidden(_))
+    ));
+    manager.assign_section(&shift).await?;
+
+    server
+        .create_order(&CreateOrder {
+            table_id: 2,
+            item_id: 1,
+            quantity: 2,
+        })
+        .await?;
+    let mine = server.staff_tables(sam).await?;
+    assert_eq!(vec![2], mine.iter().map(|t| t.table.id).collect::<Vec<_>>());
+    assert_eq!(1, mine[0].orders.len());
+    assert!(mine[0].estimated_minutes_remaining > 0);
+    assert!(matches!(
+        manager.staff_tables(42).await,
+        Err(Error::NotFound(_))
+    ));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn api_keys_only_do_what_their_scopes_allow() -> Result<(), Error> {
+    let (_, root) = start().await;
+    let manager = signed_in(&root, "Mina").await?;
+    let reader = manager
+        .create_api_key(&CreateApiKey {
+            name: "Dashboard".to_string(),
+            scopes: vec![ApiScope::OrdersRead],
+        })
+        .await?;
+    let writer = manager
+        .create_api_key(&CreateApiKey {
+            name: "POS".to_string(),
+            scopes: vec![ApiScope::OrdersWrite],
+        })
+        .await?;
+    let reader = Client::new(&root)?.with_api_key(reader.key);
+    let pos = Client::new(&root)?.with_api_key(writer.key.clone());
+
+    let order = pos
+        .create_order(&CreateOrder {
+            table_id: 1,
+            item_id: 1,
+            quantity: 1,
+        })
+        .await?;
+    assert_eq!(None, order.placed_by);
+    assert_eq!(1, reader.table_orders(1).await?.len());
+    assert!(matches!(
+        reader
+            .create_order(&CreateOrder {
+                table_id: 1,
+                item_id: 1,
+                quantity: 1,
+            })
+            .await,
+        Err(Error::Forbidden(_))
+    ));
+    assert!(matches!(
+        pos.bump(Station::Grill, order.id).await,
+        Err(Error::Forbidden(_))
+    ));
+    assert!(matches!(pos.api_keys().await, Err(Error::Forbidden(_))));
+
+    // the old key stops working as soon as it's rotated
+    let rotated = manager.rotate_api_key(writer.api_key.id).await?;
+    assert!(matches!(
+        pos.table_orders(1).await,
+        Err(Error::Unauthorized(_))
+    ));
+    let pos = Client::new(&root)?.with_api_key(rotated.key);
+    assert_eq!(1, pos.table_orders(1).await?.len());
+
+    manager.revoke_api_key(writer.api_key.id).await?;
+    assert!(matches!(
+        pos.table_orders(1).await,
+        Err(Error::Unauthorized(_))
+    ));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn order_groups_are_placed_whole_or_not_at_all() -> Result<(), Error> {
+    let (_, root) = start().await;
+    let client = signed_in(&root, "Mina").await?;
+    let group = |items: &[(u32, u32)]| CreateOrderGroup {
+        table_id: 1,
+        items: items
+            .iter()
+            .map(|(item_id, quantity)| GroupItem {
+                item_id: *item_id,
+                quantity: *quantity,
+            })
+            .collect(),
+    };
+
+    let placed = client.create_order_group(&group(&[(1, 2), (2, 1)])).await?;
+    assert_eq!(2, placed.orders.len());
+    assert!(placed
+        .orders
+        .iter()
+        .all(|o| o.group_id == Some(placed.id) && o.time_placed == placed.time_placed));
+
+    let rejected = client.create_order_group(&group(&[(1, 1), (9, 1)])).await;
+    assert!(matches!(rejected, Err(Error::BadRequest(_))));
+    assert_eq!(2, client.table_orders(1).await?.len());
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn links_offer_what_the_order_allows() -> Result<(), Error> {
+    let (_, root) = start().await;
+    let client = signed_in(&root, "Mina").await?;
+
+    let placed = client
+        .create_order(&CreateOrder {
+            table_id: 1,
+            item_id: 1,
+            quantity: 1,
+        })
+        .await?;
+    let table: TableDetails = client.follow(&placed.links.table, None::<&()>).await?;
+    assert_eq!(1, table.id);
+
+    let changed: Order = client
+        .follow(
+            placed.links.set_quantity.as_ref().unwrap(),
+            Some(&SetOrderQuantity { quantity: 3 }),
+        )
+        .await?;
+    assert_eq!(3, changed.quantity);
+
+    let ready: OrderDetails = client
+        .follow(changed.links.complete.as_ref().unwrap(), None::<&()>)
+        .await?;
+    assert_eq!(OrderStatus::Ready, ready.status);
+    assert!(ready.links.set_quantity.is_none());
+    assert!(ready.links.complete.is_none());
+
+    let cancelled: Order = client
+        .follow(ready.links.cancel.as_ref().unwrap(), None::<&()>)
+        .await?;
+    assert!(cancelled.links.self_link.is_none());
+    assert!(cancelled.links.cancel.is_none());
+    assert!(client.table_orders(1).await?.is_empty());
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn orders_are_listed_a_page_at_a_time() -> Result<(), Error> {
+    let (_, root) = start().await;
+    let client = signed_in(&root, "Mina").await?;
+
+    let mut placed = Vec::new();
+    for (item_id, quantity) in [(1, 2), (2, 1), (1, 3)] {
+        let order = CreateOrder {
+            table_id: 1,
+            item_id,
+            quantity,
+        };
+        placed.push(client.create_order(&order).await?.id);
+    }
+
+    let first = client
+        .orders(&OrderQuery {
+            sort: Some(OrderSort::Quantity),
+            direction: Some(SortDirection::Desc),
+            limit: Some(2),
+            ..Default::default()
+        })
+        .await?;
+    assert_eq!(
+        vec![placed[2], placed[0]],
+        first.orders.iter().map(|o| o.id).collect::<Vec<_>>()
+    );
+
+    let last: OrderPage = client
+        .follow(first.links.next.as_ref().unwrap(), None::<&()>)
+        .await?;
+    assert_eq!(
+        vec![placed[1]],
+        last.orders.iter().map(|o| o.id).collect::<Vec<_>>()
+    );
+    assert!(last.next_cursor.is_none());
+
+    let pasta = client
+        .orders(&OrderQuery {
+            item_id: Some(1),
+            ..Default::default()
+        })
+        .await?;
+    assert_eq!(2, pasta.orders.len());
+
+    let resorted = client
+        .orders(&OrderQuery {
+            cursor: first.next_cursor,
+            ..Default::default()
+        })
+        .await;
+    assert!(matches!(resorted, Err(Error::BadRequest(_))));
+
+    Ok(())
+}
+
+// fails the first request with a 503, as a server restarting under the client would
+async fn flaky(
+    Extension(keys): Extension<Arc<Mutex<Vec<String>>>>,
+    headers: HeaderMap,
+) -> (StatusCode, Json<serde_json::Value>) {
+    let mut keys = keys.lock().unwrap();
+    keys.push(
+        headers
+            .get(IDEMPOTENCY_KEY_HEADER)
+            .unwrap()
+            .to_str()
+            .unwrap()
+            .to_string(),
+    );
+    if keys.len() == 1 {
+        return (StatusCode::SERVICE_UNAVAILABLE, Json(json!("Restarting.")));
+    }
+
+    (
+        StatusCode::OK,
+        Json(json!({
+            "id": 1,
+            "table": {"id": 1, "seats": 4, "_links": {
+                "self": {"href": "/restaurants/1/api/v1/table/1"},
+                "orders": {"href": "/restaurants/1/api/v1/table/1/orders"},
+                "status": {"href": "/restaurants/1/api/v1/table/1/status"},
+                "place_order": {"href": "/restaurants/1/api/v1/orders", "method": "POST"},
+                "clear": {"href": "/restaurants/1/api/v1/table/1/clear", "method": "POST"},
+            }},
+            "menu_item": {"id": 1, "name": "Pasta", "cook_time": 12, "station": "grill", "available": true, "_links": {
+                "self": {"href": "/restaurants/1/api/v1/menu_items/1"},
+            }},
+            "time_placed": "2024-07-01T12:00:00Z",
+            "quantity": 1,
+            "status": "placed",
+            "_links": {
+                "table": {"href": "/restaurants/1/api/v1/table/1"},
+                "menu_item": {"href": "/restaurants/1/api/v1/menu_items/1"},
+            },
+        })),
+    )
+}
+
+#[tokio::test]
+async fn retries_reuse_the_idempotency_key() -> Result<(), Error> {
+    let keys: Arc<Mutex<Vec<String>>> = Default::default();
+    let root = common::serve(
+        Router::new()
+            .route("/api/orders", post(flaky))
+            .layer(Extension(keys.clone())),
+    )
+    .await;
+    let client = Client::new(&root)?.with_retries(RetryPolicy {
+        max_attempts: 3,
+        base_delay: Duration::from_millis(10),
+        max_delay: Duration::from_millis(10),
+    });
+
+    let order = CreateOrder {
+        table_id: 1,
+        item_id: 1,
+        quantity: 1,
+    };
+    client.create_order(&order).await?;
+    client.create_order(&order).await?;
+
+    let keys = keys.lock().unwrap().clone();
+    assert_eq!(3, keys.len());
+    assert_eq!(keys[0], keys[1]);
+    assert_ne!(keys[1], keys[2]);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn repeated_requests_are_applied_once() {
+    let (db, root) = start().await;
+    let token = Client::new(&root)
+        .unwrap()
+        .login("Sam", "1234")
+        .await
+        .unwrap()
+        .token;
+    let http = reqwest::Client::new();
+    let url = format!("{}api/orders", root);
+
+    let mut ids = Vec::new();
+    for _ in 0..2 {
+        let order: serde_json::Value = http
+            .post(&url)
+            .header("x-api-version", "v1")
+            .header(IDEMPOTENCY_KEY_HEADER, "table-1-pasta")
+            .bearer_auth(&token)
+            .json(&json!({"table_id": 1, "item_id": 1, "quantity": 1}))
+            .send()
+            .await
+            .unwrap()
+            .json()
+            .await
+            .unwrap();
+        ids.push(order["id"].clone());
+    }
+
+    assert_eq!(ids[0], ids[1]);
+    assert_eq!(1, order::get_table(&db, 1.into()).await.unwrap().len());
+}
+
+#[tokio::test]
+async fn idempotency_keys_belong_to_whoever_sent_them() {
+    let (db, root) = start().await;
+    let http = reqwest::Client::new();
+    let mut tokens = Vec::new();
+    for name in ["Sam", "Kai", "Mina"] {
+        let session = Client::new(&root).unwrap().login(name, common::PIN).await;
+        tokens.push(session.unwrap().token);
+    }
+    let post = |path: &str, token: &str| {
+        http.post(format!("{}api/{}", root, path))
+            .header("x-api-version", "v1")
+            .header(IDEMPOTENCY_KEY_HEADER, "same-key")
+            .bearer_auth(token)
+    };
+    let order = json!({"table_id": 1, "item_id": 1, "quantity": 1});
+
+    let placed = post("orders", &tokens[0])
+        .json(&order)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(StatusCode::OK, placed.status());
+    // the kitchen can't place orders, and reusing a server's key doesn't change that
+    let refused = post("orders", &tokens[1])
+        .json(&order)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(StatusCode::FORBIDDEN, refused.status());
+    // nor is a manager's request with the same key taken as a repeat of the server's
+    let placed = post("orders", &tokens[2])
+        .json(&order)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(StatusCode::OK, placed.status());
+    assert_eq!(2, order::get_table(&db, 1.into()).await.unwrap().len());
+
+    // a new api key or webhook secret is never kept, so a repeat is refused rather than replaying it, or
+    // issuing another
+    for (path, body) in [
+        (
+            "api_keys",
+            json!({"name": "till", "scopes": ["orders:read"]}),
+        ),
+        (
+            "webhooks",
+            json!({"url": "http://localhost:9/hook", "events": []}),
+        ),
+    ] {
+        let issued = post(path, &tokens[2]).json(&body).send().await.unwrap();
+        assert_eq!(StatusCode::CREATED, issued.status());
+        assert_eq!(
+            Some("no-store"),
+            issued
+                .headers()
+                .get("cache-control")
+                .and_then(|v| v.to_str().ok())
+        );
+
+        let repeat = post(path, &tokens[2]).json(&body).send().await.unwrap();
+        assert_eq!(StatusCode::CONFLICT, repeat.status());
+        assert!(!repeat.text().await.unwrap().contains("rk_"));
+    }
+    assert_eq!(1, api_key::get_all(&db).await.unwrap().len());
+}
+
+// hangs the first time it's called, and answers every time after
+async fn stalls_once(Extension(stalled): Extension<Arc<AtomicBool>>) -> StatusCode {
+    if !stalled.swap(true, Ordering::SeqCst) {
+        std::future::pending::<()>().await;
+    }
+    StatusCode::OK
+}
+
+#[tokio::test]
+async fn requests_given_up_on_can_be_retried() {
+    let app = Router::new()
+        .route("/orders", post(stalls_once))
+        .layer(axum::middleware::from_fn(|request, next| {
+            idempotency::guard(Principal::Staff(1.into()), request, next)
+        }))
+        .layer(Extension(Idempotency::default()))
+        .layer(Extension(Arc::new(AtomicBool::new(false))));
+    let request = || {
+        axum::http::Request::post("/orders")
+            .header(IDEMPOTENCY_KEY_HEADER, "same-key")
+            .body(axum::body::Body::empty())
+            .unwrap()
+    };
+
+    // the client going away drops the request partway, which mustn't leave the key in flight
+    let given_up =
+        tokio::time::timeout(Duration::from_millis(100), app.clone().oneshot(request())).await;
+    assert!(given_up.is_err());
+    let retried = app.oneshot(request()).await.unwrap();
+    assert_eq!(StatusCode::OK, retried.status());
+}
+
+#[tokio::test]
+async fn branches_only_see_their_own_orders() -> Result<(), Error> {
+    let (mut db, root) = start().await;
+    db.open(
+        2.into(),
+        vec![menu::RepoItem::new(
+            1.into(),
+            menu::Item {
+                name: "Ramen".to_string(),
+                cook_time: menu::Minutes(9),
+                station: menu::Station::Grill,
+                available: true,
+                category: None,
+                sort_order: 0,
+            },
+        )],
+        vec![layout::RepoTable::new(
+            1.into(),
+            layout::Table {
+                active: true,
+                seats: 2,
+                section: None,
+            },
+        )],
+        vec![],
+    )
+    .unwrap();
+    let here = signed_in(&root, "Mina").await?;
+    let downtown = here.clone().with_restaurant(2);
+    let quantities =
+        |orders: Vec<OrderDetails>| orders.iter().map(|o| o.quantity).collect::<Vec<u32>>();
+
+    // staff and keys only get into the restaurants they're for
+    let pos = here
+        .create_api_key(&CreateApiKey {
+            name: "POS".to_string(),
+            scopes: vec![ApiScope::OrdersRead],
+        })
+        .await?;
+    let pos = Client::new(&root)?.with_api_key(pos.key);
+    assert!(pos.table_orders(1).await?.is_empty());
+    assert!(matches!(
+        pos.with_restaurant(2).table_orders(1).await,
+        Err(Error::Forbidden(_))
+    ));
+    assert!(matches!(
+        downtown.table_orders(1).await,
+        Err(Error::Forbidden(_))
+    ));
+    staff::add_restaurant(&mut db, 1.into(), 2.into())
+        .await
+        .unwrap();
+
+    let pasta = here
+        .create_order(&CreateOrder {
+            table_id: 1,
+            item_id: 1,
+            quantity: 2,
+        })
+        .await?;
+    let ramen = downtown
+        .create_order(&CreateOrder {
+            table_id: 1,
+            item_id: 1,
+            quantity: 1,
+        })
+        .await?;
+    // ids are only unique within a restaurant, so these are both order 1 of table 1
+    assert_eq!(pasta.id, ramen.id);
+    assert_eq!("Ramen", ramen.menu_item.name);
+    assert_eq!(vec![2], quantities(here.table_orders(1).await?));
+    assert_eq!(vec![1], quantities(downtown.table_orders(1).await?));
+
+    // a second order here is nowhere to be found downtown, so there's no cancelling it from there
+    let second = here
+        .create_order(&CreateOrder {
+            table_id: 1,
+            item_id: 2,
+            quantity: 3,
+        })
+        .await?;
+    assert!(downtown.cancel_order(second.id).await.is_err());
+    assert_eq!(
+        1,
+        downtown.orders(&OrderQuery::default()).await?.orders.len()
+    );
+
+    // the path works as well as the header, and takes precedence over it
+    let token = here.login("Mina", "1234").await?.token;
+    let by_path = Client::new(&format!("{}restaurants/2/", root))?
+        .with_token(token)
+        .with_restaurant(1);
+    assert_eq!(vec![1], quantities(by_path.table_orders(1).await?));
+
+    // clearing a table downtown leaves the one here alone
+    downtown.clear_table(1).await?;
+    assert!(by_path.table_orders(1).await?.is_empty());
+    assert_eq!(vec![2, 3], quantities(here.table_orders(1).await?));
+
+    assert!(matches!(
+        here.clone().with_restaurant(9).table_orders(1).await,
+        Err(Error::NotFound(_))
+    ));
+
+    // links lead back to the restaurant they came from, even when it was chosen by header
+    assert_eq!(
+        Some("/restaurants/2/api/v1/orders/1"),
+        ramen.links.self_link.as_ref().map(|l| l.href.as_str())
+    );
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn tablets_get_the_menu_by_category() -> Result<(), Error> {
+    let (mut db, root) = start().await;
+    let desserts = menu::add_category(
+        &mut db,
+        menu::Category {
+            name: "Desserts".to_string(),
+            parent: None,
+            sort_order: 1,
+        },
+    )
+    .await
+    .unwrap();
+    let parfaits = menu::add_category(
+        &mut db,
+        menu::Category {
+            name: "Parfaits".to_string(),
+            parent: Some(desserts.id()),
+            sort_order: 1,
+        },
+    )
+    .await
+    .unwrap();
+    menu::file(&mut db, 2.into(), Some(parfaits.id()), 1)
+        .await
+        .unwrap();
+    let client = signed_in(&root, "Sam").await?;
+
+    let menu = client.menu().await?;
+    assert_eq!(1, menu.categories.len());
+    let desserts = &menu.categories[0];
+    assert_eq!("Desserts", desserts.name);
+    assert!(desserts.items.is_empty());
+    assert_eq!("Parfaits", desserts.subcategories[0].name);
+    assert_eq!(
+        vec!["和風パフェ"],
+        desserts.subcategories[0]
+            .items
+            .iter()
+            .map(|i| i.name.as_str())
+            .collect::<Vec<_>>()
+    );
+    assert_eq!(
+        vec![1],
+        menu.uncategorized.iter().map(|i| i.id).collect::<Vec<_>>()
+    );
+
+    // the flat list says where each item is listed, too
+    let items = client.menu_items().await?;
+    assert_eq!(
+        vec![None, Some(parfaits.id().into())],
+        items.iter().map(|i| i.category_id).collect::<Vec<_>>()
+    );
+
+    Ok(())
+}
@@ -0,0 +1,111 @@
+// not every test uses every builder
+#![allow(dead_code)]
+
+use axum::Router;
+use chrono::TimeDelta;
+use restaurant::{layout, memdb::Database, menu, order, staff, RepoItem};
+use restaurant_webapi::{
+    auth::Auth,
+    feed::Feed,
+    ver::Versions,
+    webhooks::{RetryPolicy, Webhooks},
+};
+
+// builders for what tests set up, with whatever they don't care about defaulted. tests that care about a field
+// override it with struct update syntax, as in `menu::Item { station: Station::Fryer, ..common::menu_item("Fries") }`
+
+// a grill item that takes 12 minutes and can be ordered
+pub fn menu_item(name: &str) -> menu::Item {
+    menu::Item {
+        name: name.to_string(),
+        cook_time: menu::Minutes(12),
+        station: menu::Station::Grill,
+        available: true,
+        category: None,
+        sort_order: 0,
+    }
+}
+
+pub fn item(id: u32, name: &str) -> menu::RepoItem {
+    RepoItem::new(id.into(), menu_item(name))
+}
+
+// an open table for four, in no section
+pub fn layout_table() -> layout::Table {
+    layout::Table {
+        active: true,
+        seats: 4,
+        section: None,
+    }
+}
+
+pub fn table(id: u32) -> layout::RepoTable {
+    RepoItem::new(id.into(), layout_table())
+}
+
+// everyone the tests hire has this PIN
+pub const PIN: &str = "1234";
+
+// the restaurant the api tests start out with: pasta and a parfait to order, table 1 and table 2 out on the
+// patio, and Mina, the manager, as staff member 1
+pub fn database() -> Database {
+    let mut db = Database::new(
+        vec![
+            item(1, "Pasta"),
+            RepoItem::new(
+                2.into(),
+                menu::Item {
+                    cook_time: menu::Minutes(8),
+                    station: menu::Station::Dessert,
+                    ..menu_item("和風パフェ")
+                },
+            ),
+        ],
+        vec![
+            table(1),
+            RepoItem::new(
+                2.into(),
+                layout::Table {
+                    section: Some("patio".to_string()),
+                    ..layout_table()
+                },
+            ),
+        ],
+        vec![],
+    );
+    futures::executor::block_on(staff::hire(&mut db, "Mina", staff::Role::Manager, PIN)).unwrap();
+    db
+}
+
+// signs tokens for the manager every database starts out with
+pub fn auth() -> Auth {
+    Auth::new(b"test", TimeDelta::hours(1))
+}
+
+pub fn bearer() -> String {
+    format!("Bearer {}", auth().issue(1.into()).0)
+}
+
+pub fn app(db: Database) -> Router {
+    app_with(db.clone(), Feed::new(db), Versions::default())
+}
+
+pub fn app_with(db: Database, feed: Feed, versions: Versions) -> Router {
+    let webhooks = Webhooks::open(None, RetryPolicy::default()).unwrap();
+    restaurant_webapi::app(
+        db,
+        feed,
+        webhooks,
+        versions,
+        order::Rules::default(),
+        auth(),
+    )
+}
+
+// serves the app on a port of its own, returning the root url to reach it at
+pub async fn serve(app: Router) -> String {
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let root = format!("http://{}/", listener.local_addr().unwrap());
+    tokio::spawn(async { axum::serve(listener, app).await });
+    root
+}
@@ -0,0 +1,207 @@
+use std::time::Duration;
+
+use futures::StreamExt;
+use restaurant_client::{v1::CreateOrder, Client};
+use restaurant_webapi::{
+    feed::Feed,
+    relay::Relay,
+    ver::Versions,
+    webhooks::{RetryPolicy, Webhooks},
+};
+use serde_json::Value;
+use tokio::time::timeout;
+
+mod common;
+
+// the usual restaurant, relaying events as main does, and a signed-in manager's client and token
+async fn start() -> (String, Client, String, Feed) {
+    let db = common::database();
+
+    let feed = Feed::new(db.clone());
+    let webhooks = Webhooks::open(None, RetryPolicy::default()).unwrap();
+    let relay = Relay::new(feed.clone(), webhooks.clone());
+    let relay_db = db.clone();
+    tokio::spawn(async move { relay.run(relay_db).await });
+
+    let root = common::serve(common::app_with(db, feed.clone(), Versions::default())).await;
+
+    let client = Client::new(&root).unwrap();
+    let token = client.login("Mina", common::PIN).await.unwrap().token;
+    (root, client.with_token(token.clone()), token, feed)
+}
+
+async fn eventually(mut f: impl FnMut() -> bool) {
+    for _ in 0..100 {
+        if f() {
+            return;
+        }
+        tokio::time::sleep(Duration::from_millis(50)).await;
+    }
+    panic!("Timed out waiting for the feed.")
+}
+
+async fn order(client: &Client, table_id: u32) -> u32 {
+    client
+        .create_order(&CreateOrder {
+            table_id,
+            item_id: 1,
+            quantity: 1,
+        })
+        .await
+        .unwrap()
+        .id
+}
+
+// reads server-sent events off of the response until `count` have arrived, as (id, event, order id)
+async fn read_events(response: &mut reqwest::Response, count: usize) -> Vec<(u32, String, u64)> {
+    let mut buffer = String::new();
+    let mut events = Vec::new();
+    while events.len() < count {
+        let chunk = timeout(Duration::from_secs(5), response.chunk())
+            .await
+            .expect("The feed should send the event.")
+            .unwrap()
+            .expect("The feed shouldn't end.");
+        buffer.push_str(std::str::from_utf8(&chunk).unwrap());
+
+        while let Some(end) = buffer.find("\n\n") {
+            let block: String = buffer.drain(..end + 2).collect();
+            let field = |name: &str| {
+                block
+                    .lines()
+                    .find_map(|l| l.strip_prefix(name))
+                    .map(|v| v.trim().to_string())
+            };
+            // keep-alives are comments, with no event in them
+            let Some(data) = field("data:") else {
+                continue;
+            };
+            let data: Value = serde_json::from_str(&data).unwrap();
+            events.push((
+                field("id:").unwrap().parse().unwrap(),
+                field("event:").unwrap(),
+                data["order"]["id"].as_u64().unwrap(),
+            ));
+        }
+    }
+    events
+}
+
+#[tokio::test]
+async fn sse_replays_what_was_missed_then_follows_live() {
+    let (root, client, token, _) = start().await;
+    let first = order(&client, 1).await;
+
+    let mut feed = reqwest::Client::new()
+        .get(format!("{}events", root))
+        .bearer_auth(&token)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(reqwest::StatusCode::OK, feed.status());
+    assert_eq!(
+        vec![(1, "placed".to_string(), first as u64)],
+        read_events(&mut feed, 1).await
+    );
+
+    client.set_quantity(first, 3).await.unwrap();
+    assert_eq!(
+        vec![(2, "updated".to_string(), first as u64)],
+        read_events(&mut feed, 1).await
+    );
+
+    // reconnecting picks up from the last event seen, as EventSource does on its own
+    let second = order(&client, 2).await;
+    let mut resumed = reqwest::Client::new()
+        .get(format!("{}events", root))
+        .bearer_auth(&token)
+        .header("last-event-id", "2")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(
+        vec![(3, "placed".to_string(), second as u64)],
+        read_events(&mut resumed, 1).await
+    );
+}
+
+#[tokio::test]
+async fn websockets_get_the_filtered_feed() {
+    let (root, client, token, _) = start().await;
+    order(&client, 1).await;
+
+    let (mut socket, _) = tokio_tungstenite::connect_async(format!(
+        "{}events/ws?table=2&access_token={}",
+        root.replacen("http", "ws", 1),
+        token
+    ))
+    .await
+    .unwrap();
+
+    // only table 2's orders come through, the one already at table 1 included
+    let second = order(&client, 2).await;
+    let message = timeout(Duration::from_secs(5), socket.next())
+        .await
+        .expect("The feed should send the event.")
+        .unwrap()
+        .unwrap();
+    let event: Value = serde_json::from_str(message.to_text().unwrap()).unwrap();
+    assert_eq!("placed", event["change"]);
+    assert_eq!(second as u64, event["order"]["id"].as_u64().unwrap());
+    assert_eq!(2, event["order"]["table"]["id"].as_u64().unwrap());
+}
+
+#[tokio::test]
+async fn the_feed_is_for_signed_in_staff() {
+    let (root, _, _, _) = start().await;
+
+    let response = reqwest::get(format!("{}events", root)).await.unwrap();
+    assert_eq!(reqwest::StatusCode::UNAUTHORIZED, response.status());
+    assert!(tokio_tungstenite::connect_async(format!(
+        "{}events/ws",
+        root.replacen("http", "ws", 1)
+    ))
+    .await
+    .is_err());
+}
+
+#[tokio::test]
+async fn only_the_feed_takes_the_token_from_the_query() {
+    let (root, _, token, _) = start().await;
+
+    let response = reqwest::Client::new()
+        .get(format!("{}api/orders?access_token={}", root, token))
+        .header("x-api-version", "v1")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(reqwest::StatusCode::UNAUTHORIZED, response.status());
+}
+
+#[tokio::test]
+async fn feeds_stop_when_their_clients_go_away() {
+    let (root, _, token, feed) = start().await;
+
+    // nothing will ever happen at table 9, so the feed only finds out the client's gone by checking
+    let response = reqwest::Client::new()
+        .get(format!("{}events?table=9", root))
+        .bearer_auth(&token)
+        .send()
+        .await
+        .unwrap();
+    eventually(|| feed.listeners() == 1).await;
+    drop(response);
+    eventually(|| feed.listeners() == 0).await;
+
+    // nor are there any ready orders to tell Mina about
+    let notices = reqwest::Client::new()
+        .get(format!("{}notifications", root))
+        .bearer_auth(&token)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(reqwest::StatusCode::OK, notices.status());
+    eventually(|| feed.listeners() == 1).await;
+    drop(notices);
+    eventually(|| feed.listeners() == 0).await;
+}
@@ -0,0 +1,927 @@
+use std::collections::BTreeSet;
+
+use axum::{
+    body::Body,
+    http::{Method, Request},
+    Router,
+};
+use restaurant::menu;
+use restaurant_webapi::ver::{v1, v2};
+use serde_json::{json, Value};
+use tower::ServiceExt;
+
+mod common;
+
+fn app() -> Router {
+    let mut db = common::database();
+    // pasta under mains, and the parfait a level down, leaving nothing uncategorized
+    futures::executor::block_on(async {
+        let category = |name: &str, parent, sort_order| menu::Category {
+            name: name.to_string(),
+            parent,
+            sort_order,
+        };
+        let mains = menu::add_category(&mut db, category("Mains", None, 1)).await?;
+        let desserts = menu::add_category(&mut db, category("Desserts", None, 2)).await?;
+        let parfaits =
+            menu::add_category(&mut db, category("Parfaits", Some(desserts.id()), 1)).await?;
+        menu::file(&mut db, 1.into(), Some(mains.id()), 0).await?;
+        menu::file(&mut db, 2.into(), Some(parfaits.id()), 0).await
+    })
+    .unwrap();
+    common::app(db)
+}
+
+async fn call(
+    app: &Router,
+    version: &str,
+    method: Method,
+    path: &str,
+    body: Option<Value>,
+) -> (u16, Option<String>, Value) {
+    let request = Request::builder()
+        .method(method)
+        .uri(format!("/api{}", path))
+        .header("x-api-version", version)
+        .header("authorization", common::bearer())
+        .header("content-type", "application/json")
+        .body(body.map_or(Body::empty(), |b| Body::from(b.to_string())))
+        .unwrap();
+    let response = app.clone().oneshot(request).await.unwrap();
+    let status = response.status().as_u16();
+    let content_type = response
+        .headers()
+        .get("content-type")
+        .map(|c| c.to_str().unwrap().split(';').next().unwrap().to_string());
+    let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+
+    (
+        status,
+        content_type,
+        serde_json::from_slice(&bytes)
+            .unwrap_or_else(|_| Value::String(String::from_utf8_lossy(&bytes).to_string())),
+    )
+}
+
+// just enough of json schema to notice the api and its spec going separate ways.
+// unlike json schema, properties the spec doesn't know about are treated as errors.
+fn validate(spec: &Value, schema: &Value, value: &Value, at: &str, errors: &mut Vec<String>) {
+    if let Some(reference) = schema["$ref"].as_str() {
+        let name = reference.trim_start_matches("#/components/schemas/");
+        return validate(
+            spec,
+            &spec["components"]["schemas"][name],
+            value,
+            at,
+            errors,
+        );
+    }
+    if let Some(variants) = schema["oneOf"].as_array().or(schema["anyOf"].as_array()) {
+        let matched = variants.iter().any(|v| {
+            let mut variant_errors = Vec::new();
+            validate(spec, v, value, at, &mut variant_errors);
+            variant_errors.is_empty()
+        });
+        if !matched {
+            errors.push(format!("{}: {} matches no variant", at, value));
+        }
+        return;
+    }
+    if let Some(values) = schema["enum"].as_array() {
+        if !values.contains(value) {
+            errors.push(format!("{}: {} is not one of {:?}", at, value, values));
+        }
+    }
+
+    let types: Vec<&str> = match &schema["type"] {
+        Value::String(t) => vec![t.as_str()],
+        Value::Array(ts) => ts.iter().filter_map(|t| t.as_str()).collect(),
+        _ => return,
+    };
+    let actual = match value {
+        Value::Null => "null",
+        Value::Bool(_) => "boolean",
+        Value::Number(n) if n.is_u64() || n.is_i64() => "integer",
+        Value::Number(_) => "number",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
+    };
+    let integer_as_number = actual == "integer" && types.contains(&"number");
+    if !types.contains(&actual) && !integer_as_number {
+        errors.push(format!("{}: expected {:?}, got {}", at, types, value));
+        return;
+    }
+
+    match value {
+        Value::Array(items) => {
+            for (i, item) in items.iter().enumerate() {
+                validate(
+                    spec,
+                    &schema["items"],
+                    item,
+                    &format!("{}[{}]", at, i),
+                    errors,
+                );
+            }
+        }
+        Value::Object(fields) => {
+            let properties = schema["properties"]
+                .as_object()
+                .cloned()
+                .unwrap_or_default();
+            for required in schema["required"].as_array().into_iter().flatten() {
+                let required = required.as_str().unwrap();
+                if !fields.contains_key(required) {
+                    errors.push(format!("{}: missing '{}'", at, required));
+                }
+            }
+            for (name, field) in fields {
+                match properties.get(name) {
+                    Some(property) => {
+                        validate(spec, property, field, &format!("{}.{}", at, name), errors)
+                    }
+                    None => errors.push(format!("{}: '{}' isn't in the spec", at, name)),
+                }
+            }
+        }
+        _ => {}
+    }
+}
+
+#[tokio::test]
+async fn v1_spec_matches_handlers() {
+    let spec = serde_json::to_value(v1::openapi()).unwrap();
+
+    // every documented operation, with the path it's documented under
+    let scenario: Vec<(Method, &str, String, Option<Value>)> = vec![
+        (Method::GET, "/menu", "/menu".into(), None),
+        (Method::GET, "/menu_items", "/menu_items".into(), None),
+        (
+            Method::GET,
+            "/menu_items/{id}",
+            "/menu_items/1".into(),
+            None,
+        ),
+        (
+            Method::GET,
+            "/menu_items/{id}",
+            "/menu_items/9".into(),
+            None,
+        ),
+        (Method::GET, "/tables", "/tables".into(), None),
+        (Method::GET, "/table/{tableid}", "/table/1".into(), None),
+        (Method::GET, "/table/{tableid}", "/table/9".into(), None),
+        (
+            Method::POST,
+            "/orders",
+            "/orders".into(),
+            Some(json!({"table_id": 1, "item_id": 1, "quantity": 2})),
+        ),
+        (
+            Method::POST,
+            "/orders",
+            "/orders".into(),
+            Some(json!({"table_id": 1, "item_id": 2, "quantity": 1})),
+        ),
+        (
+            Method::POST,
+            "/orders",
+            "/orders".into(),
+            Some(json!({"table_id": 9, "item_id": 1, "quantity": 1})),
+        ),
+        (
+            Method::GET,
+            "/table/{tableid}/orders",
+            "/table/1/orders".into(),
+            None,
+        ),
+        (
+            Method::GET,
+            "/orders",
+            "/orders?table_id=1&sort=placed&direction=desc&limit=1".into(),
+            None,
+        ),
+        (Method::GET, "/orders", "/orders?cursor=zz".into(), None),
+        (
+            Method::POST,
+            "/orders/{id}/setquantity",
+            "/orders/1/setquantity".into(),
+            Some(json!({"quantity": 3})),
+        ),
+        (Method::GET, "/orders/{id}", "/orders/1".into(), None),
+        (Method::GET, "/orders/{id}", "/orders/9".into(), None),
+        (
+            Method::GET,
+            "/stations/{station}/tickets",
+            "/stations/grill/tickets".into(),
+            None,
+        ),
+        (
+            Method::POST,
+            "/stations/{station}/tickets/{id}/bump",
+            "/stations/grill/tickets/1/bump".into(),
+            None,
+        ),
+        (
+            Method::POST,
+            "/stations/{station}/tickets/{id}/bump",
+            "/stations/grill/tickets/2/bump".into(),
+            None,
+        ),
+        (
+            Method::GET,
+            "/table/{tableid}/status",
+            "/table/1/status".into(),
+            None,
+        ),
+        (Method::DELETE, "/orders/{id}", "/orders/2".into(), None),
+        (
+            Method::POST,
+            "/table/{tableid}/clear",
+            "/table/1/clear".into(),
+            None,
+        ),
+        (
+            Method::POST,
+            "/webhooks",
+            "/webhooks".into(),
+            Some(json!({"url": "http://127.0.0.1:1/hook", "events": ["order.ready"]})),
+        ),
+        (Method::GET, "/webhooks", "/webhooks".into(), None),
+        (
+            Method::GET,
+            "/webhooks/dead_letters",
+            "/webhooks/dead_letters".into(),
+            None,
+        ),
+        (
+            Method::POST,
+            "/webhooks/dead_letters/{id}/retry",
+            "/webhooks/dead_letters/7/retry".into(),
+            None,
+        ),
+        (Method::DELETE, "/webhooks/{id}", "/webhooks/1".into(), None),
+        (
+            Method::POST,
+            "/orders/{id}/setquantity",
+            "/orders/1/setquantity".into(),
+            Some(json!({"quantity": 500})),
+        ),
+        (
+            Method::POST,
+            "/order_groups",
+            "/order_groups".into(),
+            Some(json!({"table_id": 1, "items": [{"item_id": 1, "quantity": 2}]})),
+        ),
+        (
+            Method::POST,
+            "/order_groups",
+            "/order_groups".into(),
+            Some(json!({"table_id": 1, "items": [{"item_id": 9, "quantity": 1}]})),
+        ),
+        (
+            Method::POST,
+            "/orders/{id}/transfer",
+            "/orders/3/transfer".into(),
+            Some(json!({"table_id": 2})),
+        ),
+        (
+            Method::POST,
+            "/orders/{id}/transfer",
+            "/orders/3/transfer".into(),
+            Some(json!({"table_id": 2})),
+        ),
+        (
+            Method::POST,
+            "/orders/{id}/transfer",
+            "/orders/9/transfer".into(),
+            Some(json!({"table_id": 1})),
+        ),
+        (
+            Method::POST,
+            "/table/{tableid}/transfer",
+            "/table/2/transfer".into(),
+            Some(json!({"table_id": 1})),
+        ),
+        (
+            Method::POST,
+            "/table/{tableid}/transfer",
+            "/table/1/transfer".into(),
+            Some(json!({"table_id": 9})),
+        ),
+        (
+            Method::POST,
+            "/table_groups",
+            "/table_groups".into(),
+            Some(json!({"tables": [1, 2]})),
+        ),
+        (
+            Method::POST,
+            "/table_groups",
+            "/table_groups".into(),
+            Some(json!({"tables": [2, 1]})),
+        ),
+        (
+            Method::POST,
+            "/table_groups",
+            "/table_groups".into(),
+            Some(json!({"tables": [1]})),
+        ),
+        (Method::GET, "/table_groups", "/table_groups".into(), None),
+        (
+            Method::GET,
+            "/table_groups/{id}",
+            "/table_groups/1".into(),
+            None,
+        ),
+        (
+            Method::GET,
+            "/table_groups/{id}",
+            "/table_groups/9".into(),
+            None,
+        ),
+        (
+            Method::POST,
+            "/table_groups/{id}/split",
+            "/table_groups/1/split".into(),
+            Some(json!({"table_id": 9})),
+        ),
+        (
+            Method::POST,
+            "/table_groups/{id}/split",
+            "/table_groups/1/split".into(),
+            Some(json!({})),
+        ),
+        (
+            Method::POST,
+            "/table_groups/{id}/split",
+            "/table_groups/9/split".into(),
+            Some(json!({})),
+        ),
+        (
+            Method::POST,
+            "/reservations",
+            "/reservations".into(),
+            Some(json!({
+                "table_id": 1, "party_size": 2,
+                "starts_at": "2024-06-01T18:00:00Z", "ends_at": "2024-06-01T20:00:00Z",
+                "contact": {"name": "Tanaka", "phone": "090-1234-5678"}
+            })),
+        ),
+        (
+            Method::POST,
+            "/reservations",
+            "/reservations".into(),
+            Some(json!({
+                "table_id": 1, "party_size": 2,
+                "starts_at": "2024-06-01T19:00:00Z", "ends_at": "2024-06-01T21:00:00Z",
+                "contact": {"name": "Suzuki", "phone": "090-8765-4321"}
+            })),
+        ),
+        (
+            Method::POST,
+            "/reservations",
+            "/reservations".into(),
+            Some(json!({
+                "table_id": 1, "party_size": 9,
+                "starts_at": "2024-06-01T12:00:00Z", "ends_at": "2024-06-01T13:00:00Z",
+                "contact": {"name": "Sato", "phone": "090-0000-0000"}
+            })),
+        ),
+        (
+            Method::GET,
+            "/reservations",
+            "/reservations?date=2024-06-01".into(),
+            None,
+        ),
+        (
+            Method::GET,
+            "/reservations/{id}",
+            "/reservations/1".into(),
+            None,
+        ),
+        (
+            Method::GET,
+            "/reservations/{id}",
+            "/reservations/9".into(),
+            None,
+        ),
+        (
+            Method::PATCH,
+            "/reservations/{id}",
+            "/reservations/1".into(),
+            Some(json!({"ends_at": "2024-06-01T21:00:00Z", "party_size": 3})),
+        ),
+        (
+            Method::PATCH,
+            "/reservations/{id}",
+            "/reservations/1".into(),
+            Some(json!({"starts_at": "2024-06-01T22:00:00Z"})),
+        ),
+        (
+            Method::PATCH,
+            "/reservations/{id}",
+            "/reservations/9".into(),
+            Some(json!({"party_size": 2})),
+        ),
+        (
+            Method::DELETE,
+            "/reservations/{id}",
+            "/reservations/1".into(),
+            None,
+        ),
+        (
+            Method::DELETE,
+            "/reservations/{id}",
+            "/reservations/1".into(),
+            None,
+        ),
+        (
+            Method::POST,
+            "/tables/suggest",
+            "/tables/suggest".into(),
+            Some(json!({"size": 2, "stay_minutes": 30})),
+        ),
+        (
+            Method::POST,
+            "/tables/suggest",
+            "/tables/suggest".into(),
+            Some(json!({"size": 0})),
+        ),
+        (
+            Method::GET,
+            "/waitlist/quote",
+            "/waitlist/quote?size=2".into(),
+            None,
+        ),
+        (
+            Method::GET,
+            "/waitlist/quote",
+            "/waitlist/quote?size=0".into(),
+            None,
+        ),
+        (
+            Method::POST,
+            "/waitlist",
+            "/waitlist".into(),
+            Some(json!({"name": "Tanaka", "size": 2})),
+        ),
+        (
+            Method::POST,
+            "/waitlist",
+            "/waitlist".into(),
+            Some(json!({"name": "Suzuki", "size": 2, "phone": "090-1234-5678"})),
+        ),
+        (
+            Method::POST,
+            "/waitlist",
+            "/waitlist".into(),
+            Some(json!({"name": "Sato", "size": 9})),
+        ),
+        (Method::GET, "/waitlist", "/waitlist".into(), None),
+        (Method::GET, "/waitlist/{id}", "/waitlist/1".into(), None),
+        (Method::GET, "/waitlist/{id}", "/waitlist/9".into(), None),
+        (
+            Method::POST,
+            "/waitlist/{id}/no_show",
+            "/waitlist/1/no_show".into(),
+            None,
+        ),
+        (
+            Method::POST,
+            "/waitlist/{id}/no_show",
+            "/waitlist/1/no_show".into(),
+            None,
+        ),
+        (
+            Method::POST,
+            "/waitlist/{id}/no_show",
+            "/waitlist/9/no_show".into(),
+            None,
+        ),
+        (
+            Method::POST,
+            "/waitlist/{id}/seat",
+            "/waitlist/2/seat".into(),
+            None,
+        ),
+        (
+            Method::POST,
+            "/waitlist/{id}/seat",
+            "/waitlist/2/seat".into(),
+            None,
+        ),
+        (
+            Method::POST,
+            "/waitlist/{id}/seat",
+            "/waitlist/9/seat".into(),
+            None,
+        ),
+        (
+            Method::POST,
+            "/staff/login",
+            "/staff/login".into(),
+            Some(json!({"name": "Mina", "secret": "1234"})),
+        ),
+        (
+            Method::POST,
+            "/staff/login",
+            "/staff/login".into(),
+            Some(json!({"name": "Mina", "secret": "4321"})),
+        ),
+        (
+            Method::POST,
+            "/staff",
+            "/staff".into(),
+            Some(json!({"name": "Sam", "role": "server", "secret": "5678"})),
+        ),
+        (
+            Method::POST,
+            "/staff",
+            "/staff".into(),
+            Some(json!({"name": "Sam", "role": "server", "secret": "5678"})),
+        ),
+        (
+            Method::POST,
+            "/staff",
+            "/staff".into(),
+            Some(json!({"name": "Kai", "role": "kitchen", "secret": "12"})),
+        ),
+        (Method::GET, "/staff", "/staff".into(), None),
+        (
+            Method::POST,
+            "/staff/{id}/dismiss",
+            "/staff/2/dismiss".into(),
+            None,
+        ),
+        (
+            Method::POST,
+            "/staff/{id}/dismiss",
+            "/staff/9/dismiss".into(),
+            None,
+        ),
+        (
+            Method::POST,
+            "/api_keys",
+            "/api_keys".into(),
+            Some(json!({"name": "POS", "scopes": ["orders:write"]})),
+        ),
+        (
+            Method::POST,
+            "/api_keys",
+            "/api_keys".into(),
+            Some(json!({"name": "POS", "scopes": []})),
+        ),
+        (Method::GET, "/api_keys", "/api_keys".into(), None),
+        (
+            Method::POST,
+            "/api_keys/{id}/rotate",
+            "/api_keys/1/rotate".into(),
+            None,
+        ),
+        (
+            Method::POST,
+            "/api_keys/{id}/rotate",
+            "/api_keys/9/rotate".into(),
+            None,
+        ),
+        (Method::DELETE, "/api_keys/{id}", "/api_keys/1".into(), None),
+        (Method::DELETE, "/api_keys/{id}", "/api_keys/1".into(), None),
+        (Method::DELETE, "/api_keys/{id}", "/api_keys/9".into(), None),
+        (
+            Method::POST,
+            "/api_keys/{id}/rotate",
+            "/api_keys/1/rotate".into(),
+            None,
+        ),
+        (
+            Method::POST,
+            "/sections/assignments",
+            "/sections/assignments".into(),
+            Some(json!({
+                "section": "patio",
+                "server_id": 1,
+                "starts_at": "2020-01-01T00:00:00Z",
+                "ends_at": "2100-01-01T00:00:00Z",
+            })),
+        ),
+        (
+            Method::POST,
+            "/sections/assignments",
+            "/sections/assignments".into(),
+            Some(json!({
+                "section": "patio",
+                "server_id": 1,
+                "starts_at": "2024-01-01T18:00:00Z",
+                "ends_at": "2024-01-01T23:00:00Z",
+            })),
+        ),
+        (
+            Method::POST,
+            "/sections/assignments",
+            "/sections/assignments".into(),
+            Some(json!({
+                "section": "roof",
+                "server_id": 1,
+                "starts_at": "2024-01-01T18:00:00Z",
+                "ends_at": "2024-01-01T23:00:00Z",
+            })),
+        ),
+        (
+            Method::GET,
+            "/sections/assignments",
+            "/sections/assignments?at=2024-01-01T19:00:00Z".into(),
+            None,
+        ),
+        (
+            Method::GET,
+            "/staff/{id}/tables",
+            "/staff/1/tables".into(),
+            None,
+        ),
+        (
+            Method::GET,
+            "/staff/{id}/tables",
+            "/staff/9/tables".into(),
+            None,
+        ),
+        (
+            Method::DELETE,
+            "/sections/assignments/{id}",
+            "/sections/assignments/1".into(),
+            None,
+        ),
+        (
+            Method::DELETE,
+            "/sections/assignments/{id}",
+            "/sections/assignments/1".into(),
+            None,
+        ),
+        (
+            Method::GET,
+            "/audit",
+            "/audit?operation=order&text=pasta&limit=5".into(),
+            None,
+        ),
+        (Method::GET, "/audit/verify", "/audit/verify".into(), None),
+    ];
+
+    let errors = check(&spec, &app(), "v1", scenario).await;
+    assert!(errors.is_empty(), "{:#?}", errors);
+}
+
+#[tokio::test]
+async fn v2_spec_matches_handlers() {
+    let spec = serde_json::to_value(v2::openapi()).unwrap();
+
+    let scenario: Vec<(Method, &str, String, Option<Value>)> = vec![
+        (Method::GET, "/menu/items", "/menu/items".into(), None),
+        (
+            Method::GET,
+            "/menu/items/{id}",
+            "/menu/items/1".into(),
+            None,
+        ),
+        (
+            Method::GET,
+            "/menu/items/{id}",
+            "/menu/items/9".into(),
+            None,
+        ),
+        (Method::GET, "/tables", "/tables".into(), None),
+        (Method::GET, "/tables/{id}", "/tables/1".into(), None),
+        (Method::GET, "/tables/{id}", "/tables/9".into(), None),
+        (
+            Method::POST,
+            "/tables/{id}/orders",
+            "/tables/1/orders".into(),
+            Some(json!({"item_id": 1, "quantity": 2})),
+        ),
+        (
+            Method::POST,
+            "/tables/{id}/orders",
+            "/tables/1/orders".into(),
+            Some(json!({"item_id": 2, "quantity": 1})),
+        ),
+        (
+            Method::POST,
+            "/tables/{id}/orders",
+            "/tables/1/orders".into(),
+            Some(json!({"item_id": 9, "quantity": 1})),
+        ),
+        (
+            Method::POST,
+            "/tables/{id}/orders",
+            "/tables/9/orders".into(),
+            Some(json!({"item_id": 1, "quantity": 1})),
+        ),
+        (
+            Method::GET,
+            "/tables/{id}/orders",
+            "/tables/1/orders".into(),
+            None,
+        ),
+        (
+            Method::GET,
+            "/tables/{id}/orders",
+            "/tables/9/orders".into(),
+            None,
+        ),
+        (
+            Method::GET,
+            "/orders",
+            "/orders?status=placed&sort=quantity&limit=1".into(),
+            None,
+        ),
+        (Method::GET, "/orders", "/orders?cursor=zz".into(), None),
+        (Method::GET, "/orders/{id}", "/orders/1".into(), None),
+        (Method::GET, "/orders/{id}", "/orders/9".into(), None),
+        (
+            Method::PATCH,
+            "/orders/{id}",
+            "/orders/1".into(),
+            Some(json!({"quantity": 3})),
+        ),
+        (
+            Method::PATCH,
+            "/orders/{id}",
+            "/orders/1".into(),
+            Some(json!({"quantity": 0})),
+        ),
+        (
+            Method::PATCH,
+            "/orders/{id}",
+            "/orders/9".into(),
+            Some(json!({"quantity": 1})),
+        ),
+        (
+            Method::GET,
+            "/stations/{station}/tickets",
+            "/stations/grill/tickets".into(),
+            None,
+        ),
+        (
+            Method::POST,
+            "/stations/{station}/tickets/{id}/bump",
+            "/stations/grill/tickets/1/bump".into(),
+            None,
+        ),
+        (
+            Method::POST,
+            "/stations/{station}/tickets/{id}/bump",
+            "/stations/grill/tickets/2/bump".into(),
+            None,
+        ),
+        (
+            Method::POST,
+            "/stations/{station}/tickets/{id}/bump",
+            "/stations/grill/tickets/9/bump".into(),
+            None,
+        ),
+        (
+            Method::GET,
+            "/tables/{id}/status",
+            "/tables/1/status".into(),
+            None,
+        ),
+        (
+            Method::GET,
+            "/tables/{id}/status",
+            "/tables/9/status".into(),
+            None,
+        ),
+        (Method::DELETE, "/orders/{id}", "/orders/2".into(), None),
+        (Method::DELETE, "/orders/{id}", "/orders/2".into(), None),
+        (
+            Method::DELETE,
+            "/tables/{id}/orders",
+            "/tables/1/orders".into(),
+            None,
+        ),
+        (
+            Method::DELETE,
+            "/tables/{id}/orders",
+            "/tables/9/orders".into(),
+            None,
+        ),
+        (
+            Method::POST,
+            "/tables/{id}/order_groups",
+            "/tables/1/order_groups".into(),
+            Some(json!({"items": [{"item_id": 1, "quantity": 2}, {"item_id": 2, "quantity": 1}]})),
+        ),
+        (
+            Method::POST,
+            "/tables/{id}/order_groups",
+            "/tables/1/order_groups".into(),
+            Some(json!({"items": [{"item_id": 1, "quantity": 0}]})),
+        ),
+        (
+            Method::POST,
+            "/tables/{id}/order_groups",
+            "/tables/9/order_groups".into(),
+            Some(json!({"items": [{"item_id": 1, "quantity": 1}]})),
+        ),
+        (
+            Method::POST,
+            "/orders/{id}/transfer",
+            "/orders/3/transfer".into(),
+            Some(json!({"table_id": 2})),
+        ),
+        (
+            Method::POST,
+            "/orders/{id}/transfer",
+            "/orders/3/transfer".into(),
+            Some(json!({"table_id": 2})),
+        ),
+        (
+            Method::POST,
+            "/orders/{id}/transfer",
+            "/orders/9/transfer".into(),
+            Some(json!({"table_id": 1})),
+        ),
+        (
+            Method::POST,
+            "/tables/{id}/orders/transfer",
+            "/tables/1/orders/transfer".into(),
+            Some(json!({"table_id": 2})),
+        ),
+        (
+            Method::POST,
+            "/tables/{id}/orders/transfer",
+            "/tables/9/orders/transfer".into(),
+            Some(json!({"table_id": 2})),
+        ),
+    ];
+
+    let errors = check(&spec, &app(), "v2", scenario).await;
+    assert!(errors.is_empty(), "{:#?}", errors);
+}
+
+// runs every documented operation of a version, checking the responses against its spec
+async fn check(
+    spec: &Value,
+    app: &Router,
+    version: &str,
+    scenario: Vec<(Method, &str, String, Option<Value>)>,
+) -> Vec<String> {
+    let mut errors = Vec::new();
+    let mut exercised = BTreeSet::new();
+    for (method, template, path, body) in scenario {
+        let (status, content_type, response) =
+            call(app, version, method.clone(), &path, body).await;
+        let at = format!("{} {} ({})", method, path, status);
+        exercised.insert(format!("{} {}", method, template));
+
+        let operation = &spec["paths"][template][method.as_str().to_lowercase()];
+        if operation.is_null() {
+            errors.push(format!("{}: not in the spec", at));
+            continue;
+        }
+        let documented = &operation["responses"][status.to_string()];
+        if documented.is_null() {
+            errors.push(format!("{}: status isn't documented: {}", at, response));
+            continue;
+        }
+        let Some(content_type) = content_type else {
+            continue;
+        };
+        match documented["content"][&content_type].get("schema") {
+            Some(schema) => validate(spec, schema, &response, &at, &mut errors),
+            None if documented["content"].is_null() => {
+                errors.push(format!("{}: documented without a body", at))
+            }
+            None => errors.push(format!("{}: '{}' isn't documented", at, content_type)),
+        }
+    }
+
+    let documented: BTreeSet<String> = spec["paths"]
+        .as_object()
+        .unwrap()
+        .iter()
+        .flat_map(|(path, item)| {
+            item.as_object()
+                .unwrap()
+                .keys()
+                .map(move |method| format!("{} {}", method.to_uppercase(), path))
+        })
+        .collect();
+    for missing in documented.difference(&exercised) {
+        errors.push(format!("{}: documented but not exercised here", missing));
+    }
+
+    errors
+}
+
+#[tokio::test]
+async fn specs_are_served() {
+    for (version, expected) in [("v1", v1::openapi()), ("v2", v2::openapi())] {
+        let (status, _, spec) = call(&app(), version, Method::GET, "/openapi.json", None).await;
+
+        assert_eq!(200, status);
+        assert_eq!(serde_json::to_value(expected).unwrap(), spec);
+    }
+}
@@ -0,0 +1,181 @@
+use axum::{
+    body::Body,
+    http::{Method, Request},
+    Router,
+};
+use serde_json::{json, Value};
+use tower::ServiceExt;
+
+mod common;
+
+fn app() -> Router {
+    common::app(common::database())
+}
+
+// returns the status, the entity tag if there was one, and the body
+async fn call(
+    app: &Router,
+    version: &str,
+    method: Method,
+    path: &str,
+    if_match: Option<&str>,
+    body: Option<Value>,
+) -> (u16, Option<String>, Value) {
+    let mut request = Request::builder()
+        .method(method)
+        .uri(format!("/api{}", path))
+        .header("x-api-version", version)
+        .header("authorization", common::bearer())
+        .header("content-type", "application/json");
+    if let Some(tag) = if_match {
+        request = request.header("if-match", tag);
+    }
+    let response = app
+        .clone()
+        .oneshot(
+            request
+                .body(body.map_or(Body::empty(), |b| Body::from(b.to_string())))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    let status = response.status().as_u16();
+    let etag = response
+        .headers()
+        .get("etag")
+        .map(|e| e.to_str().unwrap().to_string());
+    let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+
+    (
+        status,
+        etag,
+        serde_json::from_slice(&bytes)
+            .unwrap_or_else(|_| Value::String(String::from_utf8_lossy(&bytes).to_string())),
+    )
+}
+
+async fn place(app: &Router) -> u64 {
+    let (_, _, placed) = call(
+        app,
+        "v2",
+        Method::POST,
+        "/tables/1/orders",
+        None,
+        Some(json!({"item_id": 1, "quantity": 1})),
+    )
+    .await;
+    placed["id"].as_u64().unwrap()
+}
+
+#[tokio::test]
+async fn resources_carry_their_version() {
+    let app = app();
+    let id = place(&app).await;
+
+    for (version, path) in [
+        ("v1", format!("/orders/{}", id)),
+        ("v2", format!("/orders/{}", id)),
+        ("v1", "/menu_items/1".to_string()),
+        ("v2", "/menu/items/1".to_string()),
+        ("v1", "/table/1".to_string()),
+        ("v2", "/tables/1".to_string()),
+    ] {
+        let (status, etag, _) = call(&app, version, Method::GET, &path, None, None).await;
+        assert_eq!(200, status, "{} {}", version, path);
+        assert_eq!(Some("\"1\"".to_string()), etag, "{} {}", version, path);
+    }
+
+    let (_, etag, _) = call(
+        &app,
+        "v1",
+        Method::POST,
+        &format!("/orders/{}/setquantity", id),
+        None,
+        Some(json!({"quantity": 3})),
+    )
+    .await;
+    assert_eq!(Some("\"2\"".to_string()), etag);
+}
+
+#[tokio::test]
+async fn stale_changes_fail_their_precondition() {
+    let app = app();
+    let id = place(&app).await;
+    let path = format!("/orders/{}", id);
+
+    // both waiters saw version 1, and the first to change it wins
+    let (status, etag, _) = call(
+        &app,
+        "v2",
+        Method::PATCH,
+        &path,
+        Some("\"1\""),
+        Some(json!({"quantity": 2})),
+    )
+    .await;
+    assert_eq!((200, Some("\"2\"".to_string())), (status, etag));
+
+    let (status, _, problem) = call(
+        &app,
+        "v2",
+        Method::PATCH,
+        &path,
+        Some("\"1\""),
+        Some(json!({"quantity": 3})),
+    )
+    .await;
+    assert_eq!(412, status);
+    assert_eq!(412, problem["status"]);
+
+    let (status, _, body) = call(
+        &app,
+        "v1",
+        Method::POST,
+        &format!("/orders/{}/setquantity", id),
+        Some("\"1\""),
+        Some(json!({"quantity": 3})),
+    )
+    .await;
+    assert_eq!(412, status);
+    assert!(body.is_string());
+
+    let (status, _, _) = call(&app, "v2", Method::DELETE, &path, Some("\"1\""), None).await;
+    assert_eq!(412, status);
+
+    let (_, _, order) = call(&app, "v2", Method::GET, &path, None, None).await;
+    assert_eq!(2, order["quantity"]);
+}
+
+#[tokio::test]
+async fn matching_versions_are_applied() {
+    let app = app();
+    let id = place(&app).await;
+    let path = format!("/orders/{}", id);
+
+    let (status, _, _) = call(
+        &app,
+        "v2",
+        Method::PATCH,
+        &path,
+        Some("\"7\", \"1\""),
+        Some(json!({"quantity": 2})),
+    )
+    .await;
+    assert_eq!(200, status);
+
+    let (status, _, _) = call(
+        &app,
+        "v2",
+        Method::PATCH,
+        &path,
+        Some("*"),
+        Some(json!({"quantity": 4})),
+    )
+    .await;
+    assert_eq!(200, status);
+
+    let (status, _, _) = call(&app, "v1", Method::DELETE, &path, Some("\"3\""), None).await;
+    assert_eq!(200, status);
+}
@@ -0,0 +1,362 @@
+use axum::{
+    body::Body,
+    http::{HeaderMap, Method, Request},
+    Router,
+};
+use chrono::{TimeDelta, Utc};
+use restaurant::order;
+use restaurant_webapi::{feed::Feed, ver::Versions};
+use serde_json::{json, Value};
+use tower::ServiceExt;
+
+mod common;
+
+fn app() -> Router {
+    app_with(Versions::default())
+}
+
+fn app_with(versions: Versions) -> Router {
+    let db = common::database();
+    common::app_with(db.clone(), Feed::new(db), versions)
+}
+
+async fn send(app: &Router, request: Request<Body>) -> (u16, HeaderMap, Value) {
+    let response = app.clone().oneshot(request).await.unwrap();
+    let status = response.status().as_u16();
+    let headers = response.headers().clone();
+    let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+
+    (
+        status,
+        headers,
+        serde_json::from_slice(&bytes)
+            .unwrap_or_else(|_| Value::String(String::from_utf8_lossy(&bytes).to_string())),
+    )
+}
+
+async fn call(
+    app: &Router,
+    version: &str,
+    method: Method,
+    path: &str,
+    body: Option<Value>,
+) -> (u16, String, Value) {
+    let request = Request::builder()
+        .method(method)
+        .uri(format!("/api{}", path))
+        .header("x-api-version", version)
+        .header("authorization", common::bearer())
+        .header("content-type", "application/json")
+        .body(body.map_or(Body::empty(), |b| Body::from(b.to_string())))
+        .unwrap();
+    let (status, headers, body) = send(app, request).await;
+    let content_type = headers
+        .get("content-type")
+        .map_or("", |c| c.to_str().unwrap())
+        .to_string();
+
+    (status, content_type, body)
+}
+
+// asks for the tables with the given headers, returning the status and the version that answered
+async fn negotiate(app: &Router, headers: &[(&str, &str)]) -> (u16, HeaderMap) {
+    let mut request = Request::builder()
+        .uri("/api/tables")
+        .header("authorization", common::bearer());
+    for (name, value) in headers {
+        request = request.header(*name, *value);
+    }
+    let (status, headers, _) = send(app, request.body(Body::empty()).unwrap()).await;
+    (status, headers)
+}
+
+#[tokio::test]
+async fn versions_share_the_same_orders() {
+    let app = app();
+
+    let (_, _, from_v1) = call(
+        &app,
+        "v1",
+        Method::POST,
+        "/orders",
+        Some(json!({"table_id": 1, "item_id": 1, "quantity": 2})),
+    )
+    .await;
+    let (status, _, from_v2) = call(
+        &app,
+        "v2",
+        Method::POST,
+        "/tables/1/orders",
+        Some(json!({"item_id": 1, "quantity": 1})),
+    )
+    .await;
+    assert_eq!(201, status);
+
+    let (_, _, v1_orders) = call(&app, "v1", Method::GET, "/table/1/orders", None).await;
+    let (_, _, v2_orders) = call(&app, "v2", Method::GET, "/tables/1/orders", None).await;
+    let ids = |orders: &Value| -> Vec<Value> {
+        orders
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|o| o["id"].clone())
+            .collect()
+    };
+    assert_eq!(
+        vec![from_v1["id"].clone(), from_v2["id"].clone()],
+        ids(&v1_orders)
+    );
+    assert_eq!(ids(&v1_orders), ids(&v2_orders));
+
+    // same order, each version's own shape and links
+    assert_eq!(1, v1_orders[0]["table"]["id"]);
+    assert_eq!("Pasta", v1_orders[0]["item"]["name"]);
+    assert_eq!(
+        "/restaurants/1/api/v1/orders/1/setquantity",
+        v1_orders[0]["_links"]["set_quantity"]["href"]
+    );
+    assert_eq!(1, v2_orders[0]["table_id"]);
+    assert_eq!("Pasta", v2_orders[0]["item"]["name"]);
+    assert_eq!("PATCH", v2_orders[0]["_links"]["set_quantity"]["method"]);
+    assert_eq!(
+        "/restaurants/1/api/v2/tables/1",
+        v2_orders[0]["_links"]["table"]["href"]
+    );
+}
+
+#[tokio::test]
+async fn links_keep_to_their_version() {
+    // whatever the server's default, a link leads to the version it came from
+    let app = app_with(Versions::default().with_default("v1").unwrap());
+    let (_, _, order) = call(
+        &app,
+        "v2",
+        Method::POST,
+        "/tables/2/orders",
+        Some(json!({"item_id": 1, "quantity": 1})),
+    )
+    .await;
+
+    let follow = |href: &str| {
+        Request::builder()
+            .uri(href)
+            .header("authorization", common::bearer())
+            .body(Body::empty())
+            .unwrap()
+    };
+    let href = order["_links"]["table"]["href"].as_str().unwrap();
+    let (status, headers, table) = send(&app, follow(href)).await;
+    assert_eq!(200, status);
+    assert_eq!("v2", headers["x-api-version"]);
+    assert_eq!(
+        "/restaurants/1/api/v2/tables/2/orders",
+        table["_links"]["place_order"]["href"]
+    );
+
+    // the version in the path wins over one in the headers
+    let mut request = follow("/api/v1/table/2");
+    request
+        .headers_mut()
+        .insert("x-api-version", "v2".parse().unwrap());
+    let (status, headers, _) = send(&app, request).await;
+    assert_eq!(
+        (200, "v1"),
+        (status, headers["x-api-version"].to_str().unwrap())
+    );
+
+    let (status, _, _) = send(&app, follow("/api/v9/tables/2")).await;
+    assert_eq!(404, status);
+}
+
+#[tokio::test]
+async fn changes_in_one_version_show_in_the_other() {
+    let app = app();
+    let (_, _, order) = call(
+        &app,
+        "v2",
+        Method::POST,
+        "/tables/2/orders",
+        Some(json!({"item_id": 1, "quantity": 1})),
+    )
+    .await;
+    let id = order["id"].as_u64().unwrap();
+
+    let (status, _, patched) = call(
+        &app,
+        "v2",
+        Method::PATCH,
+        &format!("/orders/{}", id),
+        Some(json!({"quantity": 4})),
+    )
+    .await;
+    assert_eq!(200, status);
+    assert_eq!(4, patched["quantity"]);
+
+    let (_, _, v1_orders) = call(&app, "v1", Method::GET, "/table/2/orders", None).await;
+    assert_eq!(4, v1_orders[0]["quantity"]);
+
+    call(
+        &app,
+        "v1",
+        Method::POST,
+        &format!("/orders/{}/setquantity", id),
+        Some(json!({"quantity": 0})),
+    )
+    .await;
+    let (status, _, _) = call(&app, "v2", Method::GET, &format!("/orders/{}", id), None).await;
+    assert_eq!(404, status);
+}
+
+#[tokio::test]
+async fn only_v2_errors_are_problem_documents() {
+    let app = app();
+
+    let (status, content_type, body) = call(
+        &app,
+        "v1",
+        Method::POST,
+        "/orders",
+        Some(json!({"table_id": 9, "item_id": 1, "quantity": 1})),
+    )
+    .await;
+    assert_eq!(400, status);
+    assert!(content_type.starts_with("text/plain"));
+    assert!(body.is_string());
+
+    let (status, content_type, body) = call(
+        &app,
+        "v2",
+        Method::POST,
+        "/tables/9/orders",
+        Some(json!({"item_id": 1, "quantity": 1})),
+    )
+    .await;
+    assert_eq!(404, status);
+    assert_eq!("application/problem+json", content_type);
+    assert_eq!(404, body["status"]);
+    assert_eq!("Not Found", body["title"]);
+}
+
+#[tokio::test]
+async fn versions_are_negotiated() {
+    let app = app();
+    let version = |headers: &HeaderMap| headers["x-api-version"].to_str().unwrap().to_string();
+
+    let (status, headers) = negotiate(&app, &[]).await;
+    assert_eq!((200, "v1".to_string()), (status, version(&headers)));
+
+    let v2 = [("accept", "application/vnd.restaurant.v2+json")];
+    let (status, headers) = negotiate(&app, &v2).await;
+    assert_eq!((200, "v2".to_string()), (status, version(&headers)));
+
+    // the header is the more specific of the two
+    let both = [
+        ("accept", "application/vnd.restaurant.v2+json"),
+        ("x-api-version", "v1"),
+    ];
+    let (_, headers) = negotiate(&app, &both).await;
+    assert_eq!("v1", version(&headers));
+
+    let (status, _) = negotiate(&app, &[("accept", "application/vnd.restaurant.v9+json")]).await;
+    assert_eq!(406, status);
+    let (status, _) = negotiate(&app, &[("x-api-version", "v9")]).await;
+    assert_eq!(404, status);
+
+    let defaults_to_v2 = app_with(Versions::default().with_default("v2").unwrap());
+    let (_, headers) = negotiate(&defaults_to_v2, &[("accept", "application/json")]).await;
+    assert_eq!("v2", version(&headers));
+}
+
+#[tokio::test]
+async fn retired_versions_are_announced() {
+    let deprecated_at = Utc::now() - TimeDelta::days(30);
+    let sunset = Utc::now() + TimeDelta::days(30);
+    let app = app_with(
+        Versions::default()
+            .deprecate("v1", deprecated_at, Some(sunset))
+            .unwrap(),
+    );
+
+    let (_, headers) = negotiate(&app, &[("x-api-version", "v1")]).await;
+    assert_eq!(
+        format!("@{}", deprecated_at.timestamp()),
+        headers["deprecation"]
+    );
+    assert_eq!(
+        sunset.format("%a, %d %b %Y %H:%M:%S GMT").to_string(),
+        headers["sunset"]
+    );
+    let (_, headers) = negotiate(&app, &[("x-api-version", "v2")]).await;
+    assert!(headers.get("deprecation").is_none());
+
+    let (status, _, versions) = send(
+        &app,
+        Request::builder()
+            .uri("/api/versions")
+            .body(Body::empty())
+            .unwrap(),
+    )
+    .await;
+    assert_eq!(200, status);
+    assert_eq!("v1", versions[0]["version"]);
+    assert_eq!(true, versions[0]["default"]);
+    assert!(versions[0]["sunset"].is_string());
+    assert_eq!(
+        "application/vnd.restaurant.v2+json",
+        versions[1]["media_type"]
+    );
+    assert!(versions[1]["deprecated_at"].is_null());
+
+    let sunsetted = app_with(
+        Versions::default()
+            .deprecate("v1", deprecated_at, Some(Utc::now() - TimeDelta::days(1)))
+            .unwrap(),
+    );
+    let (status, _) = negotiate(&sunsetted, &[]).await;
+    assert_eq!(410, status);
+}
+
+#[tokio::test]
+async fn invalid_fields_are_named() {
+    let app = app();
+
+    let (status, _, body) = call(
+        &app,
+        "v1",
+        Method::POST,
+        "/orders",
+        Some(json!({"table_id": 9, "item_id": 1, "quantity": 0})),
+    )
+    .await;
+    assert_eq!(400, status);
+    assert_eq!(
+        vec!["/table_id", "/quantity"],
+        body.as_str()
+            .unwrap()
+            .lines()
+            .map(|l| l.split(':').next().unwrap())
+            .collect::<Vec<_>>()
+    );
+
+    let (status, content_type, body) = call(
+        &app,
+        "v2",
+        Method::POST,
+        "/tables/1/order_groups",
+        Some(json!({"items": [
+            {"item_id": 1, "quantity": 1},
+            {"item_id": 9, "quantity": 1},
+            {"item_id": 1, "quantity": order::DEFAULT_MAX_QUANTITY + 1},
+        ]})),
+    )
+    .await;
+    assert_eq!(400, status);
+    assert_eq!("application/problem+json", content_type);
+    assert_eq!("/items/1/item_id", body["errors"][0]["pointer"]);
+    assert_eq!("/items/2/quantity", body["errors"][1]["pointer"]);
+
+    let (_, _, orders) = call(&app, "v2", Method::GET, "/tables/1/orders", None).await;
+    assert_eq!(Some(0), orders.as_array().map(|o| o.len()));
+}
@@ -0,0 +1,346 @@
+use std::{
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicU32, Ordering},
+        Arc, Mutex,
+    },
+    time::Duration,
+};
+
+use axum::{
+    http::{HeaderMap, StatusCode},
+    routing::post,
+    Extension, Router,
+};
+use restaurant::{kitchen, memdb::Database, menu, order, tenant, waitlist};
+use restaurant_webapi::{
+    feed::Feed,
+    relay::Relay,
+    webhooks::{self, EventKind, RetryPolicy, Webhooks},
+};
+
+mod common;
+
+// stands in for a POS or tablet, failing the first however many deliveries it's told to
+#[derive(Clone, Default)]
+struct Receiver {
+    received: Arc<Mutex<Vec<(HeaderMap, String)>>>,
+    failures: Arc<AtomicU32>,
+}
+
+impl Receiver {
+    async fn start(failures: u32) -> (Receiver, String) {
+        let receiver = Receiver {
+            failures: Arc::new(AtomicU32::new(failures)),
+            ..Default::default()
+        };
+        let app = Router::new()
+            .route("/hook", post(receive))
+            .layer(Extension(receiver.clone()));
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/hook", listener.local_addr().unwrap());
+        tokio::spawn(async { axum::serve(listener, app).await });
+
+        (receiver, url)
+    }
+
+    fn received(&self) -> Vec<(HeaderMap, String)> {
+        self.received.lock().unwrap().clone()
+    }
+}
+
+async fn receive(
+    Extension(receiver): Extension<Receiver>,
+    headers: HeaderMap,
+    body: String,
+) -> StatusCode {
+    let failing = receiver
+        .failures
+        .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |f| f.checked_sub(1))
+        .is_ok();
+    if failing {
+        return StatusCode::INTERNAL_SERVER_ERROR;
+    }
+
+    receiver.received.lock().unwrap().push((headers, body));
+    StatusCode::OK
+}
+
+fn store_path(name: &str) -> PathBuf {
+    let path = std::env::temp_dir().join(format!("webhooks-{}-{}.json", std::process::id(), name));
+    _ = std::fs::remove_file(&path);
+    path
+}
+
+fn fast_retries(max_attempts: u32) -> RetryPolicy {
+    RetryPolicy {
+        max_attempts,
+        base_delay: Duration::from_millis(10),
+        max_delay: Duration::from_millis(50),
+    }
+}
+
+fn start(db: &Database, webhooks: &Webhooks) {
+    let relay = Relay::new(Feed::new(db.clone()), webhooks.clone());
+    let (db, webhooks) = (db.clone(), webhooks.clone());
+    tokio::spawn(async move { relay.run(db).await });
+    tokio::spawn(async move { webhooks.run().await });
+}
+
+async fn eventually<T>(mut f: impl FnMut() -> Option<T>) -> T {
+    for _ in 0..100 {
+        if let Some(result) = f() {
+            return result;
+        }
+        tokio::time::sleep(Duration::from_millis(50)).await;
+    }
+    panic!("Timed out waiting for webhooks.")
+}
+
+async fn place_order(db: &mut Database) -> order::RepoOrder {
+    order::place(
+        db,
+        &order::Rules::default(),
+        common::table(1),
+        common::item(1, "Pasta"),
+        2,
+    )
+    .await
+    .unwrap()
+}
+
+#[tokio::test]
+async fn deliveries_are_signed_and_retried() {
+    let (receiver, url) = Receiver::start(2).await;
+    let mut db = Database::default();
+    let webhooks = Webhooks::open(Some(store_path("signed")), fast_retries(5)).unwrap();
+    let subscription = webhooks.register(tenant::DEFAULT, url, vec![]).unwrap();
+    start(&db, &webhooks);
+
+    let order = place_order(&mut db).await;
+
+    let (headers, body) = eventually(|| receiver.received().into_iter().next()).await;
+    let header = |name: &str| headers.get(name).unwrap().to_str().unwrap().to_string();
+    assert_eq!(
+        webhooks::sign(
+            &subscription.secret,
+            &header(webhooks::TIMESTAMP_HEADER),
+            &body
+        ),
+        header(webhooks::SIGNATURE_HEADER)
+    );
+
+    let payload: serde_json::Value = serde_json::from_str(&body).unwrap();
+    assert_eq!("order.placed", payload["type"]);
+    assert_eq!(u32::from(order.id()), payload["order"]["id"]);
+    // the server started the once
+    assert_eq!(
+        format!("1-{}", payload["id"]),
+        header(webhooks::EVENT_ID_HEADER)
+    );
+    assert!(webhooks.dead_letters(tenant::DEFAULT).is_empty());
+}
+
+#[tokio::test]
+async fn parties_are_announced_when_their_table_is_held() {
+    let (receiver, url) = Receiver::start(0).await;
+    let mut db = Database::new(vec![], vec![common::table(1)], vec![]);
+    let webhooks = Webhooks::open(Some(store_path("waitlist")), fast_retries(5)).unwrap();
+    webhooks
+        .register(tenant::DEFAULT, url, vec![EventKind::PartyNotified])
+        .unwrap();
+    start(&db, &webhooks);
+
+    place_order(&mut db).await;
+    let party = waitlist::add(
+        &mut db,
+        &waitlist::Rules::default(),
+        waitlist::Party::new("Suzuki".to_string(), 2, Some("555-0100".to_string())),
+    )
+    .await
+    .unwrap();
+    order::clear_table(&mut db, 1.into()).await.unwrap();
+    waitlist::table_freed(&mut db, 1.into()).await.unwrap();
+
+    let (headers, body) = eventually(|| receiver.received().into_iter().next()).await;
+    let payload: serde_json::Value = serde_json::from_str(&body).unwrap();
+    assert_eq!("waitlist.notified", payload["type"]);
+    assert_eq!(u32::from(party.id()), payload["party"]["id"]);
+    assert_eq!("555-0100", payload["party"]["phone"]);
+    assert_eq!(1, payload["party"]["table"]);
+    assert_eq!(
+        format!("1-party-{}", u32::from(party.id())),
+        headers
+            .get(webhooks::EVENT_ID_HEADER)
+            .unwrap()
+            .to_str()
+            .unwrap()
+    );
+
+    // the order events weren't asked for, and the party is only announced the once
+    tokio::time::sleep(Duration::from_millis(500)).await;
+    assert_eq!(1, receiver.received().len());
+}
+
+#[tokio::test]
+async fn subscriptions_only_get_the_events_they_asked_for() {
+    let (receiver, url) = Receiver::start(0).await;
+    let mut db = Database::default();
+    let webhooks = Webhooks::open(Some(store_path("filtered")), fast_retries(5)).unwrap();
+    webhooks
+        .register(tenant::DEFAULT, url, vec![EventKind::Ready])
+        .unwrap();
+    start(&db, &webhooks);
+
+    let order = place_order(&mut db).await;
+    kitchen::bump(&mut db, menu::Station::Grill, order.id())
+        .await
+        .unwrap();
+
+    let (_, body) = eventually(|| receiver.received().into_iter().next()).await;
+    let payload: serde_json::Value = serde_json::from_str(&body).unwrap();
+    assert_eq!("order.ready", payload["type"]);
+
+    tokio::time::sleep(Duration::from_millis(300)).await;
+    assert_eq!(1, receiver.received().len());
+}
+
+#[tokio::test]
+async fn orders_are_only_announced_ready_once() {
+    let (receiver, url) = Receiver::start(0).await;
+    let mut db = Database::default();
+    let webhooks = Webhooks::open(Some(store_path("ready")), fast_retries(5)).unwrap();
+    webhooks
+        .register(
+            tenant::DEFAULT,
+            url,
+            vec![EventKind::Ready, EventKind::Updated],
+        )
+        .unwrap();
+    start(&db, &webhooks);
+
+    let order = place_order(&mut db).await;
+    kitchen::bump(&mut db, menu::Station::Grill, order.id())
+        .await
+        .unwrap();
+    // one fewer of an order that's ready keeps it ready, which isn't news
+    order::set_quantity(&mut db, &order::Rules::default(), order.id(), 1, None)
+        .await
+        .unwrap();
+
+    let mut kinds = eventually(|| {
+        let received = receiver.received();
+        (received.len() == 2).then(|| {
+            received
+                .iter()
+                .map(|(_, body)| {
+                    let payload: serde_json::Value = serde_json::from_str(body).unwrap();
+                    payload["type"].as_str().unwrap().to_string()
+                })
+                .collect::<Vec<String>>()
+        })
+    })
+    .await;
+    kinds.sort();
+    assert_eq!(vec!["order.ready", "order.updated"], kinds);
+
+    tokio::time::sleep(Duration::from_millis(300)).await;
+    assert_eq!(2, receiver.received().len());
+}
+
+#[tokio::test]
+async fn undeliverable_events_are_dead_lettered_and_survive_restarts() {
+    let (receiver, url) = Receiver::start(u32::MAX).await;
+    let mut db = Database::default();
+    let path = store_path("dead");
+    let webhooks = Webhooks::open(Some(path.clone()), fast_retries(3)).unwrap();
+    webhooks.register(tenant::DEFAULT, url, vec![]).unwrap();
+    start(&db, &webhooks);
+
+    place_order(&mut db).await;
+
+    let dead = eventually(|| webhooks.dead_letters(tenant::DEFAULT).into_iter().next()).await;
+    assert_eq!(3, dead.attempts);
+    assert!(receiver.received().is_empty());
+
+    let reopened = Webhooks::open(Some(path), fast_retries(3)).unwrap();
+    assert_eq!(1, reopened.subscriptions(tenant::DEFAULT).len());
+    assert_eq!(
+        vec![dead.id],
+        reopened
+            .dead_letters(tenant::DEFAULT)
+            .iter()
+            .map(|d| d.id)
+            .collect::<Vec<_>>()
+    );
+}
+
+#[tokio::test]
+async fn events_after_a_restart_are_not_mistaken_for_ones_before_it() {
+    let (receiver, url) = Receiver::start(3).await;
+    let path = store_path("restarted");
+    let webhooks = Webhooks::open(Some(path.clone()), fast_retries(3)).unwrap();
+    webhooks.register(tenant::DEFAULT, url, vec![]).unwrap();
+    let mut db = Database::default();
+    start(&db, &webhooks);
+    place_order(&mut db).await;
+    let dead = eventually(|| webhooks.dead_letters(tenant::DEFAULT).into_iter().next()).await;
+
+    // a fresh database numbers its events from 1 again
+    let reopened = Webhooks::open(Some(path), fast_retries(3)).unwrap();
+    let mut db = Database::default();
+    start(&db, &reopened);
+    place_order(&mut db).await;
+    assert_eq!(
+        dead.event_id,
+        Some(order::events(&db, None, &Default::default()).await.unwrap()[0].id())
+    );
+
+    let (headers, _) = eventually(|| receiver.received().into_iter().next()).await;
+    assert_eq!(
+        "2-1",
+        headers
+            .get(webhooks::EVENT_ID_HEADER)
+            .unwrap()
+            .to_str()
+            .unwrap()
+    );
+    assert_eq!(1, reopened.dead_letters(tenant::DEFAULT).len());
+}
+
+#[tokio::test]
+async fn changes_that_cant_be_saved_are_not_kept() {
+    let dir = std::env::temp_dir().join(format!("webhooks-{}-unsaved", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let webhooks = Webhooks::open(Some(dir.join("webhooks.json")), fast_retries(3)).unwrap();
+
+    std::fs::remove_dir_all(&dir).unwrap();
+    assert!(webhooks
+        .register(tenant::DEFAULT, "http://localhost/hook".to_string(), vec![])
+        .is_err());
+    assert!(webhooks.subscriptions(tenant::DEFAULT).is_empty());
+}
+
+#[tokio::test]
+async fn subscriptions_only_get_their_own_restaurants_events() {
+    let (receiver, url) = Receiver::start(0).await;
+    let db = Database::default();
+    let mut downtown = db.open(2.into(), vec![], vec![], vec![]).unwrap();
+    let webhooks = Webhooks::open(Some(store_path("branches")), fast_retries(5)).unwrap();
+    webhooks.register(2.into(), url, vec![]).unwrap();
+    start(&db, &webhooks);
+
+    // both are order 1, each at its own restaurant
+    place_order(&mut db.clone()).await;
+    place_order(&mut downtown).await;
+
+    let (_, body) = eventually(|| receiver.received().into_iter().next()).await;
+    let payload: serde_json::Value = serde_json::from_str(&body).unwrap();
+    assert_eq!("order.placed", payload["type"]);
+
+    // and only the downtown one is delivered
+    tokio::time::sleep(Duration::from_millis(300)).await;
+    assert_eq!(1, receiver.received().len());
+    assert!(webhooks.subscriptions(tenant::DEFAULT).is_empty());
+}
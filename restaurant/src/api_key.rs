@@ -0,0 +1,238 @@
+use std::fmt;
+use std::future::Future;
+
+use chrono::{DateTime, Utc};
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use subtle::ConstantTimeEq;
+use thiserror::Error;
+
+use crate::{staff, tenant, RepoItem};
+
+// NOTE: api keys are credentials for other systems, such as a POS, rather than for people. a key is
+// "rk_{id}_{secret}", where the secret is 32 random bytes in hex. only a SHA-256 of the secret is kept,
+// which is enough for something that random, so a leaked store can't be used to sign in. a key only works at the
+// restaurants it was made for, starting with the one it was created at.
+
+#[derive(Error, Debug)]
+pub enum ApiKeyError {
+    #[error("An error occurred when interacting with the repository.")]
+    RepoOperation(#[from] anyhow::Error),
+    #[error("Unable to find api key {0:?}")]
+    KeyNotFound(Id),
+    #[error("An api key needs at least one scope.")]
+    NoScopes,
+    #[error("Api key {0:?} has been revoked.")]
+    Revoked(Id),
+    #[error("The api key isn't valid.")]
+    InvalidKey,
+}
+pub type Result<T> = std::result::Result<T, ApiKeyError>;
+pub type RepoResult<T> = std::result::Result<T, anyhow::Error>;
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Scope {
+    #[serde(rename = "orders:read")]
+    OrdersRead,
+    #[serde(rename = "orders:write")]
+    OrdersWrite,
+    #[serde(rename = "kitchen:write")]
+    KitchenWrite,
+    #[serde(rename = "tables:read")]
+    TablesRead,
+    #[serde(rename = "tables:write")]
+    TablesWrite,
+    #[serde(rename = "menu:read")]
+    MenuRead,
+    #[serde(rename = "menu:admin")]
+    MenuAdmin,
+    #[serde(rename = "webhooks:admin")]
+    WebhooksAdmin,
+}
+
+impl Scope {
+    // being able to change something means being able to read it
+    pub fn covers(self, scope: Scope) -> bool {
+        self == scope
+            || matches!(
+                (self, scope),
+                (Scope::OrdersWrite, Scope::OrdersRead)
+                    | (Scope::TablesWrite, Scope::TablesRead)
+                    | (Scope::MenuAdmin, Scope::MenuRead)
+            )
+    }
+}
+
+impl fmt::Display for Scope {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Scope::OrdersRead => "orders:read",
+            Scope::OrdersWrite => "orders:write",
+            Scope::KitchenWrite => "kitchen:write",
+            Scope::TablesRead => "tables:read",
+            Scope::TablesWrite => "tables:write",
+            Scope::MenuRead => "menu:read",
+            Scope::MenuAdmin => "menu:admin",
+            Scope::WebhooksAdmin => "webhooks:admin",
+        })
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ApiKey {
+    // what the key is for, such as "Front counter POS"
+    pub name: String,
+    pub scopes: Vec<Scope>,
+    pub restaurants: Vec<tenant::Id>,
+    pub created: DateTime<Utc>,
+    pub created_by: Option<staff::Id>,
+    pub rotated: Option<DateTime<Utc>>,
+    // revoked keys are kept, so what they did can still be traced back to them
+    pub revoked: Option<DateTime<Utc>>,
+    #[serde(skip)]
+    pub hash: [u8; 32],
+}
+
+impl ApiKey {
+    pub fn allows(&self, scope: Scope) -> bool {
+        self.scopes.iter().any(|s| s.covers(scope))
+    }
+
+    pub fn works_at(&self, tenant: tenant::Id) -> bool {
+        self.restaurants.contains(&tenant)
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Id(pub u32);
+impl From<u32> for Id {
+    fn from(value: u32) -> Self {
+        Id(value)
+    }
+}
+impl From<Id> for u32 {
+    fn from(value: Id) -> Self {
+        value.0
+    }
+}
+pub type RepoApiKey = RepoItem<ApiKey, Id>;
+
+pub trait Repository {
+    fn get_all(&self) -> impl Future<Output = RepoResult<Vec<RepoApiKey>>> + Send;
+    fn get(&self, id: Id) -> impl Future<Output = RepoResult<RepoApiKey>> + Send;
+    fn create(&mut self, item: ApiKey) -> impl Future<Output = RepoResult<RepoApiKey>> + Send;
+    fn update(&mut self, item: RepoApiKey) -> impl Future<Output = RepoResult<RepoApiKey>> + Send;
+}
+
+fn new_secret() -> String {
+    let mut secret = [0u8; 32];
+    rand::thread_rng().fill_bytes(&mut secret);
+    hex::encode(secret)
+}
+
+fn hash(secret: &str) -> [u8; 32] {
+    Sha256::digest(secret.as_bytes()).into()
+}
+
+fn key(id: Id, secret: &str) -> String {
+    format!("rk_{}_{}", id.0, secret)
+}
+
+pub async fn get<T: Repository>(repo: &T, id: Id) -> Result<RepoApiKey> {
+    repo.get(id).await.map_err(|_| ApiKeyError::KeyNotFound(id))
+}
+
+// the keys for the repository's restaurant, so one restaurant's keys can't be seen or changed from another
+async fn get_here<T: Repository + tenant::Scoped>(repo: &T, id: Id) -> Result<RepoApiKey> {
+    match get(repo, id).await? {
+        api_key if api_key.works_at(repo.tenant()) => Ok(api_key),
+        _ => Err(ApiKeyError::KeyNotFound(id)),
+    }
+}
+
+pub async fn get_all<T: Repository + tenant::Scoped>(repo: &T) -> Result<Vec<RepoApiKey>> {
+    let mut all = repo.get_all().await.map_err(ApiKeyError::RepoOperation)?;
+    all.retain(|k| k.works_at(repo.tenant()));
+    Ok(all)
+}
+
+// the key itself is only ever handed out here and by `rotate`. it works at the repository's restaurant
+pub async fn create<T: Repository + staff::Acting + tenant::Scoped>(
+    repo: &mut T,
+    name: &str,
+    scopes: &[Scope],
+) -> Result<(RepoApiKey, String)> {
+    if scopes.is_empty() {
+        return Err(ApiKeyError::NoScopes);
+    }
+    let mut scopes = scopes.to_vec();
+    scopes.sort();
+    scopes.dedup();
+
+    let secret = new_secret();
+    let created = repo
+        .create(ApiKey {
+            name: name.to_string(),
+            scopes,
+            restaurants: vec![repo.tenant()],
+            created: Utc::now(),
+            created_by: repo.actor(),
+            rotated: None,
+            revoked: None,
+            hash: hash(&secret),
+        })
+        .await
+        .map_err(ApiKeyError::RepoOperation)?;
+    let key = key(created.id(), &secret);
+    Ok((created, key))
+}
+
+// swaps the key for a new one with the same id and scopes. the old key stops working straight away
+pub async fn rotate<T: Repository + tenant::Scoped>(
+    repo: &mut T,
+    id: Id,
+) -> Result<(RepoApiKey, String)> {
+    let mut api_key = get_here(repo, id).await?;
+    if api_key.revoked.is_some() {
+        return Err(ApiKeyError::Revoked(id));
+    }
+
+    let secret = new_secret();
+    api_key.hash = hash(&secret);
+    api_key.rotated = Some(Utc::now());
+    let rotated = repo
+        .update(api_key)
+        .await
+        .map_err(ApiKeyError::RepoOperation)?;
+    Ok((rotated, key(id, &secret)))
+}
+
+pub async fn revoke<T: Repository + tenant::Scoped>(repo: &mut T, id: Id) -> Result<RepoApiKey> {
+    let mut api_key = get_here(repo, id).await?;
+    if api_key.revoked.is_some() {
+        return Err(ApiKeyError::Revoked(id));
+    }
+
+    api_key.revoked = Some(Utc::now());
+    repo.update(api_key)
+        .await
+        .map_err(ApiKeyError::RepoOperation)
+}
+
+// malformed, unknown, wrong and revoked keys all fail the same way
+pub async fn authenticate<T: Repository>(repo: &T, key: &str) -> Result<RepoApiKey> {
+    let (id, secret) = key
+        .strip_prefix("rk_")
+        .and_then(|k| k.split_once('_'))
+        .ok_or(ApiKeyError::InvalidKey)?;
+    let id = Id(id.parse().map_err(|_| ApiKeyError::InvalidKey)?);
+    let api_key = repo.get(id).await.map_err(|_| ApiKeyError::InvalidKey)?;
+
+    // in constant time, so how long it takes doesn't give away how much was right
+    let matches = bool::from(hash(secret).ct_eq(&api_key.hash));
+    match matches && api_key.revoked.is_none() {
+        true => Ok(api_key),
+        false => Err(ApiKeyError::InvalidKey),
+    }
+}
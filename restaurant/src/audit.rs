@@ -0,0 +1,207 @@
+use std::future::Future;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use thiserror::Error;
+
+use crate::{api_key, staff, tenant, RepoItem};
+
+// NOTE: repositories append an entry for every change they make, in the same unit of work as the change, so
+// there's no changing anything without it being on record. the store can only be appended to, and each entry
+// carries a SHA-256 over its own contents and the previous entry's hash. editing or dropping an entry breaks
+// every link after it, which is what `verify` looks for. it doesn't stop someone rewriting the whole chain from
+// the tampered entry on, which would take anchoring the latest hash somewhere else.
+// each restaurant has a chain of its own, and what's shared between them another, so a restaurant's log can be
+// checked without reading any other's, and one restaurant's entries don't hold up another's.
+
+#[derive(Error, Debug)]
+pub enum AuditError {
+    #[error("An error occurred when interacting with the repository.")]
+    RepoOperation(#[from] anyhow::Error),
+    #[error("Audit entry {0:?} doesn't match its hash, or the entry before it.")]
+    Tampered(Id),
+}
+pub type Result<T> = std::result::Result<T, AuditError>;
+pub type RepoResult<T> = std::result::Result<T, anyhow::Error>;
+
+// what the first entry chains on from
+pub const GENESIS: [u8; 32] = [0; 32];
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Actor {
+    Staff(staff::Id),
+    ApiKey(api_key::Id),
+}
+
+// a change, as the repository that made it saw it
+#[derive(Debug, Clone, Serialize)]
+pub struct Record {
+    pub time: DateTime<Utc>,
+    // nobody, for changes the system makes on its own, such as seeding the first admin
+    pub actor: Option<Actor>,
+    // the api request the change was made for, if it came through one
+    pub request: Option<String>,
+    // the restaurant whose data changed, or none for what's shared between them, such as staff
+    pub tenant: Option<tenant::Id>,
+    // what was done, such as "order.remove"
+    pub operation: String,
+    // the item as it was before and after. creating has no before, and removing no after
+    pub before: Option<Value>,
+    pub after: Option<Value>,
+}
+
+impl Record {
+    pub fn hash(&self, previous: &[u8; 32]) -> [u8; 32] {
+        let mut hasher = Sha256::new();
+        hasher.update(previous);
+        hasher.update(serde_json::to_vec(self).expect("Audit records should be serializable."));
+        hasher.finalize().into()
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Entry {
+    #[serde(flatten)]
+    pub record: Record,
+    pub previous: [u8; 32],
+    pub hash: [u8; 32],
+}
+
+impl Entry {
+    pub fn new(record: Record, previous: [u8; 32]) -> Entry {
+        Entry {
+            hash: record.hash(&previous),
+            record,
+            previous,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Id(pub u32);
+impl From<u32> for Id {
+    fn from(value: u32) -> Self {
+        Id(value)
+    }
+}
+impl From<Id> for u32 {
+    fn from(value: Id) -> Self {
+        value.0
+    }
+}
+pub type RepoEntry = RepoItem<Entry, Id>;
+
+pub const DEFAULT_PAGE_SIZE: usize = 50;
+pub const MAX_PAGE_SIZE: usize = 200;
+
+// there's deliberately no way to change or remove an entry
+pub trait Store {
+    // chains the record on from the latest entry for the same restaurant, or the latest shared one, in the same
+    // unit of work as reading it
+    fn append(&mut self, record: Record) -> impl Future<Output = RepoResult<RepoEntry>> + Send;
+    // every entry in the restaurant's chain, or the shared one, oldest first
+    fn chain(
+        &self,
+        tenant: Option<tenant::Id>,
+    ) -> impl Future<Output = RepoResult<Vec<RepoEntry>>> + Send;
+    // the restaurant's entries and the shared ones that match the query, newest first, and no more than its
+    // limit. stores are expected to evaluate all of it themselves, so only a page's worth of entries leaves them
+    fn query(
+        &self,
+        tenant: tenant::Id,
+        query: &Query,
+    ) -> impl Future<Output = RepoResult<Vec<RepoEntry>>> + Send;
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Query {
+    pub actor: Option<Actor>,
+    // "order" matches every order operation, and "order.remove" only removals
+    pub operation: Option<String>,
+    pub request: Option<String>,
+    pub since: Option<DateTime<Utc>>,
+    pub until: Option<DateTime<Utc>>,
+    // found anywhere in the before or after snapshots, ignoring case, such as a menu item's name
+    pub text: Option<String>,
+    // where the previous page ended. ids only go up, so the next page is the entries older than this one
+    pub before: Option<Id>,
+    pub limit: Option<usize>,
+}
+
+impl Query {
+    pub fn matches(&self, entry: &RepoEntry) -> bool {
+        let record = &entry.record;
+        let snapshot = |v: &Option<Value>| v.as_ref().map(Value::to_string).unwrap_or_default();
+        self.before.map_or(true, |b| entry.id() < b)
+            && self.actor.map_or(true, |a| record.actor == Some(a))
+            && self.operation.as_ref().map_or(true, |o| {
+                record.operation == *o || record.operation.starts_with(&format!("{}.", o))
+            })
+            && self
+                .request
+                .as_ref()
+                .map_or(true, |r| record.request.as_ref() == Some(r))
+            && self.since.map_or(true, |t| record.time >= t)
+            && self.until.map_or(true, |t| record.time < t)
+            && self.text.as_ref().map_or(true, |text| {
+                let text = text.to_lowercase();
+                snapshot(&record.before).to_lowercase().contains(&text)
+                    || snapshot(&record.after).to_lowercase().contains(&text)
+            })
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Page {
+    pub entries: Vec<RepoEntry>,
+    // where to continue from, if there are more
+    pub next: Option<Id>,
+}
+
+// a page of the matching entries, newest first. only the store's own restaurant's changes are searched, along
+// with what's shared between restaurants
+pub async fn search<T: Store + tenant::Scoped>(store: &T, query: &Query) -> Result<Page> {
+    // one more than was asked for, to know whether there's a next page without a separate count
+    let limit = query
+        .limit
+        .unwrap_or(DEFAULT_PAGE_SIZE)
+        .clamp(1, MAX_PAGE_SIZE);
+    let mut entries = store
+        .query(
+            store.tenant(),
+            &Query {
+                limit: Some(limit + 1),
+                ..query.clone()
+            },
+        )
+        .await?;
+
+    let next = if entries.len() > limit {
+        entries.truncate(limit);
+        entries.last().map(|e| e.id())
+    } else {
+        None
+    };
+    Ok(Page { entries, next })
+}
+
+// walks the store's restaurant's chain and the shared one from the start, returning how many entries they hold
+// if nothing's been tampered with
+pub async fn verify<T: Store + tenant::Scoped>(store: &T) -> Result<usize> {
+    let mut count = 0;
+    for tenant in [Some(store.tenant()), None] {
+        let entries = store.chain(tenant).await?;
+        let mut previous = GENESIS;
+        for entry in &entries {
+            if entry.previous != previous || entry.record.hash(&previous) != entry.hash {
+                return Err(AuditError::Tampered(entry.id()));
+            }
+            previous = entry.hash;
+        }
+        count += entries.len();
+    }
+    Ok(count)
+}
@@ -0,0 +1,98 @@
+use std::collections::BTreeSet;
+
+use serde::Serialize;
+use thiserror::Error;
+
+use crate::{
+    layout,
+    menu::Station,
+    order::{self, RepoOrder, Status},
+    staff, NotFound,
+};
+
+#[derive(Error, Debug)]
+pub enum KitchenError {
+    #[error("An error occurred when interacting with the repository.")]
+    RepoOperation(#[from] anyhow::Error),
+    #[error("Unable to find order {0:?}")]
+    OrderNotFound(order::Id),
+    #[error("Order {order:?} is not prepared at station {station:?}")]
+    WrongStation { order: order::Id, station: Station },
+    #[error("Order {0:?} has already been bumped")]
+    AlreadyReady(order::Id),
+}
+pub type Result<T> = std::result::Result<T, KitchenError>;
+
+// NOTE: a station has no repository of its own. its tickets are just a view over the open order lines,
+// so we route off of the station each menu item declares.
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TableStatus {
+    pub table: layout::TableId,
+    pub ready: bool,
+    // stations that still have open lines for the table, so staff know who to chase
+    pub waiting_on: Vec<Station>,
+}
+
+pub async fn station_tickets<T: order::Repository>(
+    repo: &T,
+    station: Station,
+) -> Result<Vec<RepoOrder>> {
+    let mut tickets: Vec<RepoOrder> = repo
+        .get_all()
+        .await?
+        .into_iter()
+        .filter(|o| o.status == Status::Placed && o.menu_item.station == station)
+        .collect();
+
+    // fire order is first come, first served. ids break ties for orders placed in the same instant
+    tickets.sort_by_key(|o| (o.time_placed, o.id()));
+    Ok(tickets)
+}
+
+pub async fn bump<T: order::Repository + staff::Acting>(
+    repo: &mut T,
+    station: Station,
+    id: order::Id,
+) -> Result<RepoOrder> {
+    let mut order = repo.get(id).await.map_err(|e| match e.is::<NotFound>() {
+        true => KitchenError::OrderNotFound(id),
+        false => KitchenError::RepoOperation(e),
+    })?;
+
+    // stations bump independently, so one station can't mark another's work as done
+    if order.menu_item.station != station {
+        return Err(KitchenError::WrongStation { order: id, station });
+    }
+    if order.status == Status::Ready {
+        return Err(KitchenError::AlreadyReady(id));
+    }
+
+    order.status = Status::Ready;
+    order.changed_by = repo.actor();
+    repo.update(order)
+        .await
+        .map_err(KitchenError::RepoOperation)
+}
+
+pub async fn table_status<T: order::Repository>(
+    repo: &T,
+    table_id: layout::TableId,
+) -> Result<TableStatus> {
+    let orders = repo.get_table(table_id).await?;
+
+    let waiting_on: Vec<Station> = orders
+        .iter()
+        .filter(|o| o.status == Status::Placed)
+        .map(|o| o.menu_item.station)
+        .collect::<BTreeSet<Station>>()
+        .into_iter()
+        .collect();
+
+    // a table with nothing ordered has nothing to bring out, so it isn't considered ready
+    Ok(TableStatus {
+        table: table_id,
+        ready: !orders.is_empty() && waiting_on.is_empty(),
+        waiting_on,
+    })
+}
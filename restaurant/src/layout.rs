@@ -1,20 +1,44 @@
+use std::collections::HashSet;
 use std::future::Future;
 
 use serde::{Deserialize, Serialize};
 use thiserror::Error;
 
-use crate::RepoItem;
+use crate::{staff, tenant, RepoItem};
+
+pub mod assignment;
 
 #[derive(Error, Debug)]
 pub enum LayoutError {
     #[error("An error occurred when interacting with the repository.")]
     RepoOperation(#[from] anyhow::Error),
+    #[error("Unable to find table {0:?}")]
+    TableNotFound(TableId),
+    #[error("Unable to find table group {0:?}")]
+    GroupNotFound(TableGroupId),
+    #[error("A table group needs at least two different tables.")]
+    GroupTooSmall,
+    #[error("Table {0:?} is already part of group {1:?}.")]
+    AlreadyGrouped(TableId, TableGroupId),
+    #[error("Table {0:?} isn't in service.")]
+    TableInactive(TableId),
+    #[error("Table {0:?} isn't part of group {1:?}.")]
+    NotInGroup(TableId, TableGroupId),
+    #[error("A party needs at least one person.")]
+    EmptyParty,
 }
 pub type Result<T> = std::result::Result<T, LayoutError>;
 pub type RepoResult<T> = std::result::Result<T, anyhow::Error>;
 
 #[derive(Debug, Clone, Serialize)]
-pub struct Table {}
+pub struct Table {
+    // tables that aren't in service, such as a closed section, can't take orders
+    pub active: bool,
+    // how many people the table sits, which is what reservations are matched against
+    pub seats: u32,
+    // the part of the floor the table is in, which is how tables are divided between servers
+    pub section: Option<String>,
+}
 
 #[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash, PartialOrd, Ord)]
 pub struct TableId(pub u32);
@@ -30,13 +54,74 @@ impl From<TableId> for u32 {
 }
 pub type RepoTable = RepoItem<Table, TableId>;
 
-pub trait TableRepository {
+// tables pushed together for a large party. the first table is the lead, and every order for the party
+// is kept there, so the group's orders are just the lead's.
+#[derive(Debug, Clone, Serialize)]
+pub struct TableGroup {
+    pub tables: Vec<TableId>,
+    // who pushed the tables together, when it went through a signed-in staff member. groups aren't changed
+    // after that, only split up again
+    pub merged_by: Option<staff::Id>,
+}
+
+impl TableGroup {
+    pub fn lead(&self) -> TableId {
+        self.tables[0]
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct TableGroupId(pub u32);
+impl From<u32> for TableGroupId {
+    fn from(value: u32) -> Self {
+        TableGroupId(value)
+    }
+}
+impl From<TableGroupId> for u32 {
+    fn from(value: TableGroupId) -> Self {
+        value.0
+    }
+}
+pub type RepoTableGroup = RepoItem<TableGroup, TableGroupId>;
+
+// scoped to one restaurant, see the tenant module
+pub trait TableRepository: tenant::Scoped {
     fn get_all(&self) -> impl Future<Output = RepoResult<Vec<RepoTable>>> + Send;
     fn get(&self, id: TableId) -> impl Future<Output = RepoResult<RepoTable>> + Send;
 
     fn create(&mut self, item: Table) -> impl Future<Output = RepoResult<RepoTable>> + Send;
     fn remove(&mut self, id: TableId) -> impl Future<Output = RepoResult<()>> + Send;
     fn update(&mut self, item: RepoTable) -> impl Future<Output = RepoResult<()>> + Send;
+
+    fn table_groups(&self) -> impl Future<Output = RepoResult<Vec<RepoTableGroup>>> + Send;
+    fn table_group(
+        &self,
+        id: TableGroupId,
+    ) -> impl Future<Output = RepoResult<RepoTableGroup>> + Send;
+}
+
+// NOTE: the order module depends on this one for tables, so what layout needs of orders comes through the
+// `Seating` port rather than from calling into it: which tables are taken, and moving a party's orders when
+// its tables are grouped or split. the repository keeping the orders implements it, and each regrouping is a
+// single call so that it's made whole or not at all.
+pub trait Seating: TableRepository {
+    // whatever the orders are kept as
+    type Order;
+
+    // the tables with open orders at them
+    fn occupied(&self) -> impl Future<Output = RepoResult<HashSet<TableId>>> + Send;
+    // groups the tables and moves the orders already at the others to the first.
+    // fails if any of them is already in a group, so a table is never in two
+    fn merge_tables(
+        &mut self,
+        group: TableGroup,
+    ) -> impl Future<Output = RepoResult<RepoTableGroup>> + Send;
+    // ungroups the tables and moves the party's orders from the lead to `to`, returning them
+    fn split_tables(
+        &mut self,
+        id: TableGroupId,
+        to: RepoTable,
+    ) -> impl Future<Output = RepoResult<Vec<Self::Order>>> + Send;
 }
 
 // see menu module for design notes. not fully implementing because not necessary for this project.
@@ -48,3 +133,92 @@ pub async fn get_tables<T: TableRepository>(repo: &T) -> Result<Vec<RepoTable>>
 pub async fn get<T: TableRepository>(repo: &T, id: TableId) -> Result<RepoTable> {
     repo.get(id).await.map_err(LayoutError::RepoOperation)
 }
+
+pub async fn get_groups<T: TableRepository>(repo: &T) -> Result<Vec<RepoTableGroup>> {
+    repo.table_groups()
+        .await
+        .map_err(LayoutError::RepoOperation)
+}
+
+pub async fn get_group<T: TableRepository>(repo: &T, id: TableGroupId) -> Result<RepoTableGroup> {
+    repo.table_group(id)
+        .await
+        .map_err(|_| LayoutError::GroupNotFound(id))
+}
+
+pub async fn group_of<T: TableRepository>(repo: &T, id: TableId) -> Result<Option<RepoTableGroup>> {
+    Ok(get_groups(repo)
+        .await?
+        .into_iter()
+        .find(|g| g.tables.contains(&id)))
+}
+
+// the table a table's orders are kept at: its group's lead while it's merged, otherwise itself.
+// placing an order, or asking after a table's orders, goes through this so that any table of a group
+// stands for the whole party.
+pub async fn serving_table<T: TableRepository>(repo: &T, id: TableId) -> Result<RepoTable> {
+    let lead = match group_of(repo, id).await? {
+        Some(group) => group.lead(),
+        None => id,
+    };
+    repo.get(lead)
+        .await
+        .map_err(|_| LayoutError::TableNotFound(lead))
+}
+
+// the first table leads the group, and the orders already at the others move to it
+pub async fn merge<T: Seating + staff::Acting>(
+    repo: &mut T,
+    tables: &[TableId],
+) -> Result<RepoTableGroup> {
+    let mut distinct = tables.to_vec();
+    distinct.sort();
+    distinct.dedup();
+    if distinct.len() < 2 || distinct.len() != tables.len() {
+        return Err(LayoutError::GroupTooSmall);
+    }
+
+    for id in tables {
+        let table = TableRepository::get(repo, *id)
+            .await
+            .map_err(|_| LayoutError::TableNotFound(*id))?;
+        if !table.active {
+            return Err(LayoutError::TableInactive(*id));
+        }
+        if let Some(group) = group_of(repo, *id).await? {
+            return Err(LayoutError::AlreadyGrouped(*id, group.id()));
+        }
+    }
+
+    let merged_by = repo.actor();
+    repo.merge_tables(TableGroup {
+        tables: tables.to_vec(),
+        merged_by,
+    })
+    .await
+    .map_err(LayoutError::RepoOperation)
+}
+
+// the group's open orders go with whichever of its tables the party stays at.
+// everything is checked before the group is touched, so a split that can't be made leaves it as it was
+pub async fn split<T: Seating>(
+    repo: &mut T,
+    id: TableGroupId,
+    to: TableId,
+) -> Result<Vec<T::Order>> {
+    let group = get_group(repo, id).await?;
+    if !group.tables.contains(&to) {
+        return Err(LayoutError::NotInGroup(to, id));
+    }
+    let table = TableRepository::get(repo, to)
+        .await
+        .map_err(|_| LayoutError::TableNotFound(to))?;
+    // staying at the lead moves nothing, but the orders can't be moved to a table that's out of service
+    if to != group.lead() && !table.active {
+        return Err(LayoutError::TableInactive(to));
+    }
+
+    repo.split_tables(id, table)
+        .await
+        .map_err(LayoutError::RepoOperation)
+}
@@ -0,0 +1,257 @@
+use std::collections::HashMap;
+use std::future::Future;
+
+use chrono::{DateTime, TimeDelta, Utc};
+
+use super::{LayoutError, RepoResult, RepoTable, Result, Seating, TableRepository};
+use crate::{reservation, staff};
+
+// NOTE: suggesting a table is split in two. `floor` takes a snapshot of everything that bears on it,
+// and `rank` runs strategies over the snapshot. each strategy looks at one concern, such as fit or sections,
+// and either rules a table out or adds a penalty with its reason. the tables nothing ruled out are ranked by
+// their total penalty, so a new concern is a new strategy rather than a change to the ranking.
+
+// the party a table is wanted for
+#[derive(Debug, Clone)]
+pub struct Party {
+    pub size: u32,
+    // only tables in this section will do, such as when the party's asked for the patio
+    pub section: Option<String>,
+    // how long they're expected to stay, which is how far ahead reservations are checked
+    pub stay: TimeDelta,
+}
+
+impl Party {
+    pub fn new(size: u32) -> Party {
+        Party {
+            size,
+            section: None,
+            stay: TimeDelta::minutes(90),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct TableState {
+    pub table: RepoTable,
+    // someone's already sitting there, going by the table's orders
+    pub occupied: bool,
+    // pushed together with other tables for a party
+    pub grouped: bool,
+    // the first reservation for the table while the party would be there
+    pub reserved: Option<reservation::RepoReservation>,
+    // whoever has the table's section right now, if anyone
+    pub server: Option<staff::RepoStaff>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Floor {
+    pub tables: Vec<TableState>,
+    pub now: DateTime<Utc>,
+}
+
+impl Floor {
+    // how many taken tables the table's server already has, which is how busy they are. for a table nobody's
+    // serving it's how many its section has, which is as near as it gets
+    pub fn load(&self, state: &TableState) -> usize {
+        self.tables
+            .iter()
+            .filter(|t| t.occupied)
+            .filter(|t| match &state.server {
+                Some(server) => t.server.as_ref().is_some_and(|s| s.id() == server.id()),
+                None => t.table.section == state.table.section,
+            })
+            .count()
+    }
+}
+
+// what suggesting tables needs of shifts: who has which section. it's a port for the same reason `Seating` is,
+// the shift module depends on this one for its sections
+pub trait Staffing {
+    // the server each section's handed to at the time, for the sections that have one
+    fn servers(
+        &self,
+        at: DateTime<Utc>,
+    ) -> impl Future<Output = RepoResult<HashMap<String, staff::RepoStaff>>> + Send;
+}
+
+// a table that's ruled out is simply left out of the suggestions, so there's nothing to say about why
+pub enum Verdict {
+    Unsuitable,
+    // lower penalties rank higher. the reason, if any, says what the penalty is for
+    Suitable {
+        penalty: u32,
+        reason: Option<String>,
+    },
+}
+
+pub trait Strategy: Send + Sync {
+    fn assess(&self, party: &Party, table: &TableState, floor: &Floor) -> Verdict;
+}
+
+#[derive(Debug, Clone)]
+pub struct Suggestion {
+    pub table: RepoTable,
+    pub penalty: u32,
+    pub reasons: Vec<String>,
+}
+
+// the smallest free table that sits the party
+pub struct Fit;
+
+impl Strategy for Fit {
+    fn assess(&self, party: &Party, state: &TableState, _: &Floor) -> Verdict {
+        let table = &state.table;
+        // out of service, pushed together with others, taken, or too small
+        if !table.active || state.grouped || state.occupied || table.seats < party.size {
+            return Verdict::Unsuitable;
+        }
+
+        let spare = table.seats - party.size;
+        Verdict::Suitable {
+            penalty: spare * 10,
+            reason: Some(match spare {
+                0 => format!("Sits {}, an exact fit.", table.seats),
+                _ => format!("Sits {}, leaving {} spare.", table.seats, spare),
+            }),
+        }
+    }
+}
+
+pub struct Sections;
+
+impl Strategy for Sections {
+    fn assess(&self, party: &Party, state: &TableState, _: &Floor) -> Verdict {
+        match (&party.section, &state.table.section) {
+            (None, _) => Verdict::Suitable {
+                penalty: 0,
+                reason: None,
+            },
+            (Some(wanted), Some(section)) if wanted == section => Verdict::Suitable {
+                penalty: 0,
+                reason: Some(format!("In the {} section, as asked.", section)),
+            },
+            (Some(_), _) => Verdict::Unsuitable,
+        }
+    }
+}
+
+// a table that's booked before the party would be done isn't free for them
+pub struct Reservations;
+
+impl Strategy for Reservations {
+    fn assess(&self, _: &Party, state: &TableState, _: &Floor) -> Verdict {
+        match &state.reserved {
+            Some(_) => Verdict::Unsuitable,
+            None => Verdict::Suitable {
+                penalty: 0,
+                reason: None,
+            },
+        }
+    }
+}
+
+// spreads parties across servers, so one isn't run off their feet while another stands around
+pub struct Balance;
+
+impl Strategy for Balance {
+    fn assess(&self, _: &Party, state: &TableState, floor: &Floor) -> Verdict {
+        let load = floor.load(state) as u32;
+        let reason = match (&state.server, &state.table.section) {
+            _ if load == 0 => None,
+            (Some(server), _) => Some(format!("{} already has {} occupied.", server.name, load)),
+            (None, Some(section)) => Some(format!(
+                "The {} section already has {} occupied.",
+                section, load
+            )),
+            (None, None) => None,
+        };
+        Verdict::Suitable {
+            penalty: load * 3,
+            reason,
+        }
+    }
+}
+
+pub fn default_strategies() -> Vec<Box<dyn Strategy>> {
+    vec![
+        Box::new(Fit),
+        Box::new(Sections),
+        Box::new(Reservations),
+        Box::new(Balance),
+    ]
+}
+
+pub async fn floor<T: Seating + Staffing + reservation::Repository>(
+    repo: &T,
+    stay: TimeDelta,
+) -> Result<Floor> {
+    let now = Utc::now();
+
+    let occupied = repo.occupied().await?;
+    let mut grouped = Vec::new();
+    for group in repo.table_groups().await? {
+        grouped.extend(group.tables.iter().copied());
+    }
+    let bookings = reservation::Repository::overlapping(
+        repo,
+        reservation::Window {
+            starts: now,
+            ends: now + stay,
+        },
+    )
+    .await?;
+    let servers = repo.servers(now).await?;
+
+    let tables = TableRepository::get_all(repo)
+        .await?
+        .into_iter()
+        .map(|table| TableState {
+            occupied: occupied.contains(&table.id()),
+            grouped: grouped.contains(&table.id()),
+            reserved: bookings.iter().find(|b| b.table == table.id()).cloned(),
+            server: table.section.as_ref().and_then(|s| servers.get(s)).cloned(),
+            table,
+        })
+        .collect();
+    Ok(Floor { tables, now })
+}
+
+// the tables no strategy ruled out, best first. ties go to the lower table id
+pub fn rank(party: &Party, floor: &Floor, strategies: &[Box<dyn Strategy>]) -> Vec<Suggestion> {
+    let mut suggestions: Vec<Suggestion> = floor
+        .tables
+        .iter()
+        .filter_map(|state| {
+            let mut suggestion = Suggestion {
+                table: state.table.clone(),
+                penalty: 0,
+                reasons: Vec::new(),
+            };
+            for strategy in strategies {
+                match strategy.assess(party, state, floor) {
+                    Verdict::Unsuitable => return None,
+                    Verdict::Suitable { penalty, reason } => {
+                        suggestion.penalty += penalty;
+                        suggestion.reasons.extend(reason);
+                    }
+                }
+            }
+            Some(suggestion)
+        })
+        .collect();
+    suggestions.sort_by_key(|s| (s.penalty, s.table.id()));
+    suggestions
+}
+
+pub async fn suggest<T: Seating + Staffing + reservation::Repository>(
+    repo: &T,
+    party: &Party,
+    strategies: &[Box<dyn Strategy>],
+) -> Result<Vec<Suggestion>> {
+    if party.size == 0 {
+        return Err(LayoutError::EmptyParty);
+    }
+    let snapshot = floor(repo, party.stay).await?;
+    Ok(rank(party, &snapshot, strategies))
+}
@@ -1,27 +1,63 @@
 use std::ops::{Deref, DerefMut};
 
 use serde::Serialize;
+use thiserror::Error;
 
+pub mod api_key;
+pub mod audit;
+pub mod kitchen;
 pub mod layout;
 pub mod memdb;
 pub mod menu;
 pub mod order;
+pub mod outbox;
+pub mod reservation;
+pub mod shift;
+pub mod staff;
+pub mod tenant;
+pub mod waitlist;
 
 #[derive(Clone, Serialize)]
 pub struct RepoItem<T, I: Copy + Clone + Serialize> {
     id: I,
 
+    // bumped on every update, so writers can tell whether they're about to overwrite someone else's change
+    #[serde(skip)]
+    version: u32,
+
     #[serde(flatten)]
     item: T,
 }
 
+// raised by repositories when an update was based on an older version than the one stored
+#[derive(Error, Debug)]
+#[error("Item was changed since version {expected}, it's now at version {actual}.")]
+pub struct VersionConflict {
+    pub expected: u32,
+    pub actual: u32,
+}
+
+// raised by repositories when nothing is stored under the id asked for, so the domain can tell a
+// missing item apart from the repository itself failing
+#[derive(Error, Debug)]
+#[error("Unable to find item '{id}'.")]
+pub struct NotFound {
+    pub id: String,
+}
+
 impl<T, I: Copy + Clone + Serialize> RepoItem<T, I> {
     pub fn new(id: I, item: T) -> RepoItem<T, I> {
-        RepoItem { id, item }
+        RepoItem::with_version(id, 1, item)
+    }
+    pub fn with_version(id: I, version: u32, item: T) -> RepoItem<T, I> {
+        RepoItem { id, version, item }
     }
     pub fn id(&self) -> I {
         self.id
     }
+    pub fn version(&self) -> u32 {
+        self.version
+    }
     pub fn item(&self) -> &T {
         &self.item
     }
@@ -1,13 +1,17 @@
 use std::{
     clone::Clone,
-    collections::HashSet,
+    collections::{BTreeMap, BTreeSet, HashMap, HashSet, VecDeque},
     hash::Hash,
     result::Result,
     sync::atomic::AtomicU32,
     sync::{Arc, RwLock},
 };
 
-use crate::{layout, menu, order, RepoItem};
+use crate::{
+    api_key, audit, layout, menu, order, reservation, shift, staff, tenant, waitlist, NotFound,
+    RepoItem, VersionConflict,
+};
+use chrono::{DateTime, Utc};
 use serde::Serialize;
 use thiserror::Error;
 
@@ -15,6 +19,19 @@ use thiserror::Error;
 pub enum Error<I> {
     #[error("Unable to find item '{id:?}'.")]
     ItemNotFound { id: I },
+    #[error(transparent)]
+    Conflict(#[from] VersionConflict),
+}
+
+// conflicts and missing items are surfaced as the crate's own errors so the domain can recognise them,
+// whichever repository they came from
+fn into_anyhow<I: std::fmt::Debug + Send + Sync + 'static>(e: Error<I>) -> anyhow::Error {
+    match e {
+        Error::ItemNotFound { id } => anyhow::anyhow!(NotFound {
+            id: format!("{:?}", id)
+        }),
+        Error::Conflict(conflict) => anyhow::anyhow!(conflict),
+    }
 }
 
 pub struct InMemoryRepository<T: Clone, I: Copy + Serialize> {
@@ -66,15 +83,20 @@ impl<T: Clone, I: Clone + Copy + PartialEq + Eq + Hash + Serialize> InMemoryRepo
 
     pub fn update(&mut self, item: RepoItem<T, I>) -> Result<RepoItem<T, I>, Error<I>> {
         let id = item.id();
-        self.items
+        let stored = self
+            .items
             .iter_mut()
-            .find(|o| o.id() == item.id())
-            .as_mut()
-            // also, we do a copy here because either this map or the next will do a move,
-            // and we can't do it in the next one because the first move will prevent us from copying
-            .map(|o| **o = item.clone())
-            .and(Some(item.clone()))
-            .ok_or(Error::ItemNotFound { id })
+            .find(|o| o.id() == id)
+            .ok_or(Error::ItemNotFound { id })?;
+        if stored.version != item.version {
+            return Err(VersionConflict {
+                expected: item.version,
+                actual: stored.version,
+            }
+            .into());
+        }
+        *stored = RepoItem::with_version(id, item.version + 1, item.item);
+        Ok(stored.clone())
     }
 }
 
@@ -105,6 +127,15 @@ impl IdGenerator<menu::Id> for IdGeneratorImpl {
     }
 }
 
+impl IdGenerator<menu::CategoryId> for IdGeneratorImpl {
+    fn get(&self) -> menu::CategoryId {
+        menu::CategoryId(
+            self.counter
+                .fetch_add(1, std::sync::atomic::Ordering::Relaxed),
+        )
+    }
+}
+
 impl IdGenerator<order::Id> for IdGeneratorImpl {
     fn get(&self) -> order::Id {
         order::Id(
@@ -114,6 +145,78 @@ impl IdGenerator<order::Id> for IdGeneratorImpl {
     }
 }
 
+impl IdGenerator<layout::TableGroupId> for IdGeneratorImpl {
+    fn get(&self) -> layout::TableGroupId {
+        layout::TableGroupId(
+            self.counter
+                .fetch_add(1, std::sync::atomic::Ordering::Relaxed),
+        )
+    }
+}
+
+impl IdGenerator<reservation::Id> for IdGeneratorImpl {
+    fn get(&self) -> reservation::Id {
+        reservation::Id(
+            self.counter
+                .fetch_add(1, std::sync::atomic::Ordering::Relaxed),
+        )
+    }
+}
+
+impl IdGenerator<waitlist::Id> for IdGeneratorImpl {
+    fn get(&self) -> waitlist::Id {
+        waitlist::Id(
+            self.counter
+                .fetch_add(1, std::sync::atomic::Ordering::Relaxed),
+        )
+    }
+}
+
+impl IdGenerator<staff::Id> for IdGeneratorImpl {
+    fn get(&self) -> staff::Id {
+        staff::Id(
+            self.counter
+                .fetch_add(1, std::sync::atomic::Ordering::Relaxed),
+        )
+    }
+}
+
+impl IdGenerator<api_key::Id> for IdGeneratorImpl {
+    fn get(&self) -> api_key::Id {
+        api_key::Id(
+            self.counter
+                .fetch_add(1, std::sync::atomic::Ordering::Relaxed),
+        )
+    }
+}
+
+impl IdGenerator<shift::Id> for IdGeneratorImpl {
+    fn get(&self) -> shift::Id {
+        shift::Id(
+            self.counter
+                .fetch_add(1, std::sync::atomic::Ordering::Relaxed),
+        )
+    }
+}
+
+impl IdGenerator<audit::Id> for IdGeneratorImpl {
+    fn get(&self) -> audit::Id {
+        audit::Id(
+            self.counter
+                .fetch_add(1, std::sync::atomic::Ordering::Relaxed),
+        )
+    }
+}
+
+impl IdGenerator<order::GroupId> for IdGeneratorImpl {
+    fn get(&self) -> order::GroupId {
+        order::GroupId(
+            self.counter
+                .fetch_add(1, std::sync::atomic::Ordering::Relaxed),
+        )
+    }
+}
+
 impl Default for IdGeneratorImpl {
     fn default() -> Self {
         Self {
@@ -150,6 +253,16 @@ impl<T: Clone> Default for InMemoryRepository<T, menu::Id> {
     }
 }
 
+impl<T: Clone> Default for InMemoryRepository<T, menu::CategoryId> {
+    fn default() -> Self {
+        InMemoryRepository {
+            idgen: Box::new(IdGeneratorImpl::default()),
+            items: Vec::new(),
+            ids: HashSet::new(),
+        }
+    }
+}
+
 impl<T: Clone> Default for InMemoryRepository<T, layout::TableId> {
     fn default() -> Self {
         InMemoryRepository {
@@ -160,20 +273,161 @@ impl<T: Clone> Default for InMemoryRepository<T, layout::TableId> {
     }
 }
 
+impl<T: Clone> Default for InMemoryRepository<T, layout::TableGroupId> {
+    fn default() -> Self {
+        InMemoryRepository {
+            idgen: Box::new(IdGeneratorImpl::default()),
+            items: Vec::new(),
+            ids: HashSet::new(),
+        }
+    }
+}
+
+impl<T: Clone> Default for InMemoryRepository<T, reservation::Id> {
+    fn default() -> Self {
+        InMemoryRepository {
+            idgen: Box::new(IdGeneratorImpl::default()),
+            items: Vec::new(),
+            ids: HashSet::new(),
+        }
+    }
+}
+
+impl<T: Clone> Default for InMemoryRepository<T, waitlist::Id> {
+    fn default() -> Self {
+        InMemoryRepository {
+            idgen: Box::new(IdGeneratorImpl::default()),
+            items: Vec::new(),
+            ids: HashSet::new(),
+        }
+    }
+}
+
+impl<T: Clone> Default for InMemoryRepository<T, staff::Id> {
+    fn default() -> Self {
+        InMemoryRepository {
+            idgen: Box::new(IdGeneratorImpl::default()),
+            items: Vec::new(),
+            ids: HashSet::new(),
+        }
+    }
+}
+
+impl<T: Clone> Default for InMemoryRepository<T, api_key::Id> {
+    fn default() -> Self {
+        InMemoryRepository {
+            idgen: Box::new(IdGeneratorImpl::default()),
+            items: Vec::new(),
+            ids: HashSet::new(),
+        }
+    }
+}
+
+impl<T: Clone> Default for InMemoryRepository<T, shift::Id> {
+    fn default() -> Self {
+        InMemoryRepository {
+            idgen: Box::new(IdGeneratorImpl::default()),
+            items: Vec::new(),
+            ids: HashSet::new(),
+        }
+    }
+}
+
+impl<T: Clone> Default for InMemoryRepository<T, audit::Id> {
+    fn default() -> Self {
+        InMemoryRepository {
+            idgen: Box::new(IdGeneratorImpl::default()),
+            items: Vec::new(),
+            ids: HashSet::new(),
+        }
+    }
+}
+
 type Table<T, I> = Arc<RwLock<InMemoryRepository<T, I>>>;
+
+// how many order events a branch keeps by default. they're there for subscribers catching up after a reconnect
+// rather than as a history, the audit log is for that
+pub const EVENT_LOG_CAPACITY: usize = 10_000;
+
+// a branch's order events, oldest first. ids only ever go up, so finding where a subscriber left off is a
+// binary search. once over capacity the oldest are dropped, but never before the relay has dispatched them
+struct EventLog {
+    events: VecDeque<order::RepoEvent>,
+    next: u32,
+    capacity: usize,
+}
+
+impl Default for EventLog {
+    fn default() -> Self {
+        EventLog {
+            events: VecDeque::new(),
+            next: 1,
+            capacity: EVENT_LOG_CAPACITY,
+        }
+    }
+}
+
+impl EventLog {
+    fn append(
+        &mut self,
+        event: order::Event,
+        outbox: &BTreeSet<order::EventId>,
+    ) -> order::RepoEvent {
+        let event = RepoItem::new(order::EventId(self.next), event);
+        self.next += 1;
+        self.events.push_back(event.clone());
+
+        while self.events.len() > self.capacity
+            && self
+                .events
+                .front()
+                .is_some_and(|e| !outbox.contains(&e.id()))
+        {
+            self.events.pop_front();
+        }
+        event
+    }
+
+    fn after(&self, after: Option<order::EventId>) -> Vec<order::RepoEvent> {
+        let start = after.map_or(0, |after| self.events.partition_point(|e| e.id() <= after));
+        self.events.range(start..).cloned().collect()
+    }
+
+    fn get(&self, id: order::EventId) -> Option<&order::RepoEvent> {
+        self.events
+            .binary_search_by_key(&id, |e| e.id())
+            .ok()
+            .map(|index| &self.events[index])
+    }
+}
+
+// one restaurant's data. every branch has its own ids, starting from 1
 #[derive(Clone, Default)]
-pub struct Database {
+struct Branch {
     menu: Table<menu::Item, menu::Id>,
+    categories: Table<menu::Category, menu::CategoryId>,
     tables: Table<layout::Table, layout::TableId>,
+    table_groups: Table<layout::TableGroup, layout::TableGroupId>,
+    reservations: Table<reservation::Reservation, reservation::Id>,
+    waitlist: Table<waitlist::Party, waitlist::Id>,
+    sections: Table<shift::Assignment, shift::Id>,
     orders: Table<order::Order, order::Id>,
+    // order events are only ever appended while holding the orders lock,
+    // so that an event exists if and only if its mutation does
+    order_events: Arc<RwLock<EventLog>>,
+    // ids of recorded events the relay has yet to dispatch
+    outbox: Arc<RwLock<BTreeSet<order::EventId>>>,
+    // parties notified of a table that the relay has yet to announce. only added to while holding the waitlist lock
+    notices: Arc<RwLock<BTreeSet<waitlist::Id>>>,
+    group_ids: Arc<IdGeneratorImpl>,
 }
 
-impl Database {
-    pub fn new(
+impl Branch {
+    fn new(
         menu: Vec<menu::RepoItem>,
         tables: Vec<layout::RepoTable>,
         orders: Vec<order::RepoOrder>,
-    ) -> Database {
+    ) -> Branch {
         fn start_id<T, I: Copy + Serialize + PartialOrd + Ord + From<u32> + Into<u32>>(
             items: &[RepoItem<T, I>],
         ) -> I {
@@ -184,9 +438,15 @@ impl Database {
                 .unwrap_or(1.into())
         }
 
+        let next_group = orders
+            .iter()
+            .filter_map(|o| o.group)
+            .max()
+            .map_or(1, |g| g.0 + 1);
+
         // couldn't find a good way to do these generically
         // mainly because IdGeneratorImpl only implements its trait for types
-        Database {
+        Branch {
             menu: Arc::new(RwLock::new(InMemoryRepository {
                 ids: menu.iter().map(|i| i.id()).collect(),
                 idgen: Box::new(IdGeneratorImpl::new(start_id(&menu).into())),
@@ -202,21 +462,159 @@ impl Database {
                 idgen: Box::new(IdGeneratorImpl::new(start_id(&orders).into())),
                 items: orders,
             })),
+            group_ids: Arc::new(IdGeneratorImpl::new(next_group)),
+            ..Default::default()
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct Database {
+    // every restaurant's data, shared by all handles
+    branches: Arc<RwLock<BTreeMap<tenant::Id, Branch>>>,
+    // the restaurant this handle works with, and its data
+    tenant: tenant::Id,
+    branch: Branch,
+    // staff and api keys are shared between restaurants
+    staff: Table<staff::Staff, staff::Id>,
+    // when each staff member last tried to sign in, since they last got it right
+    logins: Arc<RwLock<HashMap<staff::Id, Vec<DateTime<Utc>>>>>,
+    api_keys: Table<api_key::ApiKey, api_key::Id>,
+    // every change made to the others, appended while holding the lock of whatever changed, and the latest
+    // hash of each restaurant's chain and the shared one, only written while holding the log's lock
+    audit: Table<audit::Entry, audit::Id>,
+    audit_heads: Arc<RwLock<HashMap<Option<tenant::Id>, [u8; 32]>>>,
+    // who changes made through this handle are made by, and for which request.
+    // clones share the data but not these
+    actor: Option<staff::Id>,
+    key: Option<api_key::Id>,
+    request: Option<String>,
+}
+
+impl Default for Database {
+    fn default() -> Self {
+        Database::new(vec![], vec![], vec![])
+    }
+}
+
+impl Database {
+    // a database with the one, default, restaurant
+    pub fn new(
+        menu: Vec<menu::RepoItem>,
+        tables: Vec<layout::RepoTable>,
+        orders: Vec<order::RepoOrder>,
+    ) -> Database {
+        let branch = Branch::new(menu, tables, orders);
+        Database {
+            branches: Arc::new(RwLock::new(BTreeMap::from([(
+                tenant::DEFAULT,
+                branch.clone(),
+            )]))),
+            tenant: tenant::DEFAULT,
+            branch,
+            staff: Default::default(),
+            logins: Default::default(),
+            api_keys: Default::default(),
+            audit: Default::default(),
+            audit_heads: Default::default(),
+            actor: None,
+            key: None,
+            request: None,
+        }
+    }
+
+    // adds another restaurant, returning a handle to it. fails if there's already one with the id
+    pub fn open(
+        &self,
+        id: tenant::Id,
+        menu: Vec<menu::RepoItem>,
+        tables: Vec<layout::RepoTable>,
+        orders: Vec<order::RepoOrder>,
+    ) -> anyhow::Result<Database> {
+        let mut branches = self.branches.write().unwrap();
+        if branches.contains_key(&id) {
+            return Err(anyhow::anyhow!("Restaurant {:?} already exists.", id));
+        }
+        let branch = Branch::new(menu, tables, orders);
+        branches.insert(id, branch.clone());
+
+        Ok(Database {
+            tenant: id,
+            branch,
+            ..self.clone()
+        })
+    }
+
+    // the same people and context, working with another restaurant's data, if there is such a restaurant
+    pub fn for_tenant(&self, id: tenant::Id) -> Option<Database> {
+        let branch = self.branches.read().unwrap().get(&id)?.clone();
+        Some(Database {
+            tenant: id,
+            branch,
+            ..self.clone()
+        })
+    }
+
+    // how many order events this handle's restaurant keeps for subscribers to catch up from
+    pub fn keep_events(&self, capacity: usize) {
+        self.branch.order_events.write().unwrap().capacity = capacity;
+    }
+
+    // the same data, with changes made through the returned handle put down to the staff member
+    pub fn acting_as(&self, id: staff::Id) -> Database {
+        Database {
+            actor: Some(id),
+            ..self.clone()
+        }
+    }
+
+    // the same data, with changes made through the returned handle put down to the api key
+    pub fn using_key(&self, id: api_key::Id) -> Database {
+        Database {
+            key: Some(id),
+            ..self.clone()
+        }
+    }
+
+    // the same data, with changes made through the returned handle recorded against the request
+    pub fn for_request(&self, id: &str) -> Database {
+        Database {
+            request: Some(id.to_string()),
+            ..self.clone()
         }
     }
 }
 
+impl tenant::Scoped for Database {
+    fn tenant(&self) -> tenant::Id {
+        self.tenant
+    }
+}
+
+impl tenant::Repository for Database {
+    async fn get_all(&self) -> tenant::RepoResult<Vec<tenant::Id>> {
+        Ok(self.branches.read().unwrap().keys().copied().collect())
+    }
+}
+
+impl staff::Acting for Database {
+    fn actor(&self) -> Option<staff::Id> {
+        self.actor
+    }
+}
+
 impl menu::Repository for Database {
     async fn get_all(&self) -> menu::RepoResult<Vec<menu::RepoItem>> {
-        Ok(self.menu.read().unwrap().items().clone())
+        Ok(self.branch.menu.read().unwrap().items().clone())
     }
 
     async fn get(&self, id: menu::Id) -> menu::RepoResult<menu::RepoItem> {
-        self.menu
+        self.branch
+            .menu
             .read()
             .unwrap()
             .get(id)
-            .map_err(|e| anyhow::anyhow!(e))
+            .map_err(into_anyhow)
     }
 
     async fn create(&mut self, _id: menu::Item) -> menu::RepoResult<menu::RepoItem> {
@@ -227,22 +625,52 @@ impl menu::Repository for Database {
         unimplemented!()
     }
 
-    async fn update(&mut self, _item: menu::RepoItem) -> menu::RepoResult<()> {
-        unimplemented!()
+    async fn update(&mut self, item: menu::RepoItem) -> menu::RepoResult<()> {
+        let mut menu = self.branch.menu.write().unwrap();
+        let before = menu.get(item.id()).ok();
+        let updated = menu.update(item).map_err(into_anyhow)?;
+
+        self.audit("menu.update", before.as_ref(), Some(&updated));
+        Ok(())
+    }
+
+    async fn categories(&self) -> menu::RepoResult<Vec<menu::RepoCategory>> {
+        Ok(self.branch.categories.read().unwrap().items().clone())
+    }
+
+    async fn category(&self, id: menu::CategoryId) -> menu::RepoResult<menu::RepoCategory> {
+        self.branch
+            .categories
+            .read()
+            .unwrap()
+            .get(id)
+            .map_err(into_anyhow)
+    }
+
+    async fn create_category(
+        &mut self,
+        category: menu::Category,
+    ) -> menu::RepoResult<menu::RepoCategory> {
+        let mut categories = self.branch.categories.write().unwrap();
+        let created = categories.create(category).map_err(into_anyhow)?;
+
+        self.audit("menu_category.create", None, Some(&created));
+        Ok(created)
     }
 }
 
 impl layout::TableRepository for Database {
     async fn get_all(&self) -> layout::RepoResult<Vec<layout::RepoTable>> {
-        Ok(self.tables.read().unwrap().items().clone())
+        Ok(self.branch.tables.read().unwrap().items().clone())
     }
 
     async fn get(&self, id: layout::TableId) -> menu::RepoResult<layout::RepoTable> {
-        self.tables
+        self.branch
+            .tables
             .read()
             .unwrap()
             .get(id)
-            .map_err(|e| anyhow::anyhow!(e))
+            .map_err(into_anyhow)
     }
 
     async fn create(&mut self, _id: layout::Table) -> layout::RepoResult<layout::RepoTable> {
@@ -256,85 +684,739 @@ impl layout::TableRepository for Database {
     async fn update(&mut self, _item: layout::RepoTable) -> layout::RepoResult<()> {
         unimplemented!()
     }
+
+    async fn table_groups(&self) -> layout::RepoResult<Vec<layout::RepoTableGroup>> {
+        Ok(self.branch.table_groups.read().unwrap().items().clone())
+    }
+
+    async fn table_group(
+        &self,
+        id: layout::TableGroupId,
+    ) -> layout::RepoResult<layout::RepoTableGroup> {
+        self.branch
+            .table_groups
+            .read()
+            .unwrap()
+            .get(id)
+            .map_err(into_anyhow)
+    }
+}
+
+impl layout::Seating for Database {
+    type Order = order::RepoOrder;
+
+    async fn occupied(&self) -> layout::RepoResult<HashSet<layout::TableId>> {
+        Ok(self
+            .branch
+            .orders
+            .read()
+            .unwrap()
+            .items()
+            .iter()
+            .map(|o| o.table.id())
+            .collect())
+    }
+
+    async fn merge_tables(
+        &mut self,
+        group: layout::TableGroup,
+    ) -> layout::RepoResult<layout::RepoTableGroup> {
+        // checked under the same lock as the create, so two merges can't both claim a table. the orders lock is
+        // taken after it and held as well, so that grouping and moving the orders are one change
+        let mut groups = self.branch.table_groups.write().unwrap();
+        if let Some(taken) = group
+            .tables
+            .iter()
+            .find(|t| groups.items().iter().any(|g| g.tables.contains(t)))
+        {
+            return Err(anyhow::anyhow!("Table {:?} is already grouped.", taken));
+        }
+        let lead = self
+            .branch
+            .tables
+            .read()
+            .unwrap()
+            .get(group.lead())
+            .map_err(into_anyhow)?;
+        let mut orders = self.branch.orders.write().unwrap();
+
+        // every order is moved, or none are, before there's a group to record. creating it can't fail once the
+        // tables have been checked above
+        let moves = group.tables[1..]
+            .iter()
+            .flat_map(|from| Database::orders_at(&orders, *from))
+            .collect();
+        self.move_orders(&mut orders, moves, &lead)?;
+        let created = groups.create(group).map_err(into_anyhow)?;
+        self.audit("table_group.create", None, Some(&created));
+        Ok(created)
+    }
+
+    async fn split_tables(
+        &mut self,
+        id: layout::TableGroupId,
+        to: layout::RepoTable,
+    ) -> layout::RepoResult<Vec<order::RepoOrder>> {
+        // locked in the same order as merging
+        let mut groups = self.branch.table_groups.write().unwrap();
+        let group = groups.get(id).map_err(into_anyhow)?;
+        if !group.tables.contains(&to.id()) {
+            return Err(anyhow::anyhow!(
+                "Table {:?} isn't in group {:?}.",
+                to.id(),
+                id
+            ));
+        }
+        let mut orders = self.branch.orders.write().unwrap();
+
+        let removed = groups.remove(id).map_err(into_anyhow)?;
+        self.audit("table_group.remove", Some(&removed), None);
+        if to.id() != removed.lead() {
+            self.move_table_orders(&mut orders, removed.lead(), &to)?;
+        }
+        let mut staying: Vec<order::RepoOrder> = orders
+            .items()
+            .iter()
+            .filter(|o| o.table.id() == to.id())
+            .cloned()
+            .collect();
+        staying.sort_by_key(|o| (o.time_placed, o.id()));
+        Ok(staying)
+    }
 }
 
 impl order::Repository for Database {
     async fn get_all(&self) -> order::RepoResult<Vec<order::RepoOrder>> {
-        self.orders
+        self.branch
+            .orders
             .read()
             .unwrap()
             .get_all()
-            .map_err(|e| anyhow::anyhow!(e))
+            .map_err(into_anyhow)
     }
 
     async fn get(&self, id: order::Id) -> order::RepoResult<order::RepoOrder> {
-        self.orders
+        self.branch
+            .orders
             .read()
             .unwrap()
             .get(id)
-            .map_err(|e| anyhow::anyhow!(e))
+            .map_err(into_anyhow)
     }
 
     async fn create(&mut self, item: order::Order) -> order::RepoResult<order::RepoOrder> {
-        self.orders
-            .write()
-            .unwrap()
-            .create(item)
-            .map_err(|e| anyhow::anyhow!(e))
+        let mut orders = self.branch.orders.write().unwrap();
+        let created = orders.create(item).map_err(into_anyhow)?;
+
+        self.record(order::Change::Placed, &created);
+        self.audit("order.create", None, Some(&created));
+        Ok(created)
+    }
+
+    async fn create_group(
+        &mut self,
+        items: Vec<order::Order>,
+    ) -> order::RepoResult<Vec<order::RepoOrder>> {
+        // creating in memory can't fail partway, so holding the lock throughout is all it takes
+        let mut orders = self.branch.orders.write().unwrap();
+        let group: order::GroupId = self.branch.group_ids.get();
+
+        let mut created = Vec::with_capacity(items.len());
+        for item in items {
+            let order = orders
+                .create(order::Order {
+                    group: Some(group),
+                    ..item
+                })
+                .map_err(into_anyhow)?;
+            self.record(order::Change::Placed, &order);
+            self.audit("order.create", None, Some(&order));
+            created.push(order);
+        }
+        Ok(created)
     }
 
     async fn remove(&mut self, id: order::Id) -> order::RepoResult<order::RepoOrder> {
-        self.orders
-            .write()
-            .unwrap()
-            .remove(id)
-            .map_err(|e| anyhow::anyhow!(e))
+        let mut orders = self.branch.orders.write().unwrap();
+        let removed = orders.remove(id).map_err(into_anyhow)?;
+
+        self.record(order::Change::Removed, &removed);
+        self.audit("order.remove", Some(&removed), None);
+        Ok(removed)
     }
 
     async fn update(&mut self, item: order::RepoOrder) -> order::RepoResult<order::RepoOrder> {
-        self.orders
-            .write()
-            .unwrap()
-            .update(item)
-            .map_err(|e| anyhow::anyhow!(e))
+        let mut orders = self.branch.orders.write().unwrap();
+        let before = orders.get(item.id()).ok();
+        let updated = orders.update(item).map_err(into_anyhow)?;
+
+        // the update succeeding means the order was there before it
+        let was = before.as_ref().map_or(updated.status, |b| b.status);
+        self.record_event(order::Event::updated(updated.clone(), was));
+        self.audit("order.update", before.as_ref(), Some(&updated));
+        Ok(updated)
+    }
+
+    async fn transfer(
+        &mut self,
+        id: order::Id,
+        to: layout::RepoTable,
+    ) -> order::RepoResult<order::RepoOrder> {
+        let mut orders = self.branch.orders.write().unwrap();
+        let before = orders.get(id).map_err(into_anyhow)?;
+        let from = before.table.id();
+        let mut order = before.clone();
+        order.table = to;
+        order.changed_by = self.actor;
+
+        let moved = orders.update(order).map_err(into_anyhow)?;
+        self.record_event(order::Event::transferred(moved.clone(), from));
+        self.audit("order.transfer", Some(&before), Some(&moved));
+        Ok(moved)
+    }
+
+    async fn transfer_table_orders(
+        &mut self,
+        from: layout::TableId,
+        to: layout::RepoTable,
+    ) -> order::RepoResult<Vec<order::RepoOrder>> {
+        // like clearing a table, one write lock covers finding the orders and moving them
+        let mut orders = self.branch.orders.write().unwrap();
+        self.move_table_orders(&mut orders, from, &to)
     }
 
     async fn remove_table_orders(
         &self,
         table_id: layout::TableId,
     ) -> order::RepoResult<Vec<order::RepoOrder>> {
-        let results = self
-            .orders
-            .read()
-            .unwrap()
+        // a single write lock, so that no order can be placed between finding and removing them
+        let mut orders = self.branch.orders.write().unwrap();
+        let results: Vec<order::RepoOrder> = orders
             .items
             .iter()
             .filter(|o| o.table.id() == table_id)
             .cloned()
             .collect();
 
-        self.orders
-            .write()
-            .unwrap()
-            .items
-            .retain(|o| o.table.id() != table_id);
+        for removed in results.iter() {
+            orders.ids.remove(&removed.id());
+            self.record(order::Change::Removed, removed);
+            self.audit("order.remove", Some(removed), None);
+        }
+        orders.items.retain(|o| o.table.id() != table_id);
 
         Ok(results)
     }
 
+    async fn query(&self, query: &order::Query) -> order::RepoResult<Vec<order::RepoOrder>> {
+        let orders = self.branch.orders.read().unwrap();
+        let mut results: Vec<&order::RepoOrder> =
+            orders.items().iter().filter(|o| query.matches(o)).collect();
+        results.sort_by(|a, b| query.compare(a, b));
+
+        Ok(results
+            .into_iter()
+            .take(query.limit.unwrap_or(usize::MAX))
+            .cloned()
+            .collect())
+    }
+
     async fn get_table(
         &self,
         table_id: layout::TableId,
     ) -> order::RepoResult<Vec<order::RepoOrder>> {
-        let results = self
-            .orders
+        order::Repository::query(
+            self,
+            &order::Query {
+                table: Some(table_id),
+                sort: order::SortKey::TimePlaced,
+                ..Default::default()
+            },
+        )
+        .await
+    }
+
+    async fn events(
+        &self,
+        after: Option<order::EventId>,
+    ) -> order::RepoResult<Vec<order::RepoEvent>> {
+        Ok(self.branch.order_events.read().unwrap().after(after))
+    }
+
+    async fn undispatched_events(&self) -> order::RepoResult<Vec<order::RepoEvent>> {
+        let events = self.branch.order_events.read().unwrap();
+        self.branch
+            .outbox
+            .read()
+            .unwrap()
+            .iter()
+            .map(|id| {
+                events.get(*id).cloned().ok_or_else(|| {
+                    anyhow::anyhow!(NotFound {
+                        id: format!("{:?}", id)
+                    })
+                })
+            })
+            .collect()
+    }
+
+    async fn mark_dispatched(&mut self, id: order::EventId) -> order::RepoResult<()> {
+        // marking twice is harmless, since the relay may well be retrying after a partial failure.
+        // this is bookkeeping for the relay rather than a change to anything, so it isn't audited
+        self.branch.outbox.write().unwrap().remove(&id);
+        Ok(())
+    }
+}
+
+impl reservation::Repository for Database {
+    async fn get_all(&self) -> reservation::RepoResult<Vec<reservation::RepoReservation>> {
+        Ok(self.branch.reservations.read().unwrap().items().clone())
+    }
+
+    async fn get(
+        &self,
+        id: reservation::Id,
+    ) -> reservation::RepoResult<reservation::RepoReservation> {
+        self.branch
+            .reservations
+            .read()
+            .unwrap()
+            .get(id)
+            .map_err(into_anyhow)
+    }
+
+    async fn create(
+        &mut self,
+        item: reservation::Reservation,
+    ) -> reservation::RepoResult<reservation::RepoReservation> {
+        let mut reservations = self.branch.reservations.write().unwrap();
+        double_booking(reservations.items(), None, &item)?;
+        let created = reservations.create(item).map_err(into_anyhow)?;
+
+        self.audit("reservation.create", None, Some(&created));
+        Ok(created)
+    }
+
+    async fn update(
+        &mut self,
+        item: reservation::RepoReservation,
+    ) -> reservation::RepoResult<reservation::RepoReservation> {
+        let mut reservations = self.branch.reservations.write().unwrap();
+        double_booking(reservations.items(), Some(item.id()), &item)?;
+        let before = reservations.get(item.id()).ok();
+        let updated = reservations.update(item).map_err(into_anyhow)?;
+
+        self.audit("reservation.update", before.as_ref(), Some(&updated));
+        Ok(updated)
+    }
+
+    async fn remove(
+        &mut self,
+        id: reservation::Id,
+    ) -> reservation::RepoResult<reservation::RepoReservation> {
+        let mut reservations = self.branch.reservations.write().unwrap();
+        let removed = reservations.remove(id).map_err(into_anyhow)?;
+
+        self.audit("reservation.remove", Some(&removed), None);
+        Ok(removed)
+    }
+
+    async fn overlapping(
+        &self,
+        window: reservation::Window,
+    ) -> reservation::RepoResult<Vec<reservation::RepoReservation>> {
+        let mut results: Vec<reservation::RepoReservation> = self
+            .branch
+            .reservations
             .read()
             .unwrap()
             .items()
             .iter()
-            .filter(|o| o.table.id() == table_id)
+            .filter(|r| r.window.overlaps(&window))
             .cloned()
             .collect();
+        results.sort_by_key(|r| (r.window.starts, r.id()));
         Ok(results)
     }
 }
+
+impl waitlist::Repository for Database {
+    async fn get_all(&self) -> waitlist::RepoResult<Vec<waitlist::RepoParty>> {
+        Ok(self.branch.waitlist.read().unwrap().items().clone())
+    }
+
+    async fn get(&self, id: waitlist::Id) -> waitlist::RepoResult<waitlist::RepoParty> {
+        self.branch
+            .waitlist
+            .read()
+            .unwrap()
+            .get(id)
+            .map_err(into_anyhow)
+    }
+
+    async fn create(&mut self, item: waitlist::Party) -> waitlist::RepoResult<waitlist::RepoParty> {
+        let mut waitlist = self.branch.waitlist.write().unwrap();
+        let created = waitlist.create(item).map_err(into_anyhow)?;
+
+        self.audit("waitlist.create", None, Some(&created));
+        Ok(created)
+    }
+
+    async fn update(
+        &mut self,
+        item: waitlist::RepoParty,
+    ) -> waitlist::RepoResult<waitlist::RepoParty> {
+        let mut waitlist = self.branch.waitlist.write().unwrap();
+        let before = waitlist.get(item.id()).ok();
+        let updated = waitlist.update(item).map_err(into_anyhow)?;
+
+        let notified =
+            |p: &waitlist::RepoParty| matches!(p.status, waitlist::Status::Notified { .. });
+        if notified(&updated) && !before.as_ref().is_some_and(notified) {
+            self.branch.notices.write().unwrap().insert(updated.id());
+        }
+        self.audit("waitlist.update", before.as_ref(), Some(&updated));
+        Ok(updated)
+    }
+
+    async fn unannounced(&self) -> waitlist::RepoResult<Vec<waitlist::RepoParty>> {
+        let waitlist = self.branch.waitlist.read().unwrap();
+        self.branch
+            .notices
+            .read()
+            .unwrap()
+            .iter()
+            .map(|id| waitlist.get(*id).map_err(into_anyhow))
+            .collect()
+    }
+
+    async fn mark_announced(&mut self, id: waitlist::Id) -> waitlist::RepoResult<()> {
+        // bookkeeping for the relay, like marking events dispatched, so it isn't audited either
+        self.branch.notices.write().unwrap().remove(&id);
+        Ok(())
+    }
+}
+
+impl layout::assignment::Staffing for Database {
+    async fn servers(
+        &self,
+        at: DateTime<Utc>,
+    ) -> layout::RepoResult<HashMap<String, staff::RepoStaff>> {
+        let staff = self.staff.read().unwrap();
+        self.branch
+            .sections
+            .read()
+            .unwrap()
+            .items()
+            .iter()
+            .filter(|a| a.shift.contains(at))
+            .map(|a| Ok((a.section.clone(), staff.get(a.server).map_err(into_anyhow)?)))
+            .collect()
+    }
+}
+
+impl staff::Repository for Database {
+    async fn get_all(&self) -> staff::RepoResult<Vec<staff::RepoStaff>> {
+        Ok(self.staff.read().unwrap().items().clone())
+    }
+
+    async fn get(&self, id: staff::Id) -> staff::RepoResult<staff::RepoStaff> {
+        self.staff.read().unwrap().get(id).map_err(into_anyhow)
+    }
+
+    async fn by_name(&self, name: &str) -> staff::RepoResult<Option<staff::RepoStaff>> {
+        Ok(self
+            .staff
+            .read()
+            .unwrap()
+            .items()
+            .iter()
+            .find(|s| s.name == name)
+            .cloned())
+    }
+
+    async fn create(&mut self, item: staff::Staff) -> staff::RepoResult<staff::RepoStaff> {
+        let mut staff = self.staff.write().unwrap();
+        if staff.items().iter().any(|s| s.name == item.name) {
+            return Err(anyhow::anyhow!(staff::NameTaken(item.name)));
+        }
+        let created = staff.create(item).map_err(into_anyhow)?;
+
+        self.audit_shared("staff.create", None, Some(&created));
+        Ok(created)
+    }
+
+    async fn update(&mut self, item: staff::RepoStaff) -> staff::RepoResult<staff::RepoStaff> {
+        let mut staff = self.staff.write().unwrap();
+        let before = staff.get(item.id()).ok();
+        let updated = staff.update(item).map_err(into_anyhow)?;
+
+        self.audit_shared("staff.update", before.as_ref(), Some(&updated));
+        Ok(updated)
+    }
+
+    async fn attempt_login(
+        &mut self,
+        id: staff::Id,
+        at: DateTime<Utc>,
+        since: DateTime<Utc>,
+    ) -> staff::RepoResult<Vec<DateTime<Utc>>> {
+        let mut logins = self.logins.write().unwrap();
+        let attempts = logins.entry(id).or_default();
+        attempts.retain(|a| *a >= since);
+        attempts.push(at);
+        Ok(attempts.clone())
+    }
+
+    async fn logged_in(&mut self, id: staff::Id) -> staff::RepoResult<()> {
+        self.logins.write().unwrap().remove(&id);
+        Ok(())
+    }
+}
+
+impl api_key::Repository for Database {
+    async fn get_all(&self) -> api_key::RepoResult<Vec<api_key::RepoApiKey>> {
+        Ok(self.api_keys.read().unwrap().items().clone())
+    }
+
+    async fn get(&self, id: api_key::Id) -> api_key::RepoResult<api_key::RepoApiKey> {
+        self.api_keys.read().unwrap().get(id).map_err(into_anyhow)
+    }
+
+    async fn create(&mut self, item: api_key::ApiKey) -> api_key::RepoResult<api_key::RepoApiKey> {
+        let mut api_keys = self.api_keys.write().unwrap();
+        let created = api_keys.create(item).map_err(into_anyhow)?;
+
+        self.audit_shared("api_key.create", None, Some(&created));
+        Ok(created)
+    }
+
+    async fn update(
+        &mut self,
+        item: api_key::RepoApiKey,
+    ) -> api_key::RepoResult<api_key::RepoApiKey> {
+        let mut api_keys = self.api_keys.write().unwrap();
+        let before = api_keys.get(item.id()).ok();
+        let updated = api_keys.update(item).map_err(into_anyhow)?;
+
+        self.audit_shared("api_key.update", before.as_ref(), Some(&updated));
+        Ok(updated)
+    }
+}
+
+impl shift::Repository for Database {
+    async fn get_all(&self) -> shift::RepoResult<Vec<shift::RepoAssignment>> {
+        Ok(self.branch.sections.read().unwrap().items().clone())
+    }
+
+    async fn get(&self, id: shift::Id) -> shift::RepoResult<shift::RepoAssignment> {
+        self.branch
+            .sections
+            .read()
+            .unwrap()
+            .get(id)
+            .map_err(into_anyhow)
+    }
+
+    async fn create(
+        &mut self,
+        item: shift::Assignment,
+    ) -> shift::RepoResult<shift::RepoAssignment> {
+        let mut sections = self.branch.sections.write().unwrap();
+        if let Some(taken) = sections
+            .items()
+            .iter()
+            .find(|a| a.section == item.section && a.shift.overlaps(&item.shift))
+        {
+            return Err(anyhow::anyhow!(shift::Conflict { with: taken.id() }));
+        }
+        let created = sections.create(item).map_err(into_anyhow)?;
+
+        self.audit("section.create", None, Some(&created));
+        Ok(created)
+    }
+
+    async fn remove(&mut self, id: shift::Id) -> shift::RepoResult<shift::RepoAssignment> {
+        let mut sections = self.branch.sections.write().unwrap();
+        let removed = sections.remove(id).map_err(into_anyhow)?;
+
+        self.audit("section.remove", Some(&removed), None);
+        Ok(removed)
+    }
+}
+
+impl audit::Store for Database {
+    async fn append(&mut self, record: audit::Record) -> audit::RepoResult<audit::RepoEntry> {
+        Ok(self.append_audit(record))
+    }
+
+    async fn chain(&self, tenant: Option<tenant::Id>) -> audit::RepoResult<Vec<audit::RepoEntry>> {
+        Ok(self
+            .audit
+            .read()
+            .unwrap()
+            .items()
+            .iter()
+            .filter(|e| e.record.tenant == tenant)
+            .cloned()
+            .collect())
+    }
+
+    async fn query(
+        &self,
+        tenant: tenant::Id,
+        query: &audit::Query,
+    ) -> audit::RepoResult<Vec<audit::RepoEntry>> {
+        // newest first, copying only what's on the page
+        Ok(self
+            .audit
+            .read()
+            .unwrap()
+            .items()
+            .iter()
+            .rev()
+            .filter(|e| e.record.tenant.map_or(true, |t| t == tenant) && query.matches(e))
+            .take(query.limit.unwrap_or(usize::MAX))
+            .cloned()
+            .collect())
+    }
+}
+
+// callers are expected to hold the reservations write lock, so nothing can be booked in between
+fn double_booking(
+    existing: &[reservation::RepoReservation],
+    except: Option<reservation::Id>,
+    item: &reservation::Reservation,
+) -> reservation::RepoResult<()> {
+    match existing.iter().find(|r| {
+        Some(r.id()) != except && r.table == item.table && r.window.overlaps(&item.window)
+    }) {
+        Some(taken) => Err(anyhow::anyhow!(reservation::Conflict { with: taken.id() })),
+        None => Ok(()),
+    }
+}
+
+impl Database {
+    // for changes to the restaurant's own data.
+    // callers are expected to hold the write lock of whatever changed, so entries are in the order changes were made
+    fn audit<T: Serialize>(&self, operation: &str, before: Option<&T>, after: Option<&T>) {
+        self.audit_for(Some(self.tenant), operation, before, after);
+    }
+
+    // for changes to what's shared between restaurants
+    fn audit_shared<T: Serialize>(&self, operation: &str, before: Option<&T>, after: Option<&T>) {
+        self.audit_for(None, operation, before, after);
+    }
+
+    fn audit_for<T: Serialize>(
+        &self,
+        tenant: Option<tenant::Id>,
+        operation: &str,
+        before: Option<&T>,
+        after: Option<&T>,
+    ) {
+        let snapshot = |item: &T| {
+            serde_json::to_value(item).expect("Repository items should be serializable.")
+        };
+        self.append_audit(audit::Record {
+            time: Utc::now(),
+            actor: self
+                .actor
+                .map(audit::Actor::Staff)
+                .or(self.key.map(audit::Actor::ApiKey)),
+            request: self.request.clone(),
+            tenant,
+            operation: operation.to_string(),
+            before: before.map(snapshot),
+            after: after.map(snapshot),
+        });
+    }
+
+    fn append_audit(&self, record: audit::Record) -> audit::RepoEntry {
+        // appending under one lock, so no two entries can chain on from the same one
+        let mut audit = self.audit.write().unwrap();
+        let mut heads = self.audit_heads.write().unwrap();
+        let tenant = record.tenant;
+        let previous = heads.get(&tenant).copied().unwrap_or(audit::GENESIS);
+        let entry = audit
+            .create(audit::Entry::new(record, previous))
+            .expect("Appending to the audit log can't fail in memory.");
+        heads.insert(tenant, entry.hash);
+        entry
+    }
+
+    // moves every order at one table to another, oldest first.
+    // callers pass in the orders they hold the write lock of, so nothing can be placed at `from` meanwhile
+    fn move_table_orders(
+        &self,
+        orders: &mut InMemoryRepository<order::Order, order::Id>,
+        from: layout::TableId,
+        to: &layout::RepoTable,
+    ) -> anyhow::Result<Vec<order::RepoOrder>> {
+        let moves = Database::orders_at(orders, from);
+        self.move_orders(orders, moves, to)
+    }
+
+    // the table's orders, oldest first
+    fn orders_at(
+        orders: &InMemoryRepository<order::Order, order::Id>,
+        table: layout::TableId,
+    ) -> Vec<order::RepoOrder> {
+        let mut at: Vec<order::RepoOrder> = orders
+            .items()
+            .iter()
+            .filter(|o| o.table.id() == table)
+            .cloned()
+            .collect();
+        at.sort_by_key(|o| (o.time_placed, o.id()));
+        at
+    }
+
+    // every order is checked before any is changed, so either they all move or none do, and nothing's recorded
+    // for a move that didn't happen
+    fn move_orders(
+        &self,
+        orders: &mut InMemoryRepository<order::Order, order::Id>,
+        moves: Vec<order::RepoOrder>,
+        to: &layout::RepoTable,
+    ) -> anyhow::Result<Vec<order::RepoOrder>> {
+        for order in &moves {
+            let stored = orders.get(order.id()).map_err(into_anyhow)?;
+            if stored.version != order.version {
+                return Err(anyhow::anyhow!(
+                    "Order {:?} changed while it was being moved.",
+                    order.id()
+                ));
+            }
+        }
+
+        let mut moved = Vec::with_capacity(moves.len());
+        for before in moves {
+            let mut order = before.clone();
+            order.table = to.clone();
+            order.changed_by = self.actor;
+            let order = orders.update(order).map_err(into_anyhow)?;
+            self.record_event(order::Event::transferred(order.clone(), before.table.id()));
+            self.audit("order.transfer", Some(&before), Some(&order));
+            moved.push(order);
+        }
+        Ok(moved)
+    }
+
+    // callers are expected to hold the orders write lock
+    fn record(&self, change: order::Change, order: &order::RepoOrder) {
+        self.record_event(order::Event::new(change, order.clone()));
+    }
+
+    fn record_event(&self, event: order::Event) {
+        let event = order::Event {
+            by: self.actor,
+            tenant: self.tenant,
+            ..event
+        };
+        let mut events = self.branch.order_events.write().unwrap();
+        let mut outbox = self.branch.outbox.write().unwrap();
+        let event = events.append(event, &outbox);
+        outbox.insert(event.id());
+    }
+}
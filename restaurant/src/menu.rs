@@ -1,7 +1,9 @@
 use serde::{Deserialize, Serialize};
-use std::future::Future;
+use std::{collections::HashMap, future::Future};
 use thiserror::Error;
 
+use crate::tenant;
+
 //TODO: want to try getting rid of the anyhow dependency
 //for this kind of application, it's fine, but, if possible, want to find a different way for fun
 
@@ -12,6 +14,12 @@ pub enum MenuError {
 
     #[error("Item '{item_name}' lacks an id and so cannot be mapped to repository.")]
     NoId { item_name: String },
+
+    #[error("Unable to find menu item {0:?}")]
+    ItemNotFound(Id),
+
+    #[error("Unable to find menu category {0:?}")]
+    CategoryNotFound(CategoryId),
 }
 type Result<T> = std::result::Result<T, MenuError>;
 pub type RepoResult<T> = std::result::Result<T, anyhow::Error>;
@@ -19,6 +27,16 @@ pub type RepoResult<T> = std::result::Result<T, anyhow::Error>;
 #[derive(Debug, Clone, Serialize, PartialEq, Eq)]
 pub struct Minutes(pub u32);
 
+// each item is prepared at exactly one station, which is what kitchen tickets get routed by
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[serde(rename_all = "lowercase")]
+pub enum Station {
+    Grill,
+    Fryer,
+    Dessert,
+    Drinks,
+}
+
 #[derive(Debug, Clone, Serialize)]
 pub struct Item {
     // considered having the name be the key
@@ -27,6 +45,13 @@ pub struct Item {
     // previously used chrono::TimeDelta, but it doesnt support serialization by default
     // in practice, basically every individual item in a restaurant should cook in minutes, so this actually works well
     pub cook_time: Minutes,
+    pub station: Station,
+    // whether the kitchen can make it right now. items that run out stay on the menu, but can't be ordered
+    pub available: bool,
+    // where the item is listed. items without a category are listed after all of the categories
+    pub category: Option<CategoryId>,
+    // lower comes first among the items in the same category, with ties going by name
+    pub sort_order: u32,
 }
 
 #[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash, PartialOrd, Ord)]
@@ -43,13 +68,63 @@ impl From<Id> for u32 {
 }
 pub type RepoItem = crate::RepoItem<Item, Id>;
 
-pub trait Repository {
+// NOTE: categories are how the menu is laid out for guests, such as drinks, mains and desserts, and can be nested,
+// such as wines under drinks. they're separate from stations, which are about who cooks an item rather than where
+// it's listed. categories are only ever added, so a parent always exists before its children and there's no way
+// to end up with a loop.
+#[derive(Debug, Clone, Serialize)]
+pub struct Category {
+    pub name: String,
+    // none for the top level
+    pub parent: Option<CategoryId>,
+    // lower comes first among the categories with the same parent, with ties going by name
+    pub sort_order: u32,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct CategoryId(pub u32);
+impl From<u32> for CategoryId {
+    fn from(value: u32) -> Self {
+        CategoryId(value)
+    }
+}
+impl From<CategoryId> for u32 {
+    fn from(value: CategoryId) -> Self {
+        value.0
+    }
+}
+pub type RepoCategory = crate::RepoItem<Category, CategoryId>;
+
+// scoped to one restaurant, see the tenant module
+pub trait Repository: tenant::Scoped {
     fn get_all(&self) -> impl Future<Output = RepoResult<Vec<RepoItem>>> + Send;
     fn get(&self, id: Id) -> impl Future<Output = RepoResult<RepoItem>> + Send;
 
     fn create(&mut self, item: Item) -> impl Future<Output = RepoResult<RepoItem>> + Send;
     fn remove(&mut self, id: Id) -> impl Future<Output = RepoResult<()>> + Send;
     fn update(&mut self, item: RepoItem) -> impl Future<Output = RepoResult<()>> + Send;
+
+    fn categories(&self) -> impl Future<Output = RepoResult<Vec<RepoCategory>>> + Send;
+    fn category(&self, id: CategoryId) -> impl Future<Output = RepoResult<RepoCategory>> + Send;
+    fn create_category(
+        &mut self,
+        category: Category,
+    ) -> impl Future<Output = RepoResult<RepoCategory>> + Send;
+}
+
+// a category as it's laid out, with its items and subcategories in order
+#[derive(Debug, Clone)]
+pub struct Section {
+    pub category: RepoCategory,
+    pub items: Vec<RepoItem>,
+    pub subcategories: Vec<Section>,
+}
+
+// the whole menu as it's laid out
+#[derive(Debug, Clone)]
+pub struct Tree {
+    pub categories: Vec<Section>,
+    pub uncategorized: Vec<RepoItem>,
 }
 
 pub async fn get_all<T: Repository>(repo: &T) -> Result<Vec<RepoItem>> {
@@ -60,6 +135,120 @@ pub async fn get<T: Repository>(repo: &T, id: Id) -> Result<RepoItem> {
     repo.get(id).await.map_err(MenuError::RepoOperation)
 }
 
+pub async fn add_category<T: Repository>(repo: &mut T, category: Category) -> Result<RepoCategory> {
+    if let Some(parent) = category.parent {
+        repo.category(parent)
+            .await
+            .map_err(|_| MenuError::CategoryNotFound(parent))?;
+    }
+    Ok(repo.create_category(category).await?)
+}
+
+// lists the item under the category, or takes it out of any with none
+pub async fn file<T: Repository>(
+    repo: &mut T,
+    id: Id,
+    category: Option<CategoryId>,
+    sort_order: u32,
+) -> Result<RepoItem> {
+    if let Some(category) = category {
+        repo.category(category)
+            .await
+            .map_err(|_| MenuError::CategoryNotFound(category))?;
+    }
+    let mut item = repo
+        .get(id)
+        .await
+        .map_err(|_| MenuError::ItemNotFound(id))?;
+    item.category = category;
+    item.sort_order = sort_order;
+    repo.update(item).await?;
+
+    Ok(repo.get(id).await?)
+}
+
+// every item, in order, under the category it's filed directly under, or none. the whole menu is read once
+// and sorted out here, rather than asking the repository for each category in turn
+async fn by_category<T: Repository>(
+    repo: &T,
+) -> Result<HashMap<Option<CategoryId>, Vec<RepoItem>>> {
+    let mut filed: HashMap<Option<CategoryId>, Vec<RepoItem>> = HashMap::new();
+    for item in repo.get_all().await? {
+        filed.entry(item.category).or_default().push(item);
+    }
+    Ok(filed
+        .into_iter()
+        .map(|(category, items)| (category, sorted_items(items)))
+        .collect())
+}
+
+// the category's items, followed by those of its subcategories, in the order they're laid out
+pub async fn items_in<T: Repository>(repo: &T, id: CategoryId) -> Result<Vec<RepoItem>> {
+    repo.category(id)
+        .await
+        .map_err(|_| MenuError::CategoryNotFound(id))?;
+    let categories = repo.categories().await?;
+    let mut filed = by_category(repo).await?;
+
+    let mut items = Vec::new();
+    let mut queue = vec![id];
+    while let Some(category) = queue.pop() {
+        items.extend(filed.remove(&Some(category)).unwrap_or_default());
+        // popping from the back, so the first subcategory has to go in last
+        let children = sorted_categories(
+            categories
+                .iter()
+                .filter(|c| c.parent == Some(category))
+                .cloned()
+                .collect(),
+        );
+        queue.extend(children.iter().rev().map(|c| c.id()));
+    }
+    Ok(items)
+}
+
+pub async fn tree<T: Repository>(repo: &T) -> Result<Tree> {
+    let categories = repo.categories().await?;
+    let mut items = by_category(repo).await?;
+
+    fn sections(
+        parent: Option<CategoryId>,
+        categories: &[RepoCategory],
+        items: &mut HashMap<Option<CategoryId>, Vec<RepoItem>>,
+    ) -> Vec<Section> {
+        sorted_categories(
+            categories
+                .iter()
+                .filter(|c| c.parent == parent)
+                .cloned()
+                .collect(),
+        )
+        .into_iter()
+        .map(|category| Section {
+            items: items.remove(&Some(category.id())).unwrap_or_default(),
+            subcategories: sections(Some(category.id()), categories, items),
+            category,
+        })
+        .collect()
+    }
+
+    Ok(Tree {
+        categories: sections(None, &categories, &mut items),
+        uncategorized: items.remove(&None).unwrap_or_default(),
+    })
+}
+
+fn sorted_items(mut items: Vec<RepoItem>) -> Vec<RepoItem> {
+    items.sort_by(|a, b| (a.sort_order, &a.name, a.id()).cmp(&(b.sort_order, &b.name, b.id())));
+    items
+}
+
+fn sorted_categories(mut categories: Vec<RepoCategory>) -> Vec<RepoCategory> {
+    categories
+        .sort_by(|a, b| (a.sort_order, &a.name, a.id()).cmp(&(b.sort_order, &b.name, b.id())));
+    categories
+}
+
 impl RepoItem {
     // NOTE: would be a series of functions that encompass the types of operations we'd want for a menu
     // set_cook_time is implemented as an example, not that it would work without a fully implemented MenuRepository
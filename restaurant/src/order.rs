@@ -1,4 +1,6 @@
-use crate::{layout, menu, RepoItem};
+use std::cmp::Ordering;
+
+use crate::{layout, menu, staff, tenant, RepoItem, VersionConflict};
 use chrono::{DateTime, Utc};
 use futures::Future;
 use serde::{Deserialize, Serialize};
@@ -12,16 +14,116 @@ pub enum OrderingError {
     RepoOperation(#[from] anyhow::Error),
     #[error("Unable to find order {0:?}")]
     OrderNotFound(Id),
+    #[error("The cursor doesn't belong to a query sorted this way.")]
+    InvalidCursor,
+    #[error("A group of orders needs at least one line.")]
+    EmptyGroup,
+    #[error("Some lines of the group can't be placed: {0:?}")]
+    InvalidLines(Vec<(usize, LineError)>),
+    #[error("Quantity must be at least one.")]
+    ZeroQuantity,
+    #[error("Quantity {quantity} is over the most a single order can have, {max}.")]
+    QuantityTooLarge { quantity: u32, max: u32 },
+    #[error("Table {0:?} isn't taking orders.")]
+    TableInactive(layout::TableId),
+    #[error("Menu item {0:?} isn't available.")]
+    MenuItemUnavailable(menu::Id),
+    #[error("Orders are already at table {0:?}.")]
+    SameTable(layout::TableId),
+    #[error("Order {id:?} was expected at version {expected}, but is at version {actual}.")]
+    VersionConflict { id: Id, expected: u32, actual: u32 },
+}
+
+// what's wrong with a single line of a group. a group is checked as a whole, so every one is reported.
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum LineError {
+    #[error("Menu item {0:?} doesn't exist.")]
+    MenuItemNotFound(menu::Id),
+    #[error("Menu item {0:?} isn't available.")]
+    MenuItemUnavailable(menu::Id),
+    #[error("Quantity must be at least one.")]
+    ZeroQuantity,
+    #[error("Quantity can be at most {max}.")]
+    QuantityTooLarge { max: u32 },
 }
 pub type Result<T> = std::result::Result<T, OrderingError>;
 pub type RepoResult<T> = std::result::Result<T, anyhow::Error>;
 
+pub const DEFAULT_MAX_QUANTITY: u32 = 50;
+
+// what every order has to satisfy, beyond what the types already guarantee
+#[derive(Debug, Clone)]
+pub struct Rules {
+    // the most of one item a single order can have. larger orders are far more likely to be typos
+    pub max_quantity: u32,
+}
+
+impl Default for Rules {
+    fn default() -> Self {
+        Rules {
+            max_quantity: DEFAULT_MAX_QUANTITY,
+        }
+    }
+}
+
+impl Rules {
+    pub fn check_quantity(&self, quantity: u32) -> Result<()> {
+        match quantity {
+            0 => Err(OrderingError::ZeroQuantity),
+            q if q > self.max_quantity => Err(OrderingError::QuantityTooLarge {
+                quantity,
+                max: self.max_quantity,
+            }),
+            _ => Ok(()),
+        }
+    }
+
+    pub fn check(
+        &self,
+        table: &layout::RepoTable,
+        menu_item: &menu::RepoItem,
+        quantity: u32,
+    ) -> Result<()> {
+        if !table.active {
+            return Err(OrderingError::TableInactive(table.id()));
+        }
+        if !menu_item.available {
+            return Err(OrderingError::MenuItemUnavailable(menu_item.id()));
+        }
+        self.check_quantity(quantity)
+    }
+
+    fn check_line(&self, quantity: u32) -> Option<LineError> {
+        match self.check_quantity(quantity) {
+            Ok(()) => None,
+            Err(OrderingError::QuantityTooLarge { max, .. }) => {
+                Some(LineError::QuantityTooLarge { max })
+            }
+            Err(_) => Some(LineError::ZeroQuantity),
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize)]
 pub struct Order {
     pub table: layout::RepoTable,
     pub menu_item: menu::RepoItem,
     pub time_placed: DateTime<Utc>,
     pub quantity: u32,
+    pub status: Status,
+    // set for orders that were placed together as a group, rather than one at a time
+    pub group: Option<GroupId>,
+    // who placed the order and who last changed it, when it went through a signed-in staff member
+    pub placed_by: Option<staff::Id>,
+    pub changed_by: Option<staff::Id>,
+}
+
+// an order line starts out placed and is marked ready when its station bumps it
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash)]
+#[serde(rename_all = "lowercase")]
+pub enum Status {
+    Placed,
+    Ready,
 }
 
 #[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash, PartialOrd, Ord)]
@@ -38,22 +140,324 @@ impl From<Id> for u32 {
 }
 pub type RepoOrder = RepoItem<Order, Id>;
 
-pub trait Repository {
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct GroupId(pub u32);
+impl From<u32> for GroupId {
+    fn from(value: u32) -> Self {
+        GroupId(value)
+    }
+}
+impl From<GroupId> for u32 {
+    fn from(value: GroupId) -> Self {
+        value.0
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GroupLine {
+    pub menu_item: menu::Id,
+    pub quantity: u32,
+}
+
+// repositories record an event for every mutation they make to an order, as part of that same mutation.
+// this makes the event log a change feed that consumers can replay from any point.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Change {
+    Placed,
+    Updated,
+    Removed,
+    Transferred,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Event {
+    pub change: Change,
+    // the order as of after the change, or as of its removal
+    pub order: RepoOrder,
+    // for transfers, the table the order left. the order itself already has the one it moved to
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub from: Option<layout::TableId>,
+    // for updates, the order's status before the change, so what the update did to it can be told apart
+    // from what it already was
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub was: Option<Status>,
+    pub time: DateTime<Utc>,
+    // the staff member who made the change, stamped by the repository it went through
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub by: Option<staff::Id>,
+    // the restaurant the order is at, also stamped by the repository
+    pub tenant: tenant::Id,
+}
+
+impl Event {
+    pub fn new(change: Change, order: RepoOrder) -> Event {
+        Event {
+            change,
+            order,
+            from: None,
+            was: None,
+            time: Utc::now(),
+            by: None,
+            tenant: tenant::DEFAULT,
+        }
+    }
+
+    pub fn updated(order: RepoOrder, was: Status) -> Event {
+        Event {
+            was: Some(was),
+            ..Event::new(Change::Updated, order)
+        }
+    }
+
+    // whether this is the order becoming ready, rather than a change to one that already was
+    pub fn became_ready(&self) -> bool {
+        self.change == Change::Updated
+            && self.order.status == Status::Ready
+            && self.was != Some(Status::Ready)
+    }
+
+    pub fn transferred(order: RepoOrder, from: layout::TableId) -> Event {
+        Event {
+            from: Some(from),
+            ..Event::new(Change::Transferred, order)
+        }
+    }
+}
+
+// ids are increasing, so a consumer only needs to remember the last one it saw to resume
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct EventId(pub u32);
+impl From<u32> for EventId {
+    fn from(value: u32) -> Self {
+        EventId(value)
+    }
+}
+impl From<EventId> for u32 {
+    fn from(value: EventId) -> Self {
+        value.0
+    }
+}
+pub type RepoEvent = RepoItem<Event, EventId>;
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct EventFilter {
+    pub table: Option<layout::TableId>,
+    pub station: Option<menu::Station>,
+    pub status: Option<Status>,
+}
+
+impl EventFilter {
+    pub fn matches(&self, event: &Event) -> bool {
+        // a transfer concerns the table the order left as much as the one it went to
+        self.table.map_or(true, |t| {
+            event.order.table.id() == t || event.from == Some(t)
+        }) && self
+            .station
+            .map_or(true, |s| event.order.menu_item.station == s)
+            && self.status.map_or(true, |s| event.order.status == s)
+    }
+}
+
+pub const DEFAULT_PAGE_SIZE: usize = 50;
+pub const MAX_PAGE_SIZE: usize = 200;
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum SortKey {
+    #[default]
+    Id,
+    TimePlaced,
+    Table,
+    Quantity,
+}
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Direction {
+    #[default]
+    Ascending,
+    Descending,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(untagged)]
+pub enum SortValue {
+    Number(u32),
+    Time(DateTime<Utc>),
+}
+
+impl SortKey {
+    pub fn value(&self, order: &RepoOrder) -> SortValue {
+        match self {
+            SortKey::Id => SortValue::Number(order.id().0),
+            SortKey::TimePlaced => SortValue::Time(order.time_placed),
+            SortKey::Table => SortValue::Number(order.table.id().0),
+            SortKey::Quantity => SortValue::Number(order.quantity),
+        }
+    }
+}
+
+// where a page ended. the next page starts right after the order it points at, by the same sort, which
+// keeps pages stable while orders are placed and removed in between. ties on the sort key are broken by id.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Cursor {
+    pub sort: SortKey,
+    pub direction: Direction,
+    pub value: SortValue,
+    pub id: Id,
+}
+
+// which orders to list and how. repositories are expected to evaluate all of it themselves,
+// so that only a page's worth of orders ever leaves them.
+#[derive(Debug, Clone, Default)]
+pub struct Query {
+    pub table: Option<layout::TableId>,
+    pub menu_item: Option<menu::Id>,
+    pub status: Option<Status>,
+    pub placed_after: Option<DateTime<Utc>>,
+    pub placed_before: Option<DateTime<Utc>>,
+    pub sort: SortKey,
+    pub direction: Direction,
+    pub after: Option<Cursor>,
+    pub limit: Option<usize>,
+}
+
+impl Query {
+    pub fn matches(&self, order: &RepoOrder) -> bool {
+        self.table.map_or(true, |t| order.table.id() == t)
+            && self.menu_item.map_or(true, |m| order.menu_item.id() == m)
+            && self.status.map_or(true, |s| order.status == s)
+            && self.placed_after.map_or(true, |t| order.time_placed > t)
+            && self.placed_before.map_or(true, |t| order.time_placed < t)
+            && self.after.map_or(true, |c| {
+                self.compare_to(order, c.value, c.id) == Ordering::Greater
+            })
+    }
+
+    pub fn compare(&self, a: &RepoOrder, b: &RepoOrder) -> Ordering {
+        self.compare_to(a, self.sort.value(b), b.id())
+    }
+
+    fn compare_to(&self, order: &RepoOrder, value: SortValue, id: Id) -> Ordering {
+        let ordering = (self.sort.value(order), order.id()).cmp(&(value, id));
+        match self.direction {
+            Direction::Ascending => ordering,
+            Direction::Descending => ordering.reverse(),
+        }
+    }
+
+    pub fn cursor(&self, order: &RepoOrder) -> Cursor {
+        Cursor {
+            sort: self.sort,
+            direction: self.direction,
+            value: self.sort.value(order),
+            id: order.id(),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Page {
+    pub orders: Vec<RepoOrder>,
+    // where to continue from, if there are more
+    pub next: Option<Cursor>,
+}
+
+// scoped to one restaurant, see the tenant module
+pub trait Repository: tenant::Scoped {
     fn get_all(&self) -> impl Future<Output = RepoResult<Vec<RepoOrder>>> + Send;
     fn get(&self, id: Id) -> impl Future<Output = RepoResult<RepoOrder>> + Send;
 
     fn create(&mut self, item: Order) -> impl Future<Output = RepoResult<RepoOrder>> + Send;
     fn remove(&mut self, id: Id) -> impl Future<Output = RepoResult<RepoOrder>> + Send;
     fn update(&mut self, item: RepoOrder) -> impl Future<Output = RepoResult<RepoOrder>> + Send;
+    // creates every one of the orders under a new group id, or none of them
+    fn create_group(
+        &mut self,
+        items: Vec<Order>,
+    ) -> impl Future<Output = RepoResult<Vec<RepoOrder>>> + Send;
+
+    // the orders matching the query, in its order, and no more than its limit
+    fn query(&self, query: &Query) -> impl Future<Output = RepoResult<Vec<RepoOrder>>> + Send;
 
+    // in the order they were placed
     fn get_table(
         &self,
         table_id: layout::TableId,
     ) -> impl Future<Output = RepoResult<Vec<RepoOrder>>> + Send;
+    // moves the order to the table, keeping everything else about it, including when it was placed
+    fn transfer(
+        &mut self,
+        id: Id,
+        to: layout::RepoTable,
+    ) -> impl Future<Output = RepoResult<RepoOrder>> + Send;
+    // moves every one of the table's orders, with none placed or moved in between
+    fn transfer_table_orders(
+        &mut self,
+        from: layout::TableId,
+        to: layout::RepoTable,
+    ) -> impl Future<Output = RepoResult<Vec<RepoOrder>>> + Send;
     fn remove_table_orders(
         &self,
         table_id: layout::TableId,
     ) -> impl Future<Output = RepoResult<Vec<RepoOrder>>> + Send;
+
+    fn events(
+        &self,
+        after: Option<EventId>,
+    ) -> impl Future<Output = RepoResult<Vec<RepoEvent>>> + Send;
+
+    // the outbox. events are recorded in the same unit of work as their mutation, and stay undispatched
+    // until the relay has successfully handed them off. see the outbox module.
+    fn undispatched_events(&self) -> impl Future<Output = RepoResult<Vec<RepoEvent>>> + Send;
+    fn mark_dispatched(&mut self, id: EventId) -> impl Future<Output = RepoResult<()>> + Send;
+}
+
+pub async fn events<T: Repository>(
+    repo: &T,
+    after: Option<EventId>,
+    filter: &EventFilter,
+) -> Result<Vec<RepoEvent>> {
+    repo.events(after)
+        .await
+        .map(|events| events.into_iter().filter(|e| filter.matches(e)).collect())
+        .map_err(OrderingError::RepoOperation)
+}
+
+pub async fn get<T: Repository>(repo: &T, id: Id) -> Result<RepoOrder> {
+    repo.get(id)
+        .await
+        .map_err(|_| OrderingError::OrderNotFound(id))
+}
+
+pub async fn find<T: Repository>(repo: &T, query: &Query) -> Result<Page> {
+    if let Some(cursor) = query.after {
+        if cursor.sort != query.sort || cursor.direction != query.direction {
+            return Err(OrderingError::InvalidCursor);
+        }
+    }
+
+    // one more than was asked for, to know whether there's a next page without a separate count
+    let limit = query
+        .limit
+        .unwrap_or(DEFAULT_PAGE_SIZE)
+        .clamp(1, MAX_PAGE_SIZE);
+    let mut orders = repo
+        .query(&Query {
+            limit: Some(limit + 1),
+            ..query.clone()
+        })
+        .await
+        .map_err(OrderingError::RepoOperation)?;
+
+    let next = if orders.len() > limit {
+        orders.truncate(limit);
+        orders.last().map(|o| query.cursor(o))
+    } else {
+        None
+    };
+    Ok(Page { orders, next })
 }
 
 pub async fn get_table<T: Repository>(
@@ -65,42 +469,181 @@ pub async fn get_table<T: Repository>(
         .map_err(OrderingError::RepoOperation)
 }
 
-pub async fn place<T: Repository>(
+pub async fn place<T: Repository + staff::Acting>(
     repo: &mut T,
+    rules: &Rules,
     table: layout::RepoTable,
     menu_item: menu::RepoItem,
     quantity: u32,
 ) -> Result<RepoOrder> {
+    rules.check(&table, &menu_item, quantity)?;
+
     repo.create(Order {
         table,
         menu_item,
         time_placed: Utc::now(),
         quantity,
+        status: Status::Placed,
+        group: None,
+        placed_by: repo.actor(),
+        changed_by: repo.actor(),
     })
     .await
     .map_err(OrderingError::RepoOperation)
 }
 
-pub async fn set_quantity<T: Repository>(repo: &mut T, id: Id, quantity: u32) -> Result<RepoOrder> {
+// places a table's whole order at once. nothing is placed unless every line can be.
+pub async fn place_group<T: Repository + menu::Repository + staff::Acting>(
+    repo: &mut T,
+    rules: &Rules,
+    table: layout::RepoTable,
+    lines: &[GroupLine],
+) -> Result<Vec<RepoOrder>> {
+    if lines.is_empty() {
+        return Err(OrderingError::EmptyGroup);
+    }
+    if !table.active {
+        return Err(OrderingError::TableInactive(table.id()));
+    }
+
+    let mut items = Vec::with_capacity(lines.len());
+    let mut errors = Vec::new();
+    for (index, line) in lines.iter().enumerate() {
+        if let Some(error) = rules.check_line(line.quantity) {
+            errors.push((index, error));
+        }
+        match menu::Repository::get(repo, line.menu_item).await {
+            Ok(item) if item.available => items.push(item),
+            Ok(_) => errors.push((index, LineError::MenuItemUnavailable(line.menu_item))),
+            Err(_) => errors.push((index, LineError::MenuItemNotFound(line.menu_item))),
+        }
+    }
+    if !errors.is_empty() {
+        return Err(OrderingError::InvalidLines(errors));
+    }
+
+    let time_placed = Utc::now();
+    let actor = repo.actor();
+    let orders = items
+        .into_iter()
+        .zip(lines)
+        .map(|(menu_item, line)| Order {
+            table: table.clone(),
+            menu_item,
+            time_placed,
+            quantity: line.quantity,
+            status: Status::Placed,
+            group: None,
+            placed_by: actor,
+            changed_by: actor,
+        })
+        .collect();
+    repo.create_group(orders)
+        .await
+        .map_err(OrderingError::RepoOperation)
+}
+
+// an order is only changed if it's still at the version the caller last saw, when they say which one that was
+fn check_version(order: &RepoOrder, expected: Option<u32>) -> Result<()> {
+    match expected {
+        Some(expected) if expected != order.version() => Err(OrderingError::VersionConflict {
+            id: order.id(),
+            expected,
+            actual: order.version(),
+        }),
+        _ => Ok(()),
+    }
+}
+
+// someone else may have changed the order between us reading and writing it
+fn update_failed(id: Id, e: anyhow::Error) -> OrderingError {
+    match e.downcast::<VersionConflict>() {
+        Ok(conflict) => OrderingError::VersionConflict {
+            id,
+            expected: conflict.expected,
+            actual: conflict.actual,
+        },
+        Err(e) => OrderingError::RepoOperation(e),
+    }
+}
+
+// setting the quantity to zero cancels the order
+pub async fn set_quantity<T: Repository + staff::Acting>(
+    repo: &mut T,
+    rules: &Rules,
+    id: Id,
+    quantity: u32,
+    expected: Option<u32>,
+) -> Result<RepoOrder> {
     if quantity == 0 {
-        return cancel(repo, id).await;
+        return cancel(repo, id, expected).await;
     }
+    rules.check_quantity(quantity)?;
 
     if let Ok(mut order) = repo.get(id).await {
+        check_version(&order, expected)?;
+        // the extra portions still have to be made, so the order goes back on the station's tickets
+        if quantity > order.quantity {
+            order.status = Status::Placed;
+        }
         order.quantity = quantity;
+        order.changed_by = repo.actor();
 
-        repo.update(order)
-            .await
-            .map_err(OrderingError::RepoOperation)
+        repo.update(order).await.map_err(|e| update_failed(id, e))
     } else {
         Err(OrderingError::OrderNotFound(id))
     }
 }
 
-pub async fn cancel<T: Repository>(repo: &mut T, id: Id) -> Result<RepoOrder> {
+pub async fn cancel<T: Repository>(
+    repo: &mut T,
+    id: Id,
+    expected: Option<u32>,
+) -> Result<RepoOrder> {
+    match repo.get(id).await {
+        Ok(order) => check_version(&order, expected)?,
+        Err(_) => return Err(OrderingError::OrderNotFound(id)),
+    }
+
     repo.remove(id).await.map_err(OrderingError::RepoOperation)
 }
 
+// orders keep their id and time placed when they move, and so their place in the kitchen's queue
+pub async fn transfer<T: Repository>(
+    repo: &mut T,
+    id: Id,
+    new_table: layout::RepoTable,
+) -> Result<RepoOrder> {
+    let order = get(repo, id).await?;
+    if order.table.id() == new_table.id() {
+        return Err(OrderingError::SameTable(new_table.id()));
+    }
+    if !new_table.active {
+        return Err(OrderingError::TableInactive(new_table.id()));
+    }
+
+    repo.transfer(id, new_table)
+        .await
+        .map_err(OrderingError::RepoOperation)
+}
+
+pub async fn transfer_table<T: Repository>(
+    repo: &mut T,
+    from: layout::TableId,
+    to: layout::RepoTable,
+) -> Result<Vec<RepoOrder>> {
+    if from == to.id() {
+        return Err(OrderingError::SameTable(from));
+    }
+    if !to.active {
+        return Err(OrderingError::TableInactive(to.id()));
+    }
+
+    repo.transfer_table_orders(from, to)
+        .await
+        .map_err(OrderingError::RepoOperation)
+}
+
 pub async fn clear_table<T: Repository>(
     repo: &mut T,
     table_id: layout::TableId,
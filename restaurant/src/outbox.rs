@@ -0,0 +1,51 @@
+use std::future::Future;
+
+use thiserror::Error;
+
+use crate::order::{self, EventId, RepoEvent};
+
+// NOTE: repositories record order events in the same unit of work as the mutation that caused them,
+// so an event exists if and only if its mutation persisted. relaying them is a separate step:
+// an event is only marked dispatched after it was successfully handed off, so a crash in between means it gets
+// dispatched again. that makes delivery at-least-once, and the event id is what consumers dedup on.
+
+#[derive(Error, Debug)]
+pub enum OutboxError {
+    #[error("An error occurred when interacting with the repository.")]
+    RepoOperation(#[from] anyhow::Error),
+    #[error("Unable to dispatch event {event:?}")]
+    Dispatch {
+        event: EventId,
+        #[source]
+        source: anyhow::Error,
+    },
+}
+pub type Result<T> = std::result::Result<T, OutboxError>;
+
+// whatever announces events to the outside world, be it a change feed, webhooks or a message broker
+pub trait Dispatcher {
+    fn dispatch(&self, event: &RepoEvent) -> impl Future<Output = anyhow::Result<()>> + Send;
+}
+
+// dispatches every undispatched event in order, returning the ids of those that were
+pub async fn relay<R: order::Repository, D: Dispatcher>(
+    repo: &mut R,
+    dispatcher: &D,
+) -> Result<Vec<EventId>> {
+    let mut dispatched = Vec::new();
+    for event in repo.undispatched_events().await? {
+        // stopping at the first failure keeps consumers from seeing events out of order
+        dispatcher
+            .dispatch(&event)
+            .await
+            .map_err(|source| OutboxError::Dispatch {
+                event: event.id(),
+                source,
+            })?;
+
+        repo.mark_dispatched(event.id()).await?;
+        dispatched.push(event.id());
+    }
+
+    Ok(dispatched)
+}
@@ -0,0 +1,223 @@
+use std::future::Future;
+
+use chrono::{DateTime, NaiveDate, TimeDelta, Utc};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::{layout, staff, RepoItem};
+
+#[derive(Error, Debug)]
+pub enum ReservationError {
+    #[error("An error occurred when interacting with the repository.")]
+    RepoOperation(#[from] anyhow::Error),
+    #[error("Unable to find reservation {0:?}")]
+    ReservationNotFound(Id),
+    #[error("Unable to find table {0:?}")]
+    TableNotFound(layout::TableId),
+    #[error("Table {0:?} isn't in service.")]
+    TableInactive(layout::TableId),
+    #[error("A party needs at least one person.")]
+    EmptyParty,
+    #[error("Table {table:?} sits {seats}, which isn't enough for a party of {party_size}.")]
+    TableTooSmall {
+        table: layout::TableId,
+        seats: u32,
+        party_size: u32,
+    },
+    #[error("A reservation has to end after it starts.")]
+    InvalidWindow,
+    #[error("Table {table:?} is already booked then, by reservation {with:?}.")]
+    Overlaps { table: layout::TableId, with: Id },
+}
+pub type Result<T> = std::result::Result<T, ReservationError>;
+pub type RepoResult<T> = std::result::Result<T, anyhow::Error>;
+
+// raised by repositories when a write would double-book a table
+#[derive(Error, Debug)]
+#[error("The table is already booked by reservation {with:?}.")]
+pub struct Conflict {
+    pub with: Id,
+}
+
+// when the party has the table. the end is exclusive, so back-to-back bookings don't overlap
+#[derive(Debug, Clone, Copy, Serialize, PartialEq, Eq)]
+pub struct Window {
+    pub starts: DateTime<Utc>,
+    pub ends: DateTime<Utc>,
+}
+
+impl Window {
+    pub fn overlaps(&self, other: &Window) -> bool {
+        self.starts < other.ends && other.starts < self.ends
+    }
+
+    pub fn contains(&self, at: DateTime<Utc>) -> bool {
+        self.starts <= at && at < self.ends
+    }
+
+    // the whole of a day, in UTC
+    pub fn day(day: NaiveDate) -> Window {
+        let starts = day.and_time(Default::default()).and_utc();
+        Window {
+            starts,
+            ends: starts + TimeDelta::days(1),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, PartialEq, Eq)]
+pub struct Contact {
+    pub name: String,
+    pub phone: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Reservation {
+    pub table: layout::TableId,
+    pub party_size: u32,
+    pub window: Window,
+    pub contact: Contact,
+    // who booked or last changed it, when it went through a signed-in staff member. set by `book` and `modify`
+    pub changed_by: Option<staff::Id>,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Id(pub u32);
+impl From<u32> for Id {
+    fn from(value: u32) -> Self {
+        Id(value)
+    }
+}
+impl From<Id> for u32 {
+    fn from(value: Id) -> Self {
+        value.0
+    }
+}
+pub type RepoReservation = RepoItem<Reservation, Id>;
+
+// what a booking is changing, leaving out whatever stays as it is
+#[derive(Debug, Clone, Default)]
+pub struct Changes {
+    pub table: Option<layout::TableId>,
+    pub party_size: Option<u32>,
+    pub window: Option<Window>,
+    pub contact: Option<Contact>,
+}
+
+pub trait Repository {
+    fn get_all(&self) -> impl Future<Output = RepoResult<Vec<RepoReservation>>> + Send;
+    fn get(&self, id: Id) -> impl Future<Output = RepoResult<RepoReservation>> + Send;
+
+    // both reject a reservation that overlaps another at the same table with a `Conflict`,
+    // checked in the same unit of work as the write
+    fn create(
+        &mut self,
+        item: Reservation,
+    ) -> impl Future<Output = RepoResult<RepoReservation>> + Send;
+    fn update(
+        &mut self,
+        item: RepoReservation,
+    ) -> impl Future<Output = RepoResult<RepoReservation>> + Send;
+    fn remove(&mut self, id: Id) -> impl Future<Output = RepoResult<RepoReservation>> + Send;
+
+    // the reservations overlapping the window, at every table, by when they start
+    fn overlapping(
+        &self,
+        window: Window,
+    ) -> impl Future<Output = RepoResult<Vec<RepoReservation>>> + Send;
+}
+
+fn write_failed(table: layout::TableId, e: anyhow::Error) -> ReservationError {
+    match e.downcast::<Conflict>() {
+        Ok(conflict) => ReservationError::Overlaps {
+            table,
+            with: conflict.with,
+        },
+        Err(e) => ReservationError::RepoOperation(e),
+    }
+}
+
+// everything about a booking that can be checked without looking at the other bookings
+async fn check<T: layout::TableRepository>(repo: &T, reservation: &Reservation) -> Result<()> {
+    if reservation.party_size == 0 {
+        return Err(ReservationError::EmptyParty);
+    }
+    if reservation.window.ends <= reservation.window.starts {
+        return Err(ReservationError::InvalidWindow);
+    }
+
+    let table = layout::TableRepository::get(repo, reservation.table)
+        .await
+        .map_err(|_| ReservationError::TableNotFound(reservation.table))?;
+    if !table.active {
+        return Err(ReservationError::TableInactive(table.id()));
+    }
+    if table.seats < reservation.party_size {
+        return Err(ReservationError::TableTooSmall {
+            table: table.id(),
+            seats: table.seats,
+            party_size: reservation.party_size,
+        });
+    }
+    Ok(())
+}
+
+pub async fn get<T: Repository>(repo: &T, id: Id) -> Result<RepoReservation> {
+    repo.get(id)
+        .await
+        .map_err(|_| ReservationError::ReservationNotFound(id))
+}
+
+// every reservation with any part of it on the day, by when they start
+pub async fn on_day<T: Repository>(repo: &T, day: NaiveDate) -> Result<Vec<RepoReservation>> {
+    repo.overlapping(Window::day(day))
+        .await
+        .map_err(ReservationError::RepoOperation)
+}
+
+pub async fn book<T: Repository + layout::TableRepository + staff::Acting>(
+    repo: &mut T,
+    mut reservation: Reservation,
+) -> Result<RepoReservation> {
+    check(repo, &reservation).await?;
+    reservation.changed_by = repo.actor();
+
+    let table = reservation.table;
+    Repository::create(repo, reservation)
+        .await
+        .map_err(|e| write_failed(table, e))
+}
+
+// a changed booking is checked as if it were booked afresh, other than against itself
+pub async fn modify<T: Repository + layout::TableRepository + staff::Acting>(
+    repo: &mut T,
+    id: Id,
+    changes: Changes,
+) -> Result<RepoReservation> {
+    let mut reservation = get(repo, id).await?;
+    if let Some(table) = changes.table {
+        reservation.table = table;
+    }
+    if let Some(party_size) = changes.party_size {
+        reservation.party_size = party_size;
+    }
+    if let Some(window) = changes.window {
+        reservation.window = window;
+    }
+    if let Some(contact) = changes.contact {
+        reservation.contact = contact;
+    }
+    check(repo, &reservation).await?;
+    reservation.changed_by = repo.actor();
+
+    let table = reservation.table;
+    Repository::update(repo, reservation)
+        .await
+        .map_err(|e| write_failed(table, e))
+}
+
+pub async fn cancel<T: Repository>(repo: &mut T, id: Id) -> Result<RepoReservation> {
+    Repository::remove(repo, id)
+        .await
+        .map_err(|_| ReservationError::ReservationNotFound(id))
+}
@@ -0,0 +1,213 @@
+use std::future::Future;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::{
+    layout,
+    order::{self, RepoOrder},
+    reservation::Window,
+    staff::{self, Role},
+    tenant, RepoItem,
+};
+
+// NOTE: floor managers divide the floor between servers each shift by handing out sections, the same ones
+// `layout::Table` is split into. a table's server is whoever has its section at the time, so a table without
+// a section, or in a section nobody has, has no server.
+
+#[derive(Error, Debug)]
+pub enum ShiftError {
+    #[error("An error occurred when interacting with the repository.")]
+    RepoOperation(#[from] anyhow::Error),
+    #[error("Unable to find section assignment {0:?}")]
+    AssignmentNotFound(Id),
+    #[error("Unable to find staff member {0:?}")]
+    StaffNotFound(staff::Id),
+    #[error("Staff member {0:?} doesn't wait tables.")]
+    NotAServer(staff::Id),
+    #[error("No table is in the {0} section.")]
+    UnknownSection(String),
+    #[error("A shift has to end after it starts.")]
+    InvalidWindow,
+    #[error("The {section} section already has a server then, through assignment {with:?}.")]
+    SectionTaken { section: String, with: Id },
+}
+pub type Result<T> = std::result::Result<T, ShiftError>;
+pub type RepoResult<T> = std::result::Result<T, anyhow::Error>;
+
+// raised by repositories when a section would have two servers at once
+#[derive(Error, Debug)]
+#[error("The section already has a server then, through assignment {with:?}.")]
+pub struct Conflict {
+    pub with: Id,
+}
+
+// a section handed to a server for a shift
+#[derive(Debug, Clone, Serialize)]
+pub struct Assignment {
+    pub section: String,
+    pub server: staff::Id,
+    pub shift: Window,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Id(pub u32);
+impl From<u32> for Id {
+    fn from(value: u32) -> Self {
+        Id(value)
+    }
+}
+impl From<Id> for u32 {
+    fn from(value: Id) -> Self {
+        value.0
+    }
+}
+pub type RepoAssignment = RepoItem<Assignment, Id>;
+
+pub trait Repository {
+    fn get_all(&self) -> impl Future<Output = RepoResult<Vec<RepoAssignment>>> + Send;
+    fn get(&self, id: Id) -> impl Future<Output = RepoResult<RepoAssignment>> + Send;
+
+    // rejects an assignment whose shift overlaps another for the same section with a `Conflict`,
+    // checked in the same unit of work as the write
+    fn create(
+        &mut self,
+        item: Assignment,
+    ) -> impl Future<Output = RepoResult<RepoAssignment>> + Send;
+    fn remove(&mut self, id: Id) -> impl Future<Output = RepoResult<RepoAssignment>> + Send;
+}
+
+// a table in a server's sections, along with its open orders
+#[derive(Debug, Clone)]
+pub struct ServerTable {
+    pub table: layout::RepoTable,
+    pub orders: Vec<RepoOrder>,
+}
+
+pub async fn get<T: Repository>(repo: &T, id: Id) -> Result<RepoAssignment> {
+    repo.get(id)
+        .await
+        .map_err(|_| ShiftError::AssignmentNotFound(id))
+}
+
+pub async fn get_all<T: Repository>(repo: &T) -> Result<Vec<RepoAssignment>> {
+    let mut assignments = repo.get_all().await?;
+    assignments.sort_by_key(|a| (a.shift.starts, a.section.clone()));
+    Ok(assignments)
+}
+
+// the assignments whose shift is under way at the time
+pub async fn on_shift<T: Repository>(repo: &T, at: DateTime<Utc>) -> Result<Vec<RepoAssignment>> {
+    Ok(get_all(repo)
+        .await?
+        .into_iter()
+        .filter(|a| a.shift.contains(at))
+        .collect())
+}
+
+// only to a server who works at the repository's restaurant, since sections are the restaurant's own
+pub async fn assign<
+    T: Repository + staff::Repository + layout::TableRepository + tenant::Scoped,
+>(
+    repo: &mut T,
+    assignment: Assignment,
+) -> Result<RepoAssignment> {
+    if assignment.shift.ends <= assignment.shift.starts {
+        return Err(ShiftError::InvalidWindow);
+    }
+
+    let server = match staff::Repository::get(repo, assignment.server).await {
+        Ok(server) if server.works_at(repo.tenant()) => server,
+        _ => return Err(ShiftError::StaffNotFound(assignment.server)),
+    };
+    // managers can cover a section, but the kitchen and hosts don't take tables
+    if !server.active || !server.role.covers(Role::Server) {
+        return Err(ShiftError::NotAServer(assignment.server));
+    }
+
+    let known = layout::TableRepository::get_all(repo)
+        .await?
+        .iter()
+        .any(|t| t.section.as_ref() == Some(&assignment.section));
+    if !known {
+        return Err(ShiftError::UnknownSection(assignment.section));
+    }
+
+    let section = assignment.section.clone();
+    Repository::create(repo, assignment)
+        .await
+        .map_err(|e| match e.downcast::<Conflict>() {
+            Ok(conflict) => ShiftError::SectionTaken {
+                section,
+                with: conflict.with,
+            },
+            Err(e) => ShiftError::RepoOperation(e),
+        })
+}
+
+pub async fn unassign<T: Repository>(repo: &mut T, id: Id) -> Result<RepoAssignment> {
+    repo.remove(id)
+        .await
+        .map_err(|_| ShiftError::AssignmentNotFound(id))
+}
+
+// who's serving the table at the time, if anyone
+pub async fn server_of<T: Repository>(
+    repo: &T,
+    table: &layout::Table,
+    at: DateTime<Utc>,
+) -> Result<Option<staff::Id>> {
+    let Some(section) = &table.section else {
+        return Ok(None);
+    };
+    Ok(on_shift(repo, at)
+        .await?
+        .into_iter()
+        .find(|a| &a.section == section)
+        .map(|a| a.server))
+}
+
+// the tables a server has at the time, by id, with whatever's been ordered at each
+pub async fn tables_of<
+    T: Repository + staff::Repository + layout::TableRepository + order::Repository,
+>(
+    repo: &T,
+    server: staff::Id,
+    at: DateTime<Utc>,
+) -> Result<Vec<ServerTable>> {
+    staff::Repository::get(repo, server)
+        .await
+        .map_err(|_| ShiftError::StaffNotFound(server))?;
+
+    let sections: Vec<String> = on_shift(repo, at)
+        .await?
+        .into_iter()
+        .filter(|a| a.server == server)
+        .map(|a| a.section.clone())
+        .collect();
+
+    let mut tables: Vec<layout::RepoTable> = layout::TableRepository::get_all(repo)
+        .await?
+        .into_iter()
+        .filter(|t| t.section.as_ref().is_some_and(|s| sections.contains(s)))
+        .collect();
+    tables.sort_by_key(|t| t.id());
+
+    let mut mine = Vec::with_capacity(tables.len());
+    for table in tables {
+        let orders = repo.get_table(table.id()).await?;
+        mine.push(ServerTable { table, orders });
+    }
+    Ok(mine)
+}
+
+// who to tell about an order event: the server of the order's table when it happened, if the event
+// is the order becoming ready to take out. changes to an order that's already ready, such as taking a
+// portion off, aren't news to anyone
+pub async fn to_notify<T: Repository>(repo: &T, event: &order::Event) -> Result<Option<staff::Id>> {
+    if !event.became_ready() {
+        return Ok(None);
+    }
+    server_of(repo, &event.order.table, event.time).await
+}
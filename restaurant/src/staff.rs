@@ -0,0 +1,288 @@
+use std::future::Future;
+
+use argon2::{
+    password_hash::{PasswordHash, PasswordHasher, PasswordVerifier, SaltString},
+    Argon2,
+};
+use chrono::{DateTime, TimeDelta, Utc};
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::{tenant, RepoItem};
+
+#[derive(Error, Debug)]
+pub enum StaffError {
+    #[error("An error occurred when interacting with the repository.")]
+    RepoOperation(#[from] anyhow::Error),
+    #[error("Unable to find staff member {0:?}")]
+    StaffNotFound(Id),
+    #[error("There's already someone called '{0}'.")]
+    NameTaken(String),
+    #[error("A PIN or password needs at least {MIN_SECRET_LENGTH} characters.")]
+    WeakSecret,
+    #[error("The name or PIN is wrong.")]
+    InvalidCredentials,
+    #[error("Only someone above a {0:?} can hire or dismiss one.")]
+    Outranked(Role),
+    #[error("Too many wrong PINs in a row. Try again after {0}.")]
+    LockedOut(DateTime<Utc>),
+    #[error("Only someone who works at restaurant {0:?} can add staff to it.")]
+    Elsewhere(tenant::Id),
+}
+pub type Result<T> = std::result::Result<T, StaffError>;
+pub type RepoResult<T> = std::result::Result<T, anyhow::Error>;
+
+pub const MIN_SECRET_LENGTH: usize = 4;
+
+// a four digit PIN only has ten thousand possibilities, so guessing it has to be slow going. after this many
+// sign-ins in a row within `LOCKOUT` of each other, the next has to wait until the oldest of them is `LOCKOUT` ago
+pub const MAX_FAILED_LOGINS: usize = 5;
+pub const LOCKOUT: TimeDelta = TimeDelta::minutes(15);
+
+// raised by repositories when a new staff member's name is already in use
+#[derive(Error, Debug)]
+#[error("There's already someone called '{0}'.")]
+pub struct NameTaken(pub String);
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash)]
+#[serde(rename_all = "lowercase")]
+pub enum Role {
+    Server,
+    Kitchen,
+    Host,
+    Manager,
+    Admin,
+}
+
+impl Role {
+    // managers can do anything the floor and kitchen can, and admins can do anything at all
+    pub fn covers(self, role: Role) -> bool {
+        match self {
+            Role::Admin => true,
+            Role::Manager => role != Role::Admin,
+            _ => self == role,
+        }
+    }
+
+    // who can take someone on or let them go. admins can do it for anyone, managers for the floor and kitchen,
+    // and nobody else for anyone
+    pub fn outranks(self, role: Role) -> bool {
+        match self {
+            Role::Admin => true,
+            Role::Manager => !matches!(role, Role::Manager | Role::Admin),
+            _ => false,
+        }
+    }
+}
+
+// a PIN or password, hashed with Argon2id and a salt of its own, so a leaked store isn't a list of PINs.
+// the hash is kept as a PHC string, which carries the parameters it was made with, so they can be raised
+// later without breaking secrets hashed before
+#[derive(Clone)]
+pub struct Secret(String);
+
+impl Secret {
+    pub fn new(secret: &str) -> Secret {
+        let mut salt = [0; 16];
+        rand::thread_rng().fill_bytes(&mut salt);
+        let salt = SaltString::encode_b64(&salt).expect("16 bytes should make a valid salt.");
+        let hash = Argon2::default()
+            .hash_password(secret.as_bytes(), &salt)
+            .expect("The default Argon2 parameters should hash anything.");
+        Secret(hash.to_string())
+    }
+
+    pub fn matches(&self, secret: &str) -> bool {
+        PasswordHash::new(&self.0).is_ok_and(|hash| {
+            Argon2::default()
+                .verify_password(secret.as_bytes(), &hash)
+                .is_ok()
+        })
+    }
+}
+
+impl std::fmt::Debug for Secret {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("Secret")
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Staff {
+    pub name: String,
+    pub role: Role,
+    // people who've left keep their records, but can't sign in
+    pub active: bool,
+    // the restaurants they work at, starting with the one they were hired at. admins can reach every one
+    pub restaurants: Vec<tenant::Id>,
+    #[serde(skip)]
+    pub secret: Secret,
+}
+
+impl Staff {
+    pub fn works_at(&self, tenant: tenant::Id) -> bool {
+        self.role == Role::Admin || self.restaurants.contains(&tenant)
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Id(pub u32);
+impl From<u32> for Id {
+    fn from(value: u32) -> Self {
+        Id(value)
+    }
+}
+impl From<Id> for u32 {
+    fn from(value: Id) -> Self {
+        value.0
+    }
+}
+pub type RepoStaff = RepoItem<Staff, Id>;
+
+pub trait Repository {
+    fn get_all(&self) -> impl Future<Output = RepoResult<Vec<RepoStaff>>> + Send;
+    fn get(&self, id: Id) -> impl Future<Output = RepoResult<RepoStaff>> + Send;
+    fn by_name(&self, name: &str) -> impl Future<Output = RepoResult<Option<RepoStaff>>> + Send;
+
+    // fails with `NameTaken` if someone already has the name, checked in the same unit of work as the write
+    fn create(&mut self, item: Staff) -> impl Future<Output = RepoResult<RepoStaff>> + Send;
+    fn update(&mut self, item: RepoStaff) -> impl Future<Output = RepoResult<RepoStaff>> + Send;
+
+    // records an attempt to sign in as the staff member, returning when each attempt since the last one that
+    // was right was made, oldest first and this one included. any made before `since` are forgotten. recording
+    // and reading them back is one unit of work, so attempts made at the same time can't each miss the others
+    fn attempt_login(
+        &mut self,
+        id: Id,
+        at: DateTime<Utc>,
+        since: DateTime<Utc>,
+    ) -> impl Future<Output = RepoResult<Vec<DateTime<Utc>>>> + Send;
+    // forgets the staff member's attempts, once one of them was right
+    fn logged_in(&mut self, id: Id) -> impl Future<Output = RepoResult<()>> + Send;
+}
+
+// whoever is making the changes going through a repository, if anyone's signed in.
+// domain functions stamp it on what they change
+pub trait Acting {
+    fn actor(&self) -> Option<Id>;
+}
+
+pub async fn get<T: Repository>(repo: &T, id: Id) -> Result<RepoStaff> {
+    repo.get(id)
+        .await
+        .map_err(|_| StaffError::StaffNotFound(id))
+}
+
+// everyone who works at the repository's restaurant
+pub async fn get_all<T: Repository + tenant::Scoped>(repo: &T) -> Result<Vec<RepoStaff>> {
+    let mut all = repo.get_all().await.map_err(StaffError::RepoOperation)?;
+    all.retain(|s| s.works_at(repo.tenant()));
+    Ok(all)
+}
+
+// changes made by someone signed in have to be for a role they outrank. without anyone signed in, as when the
+// server sets up its first admin, there's nobody to check
+async fn check_rank<T: Repository + Acting>(repo: &T, role: Role) -> Result<()> {
+    let Some(actor) = repo.actor() else {
+        return Ok(());
+    };
+    match get(repo, actor).await?.role.outranks(role) {
+        true => Ok(()),
+        false => Err(StaffError::Outranked(role)),
+    }
+}
+
+// at the repository's restaurant
+pub async fn hire<T: Repository + Acting + tenant::Scoped>(
+    repo: &mut T,
+    name: &str,
+    role: Role,
+    secret: &str,
+) -> Result<RepoStaff> {
+    check_rank(repo, role).await?;
+    if secret.chars().count() < MIN_SECRET_LENGTH {
+        return Err(StaffError::WeakSecret);
+    }
+
+    repo.create(Staff {
+        name: name.to_string(),
+        role,
+        active: true,
+        restaurants: vec![repo.tenant()],
+        secret: Secret::new(secret),
+    })
+    .await
+    .map_err(|e| match e.downcast::<NameTaken>() {
+        Ok(taken) => StaffError::NameTaken(taken.0),
+        Err(e) => StaffError::RepoOperation(e),
+    })
+}
+
+// unknown names, wrong secrets and people who've left all fail the same way, so none can be told apart.
+// a name that's been guessed at too often is locked out for a while, even with the right secret, which
+// does tell that it exists, but whoever it belongs to needs to know why they can't get in
+pub async fn login<T: Repository>(repo: &mut T, name: &str, secret: &str) -> Result<RepoStaff> {
+    let Some(staff) = repo.by_name(name).await? else {
+        // doing the same work as for a real name, so how long it takes doesn't give away who exists
+        Secret::new(secret);
+        return Err(StaffError::InvalidCredentials);
+    };
+
+    // counting the attempt before checking it, so guesses made all at once can't get past the limit. attempts
+    // made while locked out count too
+    let now = Utc::now();
+    let attempts = repo.attempt_login(staff.id(), now, now - LOCKOUT).await?;
+    let before = &attempts[..attempts.len().saturating_sub(1)];
+    if before.len() >= MAX_FAILED_LOGINS {
+        return Err(StaffError::LockedOut(
+            before[before.len() - MAX_FAILED_LOGINS] + LOCKOUT,
+        ));
+    }
+
+    match staff.active && staff.secret.matches(secret) {
+        true => {
+            repo.logged_in(staff.id()).await?;
+            Ok(staff)
+        }
+        false => Err(StaffError::InvalidCredentials),
+    }
+}
+
+// only from the repository's restaurant, so other restaurants' staff can't be let go from it
+pub async fn dismiss<T: Repository + Acting + tenant::Scoped>(
+    repo: &mut T,
+    id: Id,
+) -> Result<RepoStaff> {
+    let mut staff = get(repo, id).await?;
+    if !staff.works_at(repo.tenant()) {
+        return Err(StaffError::StaffNotFound(id));
+    }
+    check_rank(repo, staff.role).await?;
+    staff.active = false;
+    repo.update(staff).await.map_err(StaffError::RepoOperation)
+}
+
+// lets someone from the repository's restaurant work at another one as well. whoever does it has to outrank
+// them, and work at the other restaurant too
+pub async fn add_restaurant<T: Repository + Acting + tenant::Scoped>(
+    repo: &mut T,
+    id: Id,
+    tenant: tenant::Id,
+) -> Result<RepoStaff> {
+    let mut staff = get(repo, id).await?;
+    if !staff.works_at(repo.tenant()) {
+        return Err(StaffError::StaffNotFound(id));
+    }
+    check_rank(repo, staff.role).await?;
+    if let Some(actor) = repo.actor() {
+        if !get(repo, actor).await?.works_at(tenant) {
+            return Err(StaffError::Elsewhere(tenant));
+        }
+    }
+
+    if !staff.restaurants.contains(&tenant) {
+        staff.restaurants.push(tenant);
+    }
+    repo.update(staff).await.map_err(StaffError::RepoOperation)
+}
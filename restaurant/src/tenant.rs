@@ -0,0 +1,65 @@
+use std::future::Future;
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+// NOTE: each restaurant, or branch, is a tenant with its own menu, tables and orders, along with everything
+// hanging off them such as reservations, the waitlist and sections. repositories are scoped to a single
+// tenant, and ids are only unique within one, so order 1 at one branch has nothing to do with order 1 at
+// another. staff and api keys are kept together, since the same people and systems can work across branches,
+// but each says which restaurants it works at, and is only let in at those.
+
+#[derive(Error, Debug)]
+pub enum TenantError {
+    #[error("An error occurred when interacting with the repository.")]
+    RepoOperation(#[from] anyhow::Error),
+    #[error("Unable to find restaurant {0:?}")]
+    NotFound(Id),
+}
+pub type Result<T> = std::result::Result<T, TenantError>;
+pub type RepoResult<T> = std::result::Result<T, anyhow::Error>;
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Id(pub u32);
+impl From<u32> for Id {
+    fn from(value: u32) -> Self {
+        Id(value)
+    }
+}
+impl From<Id> for u32 {
+    fn from(value: Id) -> Self {
+        value.0
+    }
+}
+
+// the restaurant anything that doesn't say otherwise is for, which is all there was before branches
+pub const DEFAULT: Id = Id(1);
+
+impl Default for Id {
+    fn default() -> Self {
+        DEFAULT
+    }
+}
+
+// implemented by repositories that only ever see one restaurant's data
+pub trait Scoped {
+    fn tenant(&self) -> Id;
+}
+
+pub trait Repository {
+    // every restaurant there's data for
+    fn get_all(&self) -> impl Future<Output = RepoResult<Vec<Id>>> + Send;
+}
+
+pub async fn get_all<T: Repository>(repo: &T) -> Result<Vec<Id>> {
+    let mut tenants = repo.get_all().await?;
+    tenants.sort();
+    Ok(tenants)
+}
+
+pub async fn exists<T: Repository>(repo: &T, id: Id) -> Result<()> {
+    match repo.get_all().await?.contains(&id) {
+        true => Ok(()),
+        false => Err(TenantError::NotFound(id)),
+    }
+}
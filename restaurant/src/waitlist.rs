@@ -0,0 +1,350 @@
+use std::{collections::HashMap, future::Future};
+
+use chrono::{DateTime, TimeDelta, Utc};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::{layout, order, staff, tenant, RepoItem};
+
+#[derive(Error, Debug)]
+pub enum WaitlistError {
+    #[error("An error occurred when interacting with the repository.")]
+    RepoOperation(#[from] anyhow::Error),
+    #[error("Unable to find party {0:?}")]
+    PartyNotFound(Id),
+    #[error("A party needs at least one person.")]
+    EmptyParty,
+    #[error("No table in service sits a party of {0}.")]
+    NoTableFits(u32),
+    #[error("Party {0:?} isn't waiting any more.")]
+    NotWaiting(Id),
+    #[error("Unable to announce that party {party:?} was notified.")]
+    Announce {
+        party: Id,
+        #[source]
+        source: anyhow::Error,
+    },
+}
+pub type Result<T> = std::result::Result<T, WaitlistError>;
+pub type RepoResult<T> = std::result::Result<T, anyhow::Error>;
+
+#[derive(Debug, Clone)]
+pub struct Rules {
+    // how long a party usually keeps a table, from their first order until it's cleared
+    pub typical_sitting: TimeDelta,
+}
+
+impl Default for Rules {
+    fn default() -> Self {
+        Rules {
+            typical_sitting: TimeDelta::minutes(60),
+        }
+    }
+}
+
+// a party is waiting until a table that fits them frees up, when they're notified and it's held for them.
+// after that they're either seated or they didn't show
+#[derive(Debug, Clone, Copy, Serialize, PartialEq, Eq)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum Status {
+    Waiting,
+    Notified {
+        table: layout::TableId,
+        at: DateTime<Utc>,
+    },
+    Seated {
+        at: DateTime<Utc>,
+    },
+    NoShow {
+        at: DateTime<Utc>,
+    },
+}
+
+impl Status {
+    pub fn in_line(&self) -> bool {
+        matches!(self, Status::Waiting | Status::Notified { .. })
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Party {
+    pub name: String,
+    pub size: u32,
+    pub phone: Option<String>,
+    pub joined: DateTime<Utc>,
+    #[serde(flatten)]
+    pub status: Status,
+    // who added the party or last changed their status, when it went through a signed-in staff member
+    pub changed_by: Option<staff::Id>,
+}
+
+impl Party {
+    pub fn new(name: String, size: u32, phone: Option<String>) -> Party {
+        Party {
+            name,
+            size,
+            phone,
+            joined: Utc::now(),
+            status: Status::Waiting,
+            changed_by: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Id(pub u32);
+impl From<u32> for Id {
+    fn from(value: u32) -> Self {
+        Id(value)
+    }
+}
+impl From<Id> for u32 {
+    fn from(value: Id) -> Self {
+        value.0
+    }
+}
+pub type RepoParty = RepoItem<Party, Id>;
+
+pub trait Repository {
+    fn get_all(&self) -> impl Future<Output = RepoResult<Vec<RepoParty>>> + Send;
+    fn get(&self, id: Id) -> impl Future<Output = RepoResult<RepoParty>> + Send;
+
+    fn create(&mut self, item: Party) -> impl Future<Output = RepoResult<RepoParty>> + Send;
+    fn update(&mut self, item: RepoParty) -> impl Future<Output = RepoResult<RepoParty>> + Send;
+
+    // parties that have been notified of a table, and have yet to be announced. a party's recorded here by the
+    // update that notifies them, the same way order events are, see the outbox module
+    fn unannounced(&self) -> impl Future<Output = RepoResult<Vec<RepoParty>>> + Send;
+    fn mark_announced(&mut self, id: Id) -> impl Future<Output = RepoResult<()>> + Send;
+}
+
+// whatever passes on that a party's table is ready, such as webhooks to whatever texts them
+pub trait Announcer {
+    fn announce(
+        &self,
+        tenant: tenant::Id,
+        party: &RepoParty,
+    ) -> impl Future<Output = anyhow::Result<()>> + Send;
+}
+
+pub async fn get<T: Repository>(repo: &T, id: Id) -> Result<RepoParty> {
+    repo.get(id)
+        .await
+        .map_err(|_| WaitlistError::PartyNotFound(id))
+}
+
+// the parties still in line, first come first served
+pub async fn in_line<T: Repository>(repo: &T) -> Result<Vec<RepoParty>> {
+    let mut parties: Vec<RepoParty> = repo
+        .get_all()
+        .await?
+        .into_iter()
+        .filter(|p| p.status.in_line())
+        .collect();
+    parties.sort_by_key(|p| (p.joined, p.id()));
+    Ok(parties)
+}
+
+pub async fn add<T: Repository + order::Repository + layout::TableRepository + staff::Acting>(
+    repo: &mut T,
+    rules: &Rules,
+    mut party: Party,
+) -> Result<RepoParty> {
+    // a party no table can ever take would wait forever
+    quote(repo, rules, party.size).await?;
+    party.changed_by = repo.actor();
+    Repository::create(repo, party)
+        .await
+        .map_err(WaitlistError::RepoOperation)
+}
+
+// how long a party of `size` would wait if they joined the end of the line now
+pub async fn quote<T: Repository + order::Repository + layout::TableRepository>(
+    repo: &T,
+    rules: &Rules,
+    size: u32,
+) -> Result<TimeDelta> {
+    let line = in_line(repo).await?;
+    wait(repo, rules, size, &line, None).await
+}
+
+// how long a party already in line has left to wait. a notified party's table is ready for them
+pub async fn estimate<T: Repository + order::Repository + layout::TableRepository>(
+    repo: &T,
+    rules: &Rules,
+    id: Id,
+) -> Result<TimeDelta> {
+    let party = get(repo, id).await?;
+    match party.status {
+        Status::Waiting => {}
+        Status::Notified { .. } => return Ok(TimeDelta::zero()),
+        _ => return Err(WaitlistError::NotWaiting(id)),
+    }
+
+    let line = in_line(repo).await?;
+    wait(repo, rules, party.size, &line, Some(id)).await
+}
+
+// plays the line forward up to the party: every table frees up a typical sitting after its party sat down,
+// and each waiting party ahead in turn takes the first table that fits them to free up
+async fn wait<T: order::Repository + layout::TableRepository>(
+    repo: &T,
+    rules: &Rules,
+    size: u32,
+    line: &[RepoParty],
+    party: Option<Id>,
+) -> Result<TimeDelta> {
+    if size == 0 {
+        return Err(WaitlistError::EmptyParty);
+    }
+    let now = Utc::now();
+
+    // a session starts with a table's first order
+    let mut sessions: HashMap<layout::TableId, DateTime<Utc>> = HashMap::new();
+    for o in order::Repository::get_all(repo).await? {
+        sessions
+            .entry(o.table.id())
+            .and_modify(|started| *started = (*started).min(o.time_placed))
+            .or_insert(o.time_placed);
+    }
+    // a table held for a notified party is as good as taken
+    for held in line {
+        if let Status::Notified { table, at } = held.status {
+            sessions.entry(table).or_insert(at);
+        }
+    }
+    // the other tables of a group sit with the lead's party
+    let mut grouped = Vec::new();
+    for group in layout::TableRepository::table_groups(repo).await? {
+        grouped.extend(group.tables.iter().skip(1).copied());
+    }
+
+    let mut tables: Vec<(u32, DateTime<Utc>)> = layout::TableRepository::get_all(repo)
+        .await?
+        .iter()
+        .filter(|t| t.active && !grouped.contains(&t.id()))
+        .map(|t| {
+            let free = sessions
+                .get(&t.id())
+                .map_or(now, |started| *started + rules.typical_sitting);
+            (t.seats, free.max(now))
+        })
+        .collect();
+
+    let waiting = line
+        .iter()
+        .take_while(|p| Some(p.id()) != party)
+        .filter(|p| p.status == Status::Waiting)
+        .map(|p| p.size);
+    for party_size in waiting {
+        // a party nothing fits doesn't hold anyone up
+        if let Some(table) = tables
+            .iter_mut()
+            .filter(|(seats, _)| *seats >= party_size)
+            .min_by_key(|(_, free)| *free)
+        {
+            table.1 += rules.typical_sitting;
+        }
+    }
+
+    tables
+        .iter()
+        .filter(|(seats, _)| *seats >= size)
+        .map(|(_, free)| *free - now)
+        .min()
+        .ok_or(WaitlistError::NoTableFits(size))
+}
+
+// called when a table's been cleared. holds it for the first party in line that fits, if any, and notifies them
+pub async fn table_freed<T: Repository + layout::TableRepository + staff::Acting>(
+    repo: &mut T,
+    table: layout::TableId,
+) -> Result<Option<RepoParty>> {
+    let table = layout::TableRepository::get(repo, table).await?;
+    if !table.active {
+        return Ok(None);
+    }
+
+    let parties = in_line(repo).await?;
+    if parties
+        .iter()
+        .any(|p| matches!(p.status, Status::Notified { table: held, .. } if held == table.id()))
+    {
+        return Ok(None);
+    }
+    let Some(mut party) = parties
+        .into_iter()
+        .find(|p| p.status == Status::Waiting && p.size <= table.seats)
+    else {
+        return Ok(None);
+    };
+
+    party.status = Status::Notified {
+        table: table.id(),
+        at: Utc::now(),
+    };
+    party.changed_by = repo.actor();
+    Repository::update(repo, party)
+        .await
+        .map(Some)
+        .map_err(WaitlistError::RepoOperation)
+}
+
+pub async fn seat<T: Repository + staff::Acting>(repo: &mut T, id: Id) -> Result<RepoParty> {
+    let mut party = get(repo, id).await?;
+    if !party.status.in_line() {
+        return Err(WaitlistError::NotWaiting(id));
+    }
+
+    party.status = Status::Seated { at: Utc::now() };
+    party.changed_by = repo.actor();
+    Repository::update(repo, party)
+        .await
+        .map_err(WaitlistError::RepoOperation)
+}
+
+// a notified party that doesn't turn up gives their table to the next party that fits
+pub async fn no_show<T: Repository + layout::TableRepository + staff::Acting>(
+    repo: &mut T,
+    id: Id,
+) -> Result<RepoParty> {
+    let mut party = get(repo, id).await?;
+    let held = match party.status {
+        Status::Waiting => None,
+        Status::Notified { table, .. } => Some(table),
+        _ => return Err(WaitlistError::NotWaiting(id)),
+    };
+
+    party.status = Status::NoShow { at: Utc::now() };
+    party.changed_by = repo.actor();
+    let party = Repository::update(repo, party)
+        .await
+        .map_err(WaitlistError::RepoOperation)?;
+    if let Some(table) = held {
+        table_freed(repo, table).await?;
+    }
+    Ok(party)
+}
+
+// announces every party notified since last time, returning who was. like relaying order events it's
+// at-least-once: a party is only marked announced once it's been handed off, so a failure means it's tried again
+pub async fn announce<T: Repository + tenant::Scoped, A: Announcer>(
+    repo: &mut T,
+    announcer: &A,
+) -> Result<Vec<Id>> {
+    let mut announced = Vec::new();
+    for party in repo.unannounced().await? {
+        announcer
+            .announce(repo.tenant(), &party)
+            .await
+            .map_err(|source| WaitlistError::Announce {
+                party: party.id(),
+                source,
+            })?;
+
+        repo.mark_announced(party.id()).await?;
+        announced.push(party.id());
+    }
+
+    Ok(announced)
+}
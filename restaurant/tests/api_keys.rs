@@ -0,0 +1,101 @@
+use futures::executor::LocalPool;
+use pretty_assertions::assert_eq;
+use restaurant::api_key::{self, ApiKeyError, Scope};
+use restaurant::memdb::Database;
+use restaurant::staff::{self, Role};
+
+mod common;
+
+#[test]
+fn keys_sign_in_with_only_their_scopes() -> Result<(), ApiKeyError> {
+    let mut pool = LocalPool::new();
+    pool.run_until(async {
+        let mut db = Database::new(vec![], vec![], vec![]);
+        let mina = staff::hire(&mut db, "Mina", Role::Manager, "1234")
+            .await
+            .unwrap();
+        let (created, key) = api_key::create(
+            &mut db.acting_as(mina.id()),
+            "POS",
+            &[Scope::OrdersWrite, Scope::OrdersWrite],
+        )
+        .await?;
+        assert_eq!(vec![Scope::OrdersWrite], created.scopes);
+        assert_eq!(Some(mina.id()), created.created_by);
+
+        let signed_in = api_key::authenticate(&db, &key).await?;
+        assert_eq!(created.id(), signed_in.id());
+        assert!(signed_in.allows(Scope::OrdersRead));
+        assert!(!signed_in.allows(Scope::MenuAdmin));
+
+        assert!(matches!(
+            api_key::authenticate(&db, "rk_1_00").await,
+            Err(ApiKeyError::InvalidKey)
+        ));
+        assert!(matches!(
+            api_key::authenticate(&db, "not a key").await,
+            Err(ApiKeyError::InvalidKey)
+        ));
+
+        Ok(())
+    })
+}
+
+#[test]
+fn rotating_replaces_the_key_and_revoking_ends_it() -> Result<(), ApiKeyError> {
+    let mut pool = LocalPool::new();
+    pool.run_until(async {
+        let mut db = Database::new(vec![], vec![], vec![]);
+        let (created, old) = api_key::create(&mut db, "POS", &[Scope::OrdersRead]).await?;
+
+        let (rotated, new) = api_key::rotate(&mut db, created.id()).await?;
+        assert_eq!(created.id(), rotated.id());
+        assert!(rotated.rotated.is_some());
+        assert!(matches!(
+            api_key::authenticate(&db, &old).await,
+            Err(ApiKeyError::InvalidKey)
+        ));
+        api_key::authenticate(&db, &new).await?;
+
+        api_key::revoke(&mut db, created.id()).await?;
+        assert!(matches!(
+            api_key::authenticate(&db, &new).await,
+            Err(ApiKeyError::InvalidKey)
+        ));
+        assert!(matches!(
+            api_key::rotate(&mut db, created.id()).await,
+            Err(ApiKeyError::Revoked(id)) if id == created.id()
+        ));
+        assert!(matches!(
+            api_key::revoke(&mut db, created.id()).await,
+            Err(ApiKeyError::Revoked(_))
+        ));
+        // revoked keys are still listed
+        assert_eq!(1, api_key::get_all(&db).await?.len());
+
+        Ok(())
+    })
+}
+
+#[test]
+fn keys_need_a_scope() -> Result<(), ApiKeyError> {
+    let mut pool = LocalPool::new();
+    pool.run_until(async {
+        let mut db = Database::new(vec![], vec![], vec![]);
+
+        assert!(matches!(
+            api_key::create(&mut db, "POS", &[]).await,
+            Err(ApiKeyError::NoScopes)
+        ));
+        assert!(matches!(
+            api_key::rotate(&mut db, 9.into()).await,
+            Err(ApiKeyError::KeyNotFound(_))
+        ));
+
+        assert!(Scope::MenuAdmin.covers(Scope::MenuRead));
+        assert!(!Scope::MenuRead.covers(Scope::MenuAdmin));
+        assert!(!Scope::OrdersWrite.covers(Scope::KitchenWrite));
+
+        Ok(())
+    })
+}
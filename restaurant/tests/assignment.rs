@@ -0,0 +1,223 @@
+use chrono::{TimeDelta, Utc};
+use futures::executor::LocalPool;
+use pretty_assertions::assert_eq;
+use restaurant::layout::assignment::{
+    self, default_strategies, Floor, Party, Strategy, TableState, Verdict,
+};
+use restaurant::layout::{self, LayoutError};
+use restaurant::memdb::Database;
+use restaurant::staff::{self, Role};
+use restaurant::{order, reservation, shift, RepoItem};
+
+mod common;
+
+fn table(id: u32, seats: u32, section: &str) -> layout::RepoTable {
+    RepoItem::new(
+        id.into(),
+        layout::Table {
+            seats,
+            section: Some(section.to_string()),
+            ..common::layout_table()
+        },
+    )
+}
+
+async fn seat(db: &mut Database, table: layout::RepoTable) {
+    order::place(db, &order::Rules::default(), table, common::pasta(), 1)
+        .await
+        .unwrap();
+}
+
+async fn suggested(db: &Database, party: &Party) -> Result<Vec<u32>, LayoutError> {
+    Ok(assignment::suggest(db, party, &default_strategies())
+        .await?
+        .iter()
+        .map(|s| s.table.id().0)
+        .collect())
+}
+
+#[test]
+fn the_smallest_free_table_that_fits_comes_first() -> Result<(), LayoutError> {
+    let mut pool = LocalPool::new();
+    pool.run_until(async {
+        let mut closed = table(4, 4, "main");
+        closed.active = false;
+        let mut db = Database::new(
+            vec![common::pasta()],
+            vec![
+                table(1, 2, "main"),
+                table(2, 4, "main"),
+                table(3, 6, "main"),
+                closed,
+                table(5, 2, "main"),
+            ],
+            vec![],
+        );
+        seat(&mut db, table(5, 2, "main")).await;
+
+        assert_eq!(vec![1, 2, 3], suggested(&db, &Party::new(2)).await?);
+        assert_eq!(vec![2, 3], suggested(&db, &Party::new(3)).await?);
+        assert!(suggested(&db, &Party::new(7)).await?.is_empty());
+        assert!(matches!(
+            suggested(&db, &Party::new(0)).await,
+            Err(LayoutError::EmptyParty)
+        ));
+
+        let best = &assignment::suggest(&db, &Party::new(3), &default_strategies()).await?[0];
+        assert_eq!(
+            vec![
+                "Sits 4, leaving 1 spare.".to_string(),
+                "The main section already has 1 occupied.".to_string(),
+            ],
+            best.reasons
+        );
+
+        Ok(())
+    })
+}
+
+#[test]
+fn reservations_and_sections_rule_tables_out() -> Result<(), LayoutError> {
+    let mut pool = LocalPool::new();
+    pool.run_until(async {
+        let mut db = Database::new(
+            vec![common::pasta()],
+            vec![
+                table(1, 2, "patio"),
+                table(2, 2, "main"),
+                table(3, 4, "main"),
+            ],
+            vec![],
+        );
+        // table 2's booked from half an hour from now, which a party that's staying for an hour and a half would run into
+        let soon = Utc::now() + TimeDelta::minutes(30);
+        reservation::book(
+            &mut db,
+            common::reservation(
+                2,
+                2,
+                reservation::Window {
+                    starts: soon,
+                    ends: soon + TimeDelta::hours(2),
+                },
+            ),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(vec![1, 3], suggested(&db, &Party::new(2)).await?);
+        let quick = Party {
+            stay: TimeDelta::minutes(20),
+            ..Party::new(2)
+        };
+        assert_eq!(vec![1, 2, 3], suggested(&db, &quick).await?);
+
+        let patio = Party {
+            section: Some("patio".to_string()),
+            ..Party::new(2)
+        };
+        assert_eq!(vec![1], suggested(&db, &patio).await?);
+
+        Ok(())
+    })
+}
+
+// tables by the window, for parties that ask
+struct WindowSeats(Vec<u32>);
+
+impl Strategy for WindowSeats {
+    fn assess(&self, _: &Party, state: &TableState, _: &Floor) -> Verdict {
+        match self.0.contains(&state.table.id().0) {
+            true => Verdict::Suitable {
+                penalty: 0,
+                reason: Some("By the window.".to_string()),
+            },
+            false => Verdict::Suitable {
+                penalty: 50,
+                reason: None,
+            },
+        }
+    }
+}
+
+#[test]
+fn busy_sections_rank_lower_and_strategies_can_be_added() -> Result<(), LayoutError> {
+    let mut pool = LocalPool::new();
+    pool.run_until(async {
+        let mut db = Database::new(
+            vec![common::pasta()],
+            vec![
+                table(1, 4, "bar"),
+                table(2, 4, "bar"),
+                table(3, 4, "garden"),
+            ],
+            vec![],
+        );
+        seat(&mut db, table(1, 4, "bar")).await;
+
+        let ranked = assignment::suggest(&db, &Party::new(4), &default_strategies()).await?;
+        assert_eq!(
+            vec![3, 2],
+            ranked.iter().map(|s| s.table.id().0).collect::<Vec<_>>()
+        );
+        assert!(ranked[1]
+            .reasons
+            .contains(&"The bar section already has 1 occupied.".to_string()));
+
+        let mut strategies = default_strategies();
+        strategies.push(Box::new(WindowSeats(vec![2])));
+        let ranked = assignment::suggest(&db, &Party::new(4), &strategies).await?;
+        assert_eq!(2, ranked[0].table.id().0);
+        assert!(ranked[0].reasons.contains(&"By the window.".to_string()));
+
+        Ok(())
+    })
+}
+
+#[test]
+fn servers_with_the_fewest_tables_come_first() -> Result<(), LayoutError> {
+    let mut pool = LocalPool::new();
+    pool.run_until(async {
+        let mut db = Database::new(
+            vec![common::pasta()],
+            vec![
+                table(1, 4, "bar"),
+                table(2, 4, "patio"),
+                table(3, 4, "garden"),
+            ],
+            vec![],
+        );
+        // Sam has the bar and the patio, and Lee the garden
+        let sam = staff::hire(&mut db, "Sam", Role::Server, "2580")
+            .await
+            .unwrap();
+        let lee = staff::hire(&mut db, "Lee", Role::Server, "1470")
+            .await
+            .unwrap();
+        for (section, server) in [("bar", &sam), ("patio", &sam), ("garden", &lee)] {
+            shift::assign(
+                &mut db,
+                shift::Assignment {
+                    section: section.to_string(),
+                    server: server.id(),
+                    shift: common::evening(),
+                },
+            )
+            .await
+            .unwrap();
+        }
+        seat(&mut db, table(1, 4, "bar")).await;
+
+        // the patio's empty, but Sam's already busy with the bar
+        let ranked = assignment::suggest(&db, &Party::new(4), &default_strategies()).await?;
+        assert_eq!(
+            vec![3, 2],
+            ranked.iter().map(|s| s.table.id().0).collect::<Vec<_>>()
+        );
+        assert!(ranked[1]
+            .reasons
+            .contains(&"Sam already has 1 occupied.".to_string()));
+
+        Ok(())
+    })
+}
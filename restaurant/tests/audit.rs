@@ -0,0 +1,314 @@
+use chrono::Utc;
+use futures::executor::LocalPool;
+use pretty_assertions::assert_eq;
+use restaurant::audit::{self, Actor, AuditError, Record, Store};
+use restaurant::memdb::Database;
+use restaurant::staff::{self, Role};
+use restaurant::{menu, order, tenant, RepoItem};
+
+mod common;
+
+fn katsudon() -> menu::RepoItem {
+    RepoItem::new(
+        1.into(),
+        menu::Item {
+            cook_time: menu::Minutes(10),
+            station: menu::Station::Fryer,
+            ..common::menu_item("Katsudon")
+        },
+    )
+}
+
+fn record(operation: &str) -> Record {
+    Record {
+        time: Utc::now(),
+        actor: None,
+        request: None,
+        tenant: None,
+        operation: operation.to_string(),
+        before: None,
+        after: None,
+    }
+}
+
+// a store that can be edited after the fact, which is exactly what a real one mustn't allow
+#[derive(Default)]
+struct Editable(Vec<audit::RepoEntry>);
+
+impl Store for Editable {
+    async fn append(&mut self, record: Record) -> audit::RepoResult<audit::RepoEntry> {
+        let previous = self
+            .0
+            .iter()
+            .rev()
+            .find(|e| e.record.tenant == record.tenant)
+            .map_or(audit::GENESIS, |e| e.hash);
+        let entry = RepoItem::new(
+            (self.0.len() as u32 + 1).into(),
+            audit::Entry::new(record, previous),
+        );
+        self.0.push(entry.clone());
+        Ok(entry)
+    }
+
+    async fn chain(&self, tenant: Option<tenant::Id>) -> audit::RepoResult<Vec<audit::RepoEntry>> {
+        Ok(self
+            .0
+            .iter()
+            .filter(|e| e.record.tenant == tenant)
+            .cloned()
+            .collect())
+    }
+
+    async fn query(
+        &self,
+        tenant: tenant::Id,
+        query: &audit::Query,
+    ) -> audit::RepoResult<Vec<audit::RepoEntry>> {
+        Ok(self
+            .0
+            .iter()
+            .rev()
+            .filter(|e| e.record.tenant.map_or(true, |t| t == tenant) && query.matches(e))
+            .take(query.limit.unwrap_or(usize::MAX))
+            .cloned()
+            .collect())
+    }
+}
+
+impl tenant::Scoped for Editable {
+    fn tenant(&self) -> tenant::Id {
+        tenant::DEFAULT
+    }
+}
+
+#[test]
+fn changes_are_recorded_with_who_made_them() -> Result<(), AuditError> {
+    let mut pool = LocalPool::new();
+    pool.run_until(async {
+        let mut db = Database::new(vec![katsudon()], vec![common::table(7)], vec![]);
+        let sam = staff::hire(&mut db, "Sam", Role::Server, "2580")
+            .await
+            .unwrap();
+        let mut sams = db.acting_as(sam.id()).for_request("req-1");
+
+        let placed = order::place(
+            &mut sams,
+            &order::Rules::default(),
+            common::table(7),
+            katsudon(),
+            2,
+        )
+        .await
+        .unwrap();
+        order::cancel(&mut sams, placed.id(), None).await.unwrap();
+
+        // who cancelled table 7's katsudon?
+        let found = audit::search(
+            &db,
+            &audit::Query {
+                operation: Some("order.remove".to_string()),
+                text: Some("katsudon".to_string()),
+                ..Default::default()
+            },
+        )
+        .await?;
+        assert_eq!(1, found.entries.len());
+        let cancelled = &found.entries[0].record;
+        assert_eq!(Some(Actor::Staff(sam.id())), cancelled.actor);
+        assert_eq!(Some("req-1".to_string()), cancelled.request);
+        assert_eq!(
+            Some(7),
+            cancelled.before.as_ref().unwrap()["table"]["id"].as_u64()
+        );
+        assert_eq!(None, cancelled.after);
+
+        // newest first, and hiring Sam wasn't put down to anyone
+        let everything = audit::search(&db, &audit::Query::default()).await?.entries;
+        assert_eq!(
+            vec!["order.remove", "order.create", "staff.create"],
+            everything
+                .iter()
+                .map(|e| e.record.operation.as_str())
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(None, everything[2].record.actor);
+        assert_eq!(3, audit::verify(&db).await?);
+
+        Ok(())
+    })
+}
+
+#[test]
+fn secrets_stay_out_of_snapshots() -> Result<(), AuditError> {
+    let mut pool = LocalPool::new();
+    pool.run_until(async {
+        let mut db = Database::new(vec![], vec![], vec![]);
+        let sam = staff::hire(&mut db, "Sam", Role::Server, "2580")
+            .await
+            .unwrap();
+        staff::dismiss(&mut db, sam.id()).await.unwrap();
+
+        let dismissed = audit::search(
+            &db,
+            &audit::Query {
+                operation: Some("staff".to_string()),
+                ..Default::default()
+            },
+        )
+        .await?
+        .entries;
+        assert_eq!(2, dismissed.len());
+        let change = &dismissed[0].record;
+        assert_eq!(
+            Some(true),
+            change.before.as_ref().unwrap()["active"].as_bool()
+        );
+        assert_eq!(
+            Some(false),
+            change.after.as_ref().unwrap()["active"].as_bool()
+        );
+        assert!(change.after.as_ref().unwrap().get("secret").is_none());
+
+        // an operation is matched whole, or by what it's done to
+        assert!(audit::search(
+            &db,
+            &audit::Query {
+                operation: Some("sta".to_string()),
+                ..Default::default()
+            },
+        )
+        .await?
+        .entries
+        .is_empty());
+
+        Ok(())
+    })
+}
+
+#[test]
+fn searches_come_a_page_at_a_time() -> Result<(), AuditError> {
+    let mut pool = LocalPool::new();
+    pool.run_until(async {
+        let mut store = Editable::default();
+        for n in 0..5 {
+            store.append(record(&format!("order.{}", n))).await?;
+        }
+
+        let operations = |page: &audit::Page| {
+            page.entries
+                .iter()
+                .map(|e| e.record.operation.clone())
+                .collect::<Vec<_>>()
+        };
+        let query = audit::Query {
+            limit: Some(2),
+            ..Default::default()
+        };
+        let first = audit::search(&store, &query).await?;
+        assert_eq!(vec!["order.4", "order.3"], operations(&first));
+
+        let second = audit::search(
+            &store,
+            &audit::Query {
+                before: first.next,
+                ..query.clone()
+            },
+        )
+        .await?;
+        assert_eq!(vec!["order.2", "order.1"], operations(&second));
+
+        let last = audit::search(
+            &store,
+            &audit::Query {
+                before: second.next,
+                ..query
+            },
+        )
+        .await?;
+        assert_eq!(vec!["order.0"], operations(&last));
+        assert_eq!(None, last.next);
+
+        // asking for more than a page holds gets a full page and no more
+        for n in 5..audit::MAX_PAGE_SIZE + 5 {
+            store.append(record(&format!("order.{}", n))).await?;
+        }
+        let most = audit::search(
+            &store,
+            &audit::Query {
+                limit: Some(1000),
+                ..Default::default()
+            },
+        )
+        .await?;
+        assert_eq!(audit::MAX_PAGE_SIZE, most.entries.len());
+        assert!(most.next.is_some());
+        assert_eq!(
+            audit::DEFAULT_PAGE_SIZE,
+            audit::search(&store, &audit::Query::default())
+                .await?
+                .entries
+                .len()
+        );
+
+        Ok(())
+    })
+}
+
+#[test]
+fn each_restaurant_has_a_chain_of_its_own() -> Result<(), AuditError> {
+    let mut pool = LocalPool::new();
+    pool.run_until(async {
+        let mut store = Editable::default();
+        let at = |tenant: Option<u32>, operation: &str| Record {
+            tenant: tenant.map(tenant::Id),
+            ..record(operation)
+        };
+        store.append(at(Some(1), "order.create")).await?;
+        store.append(at(None, "staff.create")).await?;
+        store.append(at(Some(2), "order.create")).await?;
+        store.append(at(Some(1), "order.remove")).await?;
+
+        // the second restaurant's entry chains on from nothing, not from the first's
+        assert_eq!(audit::GENESIS, store.0[2].previous);
+        assert_eq!(store.0[0].hash, store.0[3].previous);
+        assert_eq!(3, audit::verify(&store).await?);
+
+        // so tampering with another restaurant's entries doesn't touch this one's chain
+        let mut edited = Editable(store.0.clone());
+        edited.0[2].record.operation = "order.remove".to_string();
+        assert_eq!(3, audit::verify(&edited).await?);
+
+        Ok(())
+    })
+}
+
+#[test]
+fn tampering_breaks_the_chain() -> Result<(), AuditError> {
+    let mut pool = LocalPool::new();
+    pool.run_until(async {
+        let mut store = Editable::default();
+        for operation in ["order.create", "order.update", "order.remove"] {
+            store.append(record(operation)).await?;
+        }
+        assert_eq!(3, audit::verify(&store).await?);
+
+        // rewriting what an entry says
+        let mut edited = Editable(store.0.clone());
+        edited.0[1].record.operation = "order.create".to_string();
+        assert!(matches!(
+            audit::verify(&edited).await,
+            Err(AuditError::Tampered(id)) if id == 2.into()
+        ));
+
+        // dropping an entry
+        let mut dropped = Editable(store.0.clone());
+        dropped.0.remove(1);
+        assert!(matches!(
+            audit::verify(&dropped).await,
+            Err(AuditError::Tampered(id)) if id == 3.into()
+        ));
+
+        Ok(())
+    })
+}
@@ -1,6 +1,74 @@
+// not every test uses every builder
+#![allow(dead_code)]
+
 use std::fmt::{self, Debug};
 
-use restaurant::order;
+use chrono::{TimeDelta, Utc};
+use restaurant::{layout, menu, order, reservation, RepoItem};
+
+// builders for what tests set up, with whatever they don't care about defaulted. a new field only needs adding here,
+// rather than to every test that happens to make one. tests that care about a field override it with
+// struct update syntax, as in `menu::Item { station: Station::Fryer, ..common::menu_item("Fries") }`
+
+// a grill item that takes 5 minutes and can be ordered
+pub fn menu_item(name: &str) -> menu::Item {
+    menu::Item {
+        name: name.to_string(),
+        cook_time: menu::Minutes(5),
+        station: menu::Station::Grill,
+        available: true,
+        category: None,
+        sort_order: 0,
+    }
+}
+
+pub fn item(id: u32, name: &str) -> menu::RepoItem {
+    RepoItem::new(id.into(), menu_item(name))
+}
+
+pub fn pasta() -> menu::RepoItem {
+    item(1, "Pasta")
+}
+
+// an open table for four, in no section
+pub fn layout_table() -> layout::Table {
+    layout::Table {
+        active: true,
+        seats: 4,
+        section: None,
+    }
+}
+
+pub fn table(id: u32) -> layout::RepoTable {
+    RepoItem::new(id.into(), layout_table())
+}
+
+// the evening shift, which is under way for the whole test
+pub fn evening() -> reservation::Window {
+    let starts = Utc::now() - TimeDelta::hours(1);
+    reservation::Window {
+        starts,
+        ends: starts + TimeDelta::hours(6),
+    }
+}
+
+// Tanaka's booking, made by nobody in particular
+pub fn reservation(
+    table: u32,
+    party_size: u32,
+    window: reservation::Window,
+) -> reservation::Reservation {
+    reservation::Reservation {
+        table: table.into(),
+        party_size,
+        window,
+        contact: reservation::Contact {
+            name: "Tanaka".to_string(),
+            phone: "090-1234-5678".to_string(),
+        },
+        changed_by: None,
+    }
+}
 
 pub(crate) struct ComparableOrder(pub order::RepoOrder);
 
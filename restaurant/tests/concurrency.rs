@@ -0,0 +1,76 @@
+use futures::executor::LocalPool;
+use pretty_assertions::assert_eq;
+use restaurant::memdb::Database;
+use restaurant::{
+    order::{self, OrderingError, Repository},
+    VersionConflict,
+};
+
+mod common;
+
+#[test]
+fn stale_updates_are_rejected() -> Result<(), OrderingError> {
+    let mut pool = LocalPool::new();
+    pool.run_until(async {
+        let mut db = Database::default();
+        let placed = order::place(
+            &mut db,
+            &order::Rules::default(),
+            common::table(1),
+            common::pasta(),
+            1,
+        )
+        .await?;
+        assert_eq!(1, placed.version());
+
+        // two waiters read the same order, and both try to change it
+        let mut first = db.get(placed.id()).await?;
+        let mut second = db.get(placed.id()).await?;
+        first.quantity = 2;
+        second.quantity = 3;
+
+        assert_eq!(2, db.update(first).await?.version());
+        let conflict = db
+            .update(second)
+            .await
+            .expect_err("The second write should be based on a stale version")
+            .downcast::<VersionConflict>()
+            .expect("The error should be a conflict");
+        assert_eq!((1, 2), (conflict.expected, conflict.actual));
+        assert_eq!(2, db.get(placed.id()).await?.quantity);
+
+        Ok(())
+    })
+}
+
+#[test]
+fn changes_can_require_a_version() -> Result<(), OrderingError> {
+    let mut pool = LocalPool::new();
+    pool.run_until(async {
+        let rules = order::Rules::default();
+        let mut db = Database::default();
+        let placed = order::place(&mut db, &rules, common::table(1), common::pasta(), 1).await?;
+
+        let changed = order::set_quantity(&mut db, &rules, placed.id(), 2, Some(1)).await?;
+        assert_eq!(2, changed.version());
+
+        assert!(matches!(
+            order::set_quantity(&mut db, &rules, placed.id(), 3, Some(1)).await,
+            Err(OrderingError::VersionConflict {
+                expected: 1,
+                actual: 2,
+                ..
+            })
+        ));
+        assert!(matches!(
+            order::cancel(&mut db, placed.id(), Some(1)).await,
+            Err(OrderingError::VersionConflict { .. })
+        ));
+        assert_eq!(2, order::get(&db, placed.id()).await?.quantity);
+
+        order::cancel(&mut db, placed.id(), Some(2)).await?;
+        assert!(order::get(&db, placed.id()).await.is_err());
+
+        Ok(())
+    })
+}
@@ -0,0 +1,181 @@
+use futures::executor::LocalPool;
+use pretty_assertions::assert_eq;
+use restaurant::kitchen::{self, KitchenError};
+use restaurant::memdb::Database;
+use restaurant::menu::{self, Station};
+use restaurant::{order, RepoItem};
+
+mod common;
+
+fn menu_item(id: u32, name: &str, station: Station) -> menu::RepoItem {
+    RepoItem::new(
+        id.into(),
+        menu::Item {
+            station,
+            ..common::menu_item(name)
+        },
+    )
+}
+
+#[test]
+fn station_tickets_only_include_open_lines_for_station() -> Result<(), KitchenError> {
+    let mut pool = LocalPool::new();
+    pool.run_until(async {
+        let table1 = common::table(1);
+        let table2 = common::table(2);
+        let steak = menu_item(1, "Steak", Station::Grill);
+        let fries = menu_item(2, "Fries", Station::Fryer);
+        let burger = menu_item(3, "Burger", Station::Grill);
+        let mut db = Database::default();
+
+        let first = order::place(
+            &mut db,
+            &order::Rules::default(),
+            table1.clone(),
+            steak.clone(),
+            1,
+        )
+        .await
+        .unwrap();
+        order::place(
+            &mut db,
+            &order::Rules::default(),
+            table1.clone(),
+            fries.clone(),
+            2,
+        )
+        .await
+        .unwrap();
+        let second = order::place(
+            &mut db,
+            &order::Rules::default(),
+            table2.clone(),
+            burger.clone(),
+            1,
+        )
+        .await
+        .unwrap();
+        let bumped = order::place(
+            &mut db,
+            &order::Rules::default(),
+            table2.clone(),
+            steak.clone(),
+            1,
+        )
+        .await
+        .unwrap();
+        kitchen::bump(&mut db, Station::Grill, bumped.id()).await?;
+
+        let tickets = kitchen::station_tickets(&db, Station::Grill).await?;
+        assert_eq!(
+            vec![first.id(), second.id()],
+            tickets.iter().map(|t| t.id()).collect::<Vec<_>>()
+        );
+
+        Ok(())
+    })
+}
+
+#[test]
+fn stations_cannot_bump_each_others_tickets() -> Result<(), KitchenError> {
+    let mut pool = LocalPool::new();
+    pool.run_until(async {
+        let table = common::table(1);
+        let fries = menu_item(1, "Fries", Station::Fryer);
+        let mut db = Database::default();
+
+        let order = order::place(&mut db, &order::Rules::default(), table, fries, 1)
+            .await
+            .unwrap();
+
+        assert!(matches!(
+            kitchen::bump(&mut db, Station::Grill, order.id()).await,
+            Err(KitchenError::WrongStation { .. })
+        ));
+        assert_eq!(
+            order::Status::Ready,
+            kitchen::bump(&mut db, Station::Fryer, order.id())
+                .await?
+                .status
+        );
+        assert!(matches!(
+            kitchen::bump(&mut db, Station::Fryer, order.id()).await,
+            Err(KitchenError::AlreadyReady(id)) if id == order.id()
+        ));
+        assert!(matches!(
+            kitchen::bump(&mut db, Station::Fryer, 9.into()).await,
+            Err(KitchenError::OrderNotFound(id)) if id == 9.into()
+        ));
+
+        Ok(())
+    })
+}
+
+#[test]
+fn table_is_ready_once_every_station_is_done() -> Result<(), KitchenError> {
+    let mut pool = LocalPool::new();
+    pool.run_until(async {
+        let table = common::table(1);
+        let steak = menu_item(1, "Steak", Station::Grill);
+        let parfait = menu_item(2, "Parfait", Station::Dessert);
+        let mut db = Database::default();
+
+        assert!(!kitchen::table_status(&db, table.id()).await?.ready);
+
+        let main = order::place(&mut db, &order::Rules::default(), table.clone(), steak, 1)
+            .await
+            .unwrap();
+        let dessert = order::place(&mut db, &order::Rules::default(), table.clone(), parfait, 1)
+            .await
+            .unwrap();
+
+        kitchen::bump(&mut db, Station::Grill, main.id()).await?;
+        let status = kitchen::table_status(&db, table.id()).await?;
+        assert!(!status.ready);
+        assert_eq!(vec![Station::Dessert], status.waiting_on);
+
+        kitchen::bump(&mut db, Station::Dessert, dessert.id()).await?;
+        let status = kitchen::table_status(&db, table.id()).await?;
+        assert!(status.ready);
+        assert!(status.waiting_on.is_empty());
+
+        Ok(())
+    })
+}
+
+#[test]
+fn extra_portions_go_back_to_the_station() -> Result<(), KitchenError> {
+    let mut pool = LocalPool::new();
+    pool.run_until(async {
+        let table = common::table(1);
+        let fries = menu_item(1, "Fries", Station::Fryer);
+        let mut db = Database::default();
+        let rules = order::Rules::default();
+
+        let order = order::place(&mut db, &rules, table, fries, 2)
+            .await
+            .unwrap();
+        kitchen::bump(&mut db, Station::Fryer, order.id()).await?;
+
+        // fewer portions than were made is still ready
+        let fewer = order::set_quantity(&mut db, &rules, order.id(), 1, None)
+            .await
+            .unwrap();
+        assert_eq!(order::Status::Ready, fewer.status);
+
+        let more = order::set_quantity(&mut db, &rules, order.id(), 3, None)
+            .await
+            .unwrap();
+        assert_eq!(order::Status::Placed, more.status);
+        assert_eq!(
+            vec![order.id()],
+            kitchen::station_tickets(&db, Station::Fryer)
+                .await?
+                .iter()
+                .map(|o| o.id())
+                .collect::<Vec<order::Id>>()
+        );
+
+        Ok(())
+    })
+}
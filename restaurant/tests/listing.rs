@@ -0,0 +1,185 @@
+use chrono::{DateTime, TimeDelta, Utc};
+use futures::executor::LocalPool;
+use pretty_assertions::assert_eq;
+use restaurant::memdb::Database;
+use restaurant::menu;
+use restaurant::order::{self, Direction, OrderingError, Query, SortKey};
+use restaurant::RepoItem;
+
+mod common;
+
+fn fries() -> menu::RepoItem {
+    RepoItem::new(
+        2.into(),
+        menu::Item {
+            cook_time: menu::Minutes(3),
+            station: menu::Station::Fryer,
+            ..common::menu_item("Fries")
+        },
+    )
+}
+
+fn opened() -> DateTime<Utc> {
+    DateTime::parse_from_rfc3339("2024-07-01T12:00:00Z")
+        .unwrap()
+        .to_utc()
+}
+
+// placed a minute apart, though not in the order of their ids
+fn database() -> Database {
+    let order = |id: u32, table: u32, item: menu::RepoItem, minute: i64, quantity: u32| {
+        RepoItem::new(
+            id.into(),
+            order::Order {
+                table: common::table(table),
+                menu_item: item,
+                time_placed: opened() + TimeDelta::minutes(minute),
+                quantity,
+                status: order::Status::Placed,
+                group: None,
+                placed_by: None,
+                changed_by: None,
+            },
+        )
+    };
+
+    Database::new(
+        vec![common::pasta(), fries()],
+        vec![common::table(1), common::table(2)],
+        vec![
+            order(1, 1, common::pasta(), 0, 2),
+            order(2, 2, fries(), 4, 1),
+            order(3, 1, fries(), 1, 3),
+            order(4, 2, common::pasta(), 3, 2),
+            order(5, 1, common::pasta(), 2, 1),
+        ],
+    )
+}
+
+fn ids(orders: &[order::RepoOrder]) -> Vec<u32> {
+    orders.iter().map(|o| o.id().0).collect()
+}
+
+#[test]
+fn queries_filter_orders() -> Result<(), OrderingError> {
+    let mut pool = LocalPool::new();
+    pool.run_until(async {
+        let db = database();
+
+        let page = order::find(
+            &db,
+            &Query {
+                table: Some(1.into()),
+                menu_item: Some(1.into()),
+                ..Default::default()
+            },
+        )
+        .await?;
+        assert_eq!(vec![1, 5], ids(&page.orders));
+        assert!(page.next.is_none());
+
+        let page = order::find(
+            &db,
+            &Query {
+                placed_after: Some(opened()),
+                placed_before: Some(opened() + TimeDelta::minutes(3)),
+                ..Default::default()
+            },
+        )
+        .await?;
+        assert_eq!(vec![3, 5], ids(&page.orders));
+
+        let page = order::find(
+            &db,
+            &Query {
+                status: Some(order::Status::Ready),
+                ..Default::default()
+            },
+        )
+        .await?;
+        assert!(page.orders.is_empty());
+
+        Ok(())
+    })
+}
+
+#[test]
+fn queries_sort_orders() -> Result<(), OrderingError> {
+    let mut pool = LocalPool::new();
+    pool.run_until(async {
+        let db = database();
+        let sorted = |sort, direction| Query {
+            sort,
+            direction,
+            ..Default::default()
+        };
+
+        let page = order::find(&db, &sorted(SortKey::TimePlaced, Direction::Ascending)).await?;
+        assert_eq!(vec![1, 3, 5, 4, 2], ids(&page.orders));
+
+        // ties are broken by id, in the same direction
+        let page = order::find(&db, &sorted(SortKey::Quantity, Direction::Descending)).await?;
+        assert_eq!(vec![3, 4, 1, 5, 2], ids(&page.orders));
+
+        assert_eq!(vec![1, 3, 5], ids(&order::get_table(&db, 1.into()).await?));
+
+        Ok(())
+    })
+}
+
+#[test]
+fn cursors_page_through_every_order_once() -> Result<(), OrderingError> {
+    let mut pool = LocalPool::new();
+    pool.run_until(async {
+        let mut db = database();
+        let mut query = Query {
+            sort: SortKey::Table,
+            limit: Some(2),
+            ..Default::default()
+        };
+
+        let first = order::find(&db, &query).await?;
+        assert_eq!(vec![1, 3], ids(&first.orders));
+
+        // a page keeps its place even when an order before it goes away
+        order::cancel(&mut db, 1.into(), None).await?;
+        query.after = first.next;
+        let second = order::find(&db, &query).await?;
+        assert_eq!(vec![5, 2], ids(&second.orders));
+
+        query.after = second.next;
+        let last = order::find(&db, &query).await?;
+        assert_eq!(vec![4], ids(&last.orders));
+        assert!(last.next.is_none());
+
+        Ok(())
+    })
+}
+
+#[test]
+fn cursors_only_fit_their_own_sort() {
+    let mut pool = LocalPool::new();
+    pool.run_until(async {
+        let db = database();
+        let first = order::find(
+            &db,
+            &Query {
+                limit: Some(1),
+                ..Default::default()
+            },
+        )
+        .await
+        .unwrap();
+
+        let resorted = order::find(
+            &db,
+            &Query {
+                sort: SortKey::Quantity,
+                after: first.next,
+                ..Default::default()
+            },
+        )
+        .await;
+        assert!(matches!(resorted, Err(OrderingError::InvalidCursor)));
+    })
+}
@@ -0,0 +1,139 @@
+use futures::executor::LocalPool;
+use pretty_assertions::assert_eq;
+use restaurant::memdb::Database;
+use restaurant::menu::{self, Category, CategoryId, MenuError};
+
+mod common;
+
+fn database() -> Database {
+    Database::new(
+        vec![
+            common::item(1, "Pasta"),
+            common::item(2, "Burger"),
+            common::item(3, "Red wine"),
+            common::item(4, "Lemonade"),
+            common::item(5, "Bread"),
+        ],
+        vec![],
+        vec![],
+    )
+}
+
+async fn add(
+    db: &mut Database,
+    name: &str,
+    parent: Option<CategoryId>,
+    sort_order: u32,
+) -> Result<CategoryId, MenuError> {
+    menu::add_category(
+        db,
+        Category {
+            name: name.to_string(),
+            parent,
+            sort_order,
+        },
+    )
+    .await
+    .map(|c| c.id())
+}
+
+fn names(items: &[menu::RepoItem]) -> Vec<&str> {
+    items.iter().map(|i| i.name.as_str()).collect()
+}
+
+#[test]
+fn the_menu_is_laid_out_by_category() -> Result<(), MenuError> {
+    let mut pool = LocalPool::new();
+    pool.run_until(async {
+        let mut db = database();
+        // added in a different order to how they're listed
+        let drinks = add(&mut db, "Drinks", None, 2).await?;
+        let mains = add(&mut db, "Mains", None, 1).await?;
+        let wine = add(&mut db, "Wine", Some(drinks), 1).await?;
+
+        menu::file(&mut db, 1.into(), Some(mains), 2).await?;
+        menu::file(&mut db, 2.into(), Some(mains), 1).await?;
+        menu::file(&mut db, 3.into(), Some(wine), 0).await?;
+        menu::file(&mut db, 4.into(), Some(drinks), 0).await?;
+
+        let tree = menu::tree(&db).await?;
+        assert_eq!(
+            vec!["Mains", "Drinks"],
+            tree.categories
+                .iter()
+                .map(|s| s.category.name.as_str())
+                .collect::<Vec<&str>>()
+        );
+        assert_eq!(vec!["Burger", "Pasta"], names(&tree.categories[0].items));
+        let drinks = &tree.categories[1];
+        assert_eq!(vec!["Lemonade"], names(&drinks.items));
+        assert_eq!(1, drinks.subcategories.len());
+        assert_eq!("Wine", drinks.subcategories[0].category.name);
+        assert_eq!(vec!["Red wine"], names(&drinks.subcategories[0].items));
+        assert_eq!(vec!["Bread"], names(&tree.uncategorized));
+
+        Ok(())
+    })
+}
+
+#[test]
+fn a_category_includes_its_subcategories() -> Result<(), MenuError> {
+    let mut pool = LocalPool::new();
+    pool.run_until(async {
+        let mut db = database();
+        let drinks = add(&mut db, "Drinks", None, 1).await?;
+        let soft = add(&mut db, "Soft drinks", Some(drinks), 2).await?;
+        let wine = add(&mut db, "Wine", Some(drinks), 1).await?;
+        menu::file(&mut db, 4.into(), Some(soft), 0).await?;
+        menu::file(&mut db, 3.into(), Some(wine), 0).await?;
+
+        // nothing is directly under drinks, but everything under it is in it
+        assert_eq!(vec!["Red wine"], names(&menu::items_in(&db, wine).await?));
+        assert_eq!(
+            vec!["Red wine", "Lemonade"],
+            names(&menu::items_in(&db, drinks).await?)
+        );
+        assert!(matches!(
+            menu::items_in(&db, 9.into()).await,
+            Err(MenuError::CategoryNotFound(id)) if id == 9.into()
+        ));
+
+        // taking an item out of its category puts it back with the uncategorized ones
+        let lemonade = menu::file(&mut db, 4.into(), None, 0).await?;
+        assert_eq!(None, lemonade.category);
+        assert_eq!(vec!["Red wine"], names(&menu::items_in(&db, drinks).await?));
+
+        Ok(())
+    })
+}
+
+#[test]
+fn only_what_exists_can_be_filed() -> Result<(), MenuError> {
+    let mut pool = LocalPool::new();
+    pool.run_until(async {
+        let mut db = database();
+        let mains = add(&mut db, "Mains", None, 1).await?;
+
+        assert!(matches!(
+            add(&mut db, "Pasta", Some(9.into()), 1).await,
+            Err(MenuError::CategoryNotFound(id)) if id == 9.into()
+        ));
+        assert!(matches!(
+            menu::file(&mut db, 1.into(), Some(9.into()), 0).await,
+            Err(MenuError::CategoryNotFound(id)) if id == 9.into()
+        ));
+        assert!(matches!(
+            menu::file(&mut db, 9.into(), Some(mains), 0).await,
+            Err(MenuError::ItemNotFound(id)) if id == 9.into()
+        ));
+        assert_eq!(5, menu::tree(&db).await?.uncategorized.len());
+
+        // and each restaurant lays out its own menu
+        let downtown = db
+            .open(2.into(), vec![common::item(1, "Ramen")], vec![], vec![])
+            .unwrap();
+        assert!(menu::tree(&downtown).await?.categories.is_empty());
+
+        Ok(())
+    })
+}
@@ -19,14 +19,8 @@ fn place_orders() -> Result<(), OrderingError> {
         // we don't actually need to use db for menu and layout
         // but using it to roughly illustrate its usage
         let mut db = Database::new(
-            vec![RepoItem::new(
-                1.into(),
-                menu::Item {
-                    name: "Pasta".to_string(),
-                    cook_time: menu::Minutes(5),
-                },
-            )],
-            vec![RepoItem::new(1.into(), layout::Table {})],
+            vec![common::item(1, "Pasta")],
+            vec![common::table(1)],
             vec![],
         );
 
@@ -36,7 +30,14 @@ fn place_orders() -> Result<(), OrderingError> {
         let item = menu::Repository::get(&db, menu::Id(1))
             .await
             .expect("Item 1 should exist");
-        order::place(&mut db, table.clone(), item.clone(), 3).await?;
+        order::place(
+            &mut db,
+            &order::Rules::default(),
+            table.clone(),
+            item.clone(),
+            3,
+        )
+        .await?;
 
         assert_eq!(
             &[ComparableOrder(RepoItem::new(
@@ -45,7 +46,11 @@ fn place_orders() -> Result<(), OrderingError> {
                     table: table.clone(),
                     menu_item: item.clone(),
                     time_placed: Utc::now(),
-                    quantity: 3
+                    quantity: 3,
+                    status: order::Status::Placed,
+                    group: None,
+                    placed_by: None,
+                    changed_by: None,
                 }
             )),][..],
             order::Repository::get_all(&db)
@@ -62,22 +67,10 @@ fn place_orders() -> Result<(), OrderingError> {
 fn change_order_quantity() -> Result<(), OrderingError> {
     let mut pool = LocalPool::new();
     pool.run_until(async {
-        let table1 = RepoItem::new(1.into(), layout::Table {});
-        let table2 = RepoItem::new(3.into(), layout::Table {});
-        let pasta = RepoItem::new(
-            1.into(),
-            menu::Item {
-                name: "Pasta".to_string(),
-                cook_time: menu::Minutes(5),
-            },
-        );
-        let sandwich = RepoItem::new(
-            1.into(),
-            menu::Item {
-                name: "Sandwich".to_string(),
-                cook_time: menu::Minutes(5),
-            },
-        );
+        let table1 = common::table(1);
+        let table2 = common::table(3);
+        let pasta = common::item(1, "Pasta");
+        let sandwich = common::item(1, "Sandwich");
         let mut db = Database::default();
 
         async fn place_order(
@@ -86,18 +79,25 @@ fn change_order_quantity() -> Result<(), OrderingError> {
             item: &menu::RepoItem,
             quantity: u32,
         ) -> order::Result<(order::Id, order::RepoOrder)> {
-            order::place(db, table.clone(), item.clone(), quantity)
-                .await
-                .map(|o| (o.id(), o))
+            order::place(
+                db,
+                &order::Rules::default(),
+                table.clone(),
+                item.clone(),
+                quantity,
+            )
+            .await
+            .map(|o| (o.id(), o))
         }
 
         let (id1, order1) = place_order(&mut db, &table1, &pasta, 3).await?;
         let (id2, order2) = place_order(&mut db, &table1, &sandwich, 2).await?;
         let (id3, order3) = place_order(&mut db, &table2, &sandwich, 5).await?;
 
-        order::set_quantity(&mut db, order1.id(), 1).await?;
-        let zero_quantity_order = order::set_quantity(&mut db, order2.id(), 0).await?;
-        order::set_quantity(&mut db, order3.id(), 7).await?;
+        order::set_quantity(&mut db, &order::Rules::default(), order1.id(), 1, None).await?;
+        let zero_quantity_order =
+            order::set_quantity(&mut db, &order::Rules::default(), order2.id(), 0, None).await?;
+        order::set_quantity(&mut db, &order::Rules::default(), order3.id(), 7, None).await?;
 
         let mut orders1 = order::get_table(&db, table1.id()).await?;
         orders1.sort_by_key(|a| a.id());
@@ -112,7 +112,11 @@ fn change_order_quantity() -> Result<(), OrderingError> {
                     table: table1.clone(),
                     menu_item: pasta.clone(),
                     quantity: 1,
-                    time_placed: Utc::now()
+                    time_placed: Utc::now(),
+                    status: order::Status::Placed,
+                    group: None,
+                    placed_by: None,
+                    changed_by: None,
                 }
             ))][..],
             orders1.as_slice()
@@ -126,7 +130,11 @@ fn change_order_quantity() -> Result<(), OrderingError> {
                     table: table2.clone(),
                     menu_item: sandwich.clone(),
                     quantity: 7,
-                    time_placed: Utc::now()
+                    time_placed: Utc::now(),
+                    status: order::Status::Placed,
+                    group: None,
+                    placed_by: None,
+                    changed_by: None,
                 }
             ))][..],
             orders2.as_slice()
@@ -139,7 +147,11 @@ fn change_order_quantity() -> Result<(), OrderingError> {
                     table: table1.clone(),
                     menu_item: sandwich.clone(),
                     quantity: 0,
-                    time_placed: Utc::now()
+                    time_placed: Utc::now(),
+                    status: order::Status::Placed,
+                    group: None,
+                    placed_by: None,
+                    changed_by: None,
                 }
             )),
             zero_quantity_order
@@ -153,22 +165,267 @@ fn change_order_quantity() -> Result<(), OrderingError> {
 fn cancel_order() -> Result<(), OrderingError> {
     let mut pool = LocalPool::new();
     pool.run_until(async {
-        let table = RepoItem::new(1.into(), layout::Table {});
-        let item = RepoItem::new(
-            1.into(),
-            menu::Item {
-                name: "Pasta".to_string(),
-                cook_time: menu::Minutes(5),
-            },
-        );
+        let table = common::table(1);
+        let item = common::item(1, "Pasta");
         let mut db = Database::default();
-        let order = order::place(&mut db, table.clone(), item.clone(), 12).await?;
-        order::cancel(&mut db, order.id()).await?;
+        let order = order::place(
+            &mut db,
+            &order::Rules::default(),
+            table.clone(),
+            item.clone(),
+            12,
+        )
+        .await?;
+        order::cancel(&mut db, order.id(), None).await?;
 
         if let Ok(orders) = order::get_table(&db, table.id()).await {
             assert!(orders.is_empty(), "Orders found.")
         };
+        assert!(matches!(
+            order::cancel(&mut db, order.id(), None).await,
+            Err(OrderingError::OrderNotFound(_))
+        ));
+
+        Ok(())
+    })
+}
+
+#[test]
+fn order_changes_are_recorded_as_events() -> Result<(), OrderingError> {
+    let mut pool = LocalPool::new();
+    pool.run_until(async {
+        let table1 = common::table(1);
+        let table2 = common::table(2);
+        let item = common::item(1, "Pasta");
+        let mut db = Database::default();
+
+        let order1 = order::place(
+            &mut db,
+            &order::Rules::default(),
+            table1.clone(),
+            item.clone(),
+            1,
+        )
+        .await?;
+        let order2 = order::place(
+            &mut db,
+            &order::Rules::default(),
+            table2.clone(),
+            item.clone(),
+            1,
+        )
+        .await?;
+        order::set_quantity(&mut db, &order::Rules::default(), order1.id(), 4, None).await?;
+        order::clear_table(&mut db, table1.id()).await?;
+
+        let all = order::events(&db, None, &order::EventFilter::default()).await?;
+        assert_eq!(
+            vec![
+                (order::Change::Placed, order1.id()),
+                (order::Change::Placed, order2.id()),
+                (order::Change::Updated, order1.id()),
+                (order::Change::Removed, order1.id()),
+            ],
+            all.iter()
+                .map(|e| (e.change, e.order.id()))
+                .collect::<Vec<_>>()
+        );
+
+        // resuming from a seen event only gives what came after it
+        let resumed = order::events(&db, Some(all[1].id()), &order::EventFilter::default()).await?;
+        assert_eq!(
+            all[2..].iter().map(|e| e.id()).collect::<Vec<_>>(),
+            resumed.iter().map(|e| e.id()).collect::<Vec<_>>()
+        );
+
+        let filtered = order::events(
+            &db,
+            None,
+            &order::EventFilter {
+                table: Some(table2.id()),
+                ..Default::default()
+            },
+        )
+        .await?;
+        assert_eq!(
+            vec![order2.id()],
+            filtered.iter().map(|e| e.order.id()).collect::<Vec<_>>()
+        );
+
+        Ok(())
+    })
+}
+
+#[test]
+fn place_order_group() -> Result<(), OrderingError> {
+    let mut pool = LocalPool::new();
+    pool.run_until(async {
+        let table = common::table(1);
+        let mut db = Database::new(
+            vec![common::item(1, "Pasta"), common::item(2, "Steak")],
+            vec![],
+            vec![],
+        );
+
+        let line = |id: u32, quantity| order::GroupLine {
+            menu_item: id.into(),
+            quantity,
+        };
+        let first = order::place_group(
+            &mut db,
+            &order::Rules::default(),
+            table.clone(),
+            &[line(1, 2), line(2, 1)],
+        )
+        .await?;
+        let second = order::place_group(
+            &mut db,
+            &order::Rules::default(),
+            table.clone(),
+            &[line(2, 3)],
+        )
+        .await?;
+
+        assert_eq!(
+            vec![(menu::Id(1), 2), (menu::Id(2), 1)],
+            first
+                .iter()
+                .map(|o| (o.menu_item.id(), o.quantity))
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(first[0].time_placed, first[1].time_placed);
+        assert!(first[0].group.is_some());
+        assert_eq!(first[0].group, first[1].group);
+        assert_ne!(first[0].group, second[0].group);
 
         Ok(())
     })
 }
+
+#[test]
+fn order_groups_are_placed_whole_or_not_at_all() {
+    let mut pool = LocalPool::new();
+    pool.run_until(async {
+        let table = common::table(1);
+        let item = |id: u32, available| {
+            RepoItem::new(
+                id.into(),
+                menu::Item {
+                    available,
+                    ..common::menu_item("Pasta")
+                },
+            )
+        };
+        let mut db = Database::new(vec![item(1, true), item(2, false)], vec![], vec![]);
+
+        let line = |id: u32, quantity| order::GroupLine {
+            menu_item: id.into(),
+            quantity,
+        };
+        let result = order::place_group(
+            &mut db,
+            &order::Rules::default(),
+            table.clone(),
+            &[line(1, 1), line(2, 1), line(1, 0), line(9, 1)],
+        )
+        .await;
+
+        match result {
+            Err(OrderingError::InvalidLines(lines)) => assert_eq!(
+                vec![
+                    (1, order::LineError::MenuItemUnavailable(2.into())),
+                    (2, order::LineError::ZeroQuantity),
+                    (3, order::LineError::MenuItemNotFound(9.into())),
+                ],
+                lines
+            ),
+            other => panic!("Expected the lines to be rejected, got {:?}", other),
+        }
+        assert!(matches!(
+            order::place_group(&mut db, &order::Rules::default(), table, &[]).await,
+            Err(OrderingError::EmptyGroup)
+        ));
+        assert!(order::Repository::get_all(&db).await.unwrap().is_empty());
+    })
+}
+
+#[test]
+fn orders_are_validated() {
+    let mut pool = LocalPool::new();
+    pool.run_until(async {
+        let table = |active| {
+            RepoItem::new(
+                1.into(),
+                layout::Table {
+                    active,
+                    ..common::layout_table()
+                },
+            )
+        };
+        let item = |available| {
+            RepoItem::new(
+                1.into(),
+                menu::Item {
+                    available,
+                    ..common::menu_item("Pasta")
+                },
+            )
+        };
+        let rules = order::Rules { max_quantity: 10 };
+        let mut db = Database::new(vec![item(true)], vec![], vec![]);
+
+        assert!(matches!(
+            order::place(&mut db, &rules, table(true), item(true), 0).await,
+            Err(OrderingError::ZeroQuantity)
+        ));
+        assert!(matches!(
+            order::place(&mut db, &rules, table(true), item(true), 11).await,
+            Err(OrderingError::QuantityTooLarge {
+                quantity: 11,
+                max: 10
+            })
+        ));
+        assert!(matches!(
+            order::place(&mut db, &rules, table(false), item(true), 1).await,
+            Err(OrderingError::TableInactive(layout::TableId(1)))
+        ));
+        assert!(matches!(
+            order::place(&mut db, &rules, table(true), item(false), 1).await,
+            Err(OrderingError::MenuItemUnavailable(menu::Id(1)))
+        ));
+
+        let placed = order::place(&mut db, &rules, table(true), item(true), 10)
+            .await
+            .unwrap();
+        assert!(matches!(
+            order::set_quantity(&mut db, &rules, placed.id(), 11, None).await,
+            Err(OrderingError::QuantityTooLarge { .. })
+        ));
+
+        let line = order::GroupLine {
+            menu_item: 1.into(),
+            quantity: 12,
+        };
+        match order::place_group(&mut db, &rules, table(true), &[line]).await {
+            Err(OrderingError::InvalidLines(lines)) => assert_eq!(
+                vec![(0, order::LineError::QuantityTooLarge { max: 10 })],
+                lines
+            ),
+            other => panic!("Expected the line to be rejected, got {:?}", other),
+        }
+        assert!(matches!(
+            order::place_group(&mut db, &rules, table(false), &[line]).await,
+            Err(OrderingError::TableInactive(_))
+        ));
+
+        assert_eq!(
+            vec![(placed.id(), 10)],
+            order::Repository::get_all(&db)
+                .await
+                .unwrap()
+                .iter()
+                .map(|o| (o.id(), o.quantity))
+                .collect::<Vec<_>>()
+        );
+    })
+}
@@ -0,0 +1,149 @@
+use std::sync::Mutex;
+
+use futures::executor::LocalPool;
+use pretty_assertions::assert_eq;
+use restaurant::memdb::Database;
+use restaurant::order::{self, EventId, RepoEvent};
+use restaurant::outbox::{self, Dispatcher, OutboxError};
+
+mod common;
+
+// fails once it has seen `fail_after` events, as a broker going down partway through would
+struct RecordingDispatcher {
+    seen: Mutex<Vec<EventId>>,
+    fail_after: Option<usize>,
+}
+
+impl RecordingDispatcher {
+    fn new(fail_after: Option<usize>) -> RecordingDispatcher {
+        RecordingDispatcher {
+            seen: Mutex::new(Vec::new()),
+            fail_after,
+        }
+    }
+
+    fn seen(&self) -> Vec<EventId> {
+        self.seen.lock().unwrap().clone()
+    }
+}
+
+impl Dispatcher for RecordingDispatcher {
+    async fn dispatch(&self, event: &RepoEvent) -> anyhow::Result<()> {
+        let mut seen = self.seen.lock().unwrap();
+        if self.fail_after.is_some_and(|n| seen.len() >= n) {
+            return Err(anyhow::anyhow!("Dispatcher is down."));
+        }
+        seen.push(event.id());
+        Ok(())
+    }
+}
+
+async fn place_orders(db: &mut Database, count: u32) -> Vec<order::RepoOrder> {
+    let table = common::table(1);
+    let item = common::item(1, "Pasta");
+
+    let mut orders = Vec::new();
+    for _ in 0..count {
+        orders.push(
+            order::place(db, &order::Rules::default(), table.clone(), item.clone(), 1)
+                .await
+                .unwrap(),
+        );
+    }
+    orders
+}
+
+#[test]
+fn events_are_dispatched_once_in_order() -> Result<(), OutboxError> {
+    let mut pool = LocalPool::new();
+    pool.run_until(async {
+        let mut db = Database::default();
+        let dispatcher = RecordingDispatcher::new(None);
+        place_orders(&mut db, 3).await;
+
+        let dispatched = outbox::relay(&mut db, &dispatcher).await?;
+        assert_eq!(vec![EventId(1), EventId(2), EventId(3)], dispatched);
+
+        // nothing new happened, so there's nothing to relay
+        assert!(outbox::relay(&mut db, &dispatcher).await?.is_empty());
+        assert_eq!(dispatched, dispatcher.seen());
+
+        Ok(())
+    })
+}
+
+#[test]
+fn failed_dispatches_are_retried() -> Result<(), OutboxError> {
+    let mut pool = LocalPool::new();
+    pool.run_until(async {
+        let mut db = Database::default();
+        place_orders(&mut db, 3).await;
+
+        let flaky = RecordingDispatcher::new(Some(1));
+        assert!(matches!(
+            outbox::relay(&mut db, &flaky).await,
+            Err(OutboxError::Dispatch {
+                event: EventId(2),
+                ..
+            })
+        ));
+
+        let recovered = RecordingDispatcher::new(None);
+        assert_eq!(
+            vec![EventId(2), EventId(3)],
+            outbox::relay(&mut db, &recovered).await?
+        );
+
+        Ok(())
+    })
+}
+
+#[test]
+fn failed_mutations_announce_nothing() -> Result<(), OutboxError> {
+    let mut pool = LocalPool::new();
+    pool.run_until(async {
+        let mut db = Database::default();
+        let dispatcher = RecordingDispatcher::new(None);
+
+        assert!(order::cancel(&mut db, order::Id(42), None).await.is_err());
+        assert!(
+            order::set_quantity(&mut db, &order::Rules::default(), order::Id(42), 3, None)
+                .await
+                .is_err()
+        );
+
+        assert!(outbox::relay(&mut db, &dispatcher).await?.is_empty());
+
+        Ok(())
+    })
+}
+
+#[test]
+fn only_recent_events_are_kept() -> Result<(), OutboxError> {
+    let mut pool = LocalPool::new();
+    pool.run_until(async {
+        let mut db = Database::default();
+        db.keep_events(2);
+        let dispatcher = RecordingDispatcher::new(None);
+        let all = order::EventFilter::default();
+        let ids = |events: Vec<RepoEvent>| events.iter().map(|e| e.id()).collect::<Vec<_>>();
+
+        // undispatched events are kept however many there are
+        place_orders(&mut db, 3).await;
+        assert_eq!(3, order::events(&db, None, &all).await.unwrap().len());
+        outbox::relay(&mut db, &dispatcher).await?;
+
+        place_orders(&mut db, 1).await;
+        assert_eq!(
+            vec![EventId(3), EventId(4)],
+            ids(order::events(&db, None, &all).await.unwrap())
+        );
+        assert_eq!(
+            vec![EventId(4)],
+            ids(order::events(&db, Some(EventId(3)), &all).await.unwrap())
+        );
+        assert_eq!(vec![EventId(4)], outbox::relay(&mut db, &dispatcher).await?);
+
+        Ok(())
+    })
+}
@@ -0,0 +1,186 @@
+use chrono::{DateTime, NaiveDate, TimeZone, Utc};
+use futures::executor::LocalPool;
+use pretty_assertions::assert_eq;
+use restaurant::layout::{self, TableId};
+use restaurant::memdb::Database;
+use restaurant::reservation::{self, Changes, Reservation, ReservationError, Window};
+use restaurant::RepoItem;
+
+mod common;
+
+// table 1 sits two, table 2 sits six, and table 3 is closed
+fn database() -> Database {
+    Database::new(
+        vec![],
+        vec![
+            RepoItem::new(
+                1.into(),
+                layout::Table {
+                    seats: 2,
+                    ..common::layout_table()
+                },
+            ),
+            RepoItem::new(
+                2.into(),
+                layout::Table {
+                    seats: 6,
+                    ..common::layout_table()
+                },
+            ),
+            RepoItem::new(
+                3.into(),
+                layout::Table {
+                    active: false,
+                    ..common::layout_table()
+                },
+            ),
+        ],
+        vec![],
+    )
+}
+
+fn at(hour: u32) -> DateTime<Utc> {
+    Utc.with_ymd_and_hms(2024, 6, 1, hour, 0, 0).unwrap()
+}
+
+fn booking(table: u32, party_size: u32, from: u32, to: u32) -> Reservation {
+    common::reservation(
+        table,
+        party_size,
+        Window {
+            starts: at(from),
+            ends: at(to),
+        },
+    )
+}
+
+#[test]
+fn bookings_need_a_table_that_fits() {
+    let mut pool = LocalPool::new();
+    pool.run_until(async {
+        let mut db = database();
+
+        assert!(matches!(
+            reservation::book(&mut db, booking(1, 4, 18, 20)).await,
+            Err(ReservationError::TableTooSmall {
+                seats: 2,
+                party_size: 4,
+                ..
+            })
+        ));
+        assert!(matches!(
+            reservation::book(&mut db, booking(3, 2, 18, 20)).await,
+            Err(ReservationError::TableInactive(TableId(3)))
+        ));
+        assert!(matches!(
+            reservation::book(&mut db, booking(9, 2, 18, 20)).await,
+            Err(ReservationError::TableNotFound(TableId(9)))
+        ));
+        assert!(matches!(
+            reservation::book(&mut db, booking(2, 0, 18, 20)).await,
+            Err(ReservationError::EmptyParty)
+        ));
+        assert!(matches!(
+            reservation::book(&mut db, booking(2, 4, 20, 18)).await,
+            Err(ReservationError::InvalidWindow)
+        ));
+
+        let booked = reservation::book(&mut db, booking(2, 4, 18, 20))
+            .await
+            .unwrap();
+        assert_eq!(TableId(2), booked.table);
+    })
+}
+
+#[test]
+fn tables_are_never_double_booked() -> Result<(), ReservationError> {
+    let mut pool = LocalPool::new();
+    pool.run_until(async {
+        let mut db = database();
+        let dinner = reservation::book(&mut db, booking(2, 4, 18, 20)).await?;
+
+        assert!(matches!(
+            reservation::book(&mut db, booking(2, 2, 19, 21)).await,
+            Err(ReservationError::Overlaps { with, .. }) if with == dinner.id()
+        ));
+        // the table's free again the moment the first party's time is up, and other tables are unaffected
+        reservation::book(&mut db, booking(2, 2, 20, 21)).await?;
+        reservation::book(&mut db, booking(1, 2, 19, 21)).await?;
+
+        // moving a booking is checked against every other booking, though not against itself
+        let lunch = reservation::book(&mut db, booking(2, 3, 12, 14)).await?;
+        assert!(matches!(
+            reservation::modify(
+                &mut db,
+                lunch.id(),
+                Changes {
+                    window: Some(Window {
+                        starts: at(17),
+                        ends: at(19),
+                    }),
+                    ..Default::default()
+                },
+            )
+            .await,
+            Err(ReservationError::Overlaps { .. })
+        ));
+        let later = reservation::modify(
+            &mut db,
+            lunch.id(),
+            Changes {
+                window: Some(Window {
+                    starts: at(13),
+                    ends: at(15),
+                }),
+                ..Default::default()
+            },
+        )
+        .await?;
+        assert_eq!(at(13), later.window.starts);
+
+        Ok(())
+    })
+}
+
+#[test]
+fn reservations_are_listed_by_day() -> Result<(), ReservationError> {
+    let mut pool = LocalPool::new();
+    pool.run_until(async {
+        let mut db = database();
+        let dinner = reservation::book(&mut db, booking(2, 4, 18, 20)).await?;
+        let lunch = reservation::book(&mut db, booking(1, 2, 12, 13)).await?;
+        let late = reservation::book(
+            &mut db,
+            Reservation {
+                window: Window {
+                    starts: at(23),
+                    ends: at(23) + chrono::TimeDelta::hours(2),
+                },
+                ..booking(1, 2, 0, 0)
+            },
+        )
+        .await?;
+        let cancelled = reservation::book(&mut db, booking(1, 1, 15, 16)).await?;
+        reservation::cancel(&mut db, cancelled.id()).await?;
+
+        let ids = |reservations: Vec<reservation::RepoReservation>| {
+            reservations.iter().map(|r| r.id()).collect::<Vec<_>>()
+        };
+        let day = NaiveDate::from_ymd_opt(2024, 6, 1).unwrap();
+        assert_eq!(
+            vec![lunch.id(), dinner.id(), late.id()],
+            ids(reservation::on_day(&db, day).await?)
+        );
+        // a booking running past midnight is on both days
+        assert_eq!(
+            vec![late.id()],
+            ids(reservation::on_day(&db, day.succ_opt().unwrap()).await?)
+        );
+        assert!(matches!(
+            reservation::cancel(&mut db, cancelled.id()).await,
+            Err(ReservationError::ReservationNotFound(_))
+        ));
+
+        Ok(())
+    })
+}
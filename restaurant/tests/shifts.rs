@@ -0,0 +1,225 @@
+use chrono::{TimeDelta, TimeZone, Utc};
+use futures::executor::LocalPool;
+use pretty_assertions::assert_eq;
+use restaurant::memdb::Database;
+use restaurant::reservation::Window;
+use restaurant::shift::{self, Assignment, ShiftError};
+use restaurant::staff::{self, Role};
+use restaurant::{kitchen, layout, menu, order, RepoItem};
+
+mod common;
+
+fn table(id: u32, section: &str) -> layout::RepoTable {
+    RepoItem::new(
+        id.into(),
+        layout::Table {
+            section: Some(section.to_string()),
+            ..common::layout_table()
+        },
+    )
+}
+
+fn database() -> Database {
+    Database::new(
+        vec![common::pasta()],
+        vec![table(1, "patio"), table(2, "patio"), table(3, "bar")],
+        vec![],
+    )
+}
+
+#[test]
+fn servers_see_the_tables_in_their_sections() -> Result<(), ShiftError> {
+    let mut pool = LocalPool::new();
+    pool.run_until(async {
+        let mut db = database();
+        let sam = staff::hire(&mut db, "Sam", Role::Server, "2580")
+            .await
+            .unwrap();
+        shift::assign(
+            &mut db,
+            Assignment {
+                section: "patio".to_string(),
+                server: sam.id(),
+                shift: common::evening(),
+            },
+        )
+        .await?;
+        order::place(
+            &mut db,
+            &order::Rules::default(),
+            table(2, "patio"),
+            common::pasta(),
+            1,
+        )
+        .await
+        .unwrap();
+
+        let mine = shift::tables_of(&db, sam.id(), Utc::now()).await?;
+        assert_eq!(
+            vec![(1.into(), 0), (2.into(), 1)],
+            mine.iter()
+                .map(|t| (t.table.id(), t.orders.len()))
+                .collect::<Vec<(layout::TableId, usize)>>()
+        );
+        assert_eq!(
+            Some(sam.id()),
+            shift::server_of(&db, &table(1, "patio"), Utc::now()).await?
+        );
+        assert_eq!(
+            None,
+            shift::server_of(&db, &table(3, "bar"), Utc::now()).await?
+        );
+
+        // nobody has any tables once the shift's over
+        let later = common::evening().ends + TimeDelta::minutes(1);
+        assert!(shift::tables_of(&db, sam.id(), later).await?.is_empty());
+
+        Ok(())
+    })
+}
+
+#[test]
+fn sections_have_one_server_at_a_time() -> Result<(), ShiftError> {
+    let mut pool = LocalPool::new();
+    pool.run_until(async {
+        let mut db = database();
+        let sam = staff::hire(&mut db, "Sam", Role::Server, "2580")
+            .await
+            .unwrap();
+        let kai = staff::hire(&mut db, "Kai", Role::Kitchen, "1357")
+            .await
+            .unwrap();
+        let patio = |server| Assignment {
+            section: "patio".to_string(),
+            server,
+            shift: common::evening(),
+        };
+
+        let first = shift::assign(&mut db, patio(sam.id())).await?;
+        assert!(matches!(
+            shift::assign(&mut db, patio(sam.id())).await,
+            Err(ShiftError::SectionTaken { with, .. }) if with == first.id()
+        ));
+        assert!(matches!(
+            shift::assign(&mut db, patio(kai.id())).await,
+            Err(ShiftError::NotAServer(id)) if id == kai.id()
+        ));
+        assert!(matches!(
+            shift::assign(
+                &mut db,
+                Assignment {
+                    section: "roof".to_string(),
+                    ..patio(sam.id())
+                }
+            )
+            .await,
+            Err(ShiftError::UnknownSection(section)) if section == "roof"
+        ));
+        let backwards = Window {
+            starts: common::evening().ends,
+            ends: common::evening().starts,
+        };
+        assert!(matches!(
+            shift::assign(
+                &mut db,
+                Assignment {
+                    shift: backwards,
+                    ..patio(sam.id())
+                }
+            )
+            .await,
+            Err(ShiftError::InvalidWindow)
+        ));
+
+        // the next shift can start as this one ends
+        let next = Window {
+            starts: common::evening().ends,
+            ends: common::evening().ends + TimeDelta::hours(4),
+        };
+        shift::assign(
+            &mut db,
+            Assignment {
+                shift: next,
+                ..patio(sam.id())
+            },
+        )
+        .await?;
+
+        shift::unassign(&mut db, first.id()).await?;
+        assert!(matches!(
+            shift::unassign(&mut db, first.id()).await,
+            Err(ShiftError::AssignmentNotFound(_))
+        ));
+        assert_eq!(1, shift::get_all(&db).await?.len());
+
+        Ok(())
+    })
+}
+
+#[test]
+fn ready_orders_go_to_the_tables_server() -> Result<(), ShiftError> {
+    let mut pool = LocalPool::new();
+    pool.run_until(async {
+        let mut db = database();
+        let sam = staff::hire(&mut db, "Sam", Role::Server, "2580")
+            .await
+            .unwrap();
+        shift::assign(
+            &mut db,
+            Assignment {
+                section: "patio".to_string(),
+                server: sam.id(),
+                shift: common::evening(),
+            },
+        )
+        .await?;
+
+        let patio = order::place(
+            &mut db,
+            &order::Rules::default(),
+            table(1, "patio"),
+            common::pasta(),
+            2,
+        )
+        .await
+        .unwrap();
+        let bar = order::place(
+            &mut db,
+            &order::Rules::default(),
+            table(3, "bar"),
+            common::pasta(),
+            1,
+        )
+        .await
+        .unwrap();
+        kitchen::bump(&mut db, menu::Station::Grill, patio.id())
+            .await
+            .unwrap();
+        kitchen::bump(&mut db, menu::Station::Grill, bar.id())
+            .await
+            .unwrap();
+        // still ready, with a portion fewer
+        order::set_quantity(&mut db, &order::Rules::default(), patio.id(), 1, None)
+            .await
+            .unwrap();
+
+        let events = order::events(&db, None, &order::EventFilter::default())
+            .await
+            .unwrap();
+        let mut notified = Vec::new();
+        for event in &events {
+            notified.push(shift::to_notify(&db, event).await?);
+        }
+        // placing isn't worth a notice, the bar has nobody on it, and the patio was only ready the once
+        assert_eq!(vec![None, None, Some(sam.id()), None, None], notified);
+
+        // nobody had the patio before the evening shift
+        let long_ago = Utc.with_ymd_and_hms(2020, 1, 1, 0, 0, 0).unwrap();
+        assert_eq!(
+            None,
+            shift::server_of(&db, &table(1, "patio"), long_ago).await?
+        );
+
+        Ok(())
+    })
+}
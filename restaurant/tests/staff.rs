@@ -0,0 +1,223 @@
+use chrono::{TimeDelta, Utc};
+use futures::executor::LocalPool;
+use pretty_assertions::assert_eq;
+use restaurant::memdb::Database;
+use restaurant::staff::{self, Role, StaffError};
+use restaurant::{kitchen, layout, menu, order, reservation, waitlist};
+
+mod common;
+
+#[test]
+fn staff_sign_in_with_their_own_secret() -> Result<(), StaffError> {
+    let mut pool = LocalPool::new();
+    pool.run_until(async {
+        let mut db = Database::new(vec![], vec![], vec![]);
+        let sam = staff::hire(&mut db, "Sam", Role::Server, "2580").await?;
+
+        assert_eq!(sam.id(), staff::login(&mut db, "Sam", "2580").await?.id());
+        assert!(matches!(
+            staff::login(&mut db, "Sam", "0852").await,
+            Err(StaffError::InvalidCredentials)
+        ));
+        assert!(matches!(
+            staff::login(&mut db, "Kai", "2580").await,
+            Err(StaffError::InvalidCredentials)
+        ));
+
+        staff::dismiss(&mut db, sam.id()).await?;
+        assert!(matches!(
+            staff::login(&mut db, "Sam", "2580").await,
+            Err(StaffError::InvalidCredentials)
+        ));
+
+        Ok(())
+    })
+}
+
+#[test]
+fn names_are_unique_and_secrets_long_enough() -> Result<(), StaffError> {
+    let mut pool = LocalPool::new();
+    pool.run_until(async {
+        let mut db = Database::new(vec![], vec![], vec![]);
+        staff::hire(&mut db, "Sam", Role::Server, "2580").await?;
+
+        assert!(matches!(
+            staff::hire(&mut db, "Sam", Role::Host, "1357").await,
+            Err(StaffError::NameTaken(name)) if name == "Sam"
+        ));
+        assert!(matches!(
+            staff::hire(&mut db, "Kai", Role::Kitchen, "123").await,
+            Err(StaffError::WeakSecret)
+        ));
+        assert_eq!(1, staff::get_all(&db).await?.len());
+
+        assert!(Role::Manager.covers(Role::Kitchen));
+        assert!(!Role::Manager.covers(Role::Admin));
+        assert!(Role::Admin.covers(Role::Admin));
+        assert!(!Role::Server.covers(Role::Host));
+
+        Ok(())
+    })
+}
+
+#[test]
+fn changes_are_put_down_to_whoever_made_them() -> Result<(), StaffError> {
+    let mut pool = LocalPool::new();
+    pool.run_until(async {
+        let mut db = Database::new(vec![common::pasta()], vec![common::table(1)], vec![]);
+        let sam = staff::hire(&mut db, "Sam", Role::Server, "2580").await?;
+        let kai = staff::hire(&mut db, "Kai", Role::Kitchen, "1357").await?;
+
+        let placed = order::place(
+            &mut db.acting_as(sam.id()),
+            &order::Rules::default(),
+            common::table(1),
+            common::pasta(),
+            1,
+        )
+        .await
+        .unwrap();
+        assert_eq!(
+            (Some(sam.id()), Some(sam.id())),
+            (placed.placed_by, placed.changed_by)
+        );
+
+        let bumped = kitchen::bump(
+            &mut db.acting_as(kai.id()),
+            menu::Station::Grill,
+            placed.id(),
+        )
+        .await
+        .unwrap();
+        assert_eq!(
+            (Some(sam.id()), Some(kai.id())),
+            (bumped.placed_by, bumped.changed_by)
+        );
+
+        let events = order::events(&db, None, &order::EventFilter::default())
+            .await
+            .unwrap();
+        assert_eq!(
+            vec![Some(sam.id()), Some(kai.id())],
+            events.iter().map(|e| e.by).collect::<Vec<_>>()
+        );
+
+        Ok(())
+    })
+}
+
+#[test]
+fn staff_can_only_hire_and_dismiss_below_themselves() -> Result<(), StaffError> {
+    let mut pool = LocalPool::new();
+    pool.run_until(async {
+        let mut db = Database::new(vec![], vec![], vec![]);
+        let root = staff::hire(&mut db, "Root", Role::Admin, "2580").await?;
+        let mina = staff::hire(&mut db.acting_as(root.id()), "Mina", Role::Manager, "1357").await?;
+        let other = staff::hire(&mut db.acting_as(root.id()), "Ola", Role::Manager, "1357").await?;
+
+        let mut manager = db.acting_as(mina.id());
+        let sam = staff::hire(&mut manager, "Sam", Role::Server, "2468").await?;
+        assert!(matches!(
+            staff::hire(&mut manager, "Ash", Role::Admin, "2468").await,
+            Err(StaffError::Outranked(Role::Admin))
+        ));
+        assert!(matches!(
+            staff::hire(&mut manager, "Max", Role::Manager, "2468").await,
+            Err(StaffError::Outranked(Role::Manager))
+        ));
+        assert!(matches!(
+            staff::dismiss(&mut manager, other.id()).await,
+            Err(StaffError::Outranked(Role::Manager))
+        ));
+        assert!(matches!(
+            staff::hire(&mut db.acting_as(sam.id()), "Kai", Role::Kitchen, "2468").await,
+            Err(StaffError::Outranked(Role::Kitchen))
+        ));
+
+        staff::dismiss(&mut manager, sam.id()).await?;
+        staff::dismiss(&mut db.acting_as(root.id()), other.id()).await?;
+        assert_eq!(
+            vec!["Root", "Mina", "Sam"],
+            staff::get_all(&db)
+                .await?
+                .iter()
+                .filter(|s| s.active || s.id() == sam.id())
+                .map(|s| s.name.as_str())
+                .collect::<Vec<_>>()
+        );
+
+        Ok(())
+    })
+}
+
+#[test]
+fn guessing_a_pin_locks_the_name_out() -> Result<(), StaffError> {
+    let mut pool = LocalPool::new();
+    pool.run_until(async {
+        let mut db = Database::new(vec![], vec![], vec![]);
+        staff::hire(&mut db, "Sam", Role::Server, "2580").await?;
+        staff::hire(&mut db, "Kai", Role::Kitchen, "1357").await?;
+
+        // getting it right starts the count over
+        for _ in 0..2 {
+            for _ in 0..staff::MAX_FAILED_LOGINS - 1 {
+                assert!(staff::login(&mut db, "Sam", "0000").await.is_err());
+            }
+            staff::login(&mut db, "Sam", "2580").await?;
+        }
+
+        for _ in 0..staff::MAX_FAILED_LOGINS {
+            assert!(matches!(
+                staff::login(&mut db, "Sam", "0000").await,
+                Err(StaffError::InvalidCredentials)
+            ));
+        }
+        assert!(matches!(
+            staff::login(&mut db, "Sam", "2580").await,
+            Err(StaffError::LockedOut(until)) if until > Utc::now() + staff::LOCKOUT - TimeDelta::minutes(1)
+        ));
+        // only for the name being guessed at
+        staff::login(&mut db, "Kai", "1357").await?;
+
+        Ok(())
+    })
+}
+
+#[test]
+fn bookings_parties_and_groups_are_put_down_to_whoever_made_them() -> Result<(), StaffError> {
+    let mut pool = LocalPool::new();
+    pool.run_until(async {
+        let mut db = Database::new(vec![], vec![common::table(1), common::table(2)], vec![]);
+        let hana = staff::hire(&mut db, "Hana", Role::Host, "2580").await?;
+        let yui = staff::hire(&mut db, "Yui", Role::Host, "1357").await?;
+        let (mut hana_db, mut yui_db) = (db.acting_as(hana.id()), db.acting_as(yui.id()));
+
+        let booked = reservation::book(&mut hana_db, common::reservation(1, 2, common::evening()))
+            .await
+            .unwrap();
+        assert_eq!(Some(hana.id()), booked.changed_by);
+        let changes = reservation::Changes {
+            party_size: Some(3),
+            ..Default::default()
+        };
+        let modified = reservation::modify(&mut yui_db, booked.id(), changes)
+            .await
+            .unwrap();
+        assert_eq!(Some(yui.id()), modified.changed_by);
+
+        let party = waitlist::Party::new("Sato".to_string(), 2, None);
+        let party = waitlist::add(&mut hana_db, &waitlist::Rules::default(), party)
+            .await
+            .unwrap();
+        assert_eq!(Some(hana.id()), party.changed_by);
+        let seated = waitlist::seat(&mut yui_db, party.id()).await.unwrap();
+        assert_eq!(Some(yui.id()), seated.changed_by);
+
+        let group = layout::merge(&mut hana_db, &[1.into(), 2.into()])
+            .await
+            .unwrap();
+        assert_eq!(Some(hana.id()), group.merged_by);
+
+        Ok(())
+    })
+}
@@ -0,0 +1,132 @@
+use futures::executor::LocalPool;
+use pretty_assertions::assert_eq;
+use restaurant::layout::{self, LayoutError, TableId};
+use restaurant::memdb::Database;
+use restaurant::{order, RepoItem};
+
+mod common;
+
+// tables 4, 5 and 6 are the ones that get pushed together, and 7 is closed
+fn database() -> Database {
+    Database::new(
+        vec![common::pasta()],
+        (1..7)
+            .map(common::table)
+            .chain([RepoItem::new(
+                7.into(),
+                layout::Table {
+                    active: false,
+                    ..common::layout_table()
+                },
+            )])
+            .collect(),
+        vec![],
+    )
+}
+
+async fn place(db: &mut Database, table: u32) -> order::RepoOrder {
+    let table = layout::serving_table(db, table.into()).await.unwrap();
+    order::place(db, &order::Rules::default(), table, common::pasta(), 1)
+        .await
+        .unwrap()
+}
+
+async fn tables(db: &Database, table: u32) -> Vec<u32> {
+    order::get_table(db, table.into())
+        .await
+        .unwrap()
+        .iter()
+        .map(|o| o.table.id().0)
+        .collect()
+}
+
+#[test]
+fn merged_tables_order_as_one() -> Result<(), LayoutError> {
+    let mut pool = LocalPool::new();
+    pool.run_until(async {
+        let mut db = database();
+        let early = place(&mut db, 5).await;
+
+        let group = layout::merge(&mut db, &[4.into(), 5.into(), 6.into()]).await?;
+        assert_eq!(TableId(4), group.lead());
+
+        // what was already at the other tables joins the lead, and so does anything placed at them after
+        let late = place(&mut db, 6).await;
+        assert_eq!(TableId(4), late.table.id());
+        let party = order::get_table(&db, 4.into()).await.unwrap();
+        assert_eq!(
+            vec![early.id(), late.id()],
+            party.iter().map(|o| o.id()).collect::<Vec<_>>()
+        );
+        assert_eq!(early.time_placed, party[0].time_placed);
+        assert_eq!(TableId(4), layout::serving_table(&db, 5.into()).await?.id());
+
+        Ok(())
+    })
+}
+
+#[test]
+fn splitting_leaves_the_orders_with_the_party() -> Result<(), LayoutError> {
+    let mut pool = LocalPool::new();
+    pool.run_until(async {
+        let mut db = database();
+        let group = layout::merge(&mut db, &[4.into(), 5.into()]).await?;
+        place(&mut db, 5).await;
+        place(&mut db, 4).await;
+
+        let moved = layout::split(&mut db, group.id(), 5.into()).await?;
+        assert_eq!(
+            vec![5, 5],
+            moved.iter().map(|o| o.table.id().0).collect::<Vec<_>>()
+        );
+        assert!(tables(&db, 4).await.is_empty());
+        assert_eq!(vec![5, 5], tables(&db, 5).await);
+
+        // back to being tables of their own
+        assert!(layout::group_of(&db, 4.into()).await?.is_none());
+        assert_eq!(TableId(4), place(&mut db, 4).await.table.id());
+
+        Ok(())
+    })
+}
+
+#[test]
+fn groups_need_free_open_tables() -> Result<(), LayoutError> {
+    let mut pool = LocalPool::new();
+    pool.run_until(async {
+        let mut db = database();
+        let group = layout::merge(&mut db, &[4.into(), 5.into()]).await?;
+        place(&mut db, 5).await;
+
+        assert!(matches!(
+            layout::merge(&mut db, &[1.into()]).await,
+            Err(LayoutError::GroupTooSmall)
+        ));
+        assert!(matches!(
+            layout::merge(&mut db, &[1.into(), 1.into()]).await,
+            Err(LayoutError::GroupTooSmall)
+        ));
+        assert!(matches!(
+            layout::merge(&mut db, &[6.into(), 5.into()]).await,
+            Err(LayoutError::AlreadyGrouped(TableId(5), _))
+        ));
+        assert!(matches!(
+            layout::merge(&mut db, &[6.into(), 7.into()]).await,
+            Err(LayoutError::TableInactive(TableId(7)))
+        ));
+        assert!(matches!(
+            layout::merge(&mut db, &[6.into(), 9.into()]).await,
+            Err(LayoutError::TableNotFound(TableId(9)))
+        ));
+        assert!(matches!(
+            layout::split(&mut db, group.id(), 6.into()).await,
+            Err(LayoutError::NotInGroup(TableId(6), _))
+        ));
+        // a split that can't be made leaves the party where it was
+        assert_eq!(1, layout::get_groups(&db).await?.len());
+        assert_eq!(vec![4], tables(&db, 4).await);
+        assert_eq!(TableId(4), layout::serving_table(&db, 5.into()).await?.id());
+
+        Ok(())
+    })
+}
@@ -0,0 +1,251 @@
+use futures::executor::LocalPool;
+use pretty_assertions::assert_eq;
+use restaurant::memdb::Database;
+use restaurant::order::{self, OrderingError};
+use restaurant::shift::{self, Assignment, ShiftError};
+use restaurant::staff::{self, Role, StaffError};
+use restaurant::tenant::{self, TenantError};
+use restaurant::{api_key, audit, layout, menu, RepoItem};
+
+mod common;
+
+fn item(name: &str) -> menu::RepoItem {
+    RepoItem::new(
+        1.into(),
+        menu::Item {
+            cook_time: menu::Minutes(10),
+            ..common::menu_item(name)
+        },
+    )
+}
+
+// the original restaurant, and a second branch with the same floor but its own menu
+fn branches() -> (Database, Database) {
+    let main = Database::new(vec![item("Pasta")], vec![common::table(1)], vec![]);
+    let downtown = main
+        .open(
+            2.into(),
+            vec![item("Ramen")],
+            vec![common::table(1)],
+            vec![],
+        )
+        .unwrap();
+    (main, downtown)
+}
+
+#[test]
+fn branches_never_see_each_others_orders() -> Result<(), OrderingError> {
+    let mut pool = LocalPool::new();
+    pool.run_until(async {
+        let (mut main, mut downtown) = branches();
+        let rules = order::Rules::default();
+
+        let pasta = order::place(&mut main, &rules, common::table(1), item("Pasta"), 2).await?;
+        let second = order::place(&mut main, &rules, common::table(1), item("Pasta"), 1).await?;
+        let ramen = order::place(&mut downtown, &rules, common::table(1), item("Ramen"), 3).await?;
+
+        // each branch counts its own ids
+        assert_eq!(pasta.id(), ramen.id());
+        assert_eq!(
+            vec![3],
+            order::get_table(&downtown, 1.into())
+                .await?
+                .iter()
+                .map(|o| o.quantity)
+                .collect::<Vec<u32>>()
+        );
+        assert!(order::get(&downtown, second.id()).await.is_err());
+        assert!(order::cancel(&mut downtown, second.id(), None)
+            .await
+            .is_err());
+
+        // clearing the table downtown leaves the one here alone
+        order::clear_table(&mut downtown, 1.into()).await?;
+        assert_eq!(2, order::get_table(&main, 1.into()).await?.len());
+
+        // and the events each branch announces are only its own, saying where they're from
+        let events = order::events(&downtown, None, &order::EventFilter::default()).await?;
+        assert_eq!(2, events.len());
+        assert!(events.iter().all(|e| e.tenant == 2.into()));
+        let events = order::events(&main, None, &order::EventFilter::default()).await?;
+        assert_eq!(2, events.len());
+        assert!(events.iter().all(|e| e.tenant == tenant::DEFAULT));
+
+        Ok(())
+    })
+}
+
+#[test]
+fn branches_only_find_their_own_changes_in_the_audit_log() -> Result<(), audit::AuditError> {
+    let mut pool = LocalPool::new();
+    pool.run_until(async {
+        let (mut main, mut downtown) = branches();
+        staff::hire(&mut main, "Sam", Role::Server, "2580")
+            .await
+            .unwrap();
+        order::place(
+            &mut downtown,
+            &order::Rules::default(),
+            common::table(1),
+            item("Ramen"),
+            1,
+        )
+        .await
+        .unwrap();
+
+        let operations = |page: audit::Page| {
+            page.entries
+                .iter()
+                .map(|e| e.record.operation.clone())
+                .collect::<Vec<String>>()
+        };
+        // staff work across branches, so hiring shows up at both
+        assert_eq!(
+            vec!["staff.create"],
+            operations(audit::search(&main, &audit::Query::default()).await?)
+        );
+        assert_eq!(
+            vec!["order.create", "staff.create"],
+            operations(audit::search(&downtown, &audit::Query::default()).await?)
+        );
+        assert!(audit::search(
+            &main,
+            &audit::Query {
+                text: Some("ramen".to_string()),
+                ..Default::default()
+            }
+        )
+        .await?
+        .entries
+        .is_empty());
+
+        // each branch checks its own chain, and the shared one with hiring on it
+        assert_eq!(1, audit::verify(&main).await?);
+        assert_eq!(2, audit::verify(&downtown).await?);
+
+        Ok(())
+    })
+}
+
+#[test]
+fn only_open_branches_can_be_worked_with() -> Result<(), TenantError> {
+    let mut pool = LocalPool::new();
+    pool.run_until(async {
+        let (main, downtown) = branches();
+
+        assert_eq!(
+            vec![tenant::DEFAULT, 2.into()],
+            tenant::get_all(&downtown).await?
+        );
+        tenant::exists(&main, 2.into()).await?;
+        assert!(matches!(
+            tenant::exists(&main, 3.into()).await,
+            Err(TenantError::NotFound(id)) if id == 3.into()
+        ));
+        assert!(main.for_tenant(3.into()).is_none());
+
+        // opening a branch again would throw away everything it has
+        assert!(main.open(2.into(), vec![], vec![], vec![]).is_err());
+        let again = main.for_tenant(2.into()).unwrap();
+        assert_eq!(
+            vec!["Ramen"],
+            menu::get_all(&again)
+                .await
+                .unwrap()
+                .iter()
+                .map(|i| i.name.as_str())
+                .collect::<Vec<&str>>()
+        );
+
+        Ok(())
+    })
+}
+
+#[test]
+fn staff_and_keys_work_where_they_were_taken_on() -> Result<(), StaffError> {
+    let mut pool = LocalPool::new();
+    pool.run_until(async {
+        let (mut main, mut downtown) = branches();
+        let mina = staff::hire(&mut main, "Mina", Role::Manager, "2580").await?;
+        let sam = staff::hire(&mut downtown, "Sam", Role::Server, "1234").await?;
+        let admin = staff::hire(&mut main, "Ada", Role::Admin, "0000").await?;
+        let names = |staff: Vec<staff::RepoStaff>| {
+            staff
+                .iter()
+                .map(|s| s.name.clone())
+                .collect::<Vec<String>>()
+        };
+
+        // admins reach every branch, everyone else only where they were hired
+        assert_eq!(vec!["Mina", "Ada"], names(staff::get_all(&main).await?));
+        assert_eq!(vec!["Sam", "Ada"], names(staff::get_all(&downtown).await?));
+        assert!(!mina.works_at(2.into()) && admin.works_at(2.into()));
+        assert!(matches!(
+            staff::dismiss(&mut main.acting_as(mina.id()), sam.id()).await,
+            Err(StaffError::StaffNotFound(_))
+        ));
+
+        // a manager can't send someone to a branch they don't work at themselves
+        let cole = staff::hire(&mut main, "Cole", Role::Server, "4321").await?;
+        assert!(matches!(
+            staff::add_restaurant(&mut main.acting_as(mina.id()), cole.id(), 2.into()).await,
+            Err(StaffError::Elsewhere(id)) if id == 2.into()
+        ));
+        let cole =
+            staff::add_restaurant(&mut main.acting_as(admin.id()), cole.id(), 2.into()).await?;
+        assert!(cole.works_at(tenant::DEFAULT) && cole.works_at(2.into()));
+
+        // keys can only be seen and changed from their own branch
+        let (key, _) = api_key::create(&mut main, "POS", &[api_key::Scope::OrdersRead])
+            .await
+            .unwrap();
+        assert!(key.works_at(tenant::DEFAULT) && !key.works_at(2.into()));
+        assert!(api_key::get_all(&downtown).await.unwrap().is_empty());
+        assert!(matches!(
+            api_key::revoke(&mut downtown, key.id()).await,
+            Err(api_key::ApiKeyError::KeyNotFound(_))
+        ));
+
+        Ok(())
+    })
+}
+
+#[test]
+fn sections_only_go_to_servers_from_the_same_branch() -> Result<(), ShiftError> {
+    let mut pool = LocalPool::new();
+    pool.run_until(async {
+        let patio = || {
+            layout::RepoTable::new(
+                1.into(),
+                layout::Table {
+                    section: Some("patio".to_string()),
+                    ..common::layout_table()
+                },
+            )
+        };
+        let mut main = Database::new(vec![], vec![patio()], vec![]);
+        let mut downtown = main.open(2.into(), vec![], vec![patio()], vec![]).unwrap();
+        let sam = staff::hire(&mut downtown, "Sam", Role::Server, "2580")
+            .await
+            .unwrap();
+        let evening = Assignment {
+            section: "patio".to_string(),
+            server: sam.id(),
+            shift: common::evening(),
+        };
+
+        // otherwise Sam would get another branch's ready orders
+        assert!(matches!(
+            shift::assign(&mut main, evening.clone()).await,
+            Err(ShiftError::StaffNotFound(id)) if id == sam.id()
+        ));
+        shift::assign(&mut downtown, evening.clone()).await?;
+
+        staff::add_restaurant(&mut downtown, sam.id(), tenant::DEFAULT)
+            .await
+            .unwrap();
+        shift::assign(&mut main, evening).await?;
+
+        Ok(())
+    })
+}
@@ -0,0 +1,128 @@
+use futures::executor::LocalPool;
+use pretty_assertions::assert_eq;
+use restaurant::layout;
+use restaurant::memdb::Database;
+use restaurant::menu::Station;
+use restaurant::{
+    kitchen,
+    order::{self, OrderingError},
+    RepoItem,
+};
+
+mod common;
+
+#[test]
+fn transferred_orders_keep_their_place() -> Result<(), OrderingError> {
+    let mut pool = LocalPool::new();
+    pool.run_until(async {
+        let rules = order::Rules::default();
+        let mut db = Database::default();
+        let bar = order::place(&mut db, &rules, common::table(1), common::pasta(), 1).await?;
+        let later = order::place(&mut db, &rules, common::table(2), common::pasta(), 1).await?;
+
+        let moved = order::transfer(&mut db, bar.id(), common::table(3)).await?;
+        assert_eq!(
+            (bar.id(), bar.time_placed, layout::TableId(3)),
+            (moved.id(), moved.time_placed, moved.table.id())
+        );
+        assert!(order::get_table(&db, 1.into()).await?.is_empty());
+
+        // still ahead of the order placed after it, wherever it's sitting now
+        let tickets = kitchen::station_tickets(&db, Station::Grill).await.unwrap();
+        assert_eq!(
+            vec![bar.id(), later.id()],
+            tickets.iter().map(|t| t.id()).collect::<Vec<_>>()
+        );
+
+        Ok(())
+    })
+}
+
+#[test]
+fn tables_move_every_order_at_once() -> Result<(), OrderingError> {
+    let mut pool = LocalPool::new();
+    pool.run_until(async {
+        let rules = order::Rules::default();
+        let mut db = Database::default();
+        let first = order::place(&mut db, &rules, common::table(1), common::pasta(), 1).await?;
+        let second = order::place(&mut db, &rules, common::table(1), common::pasta(), 2).await?;
+        let elsewhere = order::place(&mut db, &rules, common::table(2), common::pasta(), 1).await?;
+
+        let moved = order::transfer_table(&mut db, 1.into(), common::table(3)).await?;
+        assert_eq!(
+            vec![first.id(), second.id()],
+            moved.iter().map(|o| o.id()).collect::<Vec<_>>()
+        );
+        assert!(order::get_table(&db, 1.into()).await?.is_empty());
+        assert_eq!(
+            vec![elsewhere.id()],
+            order::get_table(&db, 2.into())
+                .await?
+                .iter()
+                .map(|o| o.id())
+                .collect::<Vec<_>>()
+        );
+
+        // the table that was left sees the orders go, even though they're no longer at it
+        let left = order::events(
+            &db,
+            None,
+            &order::EventFilter {
+                table: Some(1.into()),
+                ..Default::default()
+            },
+        )
+        .await?;
+        assert_eq!(
+            vec![
+                (order::Change::Placed, None),
+                (order::Change::Placed, None),
+                (order::Change::Transferred, Some(layout::TableId(1))),
+                (order::Change::Transferred, Some(layout::TableId(1))),
+            ],
+            left.iter().map(|e| (e.change, e.from)).collect::<Vec<_>>()
+        );
+
+        Ok(())
+    })
+}
+
+#[test]
+fn transfers_need_a_different_open_table() -> Result<(), OrderingError> {
+    let mut pool = LocalPool::new();
+    pool.run_until(async {
+        let rules = order::Rules::default();
+        let mut db = Database::default();
+        let placed = order::place(&mut db, &rules, common::table(1), common::pasta(), 1).await?;
+        let closed = RepoItem::new(
+            2.into(),
+            layout::Table {
+                active: false,
+                ..common::layout_table()
+            },
+        );
+
+        assert!(matches!(
+            order::transfer(&mut db, placed.id(), common::table(1)).await,
+            Err(OrderingError::SameTable(_))
+        ));
+        assert!(matches!(
+            order::transfer(&mut db, placed.id(), closed.clone()).await,
+            Err(OrderingError::TableInactive(_))
+        ));
+        assert!(matches!(
+            order::transfer_table(&mut db, 1.into(), closed).await,
+            Err(OrderingError::TableInactive(_))
+        ));
+        assert!(matches!(
+            order::transfer(&mut db, 42.into(), common::table(3)).await,
+            Err(OrderingError::OrderNotFound(_))
+        ));
+        assert_eq!(
+            layout::TableId(1),
+            order::get(&db, placed.id()).await?.table.id()
+        );
+
+        Ok(())
+    })
+}
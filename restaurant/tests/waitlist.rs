@@ -0,0 +1,215 @@
+use std::sync::Mutex;
+
+use chrono::TimeDelta;
+use futures::executor::LocalPool;
+use pretty_assertions::assert_eq;
+use restaurant::layout::{self, TableId};
+use restaurant::memdb::Database;
+use restaurant::waitlist::{self, Party, Rules, Status, WaitlistError};
+use restaurant::{order, tenant, RepoItem};
+
+mod common;
+
+// table 1 sits two and table 2 sits four, and both have only just sat down
+async fn full_house() -> Database {
+    let tables: Vec<layout::RepoTable> = [(1, 2), (2, 4)]
+        .into_iter()
+        .map(|(id, seats)| {
+            RepoItem::new(
+                id.into(),
+                layout::Table {
+                    seats,
+                    ..common::layout_table()
+                },
+            )
+        })
+        .collect();
+    let mut db = Database::new(vec![common::pasta()], tables.clone(), vec![]);
+    for table in tables {
+        order::place(&mut db, &order::Rules::default(), table, common::pasta(), 1)
+            .await
+            .unwrap();
+    }
+    db
+}
+
+async fn join(db: &mut Database, name: &str, size: u32) -> waitlist::RepoParty {
+    waitlist::add(
+        db,
+        &Rules::default(),
+        Party::new(name.to_string(), size, None),
+    )
+    .await
+    .unwrap()
+}
+
+// rounded up, so a wait that's a moment short of an hour is still an hour
+fn minutes(wait: TimeDelta) -> i64 {
+    (wait.num_seconds() + 59) / 60
+}
+
+#[test]
+fn waits_follow_the_line() -> Result<(), WaitlistError> {
+    let mut pool = LocalPool::new();
+    pool.run_until(async {
+        let rules = Rules::default();
+        let mut db = full_house().await;
+
+        assert_eq!(60, minutes(waitlist::quote(&db, &rules, 4).await?));
+        let first = join(&mut db, "Tanaka", 4).await;
+        // the four-top's next sitting is taken, but the two-top's isn't
+        assert_eq!(120, minutes(waitlist::quote(&db, &rules, 3).await?));
+        assert_eq!(60, minutes(waitlist::quote(&db, &rules, 2).await?));
+
+        let second = join(&mut db, "Suzuki", 3).await;
+        assert_eq!(
+            60,
+            minutes(waitlist::estimate(&db, &rules, first.id()).await?)
+        );
+        assert_eq!(
+            120,
+            minutes(waitlist::estimate(&db, &rules, second.id()).await?)
+        );
+
+        assert!(matches!(
+            waitlist::quote(&db, &rules, 5).await,
+            Err(WaitlistError::NoTableFits(5))
+        ));
+        assert!(matches!(
+            waitlist::add(&mut db, &rules, Party::new("Sato".to_string(), 0, None)).await,
+            Err(WaitlistError::EmptyParty)
+        ));
+
+        Ok(())
+    })
+}
+
+#[test]
+fn freed_tables_go_to_the_first_party_that_fits() -> Result<(), WaitlistError> {
+    let mut pool = LocalPool::new();
+    pool.run_until(async {
+        let mut db = full_house().await;
+        let four = join(&mut db, "Tanaka", 4).await;
+        let two = join(&mut db, "Suzuki", 2).await;
+
+        order::clear_table(&mut db, 1.into()).await.unwrap();
+        let notified = waitlist::table_freed(&mut db, 1.into()).await?.unwrap();
+        assert_eq!(two.id(), notified.id());
+        assert!(matches!(
+            notified.status,
+            Status::Notified {
+                table: TableId(1),
+                ..
+            }
+        ));
+        assert_eq!(
+            TimeDelta::zero(),
+            waitlist::estimate(&db, &Rules::default(), two.id()).await?
+        );
+
+        // the table's held until the party's seated
+        assert!(waitlist::table_freed(&mut db, 1.into()).await?.is_none());
+        waitlist::seat(&mut db, two.id()).await?;
+        assert_eq!(
+            vec![four.id()],
+            waitlist::in_line(&db)
+                .await?
+                .iter()
+                .map(|p| p.id())
+                .collect::<Vec<_>>()
+        );
+
+        Ok(())
+    })
+}
+
+#[test]
+fn no_shows_pass_their_table_on() -> Result<(), WaitlistError> {
+    let mut pool = LocalPool::new();
+    pool.run_until(async {
+        let mut db = full_house().await;
+        let first = join(&mut db, "Tanaka", 2).await;
+        let second = join(&mut db, "Suzuki", 2).await;
+
+        order::clear_table(&mut db, 2.into()).await.unwrap();
+        waitlist::table_freed(&mut db, 2.into()).await?;
+        let gone = waitlist::no_show(&mut db, first.id()).await?;
+        assert!(matches!(gone.status, Status::NoShow { .. }));
+
+        assert!(matches!(
+            waitlist::get(&db, second.id()).await?.status,
+            Status::Notified {
+                table: TableId(2),
+                ..
+            }
+        ));
+        assert!(matches!(
+            waitlist::no_show(&mut db, first.id()).await,
+            Err(WaitlistError::NotWaiting(_))
+        ));
+        assert!(matches!(
+            waitlist::seat(&mut db, 9.into()).await,
+            Err(WaitlistError::PartyNotFound(_))
+        ));
+
+        Ok(())
+    })
+}
+
+// remembers who it was told about, and fails if it's told to
+#[derive(Default)]
+struct Recording {
+    announced: Mutex<Vec<waitlist::Id>>,
+    failing: bool,
+}
+
+impl waitlist::Announcer for Recording {
+    async fn announce(
+        &self,
+        tenant: tenant::Id,
+        party: &waitlist::RepoParty,
+    ) -> anyhow::Result<()> {
+        assert_eq!(tenant::DEFAULT, tenant);
+        if self.failing {
+            return Err(anyhow::anyhow!("Nobody's listening."));
+        }
+        self.announced.lock().unwrap().push(party.id());
+        Ok(())
+    }
+}
+
+#[test]
+fn notified_parties_are_announced_once() -> Result<(), WaitlistError> {
+    let mut pool = LocalPool::new();
+    pool.run_until(async {
+        let mut db = full_house().await;
+        let two = join(&mut db, "Suzuki", 2).await;
+        let failing = Recording {
+            failing: true,
+            ..Default::default()
+        };
+        let recording = Recording::default();
+
+        // nobody's been notified yet
+        assert!(waitlist::announce(&mut db, &recording).await?.is_empty());
+
+        order::clear_table(&mut db, 1.into()).await.unwrap();
+        waitlist::table_freed(&mut db, 1.into()).await?;
+        assert!(matches!(
+            waitlist::announce(&mut db, &failing).await,
+            Err(WaitlistError::Announce { party, .. }) if party == two.id()
+        ));
+
+        // a failed announcement is made again, and a made one isn't
+        assert_eq!(
+            vec![two.id()],
+            waitlist::announce(&mut db, &recording).await?
+        );
+        assert!(waitlist::announce(&mut db, &recording).await?.is_empty());
+        waitlist::seat(&mut db, two.id()).await?;
+        assert!(waitlist::announce(&mut db, &recording).await?.is_empty());
+        assert_eq!(vec![two.id()], *recording.announced.lock().unwrap());
+
+        Ok(())
+    })
+}